@@ -0,0 +1,442 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, BufRead, Write},
+    ops::Range,
+};
+
+use stack_assembly::{Eval, OperatorIndex, Script};
+use stack_assembly_host::{format_memory, line_and_column};
+
+use crate::print_operand_stack;
+
+/// # Evaluate the provided script in an interactive debugger
+///
+/// The debugger reads commands from stdin, in a loop, until the user quits or
+/// stdin is closed.
+///
+/// While stepping, it checks that calls to procedures respect their declared
+/// arity, and stops there if they don't.
+pub fn run(source: &str) -> anyhow::Result<()> {
+    /// # How many steps the user can step back
+    const HISTORY_CAPACITY: usize = 64 * 1024;
+
+    let script = Script::compile(source);
+
+    let mut eval = Eval::start_at(script.start());
+    eval.enable_history(HISTORY_CAPACITY);
+    eval.load_data(&script);
+
+    let mut debugger = Debugger {
+        source,
+        script,
+        eval,
+        breakpoints: BTreeSet::new(),
+        procedure_calls: Vec::new(),
+    };
+
+    println!("Type `help` for a list of commands.");
+    debugger.print_location();
+
+    let mut previous_command = String::new();
+    let mut lines = io::stdin().lock().lines();
+
+    loop {
+        print!("(debug) ");
+        io::stdout().flush()?;
+
+        let Some(line) = lines.next() else {
+            // Stdin has been closed. Nothing else to do.
+            println!();
+            return Ok(());
+        };
+        let line = line?;
+
+        // Like in other debuggers, an empty line repeats the previous command.
+        // This makes stepping through a script much more convenient.
+        let command = if line.trim().is_empty() {
+            previous_command.clone()
+        } else {
+            line
+        };
+
+        match debugger.execute(&command) {
+            Ok(Continue::Yes) => {}
+            Ok(Continue::No) => {
+                return Ok(());
+            }
+            Err(message) => {
+                println!("{message}");
+            }
+        }
+
+        previous_command = command;
+    }
+}
+
+struct Debugger<'r> {
+    source: &'r str,
+    script: Script,
+    eval: Eval,
+    breakpoints: BTreeSet<OperatorIndex>,
+
+    /// # The calls to procedures that haven't returned yet
+    ///
+    /// Used to check that procedures respect their declared arity.
+    procedure_calls: Vec<ProcedureCall>,
+}
+
+impl Debugger<'_> {
+    fn execute(&mut self, command: &str) -> Result<Continue, String> {
+        let mut words = command.split_whitespace();
+
+        let Some(name) = words.next() else {
+            return Ok(Continue::Yes);
+        };
+        let args = words.collect::<Vec<_>>();
+
+        match (name, args.as_slice()) {
+            ("help" | "h", []) => {
+                print_help();
+            }
+            ("step" | "s", args) => {
+                let num_steps = match args {
+                    [] => 1,
+                    [num_steps] => parse_number(num_steps)?,
+                    _ => return Err(usage("step [count]")),
+                };
+
+                self.step(num_steps);
+                self.print_location();
+            }
+            ("back" | "bs", args) => {
+                let num_steps = match args {
+                    [] => 1,
+                    [num_steps] => parse_number(num_steps)?,
+                    _ => return Err(usage("back [count]")),
+                };
+
+                self.step_back(num_steps);
+                self.print_location();
+            }
+            ("continue" | "c", []) => {
+                self.continue_();
+                self.print_location();
+            }
+            ("break" | "b", [line]) => {
+                let operator =
+                    self.first_operator_on_line(parse_number(line)?)?;
+                self.breakpoints.insert(operator);
+                println!("Breakpoint set at operator {operator}.");
+            }
+            ("delete" | "d", [line]) => {
+                let operator =
+                    self.first_operator_on_line(parse_number(line)?)?;
+                if !self.breakpoints.remove(&operator) {
+                    return Err(format!(
+                        "No breakpoint at operator {operator}."
+                    ));
+                }
+                println!("Breakpoint at operator {operator} deleted.");
+            }
+            ("breakpoints", []) => {
+                for &operator in &self.breakpoints {
+                    self.print_operator(operator);
+                }
+            }
+            ("stack", []) => {
+                print_operand_stack(&self.eval.operand_stack);
+            }
+            ("calls", []) => {
+                for operator in self.eval.call_stack.call_sites() {
+                    self.print_operator(operator);
+                }
+            }
+            ("memory" | "m", args) => {
+                let (address, count) = match args {
+                    [address] => (parse_number(address)?, 1),
+                    [address, count] => {
+                        (parse_number(address)?, parse_number(count)?)
+                    }
+                    _ => return Err(usage("memory <address> [count]")),
+                };
+
+                self.print_memory(address, count)?;
+            }
+            ("list" | "l", []) => {
+                self.print_source();
+            }
+            ("clear", []) => match self.eval.clear_effect() {
+                Some((effect, _)) => println!("Cleared effect: {effect}"),
+                None => println!("No effect is active."),
+            },
+            ("quit" | "q", []) => {
+                return Ok(Continue::No);
+            }
+            _ => {
+                return Err(format!(
+                    "Unknown command or wrong arguments: `{command}`. Type \
+                    `help` for a list of commands."
+                ));
+            }
+        }
+
+        Ok(Continue::Yes)
+    }
+
+    fn step(&mut self, num_steps: u32) {
+        for _ in 0..num_steps {
+            if self.effect_is_active() {
+                break;
+            }
+
+            self.step_and_check_arity();
+        }
+    }
+
+    fn step_back(&mut self, num_steps: u32) {
+        for _ in 0..num_steps {
+            if !self.eval.step_back() {
+                println!("Can't step back any further.");
+                break;
+            }
+        }
+
+        // We don't know about calls that we stepped back into, but we can
+        // forget about the ones we stepped back out of.
+        let strand = self.eval.current_strand();
+        let depth = self.eval.call_stack.len();
+        self.procedure_calls
+            .retain(|call| call.strand != strand || call.depth <= depth);
+    }
+
+    fn continue_(&mut self) {
+        loop {
+            if self.effect_is_active() {
+                break;
+            }
+
+            if !self.step_and_check_arity() {
+                break;
+            }
+
+            if self.breakpoints.contains(&self.eval.next_operator()) {
+                println!("Hit breakpoint.");
+                break;
+            }
+        }
+    }
+
+    /// # Evaluate the next operator, checking calls to procedures
+    ///
+    /// Returns `false`, if a procedure was called with too few values on the
+    /// operand stack, or returned with a different number of values than it
+    /// declares.
+    fn step_and_check_arity(&mut self) -> bool {
+        let strand = self.eval.current_strand();
+        let depth = self.eval.call_stack.len();
+
+        self.eval.step(&self.script);
+
+        if self.eval.current_strand() != strand {
+            // Each strand has its own call stack, so comparing depths makes no
+            // sense.
+            return true;
+        }
+
+        let new_depth = self.eval.call_stack.len();
+        let num_values = self.eval.operand_stack.to_i32_slice().len();
+        let mut arity_respected = true;
+
+        if new_depth > depth {
+            let next = self.eval.next_operator();
+
+            if let Some((name, _, arity)) = self
+                .script
+                .procedures()
+                .find(|(_, operator, _)| *operator == next)
+            {
+                let inputs = arity.inputs as usize;
+
+                if num_values < inputs {
+                    println!(
+                        "Procedure `{name}` expects {inputs} inputs, but the \
+                        stack only has {num_values} values."
+                    );
+                    arity_respected = false;
+                }
+
+                self.procedure_calls.push(ProcedureCall {
+                    name: name.to_string(),
+                    strand,
+                    depth: new_depth,
+                    num_values_after_return: num_values.saturating_sub(inputs)
+                        + arity.outputs as usize,
+                });
+            }
+        }
+
+        while let Some(call) = self
+            .procedure_calls
+            .pop_if(|call| call.strand == strand && call.depth > new_depth)
+        {
+            if num_values != call.num_values_after_return {
+                println!(
+                    "Procedure `{}` returned, leaving {num_values} values on \
+                    the stack instead of {}.",
+                    call.name, call.num_values_after_return,
+                );
+                arity_respected = false;
+            }
+        }
+
+        arity_respected
+    }
+
+    fn effect_is_active(&self) -> bool {
+        let Some((effect, operator)) = self.eval.effect() else {
+            return false;
+        };
+
+        println!("Effect is active: {effect}");
+        print!("Triggered by ");
+        self.print_operator(operator);
+        println!("Use `clear` to clear it and continue the evaluation.");
+
+        true
+    }
+
+    fn first_operator_on_line(
+        &self,
+        line: u32,
+    ) -> Result<OperatorIndex, String> {
+        self.script
+            .operators()
+            .map(|(operator, _)| operator)
+            .find(|operator| {
+                self.source_range(*operator).is_some_and(|range| {
+                    line_and_column(self.source, range.start).0 == line
+                })
+            })
+            .ok_or_else(|| format!("No operator on line {line}."))
+    }
+
+    fn source_range(&self, operator: OperatorIndex) -> Option<Range<usize>> {
+        self.script.map_operator_to_source(&operator).ok()
+    }
+
+    fn print_location(&self) {
+        let operator = self.eval.next_operator();
+
+        if self.source_range(operator).is_some() {
+            print!("Next: ");
+            self.print_operator(operator);
+        } else {
+            println!("Reached the end of the script.");
+        }
+    }
+
+    fn print_operator(&self, operator: OperatorIndex) {
+        let Some(range) = self.source_range(operator) else {
+            println!("operator {operator} (not in script)");
+            return;
+        };
+
+        let routine = match self.script.label_at(operator) {
+            Some((name, _)) => format!(" in `{name}:`"),
+            None => String::new(),
+        };
+
+        let (line, column) = line_and_column(self.source, range.start);
+        println!(
+            "operator {operator}{routine} at {line}:{column}: `{}`",
+            &self.source[range],
+        );
+    }
+
+    fn print_source(&self) {
+        const CONTEXT: u32 = 5;
+
+        let Some(range) = self.source_range(self.eval.next_operator()) else {
+            println!("Reached the end of the script.");
+            return;
+        };
+        let (current_line, _) = line_and_column(self.source, range.start);
+
+        for (line, text) in (1..).zip(self.source.lines()) {
+            if line + CONTEXT < current_line || line > current_line + CONTEXT {
+                continue;
+            }
+
+            let marker = if line == current_line { ">" } else { " " };
+            println!("{marker} {line:>4} | {text}");
+        }
+    }
+
+    fn print_memory(&self, address: u32, count: u32) -> Result<(), String> {
+        let end = address.saturating_add(count);
+        print!("{}", format_memory(&self.eval.memory, address..end));
+
+        // `format_memory` leaves out the addresses that are out of bounds.
+        if let Some(last) = end.checked_sub(1)
+            && count > 0
+            && self.eval.memory.read(last).is_err()
+        {
+            let size = self.eval.memory.values().len();
+            let first_invalid = u32::try_from(size).unwrap_or(u32::MAX);
+
+            return Err(format!(
+                "Address {} is out of bounds.",
+                first_invalid.max(address),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+struct ProcedureCall {
+    name: String,
+    strand: u32,
+
+    /// # The depth of the call stack, while the procedure is running
+    depth: usize,
+
+    /// # How many values the procedure should leave on the operand stack
+    ///
+    /// This is the number of values on the whole stack, not just those that
+    /// the procedure returns.
+    num_values_after_return: usize,
+}
+
+enum Continue {
+    Yes,
+    No,
+}
+
+fn print_help() {
+    println!("Commands:");
+    println!("  step, s [count]          Evaluate the next operator(s)");
+    println!("  back, bs [count]         Undo the previous step(s)");
+    println!("  continue, c              Evaluate until effect or breakpoint");
+    println!("  break, b <line>          Set breakpoint on the given line");
+    println!("  delete, d <line>         Delete breakpoint on the given line");
+    println!("  breakpoints              List all breakpoints");
+    println!("  stack                    Print the operand stack");
+    println!("  calls                    Print the call stack");
+    println!("  memory, m <addr> [count] Print values from memory");
+    println!("  list, l                  Show source around the next operator");
+    println!("  clear                    Clear the active effect");
+    println!("  quit, q                  Quit the debugger");
+    println!();
+    println!("An empty line repeats the previous command.");
+}
+
+fn parse_number(number: &str) -> Result<u32, String> {
+    number
+        .parse()
+        .map_err(|_| format!("Expected a number, got `{number}`."))
+}
+
+fn usage(usage: &str) -> String {
+    format!("Usage: {usage}")
+}
@@ -0,0 +1,92 @@
+//! # Deliver events from the host to the script
+//!
+//! Some hosts need to tell the script about things that happen outside of it,
+//! like keys being pressed. They do that by writing events into a queue in the
+//! script's memory, whenever the script yields. The script can then consume
+//! those events at its own pace.
+//!
+//! ## Layout
+//!
+//! The queue is a ring buffer that occupies [`EventQueue::SIZE`] words,
+//! starting at the address that the host documents:
+//!
+//! - The first word is the write index. Only the host writes to it.
+//! - The second word is the read index. Only the script writes to it.
+//! - The remaining [`EventQueue::CAPACITY`] words are the slots that hold the
+//!   events.
+//!
+//! Both indices refer to slots, and are always smaller than the capacity. The
+//! queue is empty, if they are equal. To consume an event, the script reads
+//! the slot that the read index refers to, then increments the read index,
+//! wrapping around to `0` at the end. Since the capacity is a power of two,
+//! the script can do that via `1 + 31 and`.
+//!
+//! The host writes new events to the slot that the write index refers to, then
+//! increments it in the same way. If the queue is full, meaning the write
+//! index would become equal to the read index, the host drops the event
+//! instead. So at most `CAPACITY - 1` events can be waiting at any time.
+//!
+//! What an event looks like, is up to the host that delivers it.
+
+use anyhow::{Context, bail};
+use stack_assembly::{Memory, Value};
+
+/// # A queue of events in the script's memory
+///
+/// See the [module documentation](self) for details.
+pub struct EventQueue {
+    address: u32,
+}
+
+impl EventQueue {
+    /// # The number of slots in the queue
+    pub const CAPACITY: u32 = 32;
+
+    /// # The number of words that the queue occupies in memory
+    pub const SIZE: usize = 2 + Self::CAPACITY as usize;
+
+    /// # Create a queue that starts at the provided address
+    ///
+    /// The queue is empty initially, as long as the memory at that address
+    /// is zeroed.
+    pub fn new(address: u32) -> Self {
+        Self { address }
+    }
+
+    /// # Add an event to the queue
+    ///
+    /// Returns `false`, if the queue is full and the event was dropped.
+    /// Returns an error, if the queue doesn't fit into the memory, or if an
+    /// index is out of range.
+    pub fn push(
+        &self,
+        memory: &mut Memory,
+        event: Value,
+    ) -> anyhow::Result<bool> {
+        let context = "Event queue doesn't fit into memory.";
+
+        let write = memory.read(self.address).context(context)?.to_u32();
+        let read = memory.read(self.address + 1).context(context)?.to_u32();
+
+        if write >= Self::CAPACITY {
+            bail!("Event queue has invalid write index `{write}`.");
+        }
+        if read >= Self::CAPACITY {
+            bail!("Event queue has invalid read index `{read}`.");
+        }
+
+        let next = (write + 1) % Self::CAPACITY;
+        if next == read {
+            return Ok(false);
+        }
+
+        memory
+            .write(self.address + 2 + write, event)
+            .context(context)?;
+        memory
+            .write(self.address, Value::from(next))
+            .context(context)?;
+
+        Ok(true)
+    }
+}
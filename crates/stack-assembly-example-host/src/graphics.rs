@@ -0,0 +1,170 @@
+//! # Display a region of memory in a window
+//!
+//! The script draws by writing pixels to the framebuffer, a region of memory.
+//! Whenever it evaluates `yield`, the host displays the framebuffer in a
+//! window, then continues the evaluation. So to animate something, a script
+//! draws a frame, yields, draws the next frame, and so on.
+//!
+//! The host limits the number of frames it displays per second. If the script
+//! yields more often than that, the host waits before continuing the
+//! evaluation.
+//!
+//! ## Framebuffer
+//!
+//! The framebuffer has a size of 64 by 64 pixels. It is made up of the words
+//! starting at address `1024`, one per pixel, row by row, starting at the top
+//! left. The red, green, and blue components of each pixel are stored in the
+//! second-lowest, third-lowest, and lowest byte respectively, so `0xff0000` is
+//! red. The highest byte is ignored.
+//!
+//! The words below address `1024` are available to the script for other
+//! purposes, just like in the memory that the regular host provides.
+//!
+//! ## Keyboard input
+//!
+//! Whenever the host displays a frame, it also checks which keys have been
+//! pressed or released since the last one. It delivers an event for each of
+//! those to the event queue at address `5120`, right after the framebuffer.
+//! See the documentation of the host's `events` module, for how a script
+//! consumes those.
+//!
+//! The lower 16 bits of an event are the code of the key. Bit 16 is set, if
+//! the key has been released, and unset, if it has been pressed. So `97` means
+//! that <kbd>A</kbd> has been pressed, and `65633` (`0x10061`) that it has been
+//! released.
+//!
+//! Letters, digits, and the space bar use the ASCII code of their (lowercase)
+//! character. Other keys are:
+//!
+//! - <kbd>Backspace</kbd>: `8`
+//! - <kbd>Tab</kbd>: `9`
+//! - <kbd>Enter</kbd>: `10`
+//! - <kbd>Escape</kbd>: `27`
+//! - <kbd>Left</kbd>, <kbd>Right</kbd>, <kbd>Up</kbd>, <kbd>Down</kbd>: `256`
+//!   to `259`
+//!
+//! The host ignores all other keys.
+
+use anyhow::{Context, bail};
+use minifb::{Key, KeyRepeat, Scale, Window, WindowOptions};
+use stack_assembly::{Effect, Eval, Memory, Script, Value};
+
+use crate::events::EventQueue;
+
+/// # The width of the framebuffer, in pixels
+const WIDTH: usize = 64;
+
+/// # The height of the framebuffer, in pixels
+const HEIGHT: usize = 64;
+
+/// # The address of the first pixel in the framebuffer
+const FRAMEBUFFER: usize = Memory::DEFAULT_SIZE;
+
+/// # The address of the event queue that keyboard input is delivered to
+const EVENTS: usize = FRAMEBUFFER + WIDTH * HEIGHT;
+
+/// # The bit that is set in an event, if the key has been released
+const RELEASED: u32 = 1 << 16;
+
+/// # The maximum number of frames displayed per second
+const FRAMES_PER_SECOND: usize = 60;
+
+/// # Evaluate the provided script, displaying its framebuffer in a window
+pub fn run(source: &str) -> anyhow::Result<()> {
+    let script = Script::compile(source);
+
+    let mut eval = Eval::start_at(script.start());
+    eval.memory = Memory::new(EVENTS + EventQueue::SIZE);
+    eval.load_data(&script);
+
+    let Ok(events) = u32::try_from(EVENTS) else {
+        unreachable!("Event queue is at a small, constant address.");
+    };
+    let events = EventQueue::new(events);
+
+    let mut window = Window::new(
+        "StackAssembly",
+        WIDTH,
+        HEIGHT,
+        WindowOptions {
+            scale: Scale::X8,
+            ..WindowOptions::default()
+        },
+    )
+    .context("Opening window.")?;
+    window.set_target_fps(FRAMES_PER_SECOND);
+
+    while window.is_open() {
+        let (effect, _) = eval.run(&script);
+
+        match effect {
+            Effect::Yield => {
+                let memory = eval.memory.to_u32_slice();
+                window
+                    .update_with_buffer(&memory[FRAMEBUFFER..], WIDTH, HEIGHT)
+                    .context("Displaying framebuffer.")?;
+                drop(memory);
+
+                let pressed = window
+                    .get_keys_pressed(KeyRepeat::No)
+                    .into_iter()
+                    .filter_map(key_code);
+                let released = window
+                    .get_keys_released()
+                    .into_iter()
+                    .filter_map(key_code)
+                    .map(|code| code | RELEASED);
+
+                for event in pressed.chain(released) {
+                    // If the script doesn't keep up with the events, there's
+                    // nothing we can do. Dropping them is fine.
+                    events.push(&mut eval.memory, Value::from(event))?;
+                }
+            }
+            Effect::OutOfOperators | Effect::Return => {
+                // The script has finished. Keep displaying the last frame,
+                // until the user closes the window.
+                while window.is_open() {
+                    window.update();
+                }
+
+                return Ok(());
+            }
+            _ => {
+                let report = eval
+                    .error_report(&script)
+                    .map(|report| report.with_source(source).to_string())
+                    .unwrap_or_default();
+                bail!("{}", report.trim_end());
+            }
+        }
+
+        eval.clear_effect();
+    }
+
+    Ok(())
+}
+
+/// # Determine the code of a key, as delivered to the script
+///
+/// Returns `None`, if the host ignores the key.
+fn key_code(key: Key) -> Option<u32> {
+    let code = match key {
+        Key::Backspace => 8,
+        Key::Tab => 9,
+        Key::Enter => 10,
+        Key::Escape => 27,
+        Key::Space => 32,
+        Key::Left => 256,
+        Key::Right => 257,
+        Key::Up => 258,
+        Key::Down => 259,
+        key if key <= Key::Key9 => u32::from(b'0') + key as u32,
+        key if key <= Key::Z => u32::from(b'a') + (key as u32 - Key::A as u32),
+        _ => {
+            return None;
+        }
+    };
+
+    Some(code)
+}
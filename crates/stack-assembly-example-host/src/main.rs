@@ -1,74 +1,822 @@
-use std::{fs::File, io::Read, path::PathBuf, process, thread, time::Duration};
+mod debug;
+#[cfg(feature = "graphics")]
+mod events;
+#[cfg(feature = "graphics")]
+mod graphics;
+mod profile;
+mod record;
+mod services;
+mod test_runner;
+mod trace;
+mod watch;
 
-use anyhow::Context;
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Read},
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use anyhow::{Context, bail};
 use clap::Parser;
-use stack_assembly::{Effect, Eval, OperandStack, Script};
+use record::Host;
+use services::Services;
+use stack_assembly::{
+    CompileError, CompileOptions, ConformanceTest, Effect, Eval,
+    InvalidArtifact, OperandStack, Script, Value, Warning,
+};
+use stack_assembly_host::{
+    ChromeTrace, describe_location, format_operand_stack, line_and_column,
+    run_until_effect,
+};
+use trace::Trace;
 
 fn main() -> anyhow::Result<()> {
     /// Example host for the StackAssembly programming language
     #[derive(clap::Parser)]
+    #[command(args_conflicts_with_subcommands = true)]
+    #[command(subcommand_negates_reqs = true)]
     struct Args {
-        /// The path to the script that the parser should evaluate
-        path: PathBuf,
+        #[command(subcommand)]
+        command: Option<Command>,
+
+        #[command(flatten)]
+        run: RunArgs,
+    }
+
+    #[derive(clap::Subcommand)]
+    enum Command {
+        /// Compile a script into an artifact, that the host can run directly
+        ///
+        /// Refuses to write the artifact, if the script contains unknown
+        /// identifiers, integers that don't fit into 32 bits, or references to
+        /// labels that don't exist. Pass the artifact to the host instead of
+        /// the script, to run it without compiling it again.
+        Assemble {
+            /// The path to the script that should be compiled
+            path: PathBuf,
+
+            /// The path to write the artifact to
+            #[arg(long, short)]
+            output: PathBuf,
+
+            /// Link the prelude, a collection of useful routines, into the
+            /// artifact
+            #[arg(long)]
+            prelude: bool,
+
+            /// Fuse common sequences of operators, to speed up the evaluation
+            #[arg(long)]
+            optimize: bool,
+        },
+
+        /// Check scripts for problems, without evaluating them
+        ///
+        /// Reports compile errors, like unknown identifiers or references to
+        /// labels that don't exist, as well as everything that the `lint`
+        /// command warns about. Prints each problem as `path:line:column:
+        /// message`, a format that editors understand. Exits with a non-zero
+        /// status, if there are any problems.
+        Check {
+            /// The paths to the scripts that should be checked
+            #[arg(required = true)]
+            paths: Vec<PathBuf>,
+
+            /// Link the prelude into the scripts, so they can use its routines
+            #[arg(long)]
+            prelude: bool,
+        },
+
+        /// Run the conformance tests in a directory
+        ///
+        /// A conformance test is a script that states the effect it should
+        /// trigger, and the values it should leave on the stack, using `.meta
+        /// expect_effect` and `.meta expect_stack`. Exits with a non-zero
+        /// status, if any tests failed.
+        Conformance {
+            /// The directory that contains the tests
+            dir: PathBuf,
+        },
+
+        /// Evaluate a script in an interactive debugger
+        Debug {
+            /// The path to the script that the debugger should evaluate
+            path: PathBuf,
+        },
+
+        /// Evaluate a script that draws to a framebuffer, displaying it
+        ///
+        /// The script writes pixels to a region of memory, which is displayed
+        /// in a window whenever the script yields. Keyboard input is delivered
+        /// to the script as events. See the documentation of the host's
+        /// `graphics` module for details.
+        #[cfg(feature = "graphics")]
+        Graphics {
+            /// The path to the script that should be evaluated
+            path: PathBuf,
+        },
+
+        /// Check a script for code that is likely a mistake
+        ///
+        /// Reports labels that are never referenced or defined more than once,
+        /// code that can never be reached, and operators that can find too few
+        /// values on the stack. Exits with a non-zero status, if there are
+        /// any.
+        Lint {
+            /// The path to the script that should be checked
+            path: PathBuf,
+        },
+
+        /// Run the tests defined in a script
+        ///
+        /// A test is a routine that starts with a label whose name starts with
+        /// `test_`, and ends with `return`. The test passes, if it returns
+        /// without triggering any other effect, like a failed assertion.
+        Test {
+            /// The path to the script whose tests should be run
+            path: PathBuf,
+
+            /// Fail a test, after this many operators have been evaluated
+            #[arg(long, value_name = "N")]
+            max_steps: Option<u64>,
+        },
+
+        /// Compile a script into a standalone WebAssembly module
+        ///
+        /// The module imports `env.yield` and exports `run`. See the
+        /// documentation of `Script::to_wasm` for details on its interface.
+        Wasm {
+            /// The path to the script that should be compiled
+            path: PathBuf,
+
+            /// The path to write the WebAssembly module to
+            #[arg(long, short)]
+            output: PathBuf,
+        },
     }
+
     let args = Args::parse();
 
+    match args.command {
+        Some(Command::Assemble {
+            path,
+            output,
+            prelude,
+            optimize,
+        }) => {
+            let source = read_script(&path)?;
+            let options = CompileOptions {
+                prelude,
+                optimize,
+                ..CompileOptions::default()
+            };
+
+            assemble(&source, options, &output)
+        }
+        Some(Command::Check { paths, prelude }) => check(&paths, prelude),
+        Some(Command::Conformance { dir }) => conformance(&dir),
+        Some(Command::Debug { path }) => {
+            let source = read_script(&path)?;
+            debug::run(&source)
+        }
+        #[cfg(feature = "graphics")]
+        Some(Command::Graphics { path }) => {
+            let source = read_script(&path)?;
+            graphics::run(&source)
+        }
+        Some(Command::Lint { path }) => {
+            let source = read_script(&path)?;
+            lint(&source)
+        }
+        Some(Command::Test { path, max_steps }) => {
+            let source = read_script(&path)?;
+            test_runner::run(&source, max_steps)
+        }
+        Some(Command::Wasm { path, output }) => {
+            let source = read_script(&path)?;
+            let script = Script::compile(&source);
+
+            fs::write(output, script.to_wasm())
+                .context("Writing WebAssembly module.")?;
+
+            Ok(())
+        }
+        None => {
+            let Some(path) = &args.run.path else {
+                unreachable!(
+                    "`clap` requires a path, unless a subcommand is specified."
+                );
+            };
+
+            run(path, &args.run)
+        }
+    }
+}
+
+#[derive(clap::Args)]
+struct RunArgs {
+    /// The path to the script that the host should evaluate
+    #[arg(required = true)]
+    path: Option<PathBuf>,
+
+    /// Integer arguments to pass to the script
+    ///
+    /// Before the evaluation starts, the number of arguments is written to the
+    /// memory at address `896`. The arguments themselves follow at the
+    /// addresses right after that. At most 63 arguments can be passed.
+    #[arg(allow_negative_numbers = true, value_parser = parse_argument)]
+    arguments: Vec<Value>,
+
+    /// Link the prelude, a collection of useful routines, into the script
+    #[arg(long)]
+    prelude: bool,
+
+    /// Fuse common sequences of operators, to speed up the evaluation
+    #[arg(long)]
+    optimize: bool,
+
+    /// Refuse to run the script, if it has problems that compile by default
+    ///
+    /// This rejects unknown identifiers, integers that don't fit into 32 bits,
+    /// references to labels that don't exist, and anything that the `lint`
+    /// command would warn about.
+    #[arg(long)]
+    strict: bool,
+
+    /// Print the most frequently evaluated operators, once evaluation finishes
+    #[arg(long)]
+    profile: bool,
+
+    /// Print the source code annotated with coverage, once evaluation finishes
+    ///
+    /// Each line is prefixed with `+`, if all of its operators have been
+    /// evaluated, `~` if only some have, and `-` if none have.
+    #[arg(long)]
+    coverage: bool,
+
+    /// Print each operator before evaluating it
+    ///
+    /// Alongside the operator, print the top values on the operand stack.
+    #[arg(long)]
+    trace: bool,
+
+    /// Record calls into the provided file, once evaluation finishes
+    ///
+    /// The file uses the Chrome tracing format, which viewers like Perfetto
+    /// can open. Each call shows up as a slice.
+    #[arg(long, value_name = "FILE")]
+    chrome_trace: Option<PathBuf>,
+
+    /// Abort the evaluation after this many operators have been evaluated
+    #[arg(long, value_name = "N")]
+    max_steps: Option<u64>,
+
+    /// Trigger an effect on integer overflow, instead of wrapping around
+    ///
+    /// This affects `+`, `-`, and `*`, which treat their inputs as signed
+    /// integers then.
+    #[arg(long)]
+    trap_on_overflow: bool,
+
+    /// Allow the script to access the files within the provided directory
+    ///
+    /// See the documentation of the host's services for details.
+    #[arg(long, value_name = "DIR")]
+    files: Option<PathBuf>,
+
+    /// Allow the script to connect to the provided address, like `host:port`
+    ///
+    /// Can be passed multiple times, to allow multiple addresses.
+    #[arg(long, value_name = "ADDRESS")]
+    connect: Vec<String>,
+
+    /// Keep the store that scripts can put data into in the provided file
+    ///
+    /// The store allows scripts to keep data across runs. See the
+    /// documentation of the host's services for details.
+    #[arg(long, value_name = "FILE")]
+    store: Option<PathBuf>,
+
+    /// Seed the random number generator, making `random` deterministic
+    ///
+    /// Without this, the values that the `random` service outputs are
+    /// different on every run.
+    #[arg(long, value_name = "SEED")]
+    seed: Option<u64>,
+
+    /// Record the interaction with the host into the provided file
+    ///
+    /// The recording contains every effect the script triggers, and every
+    /// value the host reads and writes while handling it. Use `--replay` to
+    /// re-run the script against the recording.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["replay", "watch"])]
+    record: Option<PathBuf>,
+
+    /// Re-run the script against a recording, instead of providing services
+    ///
+    /// Reports an error, if the evaluation diverges from the recording.
+    #[arg(long, value_name = "FILE", conflicts_with = "watch")]
+    replay: Option<PathBuf>,
+
+    /// Restart the evaluation whenever the script file changes
+    #[arg(long)]
+    watch: bool,
+
+    /// Preserve the memory when restarting the evaluation in watch mode
+    ///
+    /// By default, the evaluation restarts from scratch. With this flag, only
+    /// the code is reloaded, while the memory keeps its values.
+    #[arg(long, requires = "watch")]
+    preserve_memory: bool,
+}
+
+fn parse_argument(argument: &str) -> anyhow::Result<Value> {
+    // This mirrors the rules for integers in the language itself.
+    let value = if let Some(hex) = argument.strip_prefix("0x") {
+        i32::from_str_radix(hex, 16)
+            .map(Value::from)
+            .or_else(|_| u32::from_str_radix(hex, 16).map(Value::from))
+    } else {
+        argument
+            .parse::<i32>()
+            .map(Value::from)
+            .or_else(|_| argument.parse::<u32>().map(Value::from))
+    };
+
+    value.context("Expected a 32-bit integer.")
+}
+
+fn read_script(path: &Path) -> anyhow::Result<String> {
     let mut script = String::new();
-    File::open(args.path)
+    File::open(path)
         .context("Opening script file.")?
         .read_to_string(&mut script)
         .context("Reading from script file.")?;
 
-    let script = Script::compile(&script);
+    Ok(script)
+}
+
+fn lint(source: &str) -> anyhow::Result<()> {
+    let script = Script::compile(source);
+    let warnings = script.lint();
+
+    for warning in &warnings {
+        match describe_location(source, &script, warning.operator()) {
+            Some(location) => eprintln!("Warning: {warning} at {location}"),
+            None => eprintln!("Warning: {warning} at end of script"),
+        }
+    }
+
+    if !warnings.is_empty() {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn conformance(dir: &Path) -> anyhow::Result<()> {
+    let tests = ConformanceTest::discover(dir)
+        .with_context(|| format!("Reading tests from `{}`.", dir.display()))?;
 
+    let mut num_failed = 0;
+
+    for test in &tests {
+        match test.run() {
+            Ok(()) => {
+                println!("test {} ... ok", test.path.display());
+            }
+            Err(failure) => {
+                println!("test {} ... FAILED", test.path.display());
+                println!("    {failure}");
+                num_failed += 1;
+            }
+        }
+    }
+
+    println!();
+    println!("{} passed; {num_failed} failed", tests.len() - num_failed);
+
+    if num_failed > 0 {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn check(paths: &[PathBuf], prelude: bool) -> anyhow::Result<()> {
+    let options = CompileOptions {
+        prelude,
+        ..CompileOptions::default()
+    };
+    let mut found_problems = false;
+
+    for path in paths {
+        let source = read_script(path)?;
+        let script = Script::compile_with_options(&source, options);
+
+        for err in script.check() {
+            let (severity, range) = match &err {
+                CompileError::IntegerOutOfRange { range }
+                | CompileError::UnknownIdentifier { range }
+                | CompileError::InvalidReference { range }
+                | CompileError::UnmatchedKeyword { range }
+                | CompileError::InvalidProcedure { range }
+                | CompileError::InvalidDirective { range } => {
+                    ("error", Some(range.clone()))
+                }
+                CompileError::Warning { warning, range } => {
+                    // Point to the label itself, rather than the operator it
+                    // refers to.
+                    let label = match warning {
+                        Warning::UnusedLabel { name, operator }
+                        | Warning::DuplicateLabel { name, operator } => script
+                            .label_definitions()
+                            .find(|(label, label_operator, _)| {
+                                label == name && label_operator == operator
+                            })
+                            .map(|(_, _, range)| range),
+                        _ => None,
+                    };
+
+                    ("warning", label.or(range.clone()))
+                }
+            };
+
+            let offset = range.map_or(source.len(), |range| range.start);
+            let (line, column) = line_and_column(&source, offset);
+
+            println!("{}:{line}:{column}: {severity}: {err}", path.display());
+            found_problems = true;
+        }
+    }
+
+    if found_problems {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn assemble(
+    source: &str,
+    options: CompileOptions,
+    output: &Path,
+) -> anyhow::Result<()> {
+    let script = Script::compile_with_options(source, options);
+
+    // Warnings are no reason to reject the script. Strict mode would do that,
+    // but the `lint` command is there for anyone who wants that.
+    let errors = script
+        .check()
+        .into_iter()
+        .filter(|err| !matches!(err, CompileError::Warning { .. }))
+        .collect::<Vec<_>>();
+
+    for err in &errors {
+        print_compile_error(source, err);
+    }
+    if !errors.is_empty() {
+        process::exit(2);
+    }
+
+    fs::write(output, script.to_bytes()).context("Writing artifact.")?;
+
+    Ok(())
+}
+
+fn run(path: &Path, args: &RunArgs) -> anyhow::Result<()> {
+    if args.watch {
+        return watch::run(path, args);
+    }
+
+    let bytes = fs::read(path).context("Reading script file.")?;
+    let (source, script) = match Script::from_bytes(&bytes) {
+        Ok(script) => {
+            // Artifacts don't contain the source text. That's fine, as they
+            // don't contain a source map either.
+            (String::new(), Some(script))
+        }
+        Err(InvalidArtifact::NotAnArtifact) => {
+            let source = String::from_utf8(bytes)
+                .context("Script file is neither text nor an artifact.")?;
+            (source, None)
+        }
+        Err(err) => {
+            return Err(err).context("Loading artifact.");
+        }
+    };
+
+    let mut eval = new_eval(args)?;
+
+    let mut host = match (&args.record, &args.replay) {
+        (Some(_), _) => Host::record(new_services(args)?),
+        (None, Some(path)) => Host::replay(path)?,
+        (None, None) => Host::live(new_services(args)?),
+    };
+
+    let status = match script {
+        Some(script) => evaluate_script(
+            &source,
+            &script,
+            args,
+            &mut eval,
+            &mut host,
+            &mut || false,
+        ),
+        None => evaluate(&source, args, &mut eval, &mut host, &mut || false),
+    };
+    let Some(status) = status else {
+        unreachable!("Evaluation can't be interrupted, if we never do that.");
+    };
+
+    if let Some(path) = &args.record {
+        host.save(path)?;
+    }
+
+    process::exit(status);
+}
+
+fn new_services(args: &RunArgs) -> anyhow::Result<Services> {
+    let mut services = Services::default();
+
+    if let Some(root) = &args.files {
+        services = services.with_files(root.clone());
+    }
+    if !args.connect.is_empty() {
+        services = services.with_network(args.connect.clone());
+    }
+    if let Some(seed) = args.seed {
+        services = services.with_seed(seed);
+    }
+    if let Some(path) = &args.store {
+        services = services.with_store(path.clone())?;
+    }
+
+    Ok(services)
+}
+
+fn new_eval(args: &RunArgs) -> anyhow::Result<Eval> {
     let mut eval = Eval::new();
 
-    loop {
-        let (effect, _) = eval.run(&script);
+    if args.arguments.len() > services::ARGUMENTS_CAPACITY {
+        bail!(
+            "Too many arguments. At most {} can be passed to a script.",
+            services::ARGUMENTS_CAPACITY,
+        );
+    }
+
+    eval.memory
+        .write_length_prefixed(services::ARGUMENTS, &args.arguments)
+        .context("Too many arguments to fit in memory.")?;
+    eval.set_fuel(args.max_steps);
+    eval.set_trap_on_overflow(args.trap_on_overflow);
+
+    Ok(eval)
+}
+
+/// # Evaluate the provided script until it finishes
+///
+/// Returns the exit status, or `None`, if `interrupt` returned `true` before
+/// the evaluation could finish.
+fn evaluate(
+    source: &str,
+    args: &RunArgs,
+    eval: &mut Eval,
+    host: &mut Host,
+    interrupt: &mut dyn FnMut() -> bool,
+) -> Option<i32> {
+    let options = CompileOptions {
+        prelude: args.prelude,
+        optimize: args.optimize,
+        ..CompileOptions::default()
+    };
+    let script = if args.strict {
+        Script::try_compile(source, options)
+    } else {
+        Ok(Script::compile_with_options(source, options))
+    };
+    let script = match script {
+        Ok(script) => script,
+        Err(err) => {
+            print_compile_error(source, &err);
+            return Some(2);
+        }
+    };
+
+    evaluate_script(source, &script, args, eval, host, interrupt)
+}
+
+/// # Evaluate the provided, already compiled, script until it finishes
+///
+/// Works like [`evaluate`], but skips the compilation.
+fn evaluate_script(
+    source: &str,
+    script: &Script,
+    args: &RunArgs,
+    eval: &mut Eval,
+    host: &mut Host,
+    interrupt: &mut dyn FnMut() -> bool,
+) -> Option<i32> {
+    if let Err(err) = services::check_requirements(script, eval) {
+        eprintln!("{err}");
+        return Some(2);
+    }
+
+    eval.set_next_operator(script.start());
+    eval.load_data(script);
+
+    if args.profile {
+        eval.enable_profiling();
+    }
+    if args.coverage {
+        eval.enable_coverage();
+    }
+    if args.trace {
+        eval.add_tracer(Arc::new(Mutex::new(Trace::new(source, script))));
+    }
+    let chrome_trace = args.chrome_trace.as_ref().map(|path| {
+        let trace = Arc::new(Mutex::new(ChromeTrace::new(source, script)));
+        eval.add_tracer(trace.clone());
+        (path, trace)
+    });
+
+    let status = loop {
+        let (effect, operator) = run_until_effect(script, eval, interrupt)?;
+
+        if let Err(err) = host.handle_effect(eval, effect, operator) {
+            eprintln!();
+            eprintln!("Error handling effect ({effect}): {err}");
+
+            print_operand_stack(&eval.operand_stack);
+
+            break 2;
+        }
 
         match effect {
             Effect::OutOfOperators | Effect::Return => {
+                // The value on top of the stack, if any, becomes the exit
+                // status. This allows scripts to signal success or failure to
+                // shell scripts or CI jobs that run them.
+                let status = eval
+                    .operand_stack
+                    .values
+                    .last()
+                    .map(|value| value.to_i32())
+                    .unwrap_or(0);
+
                 eprintln!();
-                eprintln!("Evaluation has finished.");
+                eprintln!("Evaluation has finished with exit status {status}.");
 
                 print_operand_stack(&eval.operand_stack);
 
-                process::exit(0);
+                break status;
             }
             Effect::Yield => {
-                print_operand_stack(&eval.operand_stack);
+                // The host has already provided the service above. But the
+                // script might have asked to continue later.
+                host.wait();
                 eval.clear_effect();
 
-                // Let's not execute scripts that fast, to give the user a
-                // chance to read the output.
-                thread::sleep(Duration::from_millis(20));
-
                 continue;
             }
-            effect => {
+            Effect::OutOfFuel => {
                 eprintln!();
-                eprintln!("Script triggered effect: {effect:?}");
+                eprintln!(
+                    "Evaluation aborted, after reaching the maximum number of \
+                    steps."
+                );
 
                 print_operand_stack(&eval.operand_stack);
 
-                process::exit(2);
+                break 2;
             }
+            _ => {
+                eprintln!();
+                if let Some(report) = eval.error_report(script) {
+                    eprint!("{}", report.with_source(source));
+                }
+
+                break 2;
+            }
+        }
+    };
+
+    if let Some(profile) = eval.profile() {
+        profile::print(profile, source, script);
+    }
+    if let Some(coverage) = eval.coverage() {
+        eprintln!();
+        eprint!("{}", coverage.annotate_source(script, source));
+    }
+    if let Some((path, trace)) = chrome_trace {
+        let trace = trace.lock().unwrap_or_else(PoisonError::into_inner);
+        let result = File::create(path)
+            .and_then(|file| trace.write_json(BufWriter::new(file)));
+
+        if let Err(err) = result {
+            eprintln!();
+            eprintln!("Error writing trace to `{}`: {err}", path.display());
+        }
+    }
+
+    Some(status)
+}
+
+fn print_compile_error(source: &str, err: &CompileError) {
+    let range = match err {
+        CompileError::IntegerOutOfRange { range }
+        | CompileError::UnknownIdentifier { range }
+        | CompileError::InvalidReference { range }
+        | CompileError::UnmatchedKeyword { range }
+        | CompileError::InvalidProcedure { range }
+        | CompileError::InvalidDirective { range } => Some(range),
+        CompileError::Warning { range, .. } => range.as_ref(),
+    };
+
+    match range {
+        Some(range) => {
+            let (line, column) = line_and_column(source, range.start);
+            eprintln!(
+                "Compile error: {err} at {line}:{column}: `{}`",
+                &source[range.clone()],
+            );
+        }
+        None => {
+            eprintln!("Compile error: {err} at end of script");
         }
     }
 }
 
 fn print_operand_stack(operand_stack: &OperandStack) {
-    let mut values = operand_stack.values.iter().peekable();
+    println!("Operand Stack: {}", format_operand_stack(operand_stack));
+}
 
-    print!("Operand Stack: ");
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
 
-    while let Some(value) = values.next() {
-        print!("{value:?}");
+    use crate::{
+        RunArgs, evaluate, new_eval, record::Host, services::Services,
+    };
 
-        if values.peek().is_some() {
-            print!(" ");
-        }
+    #[derive(clap::Parser)]
+    struct TestArgs {
+        #[command(flatten)]
+        run: RunArgs,
     }
 
-    println!();
+    #[test]
+    fn regions_and_arguments_do_not_overlap() {
+        // The host writes the arguments into the memory it reserves, so they
+        // don't overwrite the values that the script's regions start out with.
+
+        let source = "
+            @table read 7 = assert
+            @table 2 + read 9 = assert
+            @count read 3 = assert
+            @greeting read 2 = assert
+            @greeting 1 + read 104 = assert
+
+            897 read 5 = assert
+            898 read 6 = assert
+            896 read
+
+            .data table 7 8 9
+            .var count 3
+            .string greeting \"hi\"
+        ";
+        let args =
+            TestArgs::parse_from(["host", "script.stack", "5", "6", "4"]).run;
+
+        let Ok(mut eval) = new_eval(&args) else {
+            unreachable!("Arguments fit into memory.");
+        };
+        let mut host = Host::live(Services::default());
+        let status =
+            evaluate(source, &args, &mut eval, &mut host, &mut || false);
+
+        assert_eq!(status, Some(3));
+    }
+
+    #[test]
+    fn regions_must_not_overlap_reserved_memory() {
+        // A script whose regions reach into the memory that the host reserves
+        // is refused, instead of having its values overwritten.
+
+        let source = "
+            .zero buffer 896
+            .var count 3
+        ";
+        let args = TestArgs::parse_from(["host", "script.stack"]).run;
+
+        let Ok(mut eval) = new_eval(&args) else {
+            unreachable!("Arguments fit into memory.");
+        };
+        let mut host = Host::live(Services::default());
+        let status =
+            evaluate(source, &args, &mut eval, &mut host, &mut || false);
+
+        assert_eq!(status, Some(2));
+    }
 }
@@ -0,0 +1,18 @@
+use stack_assembly::{Profile, Script};
+use stack_assembly_host::describe_location;
+
+/// # The number of operators to print
+const NUM_OPERATORS: usize = 10;
+
+/// # Print the most frequently evaluated operators
+pub fn print(profile: &Profile, source: &str, script: &Script) {
+    eprintln!();
+    eprintln!("Most frequently evaluated operators:");
+
+    for (operator, count) in profile.hottest().take(NUM_OPERATORS) {
+        let location = describe_location(source, script, operator)
+            .unwrap_or_else(|| format!("operator {operator}"));
+
+        eprintln!("{count:>12} {location}");
+    }
+}
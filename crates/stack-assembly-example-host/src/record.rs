@@ -0,0 +1,319 @@
+//! # Deterministic record and replay of the interaction with the host
+//!
+//! When recording, the host logs every effect that the script triggers,
+//! alongside every value it reads from, or writes to, the operand stack and the
+//! memory while handling that effect. When replaying, the host re-runs the
+//! script against such a log, instead of providing its services for real. This
+//! makes bugs that involve the host reproducible without it.
+//!
+//! ## Format
+//!
+//! The log is a text file with one entry per line. Each effect starts with an
+//! `effect` line, followed by the accesses that happened while handling it:
+//!
+//! ```text
+//! effect 12 Yield
+//! pop 1
+//! read 100 72
+//! push 1
+//! write 100 72
+//! ```
+//!
+//! The `effect` line contains the index of the operator that triggered the
+//! effect, and the effect itself. The access lines contain the address, if
+//! applicable, and the value. All numbers are decimal.
+
+use std::{fs, path::Path, vec};
+
+use anyhow::{Context, bail};
+use stack_assembly::{Effect, Eval, OperatorIndex, Value};
+
+use crate::services::Services;
+
+/// # An access to the operand stack or memory, performed by the host
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Access {
+    Pop { value: Value },
+    Push { value: Value },
+    Read { address: u32, value: Value },
+    Write { address: u32, value: Value },
+}
+
+/// # Handles the effects that the script triggers
+pub enum Host {
+    /// # Provide services for real, optionally recording the interaction
+    Live {
+        services: Services,
+        recording: Option<Recording>,
+    },
+
+    /// # Replay a recorded interaction, instead of providing services
+    Replay { events: vec::IntoIter<Event> },
+}
+
+impl Host {
+    /// # Provide services for real, without recording
+    pub fn live(services: Services) -> Self {
+        Self::Live {
+            services,
+            recording: None,
+        }
+    }
+
+    /// # Provide services for real, recording the interaction
+    pub fn record(services: Services) -> Self {
+        Self::Live {
+            services,
+            recording: Some(Recording::default()),
+        }
+    }
+
+    /// # Replay the recording stored in the provided file
+    pub fn replay(path: &Path) -> anyhow::Result<Self> {
+        let recording = Recording::load(path)?;
+
+        Ok(Self::Replay {
+            events: recording.events.into_iter(),
+        })
+    }
+
+    /// # Handle an effect that the script triggered
+    ///
+    /// If the effect is [`Effect::Yield`], this provides the requested
+    /// service. The caller is still responsible for clearing the effect.
+    pub fn handle_effect(
+        &mut self,
+        eval: &mut Eval,
+        effect: Effect,
+        operator: OperatorIndex,
+    ) -> anyhow::Result<()> {
+        match self {
+            Self::Live {
+                services,
+                recording,
+            } => {
+                let mut accesses = Vec::new();
+
+                let result = if effect == Effect::Yield {
+                    services.handle_yield_and_log(eval, &mut accesses)
+                } else {
+                    Ok(())
+                };
+
+                if let Some(recording) = recording {
+                    recording.events.push(Event {
+                        effect: format!("{effect:?}"),
+                        operator: operator.value(),
+                        accesses,
+                    });
+                }
+
+                result
+            }
+            Self::Replay { events } => {
+                let Some(event) = events.next() else {
+                    bail!(
+                        "Script triggered `{effect:?}` at operator \
+                        `{operator}`, but the recording has ended."
+                    );
+                };
+
+                let actual = format!("{effect:?}");
+                if event.effect != actual || event.operator != operator.value()
+                {
+                    bail!(
+                        "Script triggered `{actual}` at operator `{operator}`, \
+                        but the recording expected `{}` at operator `{}`.",
+                        event.effect,
+                        event.operator,
+                    );
+                }
+
+                for access in event.accesses {
+                    replay_access(eval, access)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// # Wait until the script is ready to continue
+    ///
+    /// See [`Services::wait`]. When replaying, this returns right away. The
+    /// recording already contains the times that the script observed, so
+    /// there's no need to actually wait.
+    pub fn wait(&mut self) {
+        if let Self::Live {
+            services,
+            recording: _,
+        } = self
+        {
+            services.wait();
+        }
+    }
+
+    /// # Store the recording in the provided file, if recording
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Self::Live {
+            services: _,
+            recording: Some(recording),
+        } = self
+        {
+            recording.save(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn replay_access(eval: &mut Eval, access: Access) -> anyhow::Result<()> {
+    match access {
+        Access::Pop { value } => {
+            let actual = eval.operand_stack.pop().ok();
+            if actual != Some(value) {
+                bail!("Expected to pop `{value:?}`, found `{actual:?}`.");
+            }
+        }
+        Access::Push { value } => {
+            eval.operand_stack.push(value);
+        }
+        Access::Read { address, value } => {
+            let actual = eval.memory.read(address).ok();
+            if actual != Some(value) {
+                bail!(
+                    "Expected to read `{value:?}` from address `{address}`, \
+                    found `{actual:?}`."
+                );
+            }
+        }
+        Access::Write { address, value } => {
+            eval.memory.write(address, value).map_err(|_| {
+                anyhow::anyhow!("Address `{address}` is out of bounds.")
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// # A log of the interaction between a script and the host
+#[derive(Default)]
+pub struct Recording {
+    events: Vec<Event>,
+}
+
+impl Recording {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path).context("Reading recording.")?;
+        let mut events = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+
+            parse_line(line, &mut events).with_context(|| {
+                format!("Parsing line {line_number} of recording.")
+            })?;
+        }
+
+        Ok(Self { events })
+    }
+
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut lines = Vec::new();
+
+        for event in &self.events {
+            let Event {
+                effect,
+                operator,
+                accesses,
+            } = event;
+
+            lines.push(format!("effect {operator} {effect}"));
+
+            for access in accesses {
+                let line = match *access {
+                    Access::Pop { value } => format!("pop {}", value.to_u32()),
+                    Access::Push { value } => {
+                        format!("push {}", value.to_u32())
+                    }
+                    Access::Read { address, value } => {
+                        format!("read {address} {}", value.to_u32())
+                    }
+                    Access::Write { address, value } => {
+                        format!("write {address} {}", value.to_u32())
+                    }
+                };
+
+                lines.push(line);
+            }
+        }
+
+        let mut text = lines.join("\n");
+        text.push('\n');
+
+        fs::write(path, text).context("Writing recording.")?;
+
+        Ok(())
+    }
+}
+
+fn parse_line(line: &str, events: &mut Vec<Event>) -> anyhow::Result<()> {
+    let mut words = line.split_whitespace();
+
+    let Some(kind) = words.next() else {
+        // Ignoring empty lines.
+        return Ok(());
+    };
+
+    let mut number = || -> anyhow::Result<u32> {
+        let word = words.next().context("Expected number.")?;
+        word.parse()
+            .with_context(|| format!("Invalid number `{word}`."))
+    };
+
+    let access = match kind {
+        "effect" => {
+            let operator = number()?;
+            let effect = words.next().context("Expected effect.")?;
+
+            events.push(Event {
+                effect: effect.to_string(),
+                operator,
+                accesses: Vec::new(),
+            });
+
+            return Ok(());
+        }
+        "pop" => Access::Pop {
+            value: number()?.into(),
+        },
+        "push" => Access::Push {
+            value: number()?.into(),
+        },
+        "read" => Access::Read {
+            address: number()?,
+            value: number()?.into(),
+        },
+        "write" => Access::Write {
+            address: number()?,
+            value: number()?.into(),
+        },
+        kind => {
+            bail!("Unknown entry `{kind}`.");
+        }
+    };
+
+    let Some(event) = events.last_mut() else {
+        bail!("Access is not preceded by an effect.");
+    };
+    event.accesses.push(access);
+
+    Ok(())
+}
+
+pub struct Event {
+    effect: String,
+    operator: u32,
+    accesses: Vec<Access>,
+}
@@ -0,0 +1,959 @@
+//! # The services that this host provides to scripts
+//!
+//! A script requests a service by pushing the service's inputs, then the
+//! service's ID, then evaluating `yield`. The host pops the ID and the inputs
+//! from the operand stack, provides the service, and pushes any outputs.
+//! Afterwards, it continues the evaluation.
+//!
+//! Some services exchange bytes with the script. These are stored in memory,
+//! one byte per word, and passed to the service as the address of the first
+//! byte and the number of bytes. For text, that's the [convention for strings]
+//! that the library defines.
+//!
+//! ## Services
+//!
+//! | ID | Name           | Inputs                            | Outputs  |
+//! |----|----------------|-----------------------------------|----------|
+//! | 1  | `write`        | `address length`                  |          |
+//! | 2  | `read`         | `address capacity`                | `length` |
+//! | 3  | `flush`        |                                   |          |
+//! | 4  | `open`         | `address length mode`             | `handle` |
+//! | 5  | `read_file`    | `handle address capacity`         | `length` |
+//! | 6  | `write_file`   | `handle address length`           |          |
+//! | 7  | `close`        | `handle`                          |          |
+//! | 8  | `connect`      | `address length`                  | `handle` |
+//! | 9  | `read_socket`  | `handle address capacity`         | `length` |
+//! | 10 | `write_socket` | `handle address length`           |          |
+//! | 11 | `disconnect`   | `handle`                          |          |
+//! | 12 | `time`         |                                   | `time`   |
+//! | 13 | `sleep_until`  | `time`                            |          |
+//! | 14 | `random`       |                                   | `value`  |
+//! | 15 | `get`          | `key key_length address capacity` | `length` |
+//! | 16 | `put`          | `key key_length address length`   |          |
+//! | 17 | `delete`       | `key key_length`                  |          |
+//!
+//! - `write` writes the bytes from the provided memory region to stdout.
+//! - `read` reads up to `capacity` bytes from stdin, storing them in memory
+//!   starting at `address`. It outputs the number of bytes read, which is `0`
+//!   if stdin has been closed.
+//! - `flush` writes the bytes in the console buffer to stdout, then empties
+//!   it. See below.
+//! - `open` opens the file whose path is stored in the provided memory
+//!   region. See below.
+//! - `read_file` and `write_file` work like `read` and `write`, but access the
+//!   file identified by `handle` instead of stdin and stdout.
+//! - `close` closes the file identified by `handle`. Afterwards, the handle is
+//!   no longer valid.
+//! - `connect` opens a TCP connection to the address stored in the provided
+//!   memory region. See below.
+//! - `read_socket` and `write_socket` work like `read_file` and `write_file`,
+//!   but access the connection identified by `handle`. Unlike `read_file`,
+//!   `read_socket` doesn't wait until `capacity` bytes are available. It
+//!   outputs `0` once the other side has closed the connection.
+//! - `disconnect` closes the connection identified by `handle`.
+//! - `time` outputs the current time. See below.
+//! - `sleep_until` suspends the evaluation until the provided time. See below.
+//! - `random` outputs a random value. See below.
+//! - `get`, `put`, and `delete` access the store. See below.
+//!
+//! All services block until they are done. While they do, the evaluation is
+//! suspended, as the script is waiting for `yield` to return. A host that
+//! doesn't want to block could instead keep the [`Eval`] around, wait for the
+//! I/O to finish asynchronously, and only then provide the outputs and
+//! continue the evaluation (see [`Eval::resume`]).
+//!
+//! ## Console
+//!
+//! Instead of passing addresses to `write`, a script can also print text by
+//! writing to a designated region of memory, the console buffer, then
+//! requesting `flush`. The console buffer is made up of the following words:
+//!
+//! | Address        | Content                           |
+//! |----------------|-----------------------------------|
+//! | `960`          | the number of bytes in the buffer |
+//! | `961` - `1023` | the bytes, one per word           |
+//!
+//! After writing the bytes, `flush` sets the number of bytes to `0`.
+//!
+//! ## Reserved memory
+//!
+//! The host reserves the addresses from `896` to `1023` for its own use. Those
+//! hold the console buffer, and the arguments that are passed to the script:
+//!
+//! | Address       | Content                 |
+//! |---------------|-------------------------|
+//! | `896`         | the number of arguments |
+//! | `897` - `959` | the arguments           |
+//!
+//! The regions that a script reserves using directives, like `.data`, are
+//! placed starting at address `0`. The host refuses to run the script, if any
+//! of them overlaps the reserved addresses.
+//!
+//! ## Files
+//!
+//! Scripts can only access files, if the host has been given a directory to
+//! provide access to (via `--files`). The path passed to `open` is relative to
+//! that directory, and must not lead outside of it. Trying to open any other
+//! path is an error.
+//!
+//! The `mode` passed to `open` is `0` to open an existing file for reading,
+//! or `1` to create a file for writing, replacing any previous contents. If
+//! the file can't be opened, `open` outputs `0`. Otherwise, it outputs a
+//! handle that identifies the file to the other file services.
+//!
+//! Handles are only meaningful to the host. Scripts can't access any file they
+//! don't have a handle for, and can't forge one, as using any value that the
+//! host didn't output as a handle is an error.
+//!
+//! ## Network
+//!
+//! Scripts can only open connections to addresses that the host has been
+//! explicitly allowed to connect to (via `--connect`). The address passed to
+//! `connect` is a host name or IP address followed by a port, like
+//! `example.com:80`, and must match one of the allowed addresses exactly.
+//! Trying to connect to any other address is an error.
+//!
+//! If the connection can't be established, `connect` outputs `0`. Otherwise,
+//! it outputs a handle that identifies the connection to the other network
+//! services. Handles for connections work like handles for files, but the two
+//! can't be used interchangeably.
+//!
+//! ## Time
+//!
+//! `time` outputs the number of milliseconds since the host started. This time
+//! is monotonic, meaning it never goes backwards, except that it wraps around
+//! to `0` after about 49 days, as it is stored in a single word.
+//!
+//! `sleep_until` takes a time in the same format, and continues the
+//! evaluation once that time has come. If it already has, the evaluation
+//! continues right away. To account for the wrapping, times that are more
+//! than about 24 days in the future are considered to be in the past.
+//!
+//! Together, these services allow a script to do something at a regular
+//! interval, without wasting resources by repeatedly checking the time:
+//!
+//! ```text
+//! 12 yield       # get the current time
+//! loop:
+//!     # do something
+//!     1000 +     # compute the time of the next iteration
+//!     0 copy 13 yield
+//!     @loop jump
+//! ```
+//!
+//! ## Random numbers
+//!
+//! Each bit of the value that `random` outputs is equally likely to be `0` or
+//! `1`. To get a number in a smaller range, a script can mask the value, for
+//! example using `7 and` to get a number from `0` to `7`.
+//!
+//! The values come from a pseudorandom number generator, which is not suitable
+//! for cryptography. By default, the host seeds it differently on every run.
+//! Pass `--seed` to make it output the same sequence every time instead. When
+//! running tests, the host always uses the same seed for each test, so tests
+//! that use `random` are reproducible.
+//!
+//! ## Store
+//!
+//! Scripts can keep data across runs, by putting it into the store. The store
+//! is a file (passed to the host via `--store`) that maps keys to values. Both
+//! are sequences of bytes, which are passed to the services like any other
+//! bytes: as the address of the first byte, followed by the number of bytes.
+//!
+//! - `get` looks up the value for the key, and stores it in memory starting at
+//!   `address`, but no more than `capacity` bytes of it. It outputs the length
+//!   of the whole value, which may be larger than `capacity`, or `-1`, if the
+//!   store doesn't contain the key.
+//! - `put` stores the bytes from the provided memory region as the value for
+//!   the key, replacing any previous value.
+//! - `delete` removes the key and its value from the store. If the store
+//!   doesn't contain the key, it does nothing.
+//!
+//! The host writes any changes to the file right away. The file is a text
+//! file, with one line per key, containing the key and the value as
+//! hexadecimal numbers, separated by a space. If it doesn't exist, the store
+//! starts out empty.
+//!
+//! ## Requirements
+//!
+//! A script can declare what it requires from the host, using metadata
+//! entries. The host refuses to run the script, if it can't satisfy them.
+//!
+//! - `.meta memory <words>` requires a memory of at least that many words.
+//! - `.meta service <name>` requires the service with that name.
+//!
+//! [convention for strings]: stack_assembly::Memory#strings
+
+use std::{
+    collections::{BTreeMap, hash_map::RandomState},
+    fs::{self, File},
+    hash::{BuildHasher, Hasher},
+    io::{self, Read, Write},
+    net::TcpStream,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, bail};
+use stack_assembly::{Eval, Script, Value};
+
+use crate::record::Access;
+
+/// # The services that this host provides, and their state
+#[derive(Default)]
+pub struct Services {
+    clock: Clock,
+    random: Random,
+    files: Option<Files>,
+    network: Option<Network>,
+    store: Option<Store>,
+}
+
+impl Services {
+    /// # Make `random` output the sequence of values for the provided seed
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.random = Random { state: seed };
+        self
+    }
+
+    /// # Provide access to the files within the provided directory
+    pub fn with_files(mut self, root: PathBuf) -> Self {
+        self.files = Some(Files {
+            root,
+            open: Handles::default(),
+        });
+        self
+    }
+
+    /// # Allow connecting to the provided addresses
+    pub fn with_network(mut self, allowed: Vec<String>) -> Self {
+        self.network = Some(Network {
+            allowed,
+            open: Handles::default(),
+        });
+        self
+    }
+
+    /// # Keep the store in the provided file
+    ///
+    /// Reads the current contents of the store from the file, if it exists.
+    pub fn with_store(mut self, path: PathBuf) -> anyhow::Result<Self> {
+        self.store = Some(Store::load(path)?);
+        Ok(self)
+    }
+
+    /// # Provide the service that the script requested by yielding
+    pub fn handle_yield(&mut self, eval: &mut Eval) -> anyhow::Result<()> {
+        self.handle_yield_and_log(eval, &mut Vec::new())
+    }
+
+    /// # Provide the requested service, logging how it accesses the evaluation
+    ///
+    /// Appends every access to the operand stack or memory to `log`, in the
+    /// order they happen. This includes the accesses that happened before any
+    /// error.
+    pub fn handle_yield_and_log(
+        &mut self,
+        eval: &mut Eval,
+        log: &mut Vec<Access>,
+    ) -> anyhow::Result<()> {
+        let mut io = Io { eval, log };
+        self.clock.wake_time = None;
+
+        let service = pop(&mut io, "service ID")?.to_u32();
+
+        match service {
+            1 => write(&mut io),
+            2 => read(&mut io),
+            3 => flush(&mut io),
+            4 => open(&mut io, self.files()?),
+            5 => read_file(&mut io, self.files()?),
+            6 => write_file(&mut io, self.files()?),
+            7 => close(&mut io, self.files()?),
+            8 => connect(&mut io, self.network()?),
+            9 => read_socket(&mut io, self.network()?),
+            10 => write_socket(&mut io, self.network()?),
+            11 => disconnect(&mut io, self.network()?),
+            12 => time(&mut io, &self.clock),
+            13 => sleep_until(&mut io, &mut self.clock),
+            14 => random(&mut io, &mut self.random),
+            15 => get(&mut io, self.store()?),
+            16 => put(&mut io, self.store()?),
+            17 => delete(&mut io, self.store()?),
+            service => bail!("Script requested unknown service `{service}`."),
+        }
+    }
+
+    /// # Wait until the script is ready to continue
+    ///
+    /// If the last service that the script requested was `sleep_until`, this
+    /// blocks until the requested time. Otherwise, it returns right away.
+    ///
+    /// This is separate from providing the service, so a host that doesn't
+    /// want to block could instead check [`Services::wake_time`], and resume
+    /// the evaluation once that time has come.
+    pub fn wait(&mut self) {
+        if let Some(wake_time) = self.wake_time() {
+            thread::sleep(wake_time.saturating_duration_since(Instant::now()));
+        }
+    }
+
+    /// # Take the time at which the script wants to continue, if any
+    ///
+    /// Returns `None`, unless the last service that the script requested was
+    /// `sleep_until`. Afterwards, returns `None` until the script requests
+    /// `sleep_until` again.
+    pub fn wake_time(&mut self) -> Option<Instant> {
+        self.clock.wake_time.take()
+    }
+
+    fn files(&mut self) -> anyhow::Result<&mut Files> {
+        self.files.as_mut().context(
+            "Script requested access to files, but the host doesn't provide \
+            access to any directory. Use `--files` to do so.",
+        )
+    }
+
+    fn network(&mut self) -> anyhow::Result<&mut Network> {
+        self.network.as_mut().context(
+            "Script requested access to the network, but the host isn't \
+            allowed to connect anywhere. Use `--connect` to allow that.",
+        )
+    }
+
+    fn store(&mut self) -> anyhow::Result<&mut Store> {
+        self.store.as_mut().context(
+            "Script requested access to the store, but the host doesn't have \
+            one. Use `--store` to provide it.",
+        )
+    }
+}
+
+/// # The names of all services
+const SERVICES: [&str; 17] = [
+    "write",
+    "read",
+    "flush",
+    "open",
+    "read_file",
+    "write_file",
+    "close",
+    "connect",
+    "read_socket",
+    "write_socket",
+    "disconnect",
+    "time",
+    "sleep_until",
+    "random",
+    "get",
+    "put",
+    "delete",
+];
+
+/// # The addresses that the host reserves for its own use
+const RESERVED: Range<u32> = ARGUMENTS..1024;
+
+/// # The address of the number of arguments passed to the script
+pub const ARGUMENTS: u32 = 896;
+
+/// # The maximum number of arguments that can be passed to the script
+pub const ARGUMENTS_CAPACITY: usize = (CONSOLE_LENGTH - ARGUMENTS - 1) as usize;
+
+/// # Make sure the host can satisfy the requirements declared by the script
+///
+/// Also makes sure that the script's regions don't overlap the memory that the
+/// host reserves for its own use.
+pub fn check_requirements(script: &Script, eval: &Eval) -> anyhow::Result<()> {
+    for (name, addresses) in script.regions() {
+        if addresses.start < RESERVED.end && RESERVED.start < addresses.end {
+            bail!(
+                "Region `{name}` ({}..{}) overlaps the memory that the host \
+                reserves ({}..{}).",
+                addresses.start,
+                addresses.end,
+                RESERVED.start,
+                RESERVED.end,
+            );
+        }
+    }
+
+    for (key, value) in script.metadata() {
+        match key {
+            "memory" => {
+                let required: usize = value.parse().with_context(|| {
+                    format!("Invalid memory requirement `{value}`.")
+                })?;
+                let available = eval.memory.values().len();
+
+                if required > available {
+                    bail!(
+                        "Script requires {required} words of memory, but only \
+                        {available} are available."
+                    );
+                }
+            }
+            "service" if !SERVICES.contains(&value) => {
+                bail!("Script requires unknown service `{value}`.");
+            }
+            _ => {
+                // Other metadata is not a requirement, or the requirement is
+                // satisfied.
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// # The services' access to the evaluation
+struct Io<'r> {
+    eval: &'r mut Eval,
+    log: &'r mut Vec<Access>,
+}
+
+/// # The address of the number of bytes in the console buffer
+const CONSOLE_LENGTH: u32 = 960;
+
+/// # The address of the first byte in the console buffer
+const CONSOLE_BYTES: u32 = CONSOLE_LENGTH + 1;
+
+/// # The maximum number of bytes in the console buffer
+const CONSOLE_CAPACITY: u32 = 1024 - CONSOLE_BYTES;
+
+fn write(io: &mut Io) -> anyhow::Result<()> {
+    let length = pop(io, "length")?.to_u32();
+    let address = pop(io, "address")?.to_u32();
+
+    let bytes = load_bytes(io, address, length)?;
+    write_to_stdout(&bytes)
+}
+
+fn read(io: &mut Io) -> anyhow::Result<()> {
+    let capacity = pop(io, "capacity")?.to_u32();
+    let address = pop(io, "address")?.to_u32();
+
+    let length = read_into_memory(io, io::stdin(), address, capacity)
+        .context("Reading from stdin.")?;
+    push(io, Value::from(length));
+
+    Ok(())
+}
+
+fn flush(io: &mut Io) -> anyhow::Result<()> {
+    let length = read_memory(io, CONSOLE_LENGTH)?.to_u32();
+    if length > CONSOLE_CAPACITY {
+        bail!(
+            "Console buffer contains {length} bytes, but its capacity is \
+            {CONSOLE_CAPACITY}."
+        );
+    }
+
+    let bytes = load_bytes(io, CONSOLE_BYTES, length)?;
+    write_to_stdout(&bytes)?;
+    write_memory(io, CONSOLE_LENGTH, Value::from(0))?;
+
+    Ok(())
+}
+
+fn open(io: &mut Io, files: &mut Files) -> anyhow::Result<()> {
+    let mode = pop(io, "mode")?.to_u32();
+    let length = pop(io, "length")?.to_u32();
+    let address = pop(io, "address")?.to_u32();
+
+    let path = load_bytes(io, address, length)?;
+    let Ok(path) = String::from_utf8(path) else {
+        bail!("Path passed to `open` is not valid UTF-8.");
+    };
+    let path = files.resolve(&path)?;
+
+    let file = match mode {
+        0 => File::open(path),
+        1 => File::create(path),
+        mode => bail!("Unknown mode `{mode}` passed to `open`."),
+    };
+
+    let handle = match file {
+        Ok(file) => files.open.insert(file),
+        Err(_) => 0,
+    };
+    push(io, Value::from(handle));
+
+    Ok(())
+}
+
+fn read_file(io: &mut Io, files: &mut Files) -> anyhow::Result<()> {
+    let capacity = pop(io, "capacity")?.to_u32();
+    let address = pop(io, "address")?.to_u32();
+    let handle = pop(io, "handle")?.to_u32();
+
+    let file = files.open.get(handle)?;
+    let length = read_into_memory(io, file, address, capacity)
+        .context("Reading from file.")?;
+    push(io, Value::from(length));
+
+    Ok(())
+}
+
+fn write_file(io: &mut Io, files: &mut Files) -> anyhow::Result<()> {
+    let length = pop(io, "length")?.to_u32();
+    let address = pop(io, "address")?.to_u32();
+    let handle = pop(io, "handle")?.to_u32();
+
+    let bytes = load_bytes(io, address, length)?;
+    files
+        .open
+        .get(handle)?
+        .write_all(&bytes)
+        .context("Writing to file.")?;
+
+    Ok(())
+}
+
+fn close(io: &mut Io, files: &mut Files) -> anyhow::Result<()> {
+    let handle = pop(io, "handle")?.to_u32();
+    files.open.remove(handle)?;
+
+    Ok(())
+}
+
+fn connect(io: &mut Io, network: &mut Network) -> anyhow::Result<()> {
+    let length = pop(io, "length")?.to_u32();
+    let address = pop(io, "address")?.to_u32();
+
+    let target = load_bytes(io, address, length)?;
+    let Ok(target) = String::from_utf8(target) else {
+        bail!("Address passed to `connect` is not valid UTF-8.");
+    };
+    if !network.allowed.contains(&target) {
+        bail!("Script is not allowed to connect to `{target}`.");
+    }
+
+    let handle = match TcpStream::connect(&target) {
+        Ok(stream) => network.open.insert(stream),
+        Err(_) => 0,
+    };
+    push(io, Value::from(handle));
+
+    Ok(())
+}
+
+fn read_socket(io: &mut Io, network: &mut Network) -> anyhow::Result<()> {
+    let capacity = pop(io, "capacity")?.to_u32();
+    let address = pop(io, "address")?.to_u32();
+    let handle = pop(io, "handle")?.to_u32();
+
+    let mut bytes = vec![0; capacity as usize];
+    let length = network
+        .open
+        .get(handle)?
+        .read(&mut bytes)
+        .context("Reading from connection.")?;
+
+    let length = store_bytes(io, address, &bytes[..length])?;
+    push(io, Value::from(length));
+
+    Ok(())
+}
+
+fn write_socket(io: &mut Io, network: &mut Network) -> anyhow::Result<()> {
+    let length = pop(io, "length")?.to_u32();
+    let address = pop(io, "address")?.to_u32();
+    let handle = pop(io, "handle")?.to_u32();
+
+    let bytes = load_bytes(io, address, length)?;
+    network
+        .open
+        .get(handle)?
+        .write_all(&bytes)
+        .context("Writing to connection.")?;
+
+    Ok(())
+}
+
+fn disconnect(io: &mut Io, network: &mut Network) -> anyhow::Result<()> {
+    let handle = pop(io, "handle")?.to_u32();
+    network.open.remove(handle)?;
+
+    Ok(())
+}
+
+fn time(io: &mut Io, clock: &Clock) -> anyhow::Result<()> {
+    push(io, Value::from(clock.now()));
+    Ok(())
+}
+
+fn sleep_until(io: &mut Io, clock: &mut Clock) -> anyhow::Result<()> {
+    let time = pop(io, "time")?.to_u32();
+
+    // Interpreting the difference as a signed number takes care of the time
+    // wrapping around. If it's negative, the time has already come.
+    let remaining = time.wrapping_sub(clock.now()).cast_signed();
+    if let Ok(remaining) = u64::try_from(remaining) {
+        clock.wake_time =
+            Some(Instant::now() + Duration::from_millis(remaining));
+    }
+
+    Ok(())
+}
+
+/// # The time that scripts observe, and when they want to continue
+struct Clock {
+    start: Instant,
+    wake_time: Option<Instant>,
+}
+
+impl Clock {
+    /// # The number of milliseconds since the start, wrapping around
+    fn now(&self) -> u32 {
+        // Truncating is intentional. That's what makes the time wrap around.
+        self.start.elapsed().as_millis() as u32
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            wake_time: None,
+        }
+    }
+}
+
+fn random(io: &mut Io, random: &mut Random) -> anyhow::Result<()> {
+    push(io, Value::from(random.next()));
+    Ok(())
+}
+
+/// # The state of the pseudorandom number generator
+///
+/// This is SplitMix64, which is simple and fast, and good enough for the kind
+/// of scripts that need random numbers, like games or simulations.
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn next(&mut self) -> u32 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+
+        // The upper bits are the better ones. Truncating is intentional.
+        (z >> 32) as u32
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        // The standard library seeds the hashers it creates randomly. That's
+        // good enough as a seed, without requiring any dependency.
+        let state = RandomState::new().build_hasher().finish();
+        Self { state }
+    }
+}
+
+fn get(io: &mut Io, store: &mut Store) -> anyhow::Result<()> {
+    let capacity = pop(io, "capacity")?.to_u32();
+    let address = pop(io, "address")?.to_u32();
+    let key = pop_bytes(io)?;
+
+    let length = match store.entries.get(&key) {
+        Some(value) => {
+            let end = value.len().min(capacity as usize);
+            store_bytes(io, address, &value[..end])?;
+
+            let Ok(length) = i32::try_from(value.len()) else {
+                bail!("Value is too large to pass to script.");
+            };
+            length
+        }
+        None => -1,
+    };
+    push(io, Value::from(length));
+
+    Ok(())
+}
+
+fn put(io: &mut Io, store: &mut Store) -> anyhow::Result<()> {
+    let value = pop_bytes(io)?;
+    let key = pop_bytes(io)?;
+
+    store.entries.insert(key, value);
+    store.save()
+}
+
+fn delete(io: &mut Io, store: &mut Store) -> anyhow::Result<()> {
+    let key = pop_bytes(io)?;
+
+    if store.entries.remove(&key).is_some() {
+        store.save()?;
+    }
+
+    Ok(())
+}
+
+/// # The keys and values that scripts keep across runs
+struct Store {
+    path: PathBuf,
+    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl Store {
+    fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let mut entries = BTreeMap::new();
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err).context("Reading store."),
+        };
+
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+
+            let entry = line.split_once(' ').and_then(|(key, value)| {
+                Some((decode_hex(key)?, decode_hex(value)?))
+            });
+            let Some((key, value)) = entry else {
+                bail!("Invalid entry in line {line_number} of store.");
+            };
+
+            entries.insert(key, value);
+        }
+
+        Ok(Self { path, entries })
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let mut text = String::new();
+
+        for (key, value) in &self.entries {
+            text.push_str(&encode_hex(key));
+            text.push(' ');
+            text.push_str(&encode_hex(value));
+            text.push('\n');
+        }
+
+        fs::write(&self.path, text).context("Writing store.")
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.is_ascii() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// # The files that scripts can access, and the ones they have opened
+struct Files {
+    root: PathBuf,
+    open: Handles<File>,
+}
+
+impl Files {
+    /// # Resolve a path provided by the script
+    ///
+    /// Makes sure that the path doesn't lead outside of the root directory.
+    fn resolve(&self, path: &str) -> anyhow::Result<PathBuf> {
+        let path = Path::new(path);
+
+        let is_contained = path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if !is_contained || path.as_os_str().is_empty() {
+            bail!(
+                "Path `{}` is not relative to the accessible directory, or \
+                leads outside of it.",
+                path.display(),
+            );
+        }
+
+        // The path could still lead outside of the root directory through a
+        // symbolic link. So let's check where it actually leads to. The file
+        // itself might not exist yet, but its parent directory must.
+        let path = self.root.join(path);
+        let (Some(parent), Some(file_name)) = (path.parent(), path.file_name())
+        else {
+            unreachable!("Path has at least one normal component.");
+        };
+
+        let root = self
+            .root
+            .canonicalize()
+            .context("Resolving accessible directory.")?;
+        let parent = parent.canonicalize().with_context(|| {
+            format!("Resolving directory `{}`.", parent.display())
+        })?;
+
+        if !parent.starts_with(&root) {
+            bail!(
+                "Path `{}` leads outside of the accessible directory.",
+                path.display(),
+            );
+        }
+
+        Ok(parent.join(file_name))
+    }
+}
+
+/// # The addresses that scripts can connect to, and their open connections
+struct Network {
+    allowed: Vec<String>,
+    open: Handles<TcpStream>,
+}
+
+/// # Resources that a script has opened, identified by their handles
+struct Handles<T> {
+    resources: Vec<Option<T>>,
+}
+
+impl<T> Handles<T> {
+    /// # Store an open resource, returning its handle
+    fn insert(&mut self, resource: T) -> u32 {
+        self.resources.push(Some(resource));
+
+        // Handles start at `1`, so `0` can signal failure.
+        let Ok(handle) = u32::try_from(self.resources.len()) else {
+            panic!("Script opened more than `u32::MAX` resources.");
+        };
+        handle
+    }
+
+    fn get(&mut self, handle: u32) -> anyhow::Result<&mut T> {
+        let resource = handle
+            .checked_sub(1)
+            .and_then(|index| self.resources.get_mut(index as usize))
+            .and_then(|resource| resource.as_mut());
+
+        resource.with_context(|| format!("Invalid handle `{handle}`."))
+    }
+
+    fn remove(&mut self, handle: u32) -> anyhow::Result<()> {
+        self.get(handle)?;
+        self.resources[handle as usize - 1] = None;
+
+        Ok(())
+    }
+}
+
+impl<T> Default for Handles<T> {
+    fn default() -> Self {
+        Self {
+            resources: Vec::new(),
+        }
+    }
+}
+
+/// # Load the bytes in the provided memory region
+fn load_bytes(
+    io: &mut Io,
+    address: u32,
+    length: u32,
+) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+
+    for address in address..address.saturating_add(length) {
+        let value = read_memory(io, address)?.to_u32();
+        let Ok(byte) = u8::try_from(value) else {
+            bail!("Value `{value}` at address `{address}` is not a byte.");
+        };
+
+        bytes.push(byte);
+    }
+
+    Ok(bytes)
+}
+
+/// # Pop the address and length of a memory region, then load its bytes
+fn pop_bytes(io: &mut Io) -> anyhow::Result<Vec<u8>> {
+    let length = pop(io, "length")?.to_u32();
+    let address = pop(io, "address")?.to_u32();
+
+    load_bytes(io, address, length)
+}
+
+/// # Read up to `capacity` bytes, storing them in memory
+///
+/// Returns the number of bytes read.
+fn read_into_memory(
+    io: &mut Io,
+    reader: impl Read,
+    address: u32,
+    capacity: u32,
+) -> anyhow::Result<u32> {
+    let mut bytes = Vec::new();
+    reader.take(capacity.into()).read_to_end(&mut bytes)?;
+
+    store_bytes(io, address, &bytes)
+}
+
+/// # Store bytes in memory, starting at the provided address
+///
+/// Returns the number of bytes stored.
+fn store_bytes(io: &mut Io, address: u32, bytes: &[u8]) -> anyhow::Result<u32> {
+    for (address, byte) in (address..).zip(bytes) {
+        write_memory(io, address, Value::from(u32::from(*byte)))?;
+    }
+
+    let Ok(length) = u32::try_from(bytes.len()) else {
+        unreachable!("Can't have more bytes than fit into `u32`.");
+    };
+
+    Ok(length)
+}
+
+fn write_to_stdout(bytes: &[u8]) -> anyhow::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(bytes)?;
+    stdout.flush()?;
+
+    Ok(())
+}
+
+fn pop(io: &mut Io, input: &str) -> anyhow::Result<Value> {
+    let value = io
+        .eval
+        .operand_stack
+        .pop()
+        .map_err(|_| anyhow::anyhow!("Missing service input: {input}"))?;
+
+    io.log.push(Access::Pop { value });
+    Ok(value)
+}
+
+fn push(io: &mut Io, value: Value) {
+    io.eval.operand_stack.push(value);
+    io.log.push(Access::Push { value });
+}
+
+fn read_memory(io: &mut Io, address: u32) -> anyhow::Result<Value> {
+    let value = io.eval.memory.read(address).map_err(|_| {
+        anyhow::anyhow!("Address `{address}` is out of bounds.")
+    })?;
+
+    io.log.push(Access::Read { address, value });
+    Ok(value)
+}
+
+fn write_memory(io: &mut Io, address: u32, value: Value) -> anyhow::Result<()> {
+    io.eval.memory.write(address, value).map_err(|_| {
+        anyhow::anyhow!("Address `{address}` is out of bounds.")
+    })?;
+
+    io.log.push(Access::Write { address, value });
+    Ok(())
+}
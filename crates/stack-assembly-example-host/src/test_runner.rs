@@ -0,0 +1,106 @@
+use std::process;
+
+use stack_assembly::{Effect, Eval, OperatorIndex, Script};
+use stack_assembly_host::describe_location;
+
+use crate::services::Services;
+
+/// # Run all tests defined in the provided script
+///
+/// See [`Script::tests`] for how tests are defined. Exits the process with a
+/// non-zero status, if any tests failed.
+pub fn run(source: &str, max_steps: Option<u64>) -> anyhow::Result<()> {
+    let script = Script::compile(source);
+
+    let mut num_passed = 0;
+    let mut num_failed = 0;
+
+    for (name, operator) in script.tests() {
+        let mut eval = Eval::start_at(operator);
+        eval.set_fuel(max_steps);
+        eval.load_data(&script);
+
+        match run_test(&script, &mut eval) {
+            Ok(()) => {
+                println!("test {name} ... ok");
+                num_passed += 1;
+            }
+            Err(failure) => {
+                println!("test {name} ... FAILED");
+                println!("    {}", failure.describe(source, &script));
+                num_failed += 1;
+            }
+        }
+    }
+
+    println!();
+    println!("{num_passed} passed; {num_failed} failed");
+
+    if num_failed > 0 {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn run_test(script: &Script, eval: &mut Eval) -> Result<(), Failure> {
+    // Always using the same seed keeps tests that use `random` reproducible.
+    let mut services = Services::default().with_seed(0);
+
+    loop {
+        let (effect, operator) = eval.run(script);
+
+        match effect {
+            Effect::Return => {
+                return Ok(());
+            }
+            Effect::Yield => {
+                if let Err(err) = services.handle_yield(eval) {
+                    return Err(Failure::Service {
+                        message: err.to_string(),
+                        operator,
+                    });
+                }
+
+                services.wait();
+                eval.clear_effect();
+            }
+            effect => {
+                return Err(Failure::Effect { effect, operator });
+            }
+        }
+    }
+}
+
+enum Failure {
+    Effect {
+        effect: Effect,
+        operator: OperatorIndex,
+    },
+    Service {
+        message: String,
+        operator: OperatorIndex,
+    },
+}
+
+impl Failure {
+    fn describe(&self, source: &str, script: &Script) -> String {
+        let (description, operator) = match self {
+            Self::Effect {
+                effect: Effect::OutOfOperators,
+                operator,
+            } => ("Test did not `return`".to_string(), operator),
+            Self::Effect { effect, operator } => {
+                (format!("Triggered effect: {effect}"), operator)
+            }
+            Self::Service { message, operator } => {
+                (format!("Service error: {message}"), operator)
+            }
+        };
+
+        match describe_location(source, script, *operator) {
+            Some(location) => format!("{description} at {location}"),
+            None => format!("{description} at end of script"),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use stack_assembly::{Eval, OperatorIndex, Script, Tracer};
+use stack_assembly_host::describe_location;
+
+/// # The number of values from the top of the operand stack to print
+const NUM_VALUES: usize = 4;
+
+/// # Prints each operator, right before it is evaluated
+///
+/// Alongside the operator, prints the top values on the operand stack.
+pub struct Trace {
+    /// # The description of each operator's location in the source code
+    locations: Vec<Option<String>>,
+}
+
+impl Trace {
+    pub fn new(source: &str, script: &Script) -> Self {
+        let locations = script
+            .operators()
+            .map(|(operator, _)| describe_location(source, script, operator))
+            .collect();
+
+        Self { locations }
+    }
+}
+
+impl Tracer for Trace {
+    fn before_step(&mut self, operator: OperatorIndex, eval: &Eval) {
+        let location = self
+            .locations
+            .get(operator.value() as usize)
+            .cloned()
+            .flatten()
+            .unwrap_or_else(|| "prelude".to_string());
+
+        let values = &eval.operand_stack.values;
+        let top = &values[values.len().saturating_sub(NUM_VALUES)..];
+
+        let mut stack = String::new();
+        if values.len() > top.len() {
+            stack.push_str("... ");
+        }
+        for value in top {
+            stack.push_str(&format!("{value:?} "));
+        }
+
+        eprintln!(
+            "[{:>5}] {location:<24} | {}",
+            operator.value(),
+            stack.trim_end(),
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use std::{
+    fs,
+    path::Path,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    RunArgs, evaluate, new_eval, new_services, read_script, record::Host,
+};
+
+/// # How often to check whether the script file has changed
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// # Evaluate the script, restarting whenever the script file changes
+///
+/// This never returns, unless there's an error reading the script file.
+pub fn run(path: &Path, args: &RunArgs) -> anyhow::Result<()> {
+    let mut eval = new_eval(args)?;
+
+    loop {
+        let modified = modified(path)?;
+        let source = read_script(path)?;
+
+        eprintln!("Evaluating `{}`.", path.display());
+
+        let mut last_check = Instant::now();
+        let mut interrupt = || {
+            if last_check.elapsed() < POLL_INTERVAL {
+                return false;
+            }
+            last_check = Instant::now();
+
+            has_changed(path, modified)
+        };
+
+        let interrupted = evaluate(
+            &source,
+            args,
+            &mut eval,
+            &mut Host::live(new_services(args)?),
+            &mut interrupt,
+        )
+        .is_none();
+
+        if !interrupted {
+            eprintln!();
+            eprintln!("Waiting for changes...");
+
+            while !has_changed(path, modified) {
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+
+        eprintln!();
+        eprintln!("Script file has changed. Restarting.");
+
+        let previous = eval;
+        eval = new_eval(args)?;
+
+        if args.preserve_memory {
+            eval.memory = previous.memory;
+        }
+    }
+}
+
+fn modified(path: &Path) -> anyhow::Result<SystemTime> {
+    Ok(fs::metadata(path)?.modified()?)
+}
+
+fn has_changed(path: &Path, modified_before: SystemTime) -> bool {
+    // If we can't access the file right now, it might be in the middle of
+    // being replaced by an editor. Let's just try again later.
+    modified(path).is_ok_and(|modified| modified != modified_before)
+}
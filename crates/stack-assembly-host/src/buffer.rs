@@ -0,0 +1,123 @@
+use std::fmt;
+
+use stack_assembly::{Memory, Value};
+
+/// # Load the bytes in the provided region of memory
+///
+/// Expects one byte per word, which is how scripts conventionally pass buffers
+/// to the host. Unlike [`Memory::read_str`], the bytes don't need to be valid
+/// UTF-8.
+pub fn load_bytes(
+    memory: &Memory,
+    address: u32,
+    length: u32,
+) -> Result<Vec<u8>, BufferError> {
+    let mut bytes = Vec::new();
+
+    for address in address..address.saturating_add(length) {
+        let value = memory
+            .read(address)
+            .map_err(|_| BufferError::OutOfBounds { address })?
+            .to_u32();
+        let Ok(byte) = u8::try_from(value) else {
+            return Err(BufferError::NotAByte { address, value });
+        };
+
+        bytes.push(byte);
+    }
+
+    Ok(bytes)
+}
+
+/// # Store bytes in memory, starting at the provided address
+///
+/// Stores one byte per word, like [`load_bytes`] expects. Returns the number of
+/// bytes stored. If the bytes don't fit into the memory, nothing is stored.
+pub fn store_bytes(
+    memory: &mut Memory,
+    address: u32,
+    bytes: &[u8],
+) -> Result<u32, BufferError> {
+    let size = memory.values().len();
+    let end = usize::try_from(address)
+        .ok()
+        .and_then(|address| address.checked_add(bytes.len()));
+
+    if end.is_none_or(|end| end > size) {
+        let first_invalid = u32::try_from(size).unwrap_or(u32::MAX);
+        return Err(BufferError::OutOfBounds {
+            address: first_invalid.max(address),
+        });
+    }
+
+    for (address, byte) in (address..).zip(bytes) {
+        // We checked above, that all addresses are valid.
+        let _ = memory.write(address, Value::from(u32::from(*byte)));
+    }
+
+    // The bytes fit into the memory, whose addresses are `u32`.
+    Ok(bytes.len() as u32)
+}
+
+/// # A buffer in memory could not be accessed
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BufferError {
+    /// # The buffer extends beyond the memory
+    OutOfBounds {
+        /// # The first address that is not within the memory
+        address: u32,
+    },
+
+    /// # A word of the buffer doesn't hold a byte
+    NotAByte {
+        /// # The address of the word
+        address: u32,
+
+        /// # The value of the word
+        value: u32,
+    },
+}
+
+impl fmt::Display for BufferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OutOfBounds { address } => {
+                write!(f, "address `{address}` is out of bounds")
+            }
+            Self::NotAByte { address, value } => {
+                write!(
+                    f,
+                    "value `{value}` at address `{address}` is not a byte"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for BufferError {}
+
+#[cfg(test)]
+mod tests {
+    use stack_assembly::Memory;
+
+    use super::{BufferError, load_bytes, store_bytes};
+
+    #[test]
+    fn stored_bytes_can_be_loaded() {
+        let mut memory = Memory::new(4);
+
+        assert_eq!(store_bytes(&mut memory, 1, b"abc"), Ok(3));
+        assert_eq!(load_bytes(&memory, 1, 3), Ok(b"abc".to_vec()));
+    }
+
+    #[test]
+    fn bytes_that_dont_fit_are_not_stored() {
+        let mut memory = Memory::new(4);
+
+        assert_eq!(
+            store_bytes(&mut memory, 2, b"abc"),
+            Err(BufferError::OutOfBounds { address: 4 }),
+        );
+        assert_eq!(load_bytes(&memory, 2, 2), Ok(vec![0, 0]));
+    }
+}
@@ -0,0 +1,331 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io,
+    time::{Duration, Instant},
+};
+
+use stack_assembly::{Eval, OperatorIndex, Script, Tracer};
+
+use crate::describe_location;
+
+/// # Records the evaluation in the Chrome tracing format
+///
+/// Attach this to an evaluation using [`Eval::add_tracer`]. Afterwards, write
+/// the recorded events using [`ChromeTrace::write_json`], and open the file in
+/// a viewer that supports the format, like [Perfetto] or `about:tracing` in
+/// Chrome. That makes it possible to examine where a long run spends its time.
+///
+/// Each call shows up as a slice, named after the label of the called
+/// routine. Each strand shows up as a separate thread. Optionally, each
+/// evaluated operator can be recorded too (see [`ChromeTrace::with_steps`]).
+/// That produces a lot of events, so it's only practical for short runs.
+///
+/// [Perfetto]: https://ui.perfetto.dev/
+///
+/// ## Example
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+///
+/// use stack_assembly::{Eval, Script};
+/// use stack_assembly_host::ChromeTrace;
+///
+/// let source = "@square call square: 3 0 copy * return";
+/// let script = Script::compile(source);
+///
+/// let trace = Arc::new(Mutex::new(ChromeTrace::new(source, &script)));
+///
+/// let mut eval = Eval::new();
+/// eval.add_tracer(trace.clone());
+/// eval.run(&script);
+///
+/// let mut json = Vec::new();
+/// let Ok(trace) = trace.lock() else {
+///     unreachable!("The trace doesn't panic.");
+/// };
+/// trace.write_json(&mut json)?;
+///
+/// assert!(String::from_utf8_lossy(&json).contains(r#""name":"square""#));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// [`Eval::add_tracer`]: stack_assembly::Eval::add_tracer
+#[derive(Debug)]
+pub struct ChromeTrace {
+    start: Instant,
+    steps: bool,
+
+    /// # The name of the routine that each operator is part of
+    routines: Vec<String>,
+
+    /// # The description of each operator's location in the source text
+    locations: Vec<String>,
+
+    /// # The routines that have been called but not returned from, per strand
+    open: BTreeMap<u32, Vec<usize>>,
+
+    events: Vec<Event>,
+}
+
+impl ChromeTrace {
+    /// # Create a trace that records calls, but not individual operators
+    ///
+    /// The source text and script are used to name routines and locations.
+    /// The timestamps of the events are relative to when this is called.
+    pub fn new(source: &str, script: &Script) -> Self {
+        let routines = script
+            .operators()
+            .map(|(operator, _)| match script.label_at(operator) {
+                Some((name, _)) => name.to_string(),
+                None => format!("operator {operator}"),
+            })
+            .collect();
+        let locations = script
+            .operators()
+            .map(|(operator, _)| {
+                describe_location(source, script, operator)
+                    .unwrap_or_else(|| format!("operator {operator}"))
+            })
+            .collect();
+
+        Self {
+            start: Instant::now(),
+            steps: false,
+            routines,
+            locations,
+            open: BTreeMap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// # Also record an event for each evaluated operator
+    pub fn with_steps(mut self) -> Self {
+        self.steps = true;
+        self
+    }
+
+    /// # Write the recorded events as JSON
+    ///
+    /// Calls that haven't returned yet end with the last recorded event, so a
+    /// trace of an evaluation that is still in progress, or that ended within
+    /// a call, can be viewed too.
+    pub fn write_json(&self, mut writer: impl io::Write) -> io::Result<()> {
+        let end = self.events.last().map(|event| event.time);
+        let unfinished = self.open.iter().flat_map(|(&strand, routines)| {
+            routines.iter().rev().map(move |&routine| Event {
+                kind: EventKind::End,
+                name: routine,
+                strand,
+                time: end.unwrap_or_default(),
+                operator: None,
+            })
+        });
+
+        writeln!(writer, "{{\"traceEvents\":[")?;
+
+        for (i, event) in
+            self.events.iter().cloned().chain(unfinished).enumerate()
+        {
+            if i > 0 {
+                writeln!(writer, ",")?;
+            }
+            write!(writer, "{}", self.format_event(&event))?;
+        }
+
+        writeln!(writer)?;
+        writeln!(writer, "]}}")?;
+
+        Ok(())
+    }
+
+    fn format_event(&self, event: &Event) -> String {
+        let (phase, name) = match event.kind {
+            EventKind::Begin => ("B", &self.routines[event.name]),
+            EventKind::End => ("E", &self.routines[event.name]),
+            EventKind::Step => ("i", &self.locations[event.name]),
+        };
+
+        let mut json = String::new();
+        let _ = write!(
+            json,
+            "{{\"name\":\"{}\",\"ph\":\"{phase}\",\"ts\":{},\"pid\":1,\
+            \"tid\":{}",
+            escape(name),
+            event.time.as_micros(),
+            event.strand,
+        );
+        if let Some(operator) = event.operator {
+            let _ = write!(
+                json,
+                ",\"s\":\"t\",\"args\":{{\"operator\":{operator}}}"
+            );
+        }
+        json.push('}');
+
+        json
+    }
+}
+
+impl Tracer for ChromeTrace {
+    fn before_step(&mut self, operator: OperatorIndex, eval: &Eval) {
+        let time = self.start.elapsed();
+        let strand = eval.current_strand();
+        let index = operator.value() as usize;
+
+        let open = self.open.entry(strand).or_default();
+
+        // The call stack only changes by one frame per operator. But if the
+        // evaluation switched strands, or the host manipulated the call
+        // stack, it could be any number.
+        while open.len() > eval.call_stack.len() {
+            let Some(routine) = open.pop() else {
+                break;
+            };
+            self.events.push(Event {
+                kind: EventKind::End,
+                name: routine,
+                strand,
+                time,
+                operator: None,
+            });
+        }
+        while open.len() < eval.call_stack.len() && index < self.routines.len()
+        {
+            open.push(index);
+            self.events.push(Event {
+                kind: EventKind::Begin,
+                name: index,
+                strand,
+                time,
+                operator: None,
+            });
+        }
+
+        if self.steps && index < self.locations.len() {
+            self.events.push(Event {
+                kind: EventKind::Step,
+                name: index,
+                strand,
+                time,
+                operator: Some(operator),
+            });
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Event {
+    kind: EventKind,
+
+    /// # The index of the operator whose routine or location names the event
+    name: usize,
+
+    strand: u32,
+    time: Duration,
+    operator: Option<OperatorIndex>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum EventKind {
+    Begin,
+    End,
+    Step,
+}
+
+/// # Escape a string, so it can be embedded in a JSON string
+fn escape(s: &str) -> String {
+    let mut escaped = String::new();
+
+    for ch in s.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            ch if ch.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", u32::from(ch));
+            }
+            ch => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use stack_assembly::{Eval, Script};
+
+    use super::{ChromeTrace, escape};
+
+    fn record(source: &str, trace: ChromeTrace) -> String {
+        let script = Script::compile(source);
+        let trace = Arc::new(Mutex::new(trace));
+
+        let mut eval = Eval::new();
+        eval.add_tracer(trace.clone());
+        eval.run(&script);
+
+        let mut json = Vec::new();
+        let Ok(trace) = trace.lock() else {
+            unreachable!("Trace doesn't panic.");
+        };
+        let Ok(()) = trace.write_json(&mut json) else {
+            unreachable!("Writing to a `Vec` doesn't fail.");
+        };
+
+        String::from_utf8_lossy(&json).into_owned()
+    }
+
+    fn phases(json: &str) -> Vec<(&str, &str)> {
+        json.lines()
+            .filter_map(|line| {
+                let name =
+                    line.split("\"name\":\"").nth(1)?.split('"').next()?;
+                let phase =
+                    line.split("\"ph\":\"").nth(1)?.split('"').next()?;
+                Some((name, phase))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn calls_are_recorded_as_slices() {
+        let source = "@f call 1 yield f: @g call return g: return";
+        let script = Script::compile(source);
+        let json = record(source, ChromeTrace::new(source, &script));
+
+        assert!(json.starts_with("{\"traceEvents\":["));
+        assert_eq!(
+            phases(&json),
+            [("f", "B"), ("g", "B"), ("g", "E"), ("f", "E")],
+        );
+    }
+
+    #[test]
+    fn unfinished_calls_are_closed() {
+        let source = "@f call f: 1 yield";
+        let script = Script::compile(source);
+        let json =
+            record(source, ChromeTrace::new(source, &script).with_steps());
+
+        assert_eq!(
+            phases(&json),
+            [
+                ("1:1: `@f`", "i"),
+                ("1:4: `call`", "i"),
+                ("f", "B"),
+                ("1:12: `1`", "i"),
+                ("1:14: `yield`", "i"),
+                ("f", "E"),
+            ],
+        );
+    }
+
+    #[test]
+    fn escape_json() {
+        assert_eq!(escape("`a\"b`\\\n\t"), "`a\\\"b`\\\\\\n\\u0009");
+    }
+}
@@ -0,0 +1,77 @@
+use std::ops::Range;
+
+use stack_assembly::{Memory, OperandStack, OperatorIndex, Script};
+
+/// # Compute the 1-based line and column of the provided byte offset
+///
+/// Columns are counted in characters, not bytes.
+pub fn line_and_column(source: &str, offset: usize) -> (u32, u32) {
+    let mut line = 1;
+    let mut column = 1;
+
+    for ch in source[..offset].chars() {
+        if ch == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+
+    (line, column)
+}
+
+/// # Describe the location of an operator in the source code
+///
+/// Returns `None`, if the operator is not present in the source map.
+///
+/// ```
+/// use stack_assembly::{OperatorIndex, Script};
+/// use stack_assembly_host::describe_location;
+///
+/// let source = "1 2\n+";
+/// let script = Script::compile(source);
+///
+/// let location = describe_location(source, &script, OperatorIndex::new(2));
+/// assert_eq!(location.as_deref(), Some("2:1: `+`"));
+/// ```
+pub fn describe_location(
+    source: &str,
+    script: &Script,
+    operator: OperatorIndex,
+) -> Option<String> {
+    let range = script.map_operator_to_source(&operator).ok()?;
+    let (line, column) = line_and_column(source, range.start);
+
+    Some(format!("{line}:{column}: `{}`", &source[range]))
+}
+
+/// # Format the values on the operand stack, bottom to top
+///
+/// The values are separated by spaces.
+pub fn format_operand_stack(operand_stack: &OperandStack) -> String {
+    operand_stack
+        .values
+        .iter()
+        .map(|value| format!("{value:?}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// # Format the values in the provided range of memory
+///
+/// Formats one line per address, each ending with a newline. Addresses that
+/// are not within the memory are left out.
+pub fn format_memory(memory: &Memory, addresses: Range<u32>) -> String {
+    let mut lines = String::new();
+
+    for address in addresses {
+        let Ok(value) = memory.read(address) else {
+            break;
+        };
+
+        lines.push_str(&format!("{address:>8}: {value:?}\n"));
+    }
+
+    lines
+}
@@ -0,0 +1,34 @@
+//! # Building blocks for StackAssembly hosts
+//!
+//! The [`stack_assembly`] library evaluates scripts, but leaves it to the host
+//! to decide what happens when a script triggers an effect. Many hosts end up
+//! needing the same pieces around that, and this crate collects them:
+//!
+//! - [`run_until_effect`] and [`run_loop`], for driving the evaluation and
+//!   dispatching effects to the host.
+//! - [`line_and_column`], [`describe_location`], [`format_operand_stack`], and
+//!   [`format_memory`], for showing the state of the evaluation to the user.
+//! - [`load_bytes`] and [`store_bytes`], for exchanging buffers with a script.
+//! - [`RateLimiter`], for limiting how often a script can request a service.
+//! - [`ChromeTrace`], for examining an evaluation in a trace viewer.
+//!
+//! The example host in this repository is built on top of this crate.
+
+#![warn(missing_debug_implementations)]
+#![warn(missing_docs)]
+
+mod buffer;
+mod chrome;
+mod format;
+mod rate;
+mod run;
+
+pub use self::{
+    buffer::{BufferError, load_bytes, store_bytes},
+    chrome::ChromeTrace,
+    format::{
+        describe_location, format_memory, format_operand_stack, line_and_column,
+    },
+    rate::RateLimiter,
+    run::{Flow, run_loop, run_until_effect},
+};
@@ -0,0 +1,103 @@
+use std::time::{Duration, Instant};
+
+/// # Limits how often something can happen within a period of time
+///
+/// A host can use this to limit how often a script can request a service, for
+/// example to keep a script that prints in a loop from flooding the terminal,
+/// or one that opens connections from overwhelming a server.
+///
+/// This is a token bucket: Up to `burst` requests are allowed right away.
+/// After that, one more request becomes allowed every `interval`.
+///
+/// ```
+/// use std::time::{Duration, Instant};
+///
+/// use stack_assembly_host::RateLimiter;
+///
+/// let start = Instant::now();
+/// let mut limiter = RateLimiter::new(2, Duration::from_secs(1), start);
+///
+/// assert!(limiter.try_acquire(start));
+/// assert!(limiter.try_acquire(start));
+/// assert!(!limiter.try_acquire(start));
+///
+/// assert!(limiter.try_acquire(start + Duration::from_secs(1)));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimiter {
+    burst: u32,
+    interval: Duration,
+    available: u32,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// # Create a rate limiter that starts out allowing `burst` requests
+    ///
+    /// `now` is the current time. Pass the same clock to the other methods.
+    /// Taking the time as an argument, makes it possible to use a fake clock
+    /// for deterministic evaluation.
+    pub fn new(burst: u32, interval: Duration, now: Instant) -> Self {
+        Self {
+            burst,
+            interval,
+            available: burst,
+            last_refill: now,
+        }
+    }
+
+    /// # Try to make a request
+    ///
+    /// Returns `true`, if the request is allowed. Returns `false`, if too many
+    /// requests have been made recently. See [`RateLimiter::wait_time`] for
+    /// when the next request is allowed.
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        self.refill(now);
+
+        if self.available == 0 {
+            return false;
+        }
+
+        self.available -= 1;
+        true
+    }
+
+    /// # How long to wait, until the next request is allowed
+    ///
+    /// Returns [`Duration::ZERO`], if a request is allowed right away.
+    pub fn wait_time(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+
+        if self.available > 0 {
+            return Duration::ZERO;
+        }
+
+        (self.last_refill + self.interval).saturating_duration_since(now)
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if self.interval.is_zero() {
+            self.available = self.burst;
+            self.last_refill = now;
+            return;
+        }
+
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        let refills = elapsed.as_nanos() / self.interval.as_nanos();
+        if refills == 0 {
+            return;
+        }
+
+        let refills = u32::try_from(refills).unwrap_or(u32::MAX);
+        self.available = self.available.saturating_add(refills).min(self.burst);
+
+        // Only account for the intervals that have fully passed, so a request
+        // that is made in the middle of one doesn't lose the time until then.
+        // Unless the bucket is full, in which case there's nothing to lose.
+        if self.available == self.burst {
+            self.last_refill = now;
+        } else {
+            self.last_refill += self.interval.saturating_mul(refills);
+        }
+    }
+}
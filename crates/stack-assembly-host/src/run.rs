@@ -0,0 +1,84 @@
+use stack_assembly::{Effect, Eval, OperatorIndex, Script};
+
+/// # Advance the evaluation until it triggers an effect
+///
+/// Works like [`Eval::run`], but returns `None`, if `interrupt` returns
+/// `true`. This allows the host to stop an evaluation that doesn't finish, for
+/// example when the user presses Ctrl-C, or a file it watches changes.
+pub fn run_until_effect(
+    script: &Script,
+    eval: &mut Eval,
+    interrupt: &mut dyn FnMut() -> bool,
+) -> Option<(Effect, OperatorIndex)> {
+    // Checking for an interrupt might be expensive, so let's not do that on
+    // every single step.
+    const STEPS_BETWEEN_INTERRUPT_CHECKS: u64 = 1024;
+
+    loop {
+        if let Some(effect) =
+            eval.step_n(script, STEPS_BETWEEN_INTERRUPT_CHECKS)
+        {
+            return Some(effect);
+        }
+
+        if interrupt() {
+            return None;
+        }
+    }
+}
+
+/// # Evaluate a script, passing each effect it triggers to the host
+///
+/// Whenever the script triggers an effect, `handle` decides what happens next.
+/// If it returns [`Flow::Continue`], the effect is cleared and the evaluation
+/// continues. If it returns [`Flow::Finish`], this function returns the value
+/// it carries, leaving the effect active.
+///
+/// ```
+/// use stack_assembly::{Effect, Eval, Script};
+/// use stack_assembly_host::{Flow, run_loop};
+///
+/// let script = Script::compile("1 yield 2 yield");
+///
+/// let mut eval = Eval::new();
+/// let mut yields = 0;
+///
+/// let effect = run_loop(&script, &mut eval, |_, effect, _| match effect {
+///     Effect::Yield => {
+///         yields += 1;
+///         Flow::Continue
+///     }
+///     effect => Flow::Finish(effect),
+/// });
+///
+/// assert_eq!(effect, Effect::OutOfOperators);
+/// assert_eq!(yields, 2);
+/// ```
+pub fn run_loop<T>(
+    script: &Script,
+    eval: &mut Eval,
+    mut handle: impl FnMut(&mut Eval, Effect, OperatorIndex) -> Flow<T>,
+) -> T {
+    loop {
+        let (effect, operator) = eval.run(script);
+
+        match handle(eval, effect, operator) {
+            Flow::Continue => {
+                eval.clear_effect();
+            }
+            Flow::Finish(value) => {
+                return value;
+            }
+        }
+    }
+}
+
+/// # What [`run_loop`] does, after the host has handled an effect
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Flow<T> {
+    /// # Clear the effect and continue the evaluation
+    Continue,
+
+    /// # Stop the evaluation, returning the provided value
+    Finish(T),
+}
@@ -0,0 +1,234 @@
+use std::ops;
+
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DocumentSymbol, Hover, HoverContents,
+    MarkupContent, MarkupKind, Position, Range, SymbolKind,
+};
+use stack_assembly::{CompileError, Script, Warning};
+
+/// # A script that is open in the editor
+pub struct Document {
+    source: String,
+    script: Script,
+}
+
+impl Document {
+    pub fn new(source: String) -> Self {
+        let script = Script::compile(&source);
+        Self { source, script }
+    }
+
+    /// # Report the problems that strict mode would reject
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.script
+            .check()
+            .into_iter()
+            .filter_map(|err| {
+                let (range, severity) = match &err {
+                    CompileError::IntegerOutOfRange { range }
+                    | CompileError::UnknownIdentifier { range }
+                    | CompileError::InvalidReference { range }
+                    | CompileError::UnmatchedKeyword { range }
+                    | CompileError::InvalidProcedure { range }
+                    | CompileError::InvalidDirective { range } => {
+                        (range.clone(), DiagnosticSeverity::ERROR)
+                    }
+                    CompileError::Warning { warning, range: _ } => (
+                        self.warning_range(warning)?,
+                        DiagnosticSeverity::WARNING,
+                    ),
+                };
+
+                Some(Diagnostic {
+                    range: self.range(range),
+                    severity: Some(severity),
+                    source: Some("stack-assembly".to_string()),
+                    message: err.to_string(),
+                    ..Diagnostic::default()
+                })
+            })
+            .collect()
+    }
+
+    /// # Find the definition of the label at the provided position
+    ///
+    /// The position can be on a reference, or on the label itself.
+    pub fn definition(&self, position: Position) -> Option<Range> {
+        let name = self.label_name_at(position)?;
+        let (_, range) = self.find_definition(name)?;
+
+        Some(self.range(range))
+    }
+
+    /// # Describe the label at the provided position
+    ///
+    /// Shows the comment right above the label's definition, if any.
+    pub fn hover(&self, position: Position) -> Option<Hover> {
+        let name = self.label_name_at(position)?;
+        let (_, range) = self.find_definition(name)?;
+
+        let mut value = format!("```\n{name}:\n```");
+
+        let documentation = self.comment_above(range.start);
+        if !documentation.is_empty() {
+            value.push_str("\n\n---\n\n");
+            value.push_str(&documentation);
+        }
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: None,
+        })
+    }
+
+    /// # List the labels defined in the script
+    pub fn symbols(&self) -> Vec<DocumentSymbol> {
+        self.script
+            .label_definitions()
+            .map(|(name, _, range)| {
+                let range = self.range(range);
+
+                // The `deprecated` field is, in fact, deprecated. But we still
+                // have to initialize it.
+                #[allow(deprecated)]
+                DocumentSymbol {
+                    name: name.to_string(),
+                    detail: None,
+                    kind: SymbolKind::FUNCTION,
+                    tags: None,
+                    deprecated: None,
+                    range,
+                    selection_range: range,
+                    children: None,
+                }
+            })
+            .collect()
+    }
+
+    /// # Find the name of the label that the provided position refers to
+    fn label_name_at(&self, position: Position) -> Option<&str> {
+        let offset = self.offset(position);
+        let contains = |range: &ops::Range<usize>| {
+            // Also match, if the position is right after the token, which is
+            // where the cursor is after typing it.
+            range.start <= offset && offset <= range.end
+        };
+
+        let reference = self.script.references().find(|(_, operator)| {
+            self.script
+                .map_operator_to_source(operator)
+                .is_ok_and(|range| contains(&range))
+        });
+        if let Some((name, _)) = reference {
+            return Some(name);
+        }
+
+        self.script
+            .label_definitions()
+            .find(|(_, _, range)| contains(range))
+            .map(|(name, _, _)| name)
+    }
+
+    /// # Find the definition that references to the provided label resolve to
+    fn find_definition(&self, name: &str) -> Option<(&str, ops::Range<usize>)> {
+        // If multiple labels have the same name, references resolve to the
+        // first one.
+        self.script
+            .label_definitions()
+            .find(|(label, _, _)| *label == name)
+            .map(|(label, _, range)| (label, range))
+    }
+
+    /// # Find the range in the source text that a warning refers to
+    fn warning_range(&self, warning: &Warning) -> Option<ops::Range<usize>> {
+        match warning {
+            Warning::UnusedLabel { name, operator }
+            | Warning::DuplicateLabel { name, operator } => self
+                .script
+                .label_definitions()
+                .find(|(label, label_operator, _)| {
+                    label == name && label_operator == operator
+                })
+                .map(|(_, _, range)| range),
+            Warning::UnreachableCode { first, last } => {
+                let first = self.script.map_operator_to_source(first).ok()?;
+                let last = self.script.map_operator_to_source(last).ok()?;
+
+                Some(first.start..last.end)
+            }
+            Warning::StackUnderflow { operator }
+            | Warning::ArityMismatch { name: _, operator } => {
+                self.script.map_operator_to_source(operator).ok()
+            }
+        }
+    }
+
+    /// # Collect the comment lines right above the provided offset
+    ///
+    /// Returns the text of those lines without the `#`, or an empty string, if
+    /// there's no such comment.
+    fn comment_above(&self, offset: usize) -> String {
+        let line_start = self.source[..offset].rfind('\n').map_or(0, |i| i + 1);
+
+        let mut lines = self.source[..line_start]
+            .lines()
+            .rev()
+            .map_while(|line| line.trim_start().strip_prefix('#'))
+            .map(|line| line.strip_prefix(' ').unwrap_or(line))
+            .collect::<Vec<_>>();
+        lines.reverse();
+
+        lines.join("\n")
+    }
+
+    /// # Convert a range in the source text into the editor's format
+    fn range(&self, range: ops::Range<usize>) -> Range {
+        Range {
+            start: self.position(range.start),
+            end: self.position(range.end),
+        }
+    }
+
+    /// # Convert an offset in the source text into the editor's format
+    ///
+    /// Editors count characters in UTF-16 code units, by default.
+    fn position(&self, offset: usize) -> Position {
+        let before = &self.source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        let line = before.matches('\n').count();
+        let character = before[line_start..].encode_utf16().count();
+
+        Position {
+            line: u32::try_from(line).unwrap_or(u32::MAX),
+            character: u32::try_from(character).unwrap_or(u32::MAX),
+        }
+    }
+
+    /// # Convert a position in the editor's format into an offset
+    fn offset(&self, position: Position) -> usize {
+        let mut offset = 0;
+
+        for _ in 0..position.line {
+            match self.source[offset..].find('\n') {
+                Some(i) => offset += i + 1,
+                None => return self.source.len(),
+            }
+        }
+
+        let mut character = 0;
+        for ch in self.source[offset..].chars() {
+            if ch == '\n' || character >= position.character {
+                break;
+            }
+
+            character += ch.len_utf16() as u32;
+            offset += ch.len_utf8();
+        }
+
+        offset
+    }
+}
@@ -0,0 +1,296 @@
+//! # Language server for StackAssembly
+//!
+//! Communicates with the editor via stdin and stdout, using the Language Server
+//! Protocol. Provides the following features:
+//!
+//! - Diagnostics for everything that strict mode rejects, like unknown
+//!   identifiers, references to labels that don't exist, or unreachable code.
+//! - Go to the definition of the label that a reference refers to.
+//! - Show the comment above a label's definition, when hovering over it, or
+//!   over a reference to it.
+//! - List the labels in the script, as its symbols.
+
+mod document;
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use lsp_server::{
+    Connection, ErrorCode, Message, Notification, Request, Response,
+};
+use lsp_types::{
+    DocumentSymbolResponse, GotoDefinitionResponse, HoverProviderCapability,
+    Location, OneOf, PublishDiagnosticsParams, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Uri,
+    notification::{
+        self, DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as _, PublishDiagnostics,
+    },
+    request::{
+        self, DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as _,
+    },
+};
+use serde::Serialize;
+
+use crate::document::Document;
+
+fn main() -> anyhow::Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::FULL,
+        )),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    };
+    connection
+        .initialize(serde_json::to_value(capabilities)?)
+        .context("Initializing connection.")?;
+
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+    };
+    server.run()?;
+
+    // Drop the connection, so the I/O threads can finish.
+    drop(server);
+    io_threads.join().context("Shutting down connection.")?;
+
+    Ok(())
+}
+
+struct Server {
+    connection: Connection,
+    documents: HashMap<Uri, Document>,
+}
+
+impl Server {
+    fn run(&mut self) -> anyhow::Result<()> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+
+                    self.handle_request(request)?;
+                }
+                Message::Notification(notification) => {
+                    self.handle_notification(notification)?;
+                }
+                Message::Response(_) => {
+                    // We don't send any requests, so we don't expect any
+                    // responses either.
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_request(&mut self, request: Request) -> anyhow::Result<()> {
+        // Every request needs a response, even if it's an error. Otherwise,
+        // the editor might wait for it forever.
+        let id = request.id.clone();
+        let response = match self.answer_request(request) {
+            Ok(result) => Response::new_ok(id, result),
+            Err(RequestError { code, message }) => {
+                Response::new_err(id, code as i32, message)
+            }
+        };
+
+        self.connection
+            .sender
+            .send(Message::Response(response))
+            .context("Sending response.")
+    }
+
+    fn answer_request(
+        &self,
+        request: Request,
+    ) -> Result<serde_json::Value, RequestError> {
+        let result = match request.method.as_str() {
+            GotoDefinition::METHOD => {
+                let params = request_params::<GotoDefinition>(request.params)?;
+                let uri =
+                    params.text_document_position_params.text_document.uri;
+                let position = params.text_document_position_params.position;
+
+                let range = self
+                    .documents
+                    .get(&uri)
+                    .and_then(|document| document.definition(position));
+                let response = range.map(|range| {
+                    GotoDefinitionResponse::Scalar(Location { uri, range })
+                });
+
+                serialize_result(response)?
+            }
+            HoverRequest::METHOD => {
+                let params = request_params::<HoverRequest>(request.params)?;
+                let uri =
+                    params.text_document_position_params.text_document.uri;
+                let position = params.text_document_position_params.position;
+
+                let hover = self
+                    .documents
+                    .get(&uri)
+                    .and_then(|document| document.hover(position));
+
+                serialize_result(hover)?
+            }
+            DocumentSymbolRequest::METHOD => {
+                let params =
+                    request_params::<DocumentSymbolRequest>(request.params)?;
+
+                let symbols = self
+                    .documents
+                    .get(&params.text_document.uri)
+                    .map(|document| {
+                        DocumentSymbolResponse::Nested(document.symbols())
+                    });
+
+                serialize_result(symbols)?
+            }
+            method => {
+                // We only announce the capabilities that we handle above, so
+                // the editor shouldn't send anything else.
+                return Err(RequestError {
+                    code: ErrorCode::MethodNotFound,
+                    message: format!("Unsupported request `{method}`."),
+                });
+            }
+        };
+
+        Ok(result)
+    }
+
+    fn handle_notification(
+        &mut self,
+        notification: Notification,
+    ) -> anyhow::Result<()> {
+        let uri = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Some(params) = notification_params::<DidOpenTextDocument>(
+                    notification.params,
+                ) else {
+                    return Ok(());
+                };
+                let document = params.text_document;
+
+                self.documents
+                    .insert(document.uri.clone(), Document::new(document.text));
+
+                document.uri
+            }
+            DidChangeTextDocument::METHOD => {
+                let Some(params) = notification_params::<DidChangeTextDocument>(
+                    notification.params,
+                ) else {
+                    return Ok(());
+                };
+
+                // We only support full synchronization, so the last change
+                // contains the whole text.
+                let Some(change) = params.content_changes.into_iter().last()
+                else {
+                    return Ok(());
+                };
+
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), Document::new(change.text));
+
+                uri
+            }
+            DidCloseTextDocument::METHOD => {
+                let Some(params) = notification_params::<DidCloseTextDocument>(
+                    notification.params,
+                ) else {
+                    return Ok(());
+                };
+                self.documents.remove(&params.text_document.uri);
+
+                return Ok(());
+            }
+            _ => {
+                return Ok(());
+            }
+        };
+
+        self.publish_diagnostics(uri)
+    }
+
+    fn publish_diagnostics(&self, uri: Uri) -> anyhow::Result<()> {
+        let diagnostics = self
+            .documents
+            .get(&uri)
+            .map(|document| document.diagnostics())
+            .unwrap_or_default();
+
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        };
+        let notification = Notification {
+            method: PublishDiagnostics::METHOD.to_string(),
+            params: serialize(params)?,
+        };
+
+        self.connection
+            .sender
+            .send(Message::Notification(notification))
+            .context("Sending diagnostics.")
+    }
+}
+
+/// # An error that is sent to the editor in response to a request
+struct RequestError {
+    code: ErrorCode,
+    message: String,
+}
+
+fn request_params<R: request::Request>(
+    params: serde_json::Value,
+) -> Result<R::Params, RequestError> {
+    serde_json::from_value(params).map_err(|err| RequestError {
+        code: ErrorCode::InvalidParams,
+        message: format!("Invalid request parameters: {err}"),
+    })
+}
+
+/// # Parse the parameters of a notification
+///
+/// Notifications don't get a response, so there's no way to tell the editor
+/// about invalid parameters. Those are reported on stderr, which editors
+/// usually log, and the notification is ignored.
+fn notification_params<N: notification::Notification>(
+    params: serde_json::Value,
+) -> Option<N::Params> {
+    serde_json::from_value(params)
+        .inspect_err(|err| {
+            eprintln!(
+                "Ignoring `{}` with invalid parameters: {err}",
+                N::METHOD
+            );
+        })
+        .ok()
+}
+
+fn serialize(value: impl Serialize) -> anyhow::Result<serde_json::Value> {
+    serde_json::to_value(value).context("Serializing result.")
+}
+
+fn serialize_result(
+    value: impl Serialize,
+) -> Result<serde_json::Value, RequestError> {
+    serialize(value).map_err(|err| RequestError {
+        code: ErrorCode::InternalError,
+        message: format!("{err:#}"),
+    })
+}
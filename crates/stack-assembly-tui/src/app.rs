@@ -0,0 +1,239 @@
+use std::{collections::BTreeSet, ops::Range};
+
+use ratatui::crossterm::event::KeyCode;
+use stack_assembly::{Eval, OperatorIndex, Script};
+
+/// # The keys that control the debugger, and what they do
+pub const KEYS: &[(&str, &str)] = &[
+    ("s", "step"),
+    ("u", "step back"),
+    ("c", "continue"),
+    ("x", "clear effect"),
+    ("b", "toggle breakpoint"),
+    ("↑↓", "move cursor"),
+    ("PgUp/PgDn", "scroll memory"),
+    ("q", "quit"),
+];
+
+/// # The number of words shown per row of the memory view
+pub const MEMORY_COLUMNS: u32 = 4;
+
+/// # The number of words that PgUp and PgDn scroll the memory view by
+const MEMORY_PAGE: u32 = MEMORY_COLUMNS * 16;
+
+/// # The number of steps that `continue` takes, before checking for input
+const STEPS_PER_CHUNK: u32 = 16 * 1024;
+
+/// # The state of the debugger
+pub struct App {
+    pub source: String,
+    pub script: Script,
+    pub eval: Eval,
+
+    /// # The operators that `continue` stops at
+    pub breakpoints: BTreeSet<OperatorIndex>,
+
+    /// # The line that the cursor is on, starting at `0`
+    ///
+    /// Breakpoints are toggled on this line. The cursor follows the next
+    /// operator, whenever the evaluation advances.
+    pub cursor: usize,
+
+    /// # The address that the memory view starts at
+    pub memory_start: u32,
+
+    /// # A message about the result of the last action
+    pub message: String,
+
+    /// # Whether `continue` is in progress
+    ///
+    /// While this is the case, the evaluation advances in chunks (see
+    /// [`App::run_chunk`]), and any key stops it.
+    pub running: bool,
+}
+
+impl App {
+    pub fn new(source: String) -> Self {
+        /// # How many steps the user can step back
+        const HISTORY_CAPACITY: usize = 64 * 1024;
+
+        let script = Script::compile(&source);
+
+        let mut eval = Eval::start_at(script.start());
+        eval.enable_history(HISTORY_CAPACITY);
+        eval.load_data(&script);
+
+        let mut app = Self {
+            source,
+            script,
+            eval,
+            breakpoints: BTreeSet::new(),
+            cursor: 0,
+            memory_start: 0,
+            message: String::new(),
+            running: false,
+        };
+        app.follow_next_operator();
+
+        app
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> Continue {
+        self.message.clear();
+
+        match key {
+            KeyCode::Char('s') => {
+                if !self.effect_is_active() {
+                    self.eval.step(&self.script);
+                }
+                self.follow_next_operator();
+            }
+            KeyCode::Char('u') => {
+                if !self.eval.step_back() {
+                    self.message = "Can't step back any further.".to_string();
+                }
+                self.follow_next_operator();
+            }
+            KeyCode::Char('c') => {
+                // If an effect is active, the first chunk stops right away.
+                self.running = true;
+                self.message = "Running. Press any key to stop.".to_string();
+            }
+            KeyCode::Char('x') => {
+                self.message = match self.eval.clear_effect() {
+                    Some((effect, _)) => format!("Cleared effect: {effect}"),
+                    None => "No effect is active.".to_string(),
+                };
+            }
+            KeyCode::Char('b') => {
+                self.toggle_breakpoint();
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.cursor = self.cursor.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                let last_line = self.source.lines().count().saturating_sub(1);
+                self.cursor = (self.cursor + 1).min(last_line);
+            }
+            KeyCode::PageUp => {
+                self.memory_start =
+                    self.memory_start.saturating_sub(MEMORY_PAGE);
+            }
+            KeyCode::PageDown => {
+                let start = self.memory_start.saturating_add(MEMORY_PAGE);
+                if start < self.memory_size() {
+                    self.memory_start = start;
+                }
+            }
+            KeyCode::Char('q') | KeyCode::Esc => {
+                return Continue::No;
+            }
+            _ => {}
+        }
+
+        Continue::Yes
+    }
+
+    /// # Advance a running evaluation by a bounded number of steps
+    ///
+    /// Stops running, if an effect triggers or a breakpoint is hit. Otherwise,
+    /// the caller is expected to check for input, then call this again.
+    pub fn run_chunk(&mut self) {
+        for _ in 0..STEPS_PER_CHUNK {
+            if self.effect_is_active() {
+                self.running = false;
+                break;
+            }
+
+            self.eval.step(&self.script);
+
+            if self.breakpoints.contains(&self.eval.next_operator()) {
+                self.message = "Hit breakpoint.".to_string();
+                self.running = false;
+                break;
+            }
+        }
+
+        self.follow_next_operator();
+    }
+
+    /// # Stop a running evaluation, before it reaches an effect or breakpoint
+    pub fn stop(&mut self) {
+        self.running = false;
+        self.message = "Stopped.".to_string();
+        self.follow_next_operator();
+    }
+
+    fn effect_is_active(&mut self) -> bool {
+        let Some((effect, _)) = self.eval.effect() else {
+            return false;
+        };
+
+        self.message = format!(
+            "Effect is active: {effect}. Press `x` to clear it and continue \
+            the evaluation."
+        );
+
+        true
+    }
+
+    fn toggle_breakpoint(&mut self) {
+        let operator = self
+            .script
+            .operators()
+            .map(|(operator, _)| operator)
+            .find(|operator| self.line_of(*operator) == Some(self.cursor));
+
+        let Some(operator) = operator else {
+            self.message = format!("No operator on line {}.", self.cursor + 1);
+            return;
+        };
+
+        if self.breakpoints.remove(&operator) {
+            self.message =
+                format!("Breakpoint at operator {operator} deleted.");
+        } else {
+            self.breakpoints.insert(operator);
+            self.message = format!("Breakpoint set at operator {operator}.");
+        }
+    }
+
+    fn follow_next_operator(&mut self) {
+        if let Some(line) = self.line_of(self.eval.next_operator()) {
+            self.cursor = line;
+        }
+    }
+
+    fn memory_size(&self) -> u32 {
+        let Ok(size) = u32::try_from(self.eval.memory.values().len()) else {
+            unreachable!("Memory is addressed using `u32`.");
+        };
+        size
+    }
+
+    pub fn source_range(
+        &self,
+        operator: OperatorIndex,
+    ) -> Option<Range<usize>> {
+        self.script.map_operator_to_source(&operator).ok()
+    }
+
+    /// # The line that the operator is on, starting at `0`
+    pub fn line_of(&self, operator: OperatorIndex) -> Option<usize> {
+        let range = self.source_range(operator)?;
+        Some(self.source[..range.start].matches('\n').count())
+    }
+
+    /// # The lines that have a breakpoint on them, starting at `0`
+    pub fn breakpoint_lines(&self) -> BTreeSet<usize> {
+        self.breakpoints
+            .iter()
+            .filter_map(|operator| self.line_of(*operator))
+            .collect()
+    }
+}
+
+pub enum Continue {
+    Yes,
+    No,
+}
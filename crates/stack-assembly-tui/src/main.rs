@@ -0,0 +1,81 @@
+//! # Terminal user interface for debugging StackAssembly scripts
+//!
+//! Shows the source code with the next operator highlighted, alongside the
+//! operand stack, the call stack, and the memory. See [`app::KEYS`] for how to
+//! control the evaluation.
+
+mod app;
+mod ui;
+
+use std::{fs, path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use clap::Parser;
+use ratatui::{
+    DefaultTerminal,
+    crossterm::event::{self, Event, KeyEventKind},
+};
+
+use crate::app::{App, Continue};
+
+fn main() -> anyhow::Result<()> {
+    /// Debugger for the StackAssembly programming language
+    #[derive(clap::Parser)]
+    struct Args {
+        /// The path to the script that should be debugged
+        path: PathBuf,
+    }
+
+    let args = Args::parse();
+    let source =
+        fs::read_to_string(&args.path).context("Reading script file.")?;
+
+    let app = App::new(source);
+
+    let terminal = ratatui::init();
+    let result = run(terminal, app);
+    ratatui::restore();
+
+    result
+}
+
+fn run(mut terminal: DefaultTerminal, mut app: App) -> anyhow::Result<()> {
+    loop {
+        terminal
+            .draw(|frame| ui::render(frame, &app))
+            .context("Drawing user interface.")?;
+
+        if app.running {
+            // A script that never triggers an effect would run forever. So we
+            // run it in chunks, and allow the user to stop it in between.
+            if event::poll(Duration::ZERO).context("Polling input.")? {
+                if let Event::Key(key) =
+                    event::read().context("Reading input.")?
+                    && key.kind == KeyEventKind::Press
+                {
+                    app.stop();
+                }
+            } else {
+                app.run_chunk();
+            }
+
+            continue;
+        }
+
+        let Event::Key(key) = event::read().context("Reading input.")? else {
+            // We only care about key presses. Anything else, like a resize,
+            // just requires redrawing the user interface.
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match app.handle_key(key.code) {
+            Continue::Yes => {}
+            Continue::No => {
+                return Ok(());
+            }
+        }
+    }
+}
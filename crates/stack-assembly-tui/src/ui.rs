@@ -0,0 +1,163 @@
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, Paragraph},
+};
+
+use crate::app::{App, KEYS, MEMORY_COLUMNS};
+
+pub fn render(frame: &mut Frame, app: &App) {
+    let [main, status] =
+        Layout::vertical([Constraint::Fill(1), Constraint::Length(2)])
+            .areas(frame.area());
+    let [source, side] =
+        Layout::horizontal([Constraint::Fill(3), Constraint::Fill(2)])
+            .areas(main);
+    let [stacks, memory] =
+        Layout::vertical([Constraint::Fill(1), Constraint::Fill(1)])
+            .areas(side);
+    let [operand_stack, call_stack] =
+        Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)])
+            .areas(stacks);
+
+    render_source(frame, app, source);
+    render_operand_stack(frame, app, operand_stack);
+    render_call_stack(frame, app, call_stack);
+    render_memory(frame, app, memory);
+    render_status(frame, app, status);
+}
+
+fn render_source(frame: &mut Frame, app: &App, area: Rect) {
+    let next_operator = app.source_range(app.eval.next_operator());
+    let breakpoints = app.breakpoint_lines();
+
+    let mut lines = Vec::new();
+    let mut start = 0;
+
+    for (i, text) in app.source.split('\n').enumerate() {
+        let end = start + text.len();
+
+        let marker = if breakpoints.contains(&i) { "●" } else { " " };
+        let mut spans = vec![
+            Span::from(marker).red(),
+            Span::from(format!("{:>4} ", i + 1)).dark_gray(),
+        ];
+
+        // Highlight the next operator, if it's on this line.
+        match &next_operator {
+            Some(range) if range.start >= start && range.start <= end => {
+                let operator = range.start - start..range.end.min(end) - start;
+
+                spans.push(Span::from(&text[..operator.start]));
+                spans.push(
+                    Span::from(&text[operator.clone()]).black().on_yellow(),
+                );
+                spans.push(Span::from(&text[operator.end..]));
+            }
+            _ => {
+                spans.push(Span::from(text));
+            }
+        }
+
+        let mut line = Line::from(spans);
+        if i == app.cursor {
+            line = line.style(Style::new().on_dark_gray());
+        }
+        lines.push(line);
+
+        start = end + 1;
+    }
+
+    // Keep the cursor in the middle of the view, where possible.
+    let height = usize::from(area.height.saturating_sub(2));
+    let scroll = app.cursor.saturating_sub(height / 2);
+    let scroll = u16::try_from(scroll).unwrap_or(u16::MAX);
+
+    let title = if next_operator.is_some() {
+        " Source ".to_string()
+    } else {
+        " Source (reached the end of the script) ".to_string()
+    };
+
+    let source = Paragraph::new(lines)
+        .block(Block::bordered().title(title))
+        .scroll((scroll, 0));
+    frame.render_widget(source, area);
+}
+
+fn render_operand_stack(frame: &mut Frame, app: &App, area: Rect) {
+    // Show the top of the stack first, numbered like the indices that `copy`
+    // and `drop` expect.
+    let values = app
+        .eval
+        .operand_stack
+        .values
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, value)| format!("{index:>3}: {}", value.to_i32()));
+
+    let list = List::new(values).block(Block::bordered().title(" Operands "));
+    frame.render_widget(list, area);
+}
+
+fn render_call_stack(frame: &mut Frame, app: &App, area: Rect) {
+    let calls = app.eval.call_stack.call_sites().map(|operator| {
+        match app.script.label_at(operator) {
+            Some((name, _)) => format!("{operator} in {name}:"),
+            None => format!("{operator}"),
+        }
+    });
+
+    let list = List::new(calls).block(Block::bordered().title(" Calls "));
+    frame.render_widget(list, area);
+}
+
+fn render_memory(frame: &mut Frame, app: &App, area: Rect) {
+    let values = app.eval.memory.to_u32_slice();
+    let num_rows = usize::from(area.height.saturating_sub(2));
+
+    let rows = values
+        .chunks(MEMORY_COLUMNS as usize)
+        .zip((0..).step_by(MEMORY_COLUMNS as usize))
+        .skip(app.memory_start as usize / MEMORY_COLUMNS as usize)
+        .take(num_rows)
+        .map(|(words, address): (_, u32)| {
+            let mut spans =
+                vec![Span::from(format!("{address:>6}:")).dark_gray()];
+
+            for &word in words {
+                let span = Span::from(format!(" {word:08x}"));
+                spans.push(if word == 0 { span.dark_gray() } else { span });
+            }
+
+            Line::from(spans)
+        })
+        .collect::<Vec<_>>();
+
+    let memory =
+        Paragraph::new(rows).block(Block::bordered().title(" Memory "));
+    frame.render_widget(memory, area);
+}
+
+fn render_status(frame: &mut Frame, app: &App, area: Rect) {
+    let message = match app.eval.effect() {
+        _ if !app.message.is_empty() => Line::from(app.message.as_str()),
+        Some((effect, operator)) => Line::from(format!(
+            "Effect triggered by operator {operator}: {effect}"
+        ))
+        .yellow(),
+        None => Line::from(""),
+    };
+
+    let keys = KEYS
+        .iter()
+        .flat_map(|(key, action)| {
+            [Span::from(*key).bold(), Span::from(format!(" {action}  "))]
+        })
+        .collect::<Line>();
+
+    frame.render_widget(Paragraph::new(vec![message, keys]), area);
+}
@@ -0,0 +1,288 @@
+//! # WebAssembly bindings for StackAssembly
+//!
+//! This crate wraps the [StackAssembly interpreter][stack-assembly] in an API
+//! that can be exported to JavaScript using [`wasm-bindgen`]. It is intended
+//! to power in-browser tools, like a playground or an online debugger.
+//!
+//! The API mirrors that of the interpreter library closely. You compile a
+//! script using [`compile`], then advance its evaluation using [`Eval`].
+//!
+//! [stack-assembly]: https://docs.rs/stack-assembly/latest/stack_assembly/
+//! [`wasm-bindgen`]: https://docs.rs/wasm-bindgen/latest/wasm_bindgen/
+
+#![warn(missing_debug_implementations)]
+#![warn(missing_docs)]
+
+use stack_assembly::{OperatorIndex, Value};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// # Compile the source text of a script
+///
+/// See [`stack_assembly::Script::compile`].
+#[wasm_bindgen]
+pub fn compile(source: &str) -> Script {
+    Script {
+        inner: stack_assembly::Script::compile(source),
+    }
+}
+
+/// # A compiled script
+///
+/// Wraps [`stack_assembly::Script`]. Use [`compile`] to create an instance.
+#[derive(Debug)]
+#[wasm_bindgen]
+pub struct Script {
+    inner: stack_assembly::Script,
+}
+
+#[wasm_bindgen]
+impl Script {
+    /// # Map an operator to the range in the source that it was compiled from
+    ///
+    /// Returns the start and end of the range, as byte offsets into the
+    /// source text that was passed to [`compile`]. Returns `undefined`, if the
+    /// provided index does not refer to an operator in the script.
+    ///
+    /// See [`stack_assembly::Script::map_operator_to_source`].
+    #[wasm_bindgen(js_name = mapOperatorToSource)]
+    pub fn map_operator_to_source(&self, operator: u32) -> Option<Vec<u32>> {
+        let operator = OperatorIndex::new(operator);
+        let range = self.inner.map_operator_to_source(&operator).ok()?;
+
+        let start = range.start.try_into().ok()?;
+        let end = range.end.try_into().ok()?;
+
+        Some(vec![start, end])
+    }
+
+    /// # Access the index of the operator that the evaluation starts at
+    ///
+    /// See [`stack_assembly::Script::start`].
+    pub fn start(&self) -> u32 {
+        self.inner.start().value()
+    }
+}
+
+/// # The ongoing evaluation of a script
+///
+/// Wraps [`stack_assembly::Eval`].
+#[derive(Debug, Default)]
+#[wasm_bindgen]
+pub struct Eval {
+    inner: stack_assembly::Eval,
+}
+
+#[wasm_bindgen]
+impl Eval {
+    /// # Create an evaluation that is ready to evaluate a script
+    ///
+    /// See [`stack_assembly::Eval::new`].
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Create an evaluation that starts at the provided operator
+    ///
+    /// See [`stack_assembly::Eval::start_at`].
+    #[wasm_bindgen(js_name = startAt)]
+    pub fn start_at(operator: u32) -> Self {
+        Self {
+            inner: stack_assembly::Eval::start_at(OperatorIndex::new(operator)),
+        }
+    }
+
+    /// # Limit the number of operators that can be evaluated
+    ///
+    /// Pass `undefined` to allow an unlimited number of operators to be
+    /// evaluated.
+    ///
+    /// See [`stack_assembly::Eval::set_fuel`].
+    #[wasm_bindgen(js_name = setFuel)]
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.inner.set_fuel(fuel);
+    }
+
+    /// # Write the values that the script's memory starts out with
+    ///
+    /// See [`stack_assembly::Eval::load_data`].
+    #[wasm_bindgen(js_name = loadData)]
+    pub fn load_data(&mut self, script: &Script) {
+        self.inner.load_data(&script.inner);
+    }
+
+    /// # Advance the evaluation until it triggers an effect
+    ///
+    /// See [`stack_assembly::Eval::run`].
+    pub fn run(&mut self, script: &Script) -> TriggeredEffect {
+        let (effect, operator) = self.inner.run(&script.inner);
+        TriggeredEffect::new(effect, operator)
+    }
+
+    /// # Advance the evaluation by one step
+    ///
+    /// Returns `undefined`, if no effect has triggered.
+    ///
+    /// See [`stack_assembly::Eval::step`].
+    pub fn step(&mut self, script: &Script) -> Option<TriggeredEffect> {
+        let (effect, operator) = self.inner.step(&script.inner)?;
+        Some(TriggeredEffect::new(effect, operator))
+    }
+
+    /// # Clear the active effect, if any
+    ///
+    /// Returns the effect that has been cleared, or `undefined`, if no effect
+    /// was active.
+    ///
+    /// See [`stack_assembly::Eval::clear_effect`].
+    #[wasm_bindgen(js_name = clearEffect)]
+    pub fn clear_effect(&mut self) -> Option<TriggeredEffect> {
+        let (effect, operator) = self.inner.clear_effect()?;
+        Some(TriggeredEffect::new(effect, operator))
+    }
+
+    /// # Access the current call stack
+    ///
+    /// Returns the indices of the operators on the call stack, starting with
+    /// the top-most one.
+    ///
+    /// See [`stack_assembly::CallStack::call_sites`].
+    #[wasm_bindgen(js_name = callStack)]
+    pub fn call_stack(&self) -> Vec<u32> {
+        self.inner
+            .call_stack
+            .call_sites()
+            .map(|index| index.value())
+            .collect()
+    }
+
+    /// # Access the operand stack, interpreting all values as signed
+    ///
+    /// Returns the values on the operand stack, starting with the bottom-most
+    /// one.
+    #[wasm_bindgen(js_name = operandStack)]
+    pub fn operand_stack(&self) -> Vec<i32> {
+        self.inner.operand_stack.to_i32_slice().to_vec()
+    }
+
+    /// # Push a value to the top of the operand stack
+    pub fn push(&mut self, value: i32) {
+        self.inner.operand_stack.push(value);
+    }
+
+    /// # Pop a value from the top of the operand stack
+    ///
+    /// Returns `undefined`, if the operand stack is empty.
+    pub fn pop(&mut self) -> Option<i32> {
+        let value = self.inner.operand_stack.pop().ok()?;
+        Some(value.to_i32())
+    }
+
+    /// # Access the memory, interpreting all values as signed
+    pub fn memory(&self) -> Vec<i32> {
+        self.inner.memory.to_i32_slice().to_vec()
+    }
+
+    /// # Read the value at the provided memory address
+    ///
+    /// Returns `undefined`, if the address is out of bounds.
+    pub fn read(&self, address: u32) -> Option<i32> {
+        let value = self.inner.memory.read(address).ok()?;
+        Some(value.to_i32())
+    }
+
+    /// # Write a value to the provided memory address
+    ///
+    /// Returns `false`, if the address is out of bounds.
+    pub fn write(&mut self, address: u32, value: i32) -> bool {
+        self.inner.memory.write(address, Value::from(value)).is_ok()
+    }
+}
+
+/// # An effect, together with the operator that triggered it
+#[derive(Clone, Copy, Debug)]
+#[wasm_bindgen]
+pub struct TriggeredEffect {
+    /// # The effect that has triggered
+    pub effect: Effect,
+
+    /// # The index of the operator that triggered the effect
+    pub operator: u32,
+}
+
+impl TriggeredEffect {
+    fn new(effect: stack_assembly::Effect, operator: OperatorIndex) -> Self {
+        Self {
+            effect: effect.into(),
+            operator: operator.value(),
+        }
+    }
+}
+
+/// # An event triggered by scripts, to signal a specific condition
+///
+/// Mirrors [`stack_assembly::Effect`]. Please refer to its documentation for
+/// information on the individual effects.
+///
+/// The numeric value of each variant is the stable code returned by
+/// [`stack_assembly::Effect::code`].
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[wasm_bindgen]
+pub enum Effect {
+    AssertionFailed = 0,
+    CallStackOverflow = 20,
+    Cancelled = 14,
+    ChannelEmpty = 13,
+    DisabledOperator = 18,
+    DivisionByZero = 1,
+    IntegerOverflow = 2,
+    InvalidAddress = 3,
+    InvalidLocal = 15,
+    InvalidOperandStackIndex = 4,
+    InvalidReference = 5,
+    InvalidStrand = 10,
+    NonReferenceTarget = 21,
+    OperandStackOverflow = 19,
+    OperandStackUnderflow = 6,
+    OutOfFuel = 11,
+    OutOfOperators = 7,
+    Return = 8,
+    Todo = 17,
+    TooManyStrands = 22,
+    UnknownIdentifier = 9,
+    Unreachable = 16,
+    Yield = 12,
+}
+
+impl From<stack_assembly::Effect> for Effect {
+    fn from(effect: stack_assembly::Effect) -> Self {
+        use stack_assembly::Effect as E;
+
+        match effect {
+            E::AssertionFailed => Self::AssertionFailed,
+            E::CallStackOverflow => Self::CallStackOverflow,
+            E::Cancelled => Self::Cancelled,
+            E::ChannelEmpty => Self::ChannelEmpty,
+            E::DisabledOperator => Self::DisabledOperator,
+            E::DivisionByZero => Self::DivisionByZero,
+            E::IntegerOverflow => Self::IntegerOverflow,
+            E::InvalidAddress => Self::InvalidAddress,
+            E::InvalidLocal => Self::InvalidLocal,
+            E::InvalidOperandStackIndex => Self::InvalidOperandStackIndex,
+            E::InvalidReference => Self::InvalidReference,
+            E::InvalidStrand => Self::InvalidStrand,
+            E::NonReferenceTarget => Self::NonReferenceTarget,
+            E::OperandStackOverflow => Self::OperandStackOverflow,
+            E::OperandStackUnderflow => Self::OperandStackUnderflow,
+            E::OutOfFuel => Self::OutOfFuel,
+            E::OutOfOperators => Self::OutOfOperators,
+            E::Return => Self::Return,
+            E::Todo => Self::Todo,
+            E::TooManyStrands => Self::TooManyStrands,
+            E::UnknownIdentifier => Self::UnknownIdentifier,
+            E::Unreachable => Self::Unreachable,
+            E::Yield => Self::Yield,
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! # Benchmarks for the evaluation of scripts
+//!
+//! Run them with `cargo bench --package stack-assembly`. Each benchmark runs a
+//! script a few times and reports the fastest run, to reduce noise from other
+//! processes. This is not nearly as rigorous as a proper benchmarking
+//! framework, but good enough to see whether a change to the interpreter makes
+//! a difference.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use stack_assembly::{Eval, Script};
+
+const BENCHMARKS: &[(&str, &str)] = &[
+    (
+        "count",
+        "
+        0
+        loop:
+            1 +
+            0 copy 1000000 <
+            @loop jump_if
+        ",
+    ),
+    (
+        "fibonacci",
+        "
+        0 1 0
+        loop:
+            1 +
+            2 copy 2 copy +
+            3 drop
+            1 copy
+            0 copy 1000000 <
+            @loop jump_if
+        ",
+    ),
+    (
+        "call",
+        "
+        0
+        loop:
+            @increment call
+            0 copy 1000000 <
+            @loop jump_if
+            return
+
+        increment:
+            1 +
+            return
+        ",
+    ),
+    (
+        "memory",
+        "
+        0
+        loop:
+            0 copy 1023 and 0 copy write
+            0 copy 1023 and read 0 drop
+            1 +
+            0 copy 1000000 <
+            @loop jump_if
+        ",
+    ),
+];
+
+const RUNS: u32 = 5;
+
+fn main() {
+    for (name, source) in BENCHMARKS {
+        let script = Script::compile(source);
+
+        let num_operators = {
+            let mut eval = Eval::new();
+            eval.set_fuel(Some(u64::MAX));
+            eval.run(&script);
+
+            let Some(remaining) = eval.fuel() else {
+                unreachable!("Fuel has been set above.");
+            };
+            u64::MAX - remaining
+        };
+
+        let fastest = (0..RUNS)
+            .map(|_| {
+                let mut eval = Eval::new();
+
+                let start = Instant::now();
+                black_box(eval.run(black_box(&script)));
+                start.elapsed()
+            })
+            .min()
+            .unwrap_or(Duration::ZERO);
+
+        let per_operator = fastest.as_secs_f64() * 1e9 / num_operators as f64;
+
+        println!(
+            "{name:>10}: {fastest:>10.2?} ({num_operators} operators, \
+            {per_operator:.2} ns/operator)"
+        );
+    }
+}
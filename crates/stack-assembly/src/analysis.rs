@@ -0,0 +1,1140 @@
+//! # Abstract interpretation of a script
+//!
+//! See [`Script::analyze`].
+
+use std::cmp::Ordering;
+
+use crate::{
+    Effect, Eval, OperatorIndex, Script, Value, eval::Instruction,
+    extension::Extension, opcode::Opcode, script::Operator,
+    stack_depth::fixed_arity,
+};
+
+impl Script {
+    /// # Evaluate the script abstractly, to find out what it can do
+    ///
+    /// Follows the evaluation from the start of the script (see
+    /// [`Script::start`]), from the start of each test (see [`Script::tests`]),
+    /// and from the start of each procedure (see [`Script::procedures`]),
+    /// without actually evaluating anything. For each operator that this
+    /// reaches, the analysis determines what is known about the operand stack
+    /// right before it, and which effects evaluating it can trigger.
+    ///
+    /// Each value on the stack is tracked as the range of integers it can be
+    /// (see [`Interval`]). Where paths of the evaluation come together, what is
+    /// known about them is combined. So where the number of values on the
+    /// stack depends on the path that the evaluation took, only the values on
+    /// top of the stack that all paths agree on remain known. The same goes
+    /// for what the analysis can't know about, like what the host does with the
+    /// stack when the script yields, or what a routine that is called does
+    /// with it. Calls to procedures are the exception, as they declare what
+    /// they do with the stack.
+    ///
+    /// The analysis only follows jumps and calls to targets that it knows. It
+    /// assumes that the host uses the default settings, and it only reports
+    /// effects that depend on the script itself. Effects that depend on how
+    /// the host sets up the evaluation, like [`Effect::InvalidAddress`] or
+    /// [`Effect::OutOfFuel`], are never reported.
+    ///
+    /// ```
+    /// use stack_assembly::{Effect, Interval, OperatorIndex, Script};
+    ///
+    /// let script = Script::compile("1 2 + 0 /");
+    /// let analysis = script.analyze();
+    ///
+    /// let Some(stack) = analysis.stack(OperatorIndex::new(4)) else {
+    ///     unreachable!("The operator is reachable.");
+    /// };
+    /// assert_eq!(
+    ///     stack.values(),
+    ///     &[Interval::constant(3), Interval::constant(0)],
+    /// );
+    /// assert_eq!(
+    ///     analysis.certain_effect(OperatorIndex::new(4)),
+    ///     Some(Effect::DivisionByZero),
+    /// );
+    /// ```
+    pub fn analyze(&self) -> Analysis {
+        // The evaluation of a script, as well as that of a test, starts with an
+        // empty stack. A procedure starts with its inputs.
+        let entry_points = [(self.start(), 0)]
+            .into_iter()
+            .chain(self.tests().map(|(_, operator)| (operator, 0)))
+            .chain(
+                self.procedures()
+                    .map(|(_, operator, arity)| (operator, arity.inputs)),
+            );
+
+        analyze(self, entry_points)
+    }
+}
+
+/// # The result of evaluating a script abstractly
+///
+/// See [`Script::analyze`].
+#[derive(Clone, Debug)]
+pub struct Analysis {
+    stacks: Vec<Option<AbstractStack>>,
+    effects: Vec<Effects>,
+}
+
+impl Analysis {
+    /// # Determine whether the analysis reached the provided operator
+    ///
+    /// Operators that the analysis didn't reach, might still be reached by
+    /// the evaluation, through a jump or call to a target that the analysis
+    /// doesn't know.
+    pub fn is_reachable(&self, operator: OperatorIndex) -> bool {
+        self.stack(operator).is_some()
+    }
+
+    /// # Access what is known about the stack, right before an operator
+    ///
+    /// Returns `None`, if the analysis didn't reach the operator.
+    pub fn stack(&self, operator: OperatorIndex) -> Option<&AbstractStack> {
+        self.stacks.get(operator.value() as usize)?.as_ref()
+    }
+
+    /// # Access the effects that evaluating an operator can trigger
+    pub fn possible_effects(&self, operator: OperatorIndex) -> &[Effect] {
+        self.effects
+            .get(operator.value() as usize)
+            .map(|effects| effects.possible.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// # Access the effect that evaluating an operator always triggers
+    ///
+    /// Returns `None`, unless the operator triggers the effect on every path
+    /// that the analysis followed to it.
+    pub fn certain_effect(&self, operator: OperatorIndex) -> Option<Effect> {
+        self.effects.get(operator.value() as usize)?.certain
+    }
+
+    /// # Iterate over all effects that operators can trigger
+    ///
+    /// Yields each operator alongside each of its possible effects, ordered by
+    /// the index of the operator.
+    pub fn effects(&self) -> impl Iterator<Item = (OperatorIndex, Effect)> {
+        self.effects.iter().zip(0..).flat_map(|(effects, index)| {
+            effects
+                .possible
+                .iter()
+                .map(move |&effect| (OperatorIndex::new(index), effect))
+        })
+    }
+}
+
+/// # What is known about the operand stack, right before an operator
+///
+/// See [`Script::analyze`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AbstractStack {
+    values: Vec<Interval>,
+    is_complete: bool,
+}
+
+impl AbstractStack {
+    /// # The stack of a strand that hasn't done anything yet
+    fn empty() -> Self {
+        Self {
+            values: Vec::new(),
+            is_complete: true,
+        }
+    }
+
+    /// # A stack that nothing is known about
+    fn unknown() -> Self {
+        Self {
+            values: Vec::new(),
+            is_complete: false,
+        }
+    }
+
+    /// # Access the values on top of the stack that are known
+    ///
+    /// The top of the stack comes last. If the number of values on the stack
+    /// is known (see [`AbstractStack::depth`]), these are all of them.
+    /// Otherwise, there can be any number of values below them.
+    pub fn values(&self) -> &[Interval] {
+        &self.values
+    }
+
+    /// # Access the number of values on the stack, if it is known
+    pub fn depth(&self) -> Option<usize> {
+        self.is_complete.then_some(self.values.len())
+    }
+
+    fn pop(&mut self) -> Result<Interval, Effect> {
+        match self.values.pop() {
+            Some(value) => Ok(value),
+            None if self.is_complete => Err(Effect::OperandStackUnderflow),
+            None => Ok(Interval::FULL),
+        }
+    }
+
+    fn push(&mut self, value: Interval) {
+        self.values.push(value);
+    }
+
+    /// # Find the value that an index refers to, like `copy` expects it
+    fn locate(&self, index: i32) -> Location {
+        let len = self.values.len();
+
+        let position = if index < 0 {
+            usize::try_from(!index).ok()
+        } else {
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| len.checked_sub(index)?.checked_sub(1))
+        };
+
+        match position {
+            Some(position)
+                if position < len && (index >= 0 || self.is_complete) =>
+            {
+                Location::Known { position }
+            }
+            _ if self.is_complete => Location::Invalid,
+            _ => Location::Unknown,
+        }
+    }
+
+    /// # Combine what is known about two paths to the same operator
+    fn merge(&self, other: &Self) -> Self {
+        if self.is_complete
+            && other.is_complete
+            && self.values.len() == other.values.len()
+        {
+            let values = self
+                .values
+                .iter()
+                .zip(&other.values)
+                .map(|(a, b)| a.hull(*b))
+                .collect();
+
+            return Self {
+                values,
+                is_complete: true,
+            };
+        }
+
+        // Only the values on top of the stack that both paths know about
+        // remain known.
+        let num_values = self.values.len().min(other.values.len());
+        let a = &self.values[self.values.len() - num_values..];
+        let b = &other.values[other.values.len() - num_values..];
+
+        Self {
+            values: a.iter().zip(b).map(|(a, b)| a.hull(*b)).collect(),
+            is_complete: false,
+        }
+    }
+
+    /// # Extrapolate the growth of the values, so the analysis finishes
+    ///
+    /// Expects `next` to be the result of merging another stack into `self`.
+    fn widen(&self, next: Self) -> Self {
+        if self.values.len() != next.values.len()
+            || self.is_complete != next.is_complete
+        {
+            // The number of known values only ever goes down, so this can't
+            // go on forever.
+            return next;
+        }
+
+        let values = self
+            .values
+            .iter()
+            .zip(&next.values)
+            .map(|(a, b)| a.widen(*b))
+            .collect();
+
+        Self {
+            values,
+            is_complete: next.is_complete,
+        }
+    }
+}
+
+/// # Where a value on the stack is, according to what's known about the stack
+enum Location {
+    Known { position: usize },
+    Unknown,
+    Invalid,
+}
+
+/// # The range of integers that a value can be
+///
+/// The bounds are inclusive, and interpret the value as a signed integer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Interval {
+    /// # The lowest integer that the value can be
+    pub min: i32,
+
+    /// # The highest integer that the value can be
+    pub max: i32,
+}
+
+impl Interval {
+    /// # The interval of a value that can be any integer
+    pub const FULL: Self = Self {
+        min: i32::MIN,
+        max: i32::MAX,
+    };
+
+    /// # The interval of a value that is always the provided integer
+    pub fn constant(value: i32) -> Self {
+        Self {
+            min: value,
+            max: value,
+        }
+    }
+
+    /// # Access the integer that the value always is, if there is one
+    pub fn as_constant(&self) -> Option<i32> {
+        (self.min == self.max).then_some(self.min)
+    }
+
+    /// # Determine whether the value can be the provided integer
+    pub fn contains(&self, value: i32) -> bool {
+        self.min <= value && value <= self.max
+    }
+
+    fn from_i64(min: i64, max: i64) -> Self {
+        match (i32::try_from(min), i32::try_from(max)) {
+            (Ok(min), Ok(max)) => Self { min, max },
+            _ => {
+                // The result wraps around, so it can be anything.
+                Self::FULL
+            }
+        }
+    }
+
+    fn from_bool(can_be_false: bool, can_be_true: bool) -> Self {
+        Self {
+            min: if can_be_false { 0 } else { 1 },
+            max: if can_be_true { 1 } else { 0 },
+        }
+    }
+
+    fn hull(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    fn widen(self, next: Self) -> Self {
+        Self {
+            min: if next.min < self.min {
+                i32::MIN
+            } else {
+                self.min
+            },
+            max: if next.max > self.max {
+                i32::MAX
+            } else {
+                self.max
+            },
+        }
+    }
+
+    fn can_be_true(&self) -> bool {
+        *self != Self::constant(0)
+    }
+
+    fn can_be_false(&self) -> bool {
+        self.contains(0)
+    }
+
+    /// # Determine the orderings that comparing two values can result in
+    fn orderings(self, other: Self) -> impl Iterator<Item = Ordering> {
+        [
+            (Ordering::Less, self.min < other.max),
+            (
+                Ordering::Equal,
+                self.min <= other.max && other.min <= self.max,
+            ),
+            (Ordering::Greater, self.max > other.min),
+        ]
+        .into_iter()
+        .filter_map(|(ordering, is_possible)| is_possible.then_some(ordering))
+    }
+}
+
+/// # The effects that evaluating an operator can trigger
+#[derive(Clone, Debug, Default)]
+struct Effects {
+    possible: Vec<Effect>,
+    certain: Option<Effect>,
+}
+
+impl Effects {
+    fn possible(&mut self, effect: Effect) {
+        if !self.possible.contains(&effect) {
+            self.possible.push(effect);
+        }
+    }
+
+    fn certain(&mut self, effect: Effect) {
+        self.possible(effect);
+        self.certain = Some(effect);
+    }
+}
+
+/// # Evaluate the script abstractly, starting from the provided operators
+///
+/// Each entry point starts with the provided number of unknown values on the
+/// stack.
+pub(crate) fn analyze(
+    script: &Script,
+    entry_points: impl IntoIterator<Item = (OperatorIndex, u32)>,
+) -> Analysis {
+    let operators = script
+        .operators()
+        .map(|(_, operator)| operator)
+        .collect::<Vec<_>>();
+
+    let mut stacks = vec![None; operators.len()];
+    let mut num_changes = vec![0; operators.len()];
+    let mut queue = Vec::new();
+
+    // Constant values are computed by actually evaluating the operators, so
+    // the analysis can't disagree with the evaluation about them.
+    let mut scratch = Eval::new();
+
+    for (entry_point, num_values) in entry_points {
+        let stack = AbstractStack {
+            values: vec![Interval::FULL; num_values as usize],
+            is_complete: true,
+        };
+        enter(
+            &mut stacks,
+            &mut num_changes,
+            &mut queue,
+            entry_point.value(),
+            stack,
+        );
+    }
+
+    while let Some(index) = queue.pop() {
+        let Some(stack) = stacks[index].clone() else {
+            unreachable!("Only operators with a known stack are queued.");
+        };
+
+        let (successors, _) = evaluate(
+            operators[index],
+            index as u32,
+            stack,
+            script,
+            &mut scratch,
+        );
+
+        for (target, stack) in successors {
+            enter(&mut stacks, &mut num_changes, &mut queue, target, stack);
+        }
+    }
+
+    let effects = stacks
+        .iter()
+        .zip(&operators)
+        .zip(0..)
+        .map(|((stack, operator), index)| match stack {
+            Some(stack) => {
+                let (_, effects) = evaluate(
+                    operator,
+                    index,
+                    stack.clone(),
+                    script,
+                    &mut scratch,
+                );
+                effects
+            }
+            None => Effects::default(),
+        })
+        .collect();
+
+    Analysis { stacks, effects }
+}
+
+/// # Record that the evaluation can reach an operator with the provided stack
+///
+/// Queues the operator, if that changes what we know about its stack.
+fn enter(
+    stacks: &mut [Option<AbstractStack>],
+    num_changes: &mut [u32],
+    queue: &mut Vec<usize>,
+    index: u32,
+    stack: AbstractStack,
+) {
+    /// # The number of changes to a stack, after which it gets widened
+    ///
+    /// Without widening, a loop that counts up would be followed once per
+    /// iteration. Widening earlier loses precision, widening later takes more
+    /// time.
+    const CHANGES_BEFORE_WIDENING: u32 = 8;
+
+    let index = index as usize;
+    let Some(previous) = stacks.get_mut(index) else {
+        // Jumping past the end of the script ends the evaluation.
+        return;
+    };
+
+    let merged = match previous {
+        Some(previous) if num_changes[index] >= CHANGES_BEFORE_WIDENING => {
+            previous.widen(previous.merge(&stack))
+        }
+        Some(previous) => previous.merge(&stack),
+        None => stack,
+    };
+
+    if previous.as_ref() != Some(&merged) {
+        *previous = Some(merged);
+        num_changes[index] += 1;
+        queue.push(index);
+    }
+}
+
+/// # Apply an operator to what we know about the stack
+///
+/// Returns the operators that the evaluation can continue with, alongside the
+/// stack that each of them starts with, as well as the effects that the
+/// operator can trigger.
+fn evaluate(
+    operator: &Operator,
+    index: u32,
+    mut stack: AbstractStack,
+    script: &Script,
+    scratch: &mut Eval,
+) -> (Vec<(u32, AbstractStack)>, Effects) {
+    let mut successors = Vec::new();
+    let mut effects = Effects::default();
+
+    let result = apply(
+        operator,
+        index,
+        &mut stack,
+        script,
+        scratch,
+        &mut successors,
+        &mut effects,
+    );
+
+    match result {
+        Ok(Flow::Next) => {
+            successors.push((index + 1, stack));
+        }
+        Ok(Flow::Stop) => {}
+        Err(effect) => {
+            effects.certain(effect);
+        }
+    }
+
+    (successors, effects)
+}
+
+/// # Whether the evaluation continues with the next operator
+enum Flow {
+    Next,
+    Stop,
+}
+
+/// # Apply an operator to what we know about the stack
+///
+/// Returns an error, if the operator always triggers an effect. Records any
+/// other operators that the evaluation can continue with in `successors`.
+fn apply(
+    operator: &Operator,
+    index: u32,
+    stack: &mut AbstractStack,
+    script: &Script,
+    scratch: &mut Eval,
+    successors: &mut Vec<(u32, AbstractStack)>,
+    effects: &mut Effects,
+) -> Result<Flow, Effect> {
+    let opcode = match operator {
+        Operator::End => {
+            return Err(Effect::OutOfOperators);
+        }
+        Operator::Fused { superinstruction } => {
+            // The superinstruction behaves exactly like the operators it
+            // replaces, and the second of those stays in place.
+            return apply(
+                &superinstruction.unfused(),
+                index,
+                stack,
+                script,
+                scratch,
+                successors,
+                effects,
+            );
+        }
+        Operator::Identifier { value: _ } => {
+            // This could be a native operator, which can do anything with the
+            // stack.
+            effects.possible(Effect::UnknownIdentifier);
+            *stack = AbstractStack::unknown();
+            return Ok(Flow::Next);
+        }
+        Operator::Integer { value } => {
+            stack.push(Interval::constant(*value));
+            return Ok(Flow::Next);
+        }
+        Operator::Reference {
+            name: _,
+            target: Some(target),
+        } => {
+            stack.push(Interval::constant(target.value().cast_signed()));
+            return Ok(Flow::Next);
+        }
+        Operator::Reference {
+            name: _,
+            target: None,
+        } => {
+            return Err(Effect::InvalidReference);
+        }
+        Operator::Opcode { opcode } => *opcode,
+    };
+
+    match opcode {
+        Opcode::Copy => {
+            let index = stack.pop()?;
+
+            let value = match index
+                .as_constant()
+                .map(|index| stack.locate(index))
+            {
+                Some(Location::Known { position }) => stack.values[position],
+                Some(Location::Invalid) => {
+                    return Err(Effect::InvalidOperandStackIndex);
+                }
+                Some(Location::Unknown) => Interval::FULL,
+                None => {
+                    check_index_range(stack, index, effects)?;
+                    Interval::FULL
+                }
+            };
+
+            stack.push(value);
+        }
+        Opcode::Drop => {
+            let index = stack.pop()?;
+
+            match index
+                .as_constant()
+                .map(|index| (index, stack.locate(index)))
+            {
+                Some((_, Location::Known { position })) => {
+                    stack.values.remove(position);
+                }
+                Some((_, Location::Invalid)) => {
+                    return Err(Effect::InvalidOperandStackIndex);
+                }
+                Some((index, Location::Unknown)) if index >= 0 => {
+                    // The value is below the ones we know about, so those
+                    // stay where they are.
+                }
+                Some((_, Location::Unknown)) => {
+                    *stack = AbstractStack::unknown();
+                }
+                None => {
+                    check_index_range(stack, index, effects)?;
+
+                    // We don't know which value is dropped, so we no longer
+                    // know where the remaining ones are.
+                    stack.values.pop();
+                    stack.values.fill(Interval::FULL);
+                }
+            }
+        }
+        Opcode::ReverseN => {
+            let num_values = stack.pop()?;
+            let len = stack.values.len();
+
+            match num_values.as_constant() {
+                Some(num_values) => {
+                    match usize::try_from(num_values.cast_unsigned())
+                        .ok()
+                        .and_then(|num_values| len.checked_sub(num_values))
+                    {
+                        Some(start) => {
+                            stack.values[start..].reverse();
+                        }
+                        None if stack.is_complete => {
+                            return Err(Effect::InvalidOperandStackIndex);
+                        }
+                        None => {
+                            // Values we don't know about end up on top.
+                            *stack = AbstractStack::unknown();
+                        }
+                    }
+                }
+                None => {
+                    let max = i32::try_from(len).unwrap_or(i32::MAX);
+                    if stack.is_complete
+                        && (num_values.min < 0 || num_values.max > max)
+                    {
+                        effects.possible(Effect::InvalidOperandStackIndex);
+                    }
+
+                    // We don't know how many values are reversed, so we no
+                    // longer know where any of them are.
+                    stack.values.fill(Interval::FULL);
+                    if !stack.is_complete {
+                        *stack = AbstractStack::unknown();
+                    }
+                }
+            }
+        }
+        Opcode::Jump => {
+            let target = stack.pop()?;
+
+            if let Some(target) = target.as_constant() {
+                successors.push((target.cast_unsigned(), stack.clone()));
+            }
+            return Ok(Flow::Stop);
+        }
+        Opcode::JumpIf => {
+            let target = stack.pop()?;
+            let condition = stack.pop()?;
+
+            return Ok(branch(
+                target,
+                condition.can_be_true(),
+                condition.can_be_false(),
+                stack,
+                successors,
+            ));
+        }
+        Opcode::JumpIfEqual
+        | Opcode::JumpIfNotEqual
+        | Opcode::JumpIfLess
+        | Opcode::JumpIfLessOrEqual
+        | Opcode::JumpIfGreater
+        | Opcode::JumpIfGreaterOrEqual => {
+            let target = stack.pop()?;
+            let b = stack.pop()?;
+            let a = stack.pop()?;
+
+            let expected = match opcode {
+                Opcode::JumpIfEqual => Ordering::is_eq,
+                Opcode::JumpIfNotEqual => Ordering::is_ne,
+                Opcode::JumpIfLess => Ordering::is_lt,
+                Opcode::JumpIfLessOrEqual => Ordering::is_le,
+                Opcode::JumpIfGreater => Ordering::is_gt,
+                _ => Ordering::is_ge,
+            };
+            let (can_jump, can_continue) = compare(a, b, expected);
+
+            return Ok(branch(
+                target,
+                can_jump,
+                can_continue,
+                stack,
+                successors,
+            ));
+        }
+        Opcode::Return => {
+            effects.possible(Effect::Return);
+            return Ok(Flow::Stop);
+        }
+        Opcode::Unreachable => {
+            return Err(Effect::Unreachable);
+        }
+        Opcode::Todo => {
+            return Err(Effect::Todo);
+        }
+        Opcode::Yield => {
+            effects.certain(Effect::Yield);
+
+            // The host can do anything with the stack, before the evaluation
+            // continues.
+            *stack = AbstractStack::unknown();
+        }
+        Opcode::Call => {
+            let target = stack.pop()?;
+            let target = target.as_constant().map(i32::cast_unsigned);
+
+            match target
+                .and_then(|target| script.arity(OperatorIndex::new(target)))
+            {
+                Some(arity) => {
+                    // Procedures are analyzed on their own, starting with their
+                    // inputs.
+                    for _ in 0..arity.inputs {
+                        stack.pop()?;
+                    }
+                    for _ in 0..arity.outputs {
+                        stack.push(Interval::FULL);
+                    }
+                }
+                None => {
+                    if let Some(target) = target {
+                        successors.push((target, stack.clone()));
+                    }
+
+                    // The called routine can do anything with the stack.
+                    *stack = AbstractStack::unknown();
+                }
+            }
+        }
+        Opcode::CallEither => {
+            let else_ = stack.pop()?;
+            let then = stack.pop()?;
+            let condition = stack.pop()?;
+
+            for (target, is_possible) in [
+                (then, condition.can_be_true()),
+                (else_, condition.can_be_false()),
+            ] {
+                if let Some(target) =
+                    target.as_constant().map(i32::cast_unsigned)
+                    && is_possible
+                    && script.arity(OperatorIndex::new(target)).is_none()
+                {
+                    successors.push((target, stack.clone()));
+                }
+            }
+
+            *stack = AbstractStack::unknown();
+        }
+        Opcode::Pc => {
+            stack.push(Interval::constant(index.cast_signed()));
+        }
+        Opcode::Spawn => {
+            let target = stack.pop()?;
+
+            if let Some(target) = target.as_constant() {
+                successors
+                    .push((target.cast_unsigned(), AbstractStack::empty()));
+            }
+            stack.push(Interval::FULL);
+        }
+        Opcode::Resume | Opcode::Exec => {
+            let num_inputs = if let Opcode::Exec = opcode { 2 } else { 1 };
+            for _ in 0..num_inputs {
+                stack.pop()?;
+            }
+
+            // Another strand, or the generated code, can do anything with the
+            // stack.
+            *stack = AbstractStack::unknown();
+        }
+        Opcode::Assert => {
+            let condition = stack.pop()?;
+
+            if !condition.can_be_true() {
+                return Err(Effect::AssertionFailed);
+            }
+            if condition.can_be_false() {
+                effects.possible(Effect::AssertionFailed);
+            }
+        }
+        opcode => {
+            let Some((num_inputs, num_outputs)) = fixed_arity(opcode) else {
+                unreachable!(
+                    "Operators without a fixed number of inputs and outputs \
+                    have been handled above."
+                );
+            };
+
+            let mut inputs = Vec::new();
+            for _ in 0..num_inputs {
+                inputs.push(stack.pop()?);
+            }
+            inputs.reverse();
+
+            let outputs = match opcode.extension() {
+                Extension::Arithmetic | Extension::Bitwise => {
+                    compute(opcode, &inputs, scratch, effects)?
+                }
+                _ => vec![Interval::FULL; num_outputs],
+            };
+
+            stack.values.extend(outputs);
+        }
+    }
+
+    Ok(Flow::Next)
+}
+
+/// # Record where a conditional jump can continue
+fn branch(
+    target: Interval,
+    can_jump: bool,
+    can_continue: bool,
+    stack: &AbstractStack,
+    successors: &mut Vec<(u32, AbstractStack)>,
+) -> Flow {
+    if let Some(target) = target.as_constant()
+        && can_jump
+    {
+        successors.push((target.cast_unsigned(), stack.clone()));
+    }
+
+    if can_continue { Flow::Next } else { Flow::Stop }
+}
+
+/// # Determine whether comparing two values can have the expected result
+///
+/// Returns whether the result can be `true`, and whether it can be `false`.
+fn compare(
+    a: Interval,
+    b: Interval,
+    expected: fn(Ordering) -> bool,
+) -> (bool, bool) {
+    let orderings = a.orderings(b).collect::<Vec<_>>();
+
+    (
+        orderings.iter().any(|&ordering| expected(ordering)),
+        orderings.iter().any(|&ordering| !expected(ordering)),
+    )
+}
+
+/// # Record, if an index into the stack might be invalid
+///
+/// Returns an error, if it's always invalid.
+fn check_index_range(
+    stack: &AbstractStack,
+    index: Interval,
+    effects: &mut Effects,
+) -> Result<(), Effect> {
+    if !stack.is_complete {
+        // We don't know how many values there are.
+        return Ok(());
+    }
+
+    // Non-negative indices count from the top, negative ones from the bottom.
+    let len = i32::try_from(stack.values.len()).unwrap_or(i32::MAX);
+    let valid = Interval {
+        min: -len,
+        max: len - 1,
+    };
+
+    if index.max < valid.min || index.min > valid.max {
+        return Err(Effect::InvalidOperandStackIndex);
+    }
+    if index.min < valid.min || index.max > valid.max {
+        effects.possible(Effect::InvalidOperandStackIndex);
+    }
+
+    Ok(())
+}
+
+/// # Compute the outputs of an arithmetic or bitwise operator
+///
+/// Returns an error, if the operator always triggers an effect.
+fn compute(
+    opcode: Opcode,
+    inputs: &[Interval],
+    scratch: &mut Eval,
+    effects: &mut Effects,
+) -> Result<Vec<Interval>, Effect> {
+    let constants = inputs
+        .iter()
+        .map(Interval::as_constant)
+        .collect::<Option<Vec<_>>>();
+
+    if let Some(constants) = constants {
+        scratch.operand_stack.values.clear();
+        for value in constants {
+            scratch.operand_stack.push(value);
+        }
+
+        Instruction::decode(&Operator::Opcode { opcode }).evaluate(scratch)?;
+
+        let outputs = scratch
+            .operand_stack
+            .values
+            .iter()
+            .copied()
+            .map(Value::to_i32)
+            .map(Interval::constant)
+            .collect();
+        return Ok(outputs);
+    }
+
+    let outputs = match (opcode, inputs) {
+        (Opcode::Add, &[a, b]) => {
+            vec![Interval::from_i64(
+                i64::from(a.min) + i64::from(b.min),
+                i64::from(a.max) + i64::from(b.max),
+            )]
+        }
+        (Opcode::Subtract, &[a, b]) => {
+            vec![Interval::from_i64(
+                i64::from(a.min) - i64::from(b.max),
+                i64::from(a.max) - i64::from(b.min),
+            )]
+        }
+        (Opcode::Multiply, &[a, b]) => {
+            let products = [
+                i64::from(a.min) * i64::from(b.min),
+                i64::from(a.min) * i64::from(b.max),
+                i64::from(a.max) * i64::from(b.min),
+                i64::from(a.max) * i64::from(b.max),
+            ];
+            let min = products.iter().copied().min().unwrap_or_default();
+            let max = products.iter().copied().max().unwrap_or_default();
+
+            vec![Interval::from_i64(min, max)]
+        }
+        (
+            Opcode::Less
+            | Opcode::LessOrEqual
+            | Opcode::Equal
+            | Opcode::Greater
+            | Opcode::GreaterOrEqual,
+            &[a, b],
+        ) => {
+            let expected = match opcode {
+                Opcode::Less => Ordering::is_lt,
+                Opcode::LessOrEqual => Ordering::is_le,
+                Opcode::Equal => Ordering::is_eq,
+                Opcode::Greater => Ordering::is_gt,
+                _ => Ordering::is_ge,
+            };
+            let (can_be_true, can_be_false) = compare(a, b, expected);
+
+            vec![Interval::from_bool(can_be_false, can_be_true)]
+        }
+        (Opcode::Divide | Opcode::DivFloor, &[a, b]) => {
+            if b.contains(0) {
+                effects.possible(Effect::DivisionByZero);
+            }
+            if a.contains(i32::MIN) && b.contains(-1) {
+                effects.possible(Effect::IntegerOverflow);
+            }
+
+            let num_outputs = if let Opcode::Divide = opcode { 2 } else { 1 };
+            vec![Interval::FULL; num_outputs]
+        }
+        (Opcode::ModFloor, &[_, b]) => {
+            if b.contains(0) {
+                effects.possible(Effect::DivisionByZero);
+            }
+
+            // The modulo has the sign of the divisor, and is smaller.
+            let output = if b.min > 0 {
+                Interval {
+                    min: 0,
+                    max: b.max - 1,
+                }
+            } else {
+                Interval::FULL
+            };
+            vec![output]
+        }
+        (Opcode::DivmodU, &[_, b]) => {
+            if b.contains(0) {
+                effects.possible(Effect::DivisionByZero);
+            }
+
+            vec![Interval::FULL; 2]
+        }
+        (
+            Opcode::CountOnes | Opcode::LeadingZeros | Opcode::TrailingZeros,
+            _,
+        ) => {
+            vec![Interval { min: 0, max: 32 }]
+        }
+        _ => vec![Interval::FULL],
+    };
+
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Effect, Interval, OperatorIndex, Script};
+
+    #[test]
+    fn track_intervals_through_branches() {
+        // Where paths come together, the values are combined into an interval
+        // that covers both.
+        let script = Script::compile(
+            "
+            0 read @one jump_if
+                2 @end jump
+            one:
+                1
+            end:
+                10 +
+            ",
+        );
+        let analysis = script.analyze();
+
+        let stack = analysis.stack(OperatorIndex::new(9));
+        assert_eq!(
+            stack.map(|stack| stack.values()),
+            Some(
+                [Interval { min: 1, max: 2 }, Interval::constant(10)]
+                    .as_slice()
+            ),
+        );
+        assert_eq!(stack.and_then(|stack| stack.depth()), Some(2));
+    }
+
+    #[test]
+    fn report_possible_and_certain_effects() {
+        let script = Script::compile("0 read 2 / 0 read / 5 0 / unknown");
+        let analysis = script.analyze();
+
+        // Dividing by a value that might be zero.
+        assert_eq!(
+            analysis.possible_effects(OperatorIndex::new(6)),
+            &[Effect::DivisionByZero, Effect::IntegerOverflow],
+        );
+        assert_eq!(analysis.certain_effect(OperatorIndex::new(6)), None);
+
+        // Dividing by zero. The evaluation doesn't get any further.
+        assert_eq!(
+            analysis.certain_effect(OperatorIndex::new(9)),
+            Some(Effect::DivisionByZero),
+        );
+        assert!(!analysis.is_reachable(OperatorIndex::new(10)));
+    }
+
+    #[test]
+    fn loops_are_widened() {
+        // A loop that counts up would take forever to analyze, if the analysis
+        // followed every iteration. Since addition wraps around, the counter
+        // can end up being any value. But the number of values stays known.
+        let script = Script::compile(
+            "
+            0
+            loop:
+                1 +
+                0 copy 1000000 @loop jump_if_lt
+            ",
+        );
+        let analysis = script.analyze();
+
+        let stack = analysis.stack(OperatorIndex::new(1));
+        assert_eq!(
+            stack.map(|stack| stack.values()),
+            Some([Interval::FULL].as_slice()),
+        );
+        assert_eq!(stack.and_then(|stack| stack.depth()), Some(1));
+    }
+
+    #[test]
+    fn follow_calls_with_unknown_stack_afterwards() {
+        let script = Script::compile("1 @f call @end jump f: 2 return end:");
+        let analysis = script.analyze();
+
+        // The called routine starts with the stack of the caller.
+        let stack = analysis.stack(OperatorIndex::new(5));
+        assert_eq!(
+            stack.map(|stack| stack.values()),
+            Some([Interval::constant(1)].as_slice()),
+        );
+        assert_eq!(stack.and_then(|stack| stack.depth()), Some(1));
+
+        // After the call, nothing is known about the stack.
+        let stack = analysis.stack(OperatorIndex::new(3));
+        assert_eq!(stack.and_then(|stack| stack.depth()), None);
+        assert_eq!(analysis.possible_effects(OperatorIndex::new(3)), &[]);
+    }
+}
@@ -0,0 +1,522 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    Arity, OperatorIndex, Script,
+    data::{Datum, Region},
+    fuse::Superinstruction,
+    opcode::Opcode,
+    script::{Label, Operator, Procedure, Start},
+};
+
+/// # The bytes that every artifact starts with
+const MAGIC: &[u8; 4] = b"SASM";
+
+/// # The version of the artifact format
+///
+/// Must be incremented, whenever the format changes in an incompatible way.
+const VERSION: u32 = 5;
+
+impl Script {
+    /// # Encode the compiled script into an artifact
+    ///
+    /// An artifact is a binary representation of the script, that can be
+    /// written to disk and loaded again using [`Script::from_bytes`]. This
+    /// allows shipping a script, without compiling it from the source text
+    /// again.
+    ///
+    /// The artifact contains the operators, labels, procedures, regions
+    /// (including the values they start out with), entry point, and metadata
+    /// of the script, but not the source text. Consequently, the loaded script
+    /// has no source map, and [`Script::map_operator_to_source`] always
+    /// returns an error.
+    ///
+    /// ```
+    /// use stack_assembly::{Eval, Script};
+    ///
+    /// let artifact = Script::compile("1 2 +").to_bytes();
+    ///
+    /// let Ok(script) = Script::from_bytes(&artifact) else {
+    ///     unreachable!("We just created a valid artifact.");
+    /// };
+    ///
+    /// let mut eval = Eval::new();
+    /// eval.run(&script);
+    ///
+    /// assert_eq!(eval.operand_stack.to_i32_slice(), &[3]);
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.u32(VERSION);
+
+        writer.len(self.operators().count());
+        for (_, operator) in self.operators() {
+            match operator {
+                Operator::End => {
+                    writer.u8(0);
+                }
+                Operator::Fused { superinstruction } => {
+                    writer.u8(1);
+
+                    match *superinstruction {
+                        Superinstruction::AddImmediate { value } => {
+                            writer.u8(0);
+                            writer.i32(value);
+                        }
+                        Superinstruction::CopyJumpIf => {
+                            writer.u8(1);
+                        }
+                        Superinstruction::WriteImmediate { value } => {
+                            writer.u8(2);
+                            writer.i32(value);
+                        }
+                    }
+                }
+                Operator::Identifier { value } => {
+                    writer.u8(2);
+                    writer.str(value);
+                }
+                Operator::Integer { value } => {
+                    writer.u8(3);
+                    writer.i32(*value);
+                }
+                Operator::Opcode { opcode } => {
+                    writer.u8(4);
+                    writer.str(opcode.identifier());
+                }
+                Operator::Reference { name, target: _ } => {
+                    // References are resolved again, when loading the
+                    // artifact.
+                    writer.u8(5);
+                    writer.str(name);
+                }
+            }
+        }
+
+        writer.len(self.labels().count());
+        for (name, operator) in self.labels() {
+            writer.str(name);
+            writer.u32(operator.value);
+        }
+
+        writer.len(self.procedures().count());
+        for (name, operator, arity) in self.procedures() {
+            writer.str(name);
+            writer.u32(operator.value);
+            writer.u32(arity.inputs);
+            writer.u32(arity.outputs);
+        }
+
+        writer.len(self.region_definitions().len());
+        for region in self.region_definitions() {
+            writer.str(&region.name);
+            writer.u32(region.addresses.start);
+            writer.u32(region.addresses.end);
+
+            writer.len(region.values.len());
+            for datum in &region.values {
+                match datum {
+                    Datum::Integer { value } => {
+                        writer.u8(0);
+                        writer.i32(*value);
+                    }
+                    Datum::Reference {
+                        name,
+                        target: _,
+                        range: _,
+                    } => {
+                        // Like the references in the operators, these are
+                        // resolved again.
+                        writer.u8(1);
+                        writer.str(name);
+                    }
+                }
+            }
+        }
+
+        writer.len(self.metadata().count());
+        for (key, value) in self.metadata() {
+            writer.str(key);
+            writer.str(value);
+        }
+
+        match self.start_label() {
+            Some(name) => {
+                // Like references, the label is resolved again.
+                writer.u8(1);
+                writer.str(name);
+            }
+            None => {
+                writer.u8(0);
+            }
+        }
+
+        writer.bytes
+    }
+
+    /// # Load a script from an artifact
+    ///
+    /// Expects an artifact created by [`Script::to_bytes`]. Returns an error,
+    /// if the provided bytes are not such an artifact, or if it was created by
+    /// an incompatible version of this library.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, InvalidArtifact> {
+        let Some(bytes) = bytes.strip_prefix(MAGIC) else {
+            return Err(InvalidArtifact::NotAnArtifact);
+        };
+        let mut reader = Reader { bytes };
+
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(InvalidArtifact::UnsupportedVersion { version });
+        }
+
+        let mut operators = Vec::new();
+        for _ in 0..reader.u32()? {
+            let operator = match reader.u8()? {
+                0 => Operator::End,
+                1 => {
+                    let superinstruction = match reader.u8()? {
+                        0 => Superinstruction::AddImmediate {
+                            value: reader.i32()?,
+                        },
+                        1 => Superinstruction::CopyJumpIf,
+                        2 => Superinstruction::WriteImmediate {
+                            value: reader.i32()?,
+                        },
+                        _ => return Err(InvalidArtifact::Malformed),
+                    };
+
+                    Operator::Fused { superinstruction }
+                }
+                2 => Operator::Identifier {
+                    value: reader.string()?,
+                },
+                3 => Operator::Integer {
+                    value: reader.i32()?,
+                },
+                4 => {
+                    let Some(opcode) =
+                        Opcode::from_identifier(&reader.string()?)
+                    else {
+                        return Err(InvalidArtifact::Malformed);
+                    };
+
+                    Operator::Opcode { opcode }
+                }
+                5 => Operator::Reference {
+                    name: reader.string()?,
+                    target: None,
+                },
+                _ => return Err(InvalidArtifact::Malformed),
+            };
+
+            operators.push(operator);
+        }
+
+        let mut labels = Vec::new();
+        for _ in 0..reader.u32()? {
+            let name = reader.string()?;
+            let operator = OperatorIndex::new(reader.u32()?);
+
+            // A label can refer to the position right after the last
+            // operator, but not further.
+            if operator.value as usize > operators.len() {
+                return Err(InvalidArtifact::Malformed);
+            }
+
+            labels.push(Label {
+                name,
+                operator,
+                range: None,
+            });
+        }
+
+        let mut procedures = Vec::new();
+        for _ in 0..reader.u32()? {
+            let name = reader.string()?;
+            let operator = OperatorIndex::new(reader.u32()?);
+            let arity = Arity {
+                inputs: reader.u32()?,
+                outputs: reader.u32()?,
+            };
+
+            if operator.value as usize > operators.len() {
+                return Err(InvalidArtifact::Malformed);
+            }
+
+            procedures.push(Procedure {
+                name,
+                operator,
+                arity,
+            });
+        }
+
+        let mut regions = Vec::new();
+        for _ in 0..reader.u32()? {
+            let name = reader.string()?;
+            let addresses = reader.u32()?..reader.u32()?;
+
+            if addresses.start > addresses.end {
+                return Err(InvalidArtifact::Malformed);
+            }
+
+            let mut values = Vec::new();
+            for _ in 0..reader.u32()? {
+                let datum = match reader.u8()? {
+                    0 => Datum::Integer {
+                        value: reader.i32()?,
+                    },
+                    1 => Datum::Reference {
+                        name: reader.string()?,
+                        target: None,
+                        range: None,
+                    },
+                    _ => return Err(InvalidArtifact::Malformed),
+                };
+
+                values.push(datum);
+            }
+
+            // A region has either no values, or one for each word.
+            if !values.is_empty() && values.len() != addresses.len() {
+                return Err(InvalidArtifact::Malformed);
+            }
+
+            regions.push(Region {
+                name,
+                addresses,
+                values,
+            });
+        }
+
+        let mut metadata = Vec::new();
+        for _ in 0..reader.u32()? {
+            metadata.push((reader.string()?, reader.string()?));
+        }
+
+        let start = match reader.u8()? {
+            0 => None,
+            1 => Some(Start {
+                name: reader.string()?,
+                target: None,
+                range: None,
+            }),
+            _ => {
+                return Err(InvalidArtifact::Malformed);
+            }
+        };
+
+        if !reader.bytes.is_empty() {
+            return Err(InvalidArtifact::Malformed);
+        }
+
+        let mut script = Self::from_parts(
+            operators,
+            labels,
+            procedures,
+            regions,
+            BTreeMap::new(),
+            metadata,
+            start,
+        );
+        script.decode_instructions();
+
+        Ok(script)
+    }
+}
+
+/// # The provided bytes are not a valid artifact
+///
+/// See [`Script::from_bytes`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum InvalidArtifact {
+    /// # The bytes don't start like an artifact does
+    NotAnArtifact,
+
+    /// # The artifact was created by an incompatible version of the library
+    UnsupportedVersion {
+        /// # The version of the artifact format
+        version: u32,
+    },
+
+    /// # The artifact is truncated or otherwise corrupted
+    Malformed,
+}
+
+impl fmt::Display for InvalidArtifact {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotAnArtifact => {
+                write!(f, "not a StackAssembly artifact")
+            }
+            Self::UnsupportedVersion { version } => {
+                write!(
+                    f,
+                    "unsupported artifact version {version} (expected \
+                    {VERSION})"
+                )
+            }
+            Self::Malformed => {
+                write!(f, "artifact is malformed")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvalidArtifact {}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn len(&mut self, len: usize) {
+        let Ok(len) = len.try_into() else {
+            panic!(
+                "Trying to encode a length that doesn't fit into `u32`. The \
+                script couldn't have been evaluated anyway, since operators \
+                are addressed using `u32`."
+            );
+        };
+
+        self.u32(len);
+    }
+
+    fn str(&mut self, value: &str) {
+        self.len(value.len());
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+}
+
+struct Reader<'r> {
+    bytes: &'r [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], InvalidArtifact> {
+        let Some((bytes, rest)) = self.bytes.split_first_chunk() else {
+            return Err(InvalidArtifact::Malformed);
+        };
+        self.bytes = rest;
+
+        Ok(*bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, InvalidArtifact> {
+        let [value] = self.take()?;
+        Ok(value)
+    }
+
+    fn u32(&mut self) -> Result<u32, InvalidArtifact> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, InvalidArtifact> {
+        self.take().map(i32::from_le_bytes)
+    }
+
+    fn string(&mut self) -> Result<String, InvalidArtifact> {
+        let len = self.u32()? as usize;
+        if len > self.bytes.len() {
+            return Err(InvalidArtifact::Malformed);
+        }
+
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| InvalidArtifact::Malformed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompileOptions, Eval, Script};
+
+    use super::InvalidArtifact;
+
+    #[test]
+    fn round_trip() {
+        let source = "
+            .meta name Round Trip
+            .start main
+
+            1 @double call
+            unknown
+
+            double:
+                0 copy +
+                return
+
+            proc triple in 1 out 1
+                3 *
+            end
+
+            .zero buffer 4
+            .data handlers @double -1
+
+            main:
+                3
+        ";
+        let options = CompileOptions {
+            prelude: true,
+            optimize: true,
+            ..CompileOptions::default()
+        };
+        let script = Script::compile_with_options(source, options);
+
+        let Ok(loaded) = Script::from_bytes(&script.to_bytes()) else {
+            unreachable!("Artifact has just been created.");
+        };
+
+        assert!(loaded.labels().eq(script.labels()));
+        assert!(loaded.procedures().eq(script.procedures()));
+        assert!(loaded.regions().eq(script.regions()));
+        assert!(loaded.initial_values().eq(script.initial_values()));
+        assert!(loaded.metadata().eq(script.metadata()));
+        assert_eq!(loaded.start(), script.start());
+
+        let mut eval = Eval::new();
+        eval.run(&loaded);
+        assert_eq!(eval.operand_stack.to_i32_slice(), &[2]);
+
+        let mut eval = Eval::start_at(loaded.start());
+        eval.run(&loaded);
+        assert_eq!(eval.operand_stack.to_i32_slice(), &[3]);
+    }
+
+    #[test]
+    fn reject_invalid_artifacts() {
+        let artifact = Script::compile("1 2 +").to_bytes();
+
+        assert!(matches!(
+            Script::from_bytes(b"1 2 +"),
+            Err(InvalidArtifact::NotAnArtifact),
+        ));
+        assert!(matches!(
+            Script::from_bytes(&artifact[..artifact.len() - 1]),
+            Err(InvalidArtifact::Malformed),
+        ));
+
+        let mut future = artifact.clone();
+        future[4] += 1;
+        assert!(matches!(
+            Script::from_bytes(&future),
+            Err(InvalidArtifact::UnsupportedVersion { version: 6 }),
+        ));
+    }
+}
@@ -0,0 +1,205 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{CompileError, CompileOptions, InvalidArtifact, Script};
+
+/// # Reuses scripts that have been compiled before
+///
+/// Compiling a script takes time. For a host that evaluates the same small
+/// scripts over and over, like a handler that runs for every request, most of
+/// that time is wasted. A cache looks up its scripts by their source text (or,
+/// for scripts loaded via [`Script::from_bytes`], by their artifact), and only
+/// compiles a script the first time it encounters it.
+///
+/// Scripts are returned as an [`Arc`], so they can be shared between threads,
+/// without the cache having to be borrowed while they are evaluated.
+///
+/// The cache never evicts any scripts by itself. If the set of scripts is not
+/// bounded, call [`ScriptCache::clear`] from time to time.
+///
+/// ## Example
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use stack_assembly::{Eval, ScriptCache};
+///
+/// let mut cache = ScriptCache::new();
+///
+/// let a = cache.get_or_compile("1 2 +");
+/// let b = cache.get_or_compile("1 2 +");
+///
+/// assert!(Arc::ptr_eq(&a, &b));
+/// assert_eq!(cache.len(), 1);
+///
+/// let mut eval = Eval::new();
+/// eval.run(&b);
+///
+/// assert_eq!(eval.operand_stack.to_i32_slice(), &[3]);
+/// ```
+#[derive(Debug, Default)]
+pub struct ScriptCache {
+    options: CompileOptions,
+    compiled: HashMap<String, Arc<Script>>,
+    checked: HashMap<String, Arc<Script>>,
+    loaded: HashMap<Vec<u8>, Arc<Script>>,
+}
+
+impl ScriptCache {
+    /// # Create an empty cache, which compiles with the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Create an empty cache, which compiles with the provided options
+    ///
+    /// All scripts compiled by the cache use the same options. Use one cache
+    /// per set of options, if you need more than one.
+    pub fn with_options(options: CompileOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// # Access the script compiled from the provided source text
+    ///
+    /// Compiles the script using [`Script::compile_with_options`], if the
+    /// cache doesn't contain it yet. Use [`ScriptCache::try_get_or_compile`],
+    /// to reject scripts with problems instead.
+    pub fn get_or_compile(&mut self, source: &str) -> Arc<Script> {
+        if let Some(script) = self.compiled.get(source) {
+            return script.clone();
+        }
+
+        let script =
+            Arc::new(Script::compile_with_options(source, self.options));
+        self.compiled.insert(source.to_string(), script.clone());
+
+        script
+    }
+
+    /// # Access the script strictly compiled from the provided source text
+    ///
+    /// Compiles the script using [`Script::try_compile`], if the cache doesn't
+    /// contain it yet. Scripts that fail to compile are not cached, and return
+    /// the same error every time.
+    ///
+    /// A source text that has been compiled by [`ScriptCache::get_or_compile`]
+    /// before is compiled again, as it was never checked.
+    pub fn try_get_or_compile(
+        &mut self,
+        source: &str,
+    ) -> Result<Arc<Script>, CompileError> {
+        if let Some(script) = self.checked.get(source) {
+            return Ok(script.clone());
+        }
+
+        let script = Arc::new(Script::try_compile(source, self.options)?);
+        self.checked.insert(source.to_string(), script.clone());
+
+        Ok(script)
+    }
+
+    /// # Access the script loaded from the provided artifact
+    ///
+    /// Loads the script using [`Script::from_bytes`], if the cache doesn't
+    /// contain it yet. Invalid artifacts are not cached, and return the same
+    /// error every time.
+    pub fn get_or_load(
+        &mut self,
+        artifact: &[u8],
+    ) -> Result<Arc<Script>, InvalidArtifact> {
+        if let Some(script) = self.loaded.get(artifact) {
+            return Ok(script.clone());
+        }
+
+        let script = Arc::new(Script::from_bytes(artifact)?);
+        self.loaded.insert(artifact.to_vec(), script.clone());
+
+        Ok(script)
+    }
+
+    /// # The number of scripts in the cache
+    pub fn len(&self) -> usize {
+        self.compiled.len() + self.checked.len() + self.loaded.len()
+    }
+
+    /// # Indicate whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// # Remove all scripts from the cache
+    ///
+    /// Scripts that have been returned before stay valid. They are just no
+    /// longer shared with later calls.
+    pub fn clear(&mut self) {
+        self.compiled.clear();
+        self.checked.clear();
+        self.loaded.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{CompileError, Eval, Script};
+
+    use super::ScriptCache;
+
+    #[test]
+    fn different_sources_compile_to_different_scripts() {
+        let mut cache = ScriptCache::new();
+
+        let a = cache.get_or_compile("1");
+        let b = cache.get_or_compile("2");
+
+        assert!(!Arc::ptr_eq(&a, &b));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn strict_compilation_rejects_problems() {
+        let mut cache = ScriptCache::new();
+
+        let (Ok(a), Ok(b)) = (
+            cache.try_get_or_compile("1 2 +"),
+            cache.try_get_or_compile("1 2 +"),
+        ) else {
+            unreachable!("Script has no problems.");
+        };
+        assert!(Arc::ptr_eq(&a, &b));
+
+        assert_eq!(
+            cache.try_get_or_compile("1 unknown").err(),
+            Some(CompileError::UnknownIdentifier { range: 2..9 }),
+        );
+
+        // Compiling a script without checking it doesn't make it pass the
+        // check later.
+        cache.get_or_compile("1 unknown");
+        assert!(cache.try_get_or_compile("1 unknown").is_err());
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn loaded_scripts_are_cached_by_artifact() {
+        let artifact = Script::compile("1 2 +").to_bytes();
+        let mut cache = ScriptCache::new();
+
+        let (Ok(a), Ok(b)) =
+            (cache.get_or_load(&artifact), cache.get_or_load(&artifact))
+        else {
+            unreachable!("Artifact is valid.");
+        };
+        assert!(Arc::ptr_eq(&a, &b));
+
+        let mut eval = Eval::new();
+        eval.run(&a);
+        assert_eq!(eval.operand_stack.to_i32_slice(), &[3]);
+
+        assert!(cache.get_or_load(b"not an artifact").is_err());
+        assert_eq!(cache.len(), 1);
+    }
+}
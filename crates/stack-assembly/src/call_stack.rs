@@ -0,0 +1,150 @@
+use std::fmt;
+
+use crate::{Effect, OperatorIndex};
+
+/// # The call stack
+///
+/// Every call pushes the operator to return to, once the called routine
+/// evaluates `return`. Please refer to [`Eval`]'s [`call_stack`] field for
+/// more information on how the host may access it.
+///
+/// ## Limiting the depth
+///
+/// By default, the call stack grows as needed. A call stack that was created
+/// with [`CallStack::with_max_depth`] refuses to grow beyond that, and a call
+/// that would make it do so triggers [`Effect::CallStackOverflow`] instead.
+/// See also [`Limits::max_call_depth`].
+///
+/// [`Eval`]: crate::Eval
+/// [`call_stack`]: struct.Eval.html#structfield.call_stack
+/// [`Limits::max_call_depth`]: crate::Limits::max_call_depth
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CallStack {
+    /// # The operators to return to, one for each call
+    ///
+    /// Ordered from the bottom of the stack to its top. Each of them is the
+    /// operator right after the one that made the call.
+    pub frames: Vec<OperatorIndex>,
+
+    max_depth: Option<usize>,
+}
+
+impl CallStack {
+    /// # Create an empty call stack that can hold at most `max_depth` frames
+    ///
+    /// Pass `None` for a call stack without limit.
+    pub fn with_max_depth(max_depth: Option<usize>) -> Self {
+        Self {
+            frames: Vec::new(),
+            max_depth,
+        }
+    }
+
+    /// # The number of frames that the call stack can hold at most
+    ///
+    /// Returns `None`, if the call stack has no limit.
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// # The number of frames on the call stack
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// # Determine whether the call stack is empty
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// # Push the operator to return to onto the top of the stack
+    ///
+    /// Returns [`CallStackOverflow`], if the stack already holds the maximum
+    /// number of frames, which provides an automatic conversion to [`Effect`].
+    pub fn push(
+        &mut self,
+        return_to: OperatorIndex,
+    ) -> Result<(), CallStackOverflow> {
+        if self
+            .max_depth
+            .is_some_and(|max_depth| self.frames.len() >= max_depth)
+        {
+            return Err(CallStackOverflow);
+        }
+
+        self.frames.push(return_to);
+
+        Ok(())
+    }
+
+    /// # Pop the operator to return to from the top of the stack
+    ///
+    /// Returns `None`, if the stack is empty.
+    pub fn pop(&mut self) -> Option<OperatorIndex> {
+        self.frames.pop()
+    }
+
+    /// # Iterate over the operators to return to
+    ///
+    /// Starts with the top-most one.
+    pub fn iter(&self) -> impl Iterator<Item = OperatorIndex> {
+        self.frames.iter().copied().rev()
+    }
+
+    /// # Iterate over the operators that made the calls
+    ///
+    /// Starts with the most recent call. This is what a host would display as
+    /// a backtrace.
+    pub fn call_sites(&self) -> impl Iterator<Item = OperatorIndex> {
+        self.iter().map(|index| {
+            // A host could push anything, so this isn't guaranteed to be
+            // valid. But the operator to return to is normally the one after
+            // the call, so there's one before it.
+            OperatorIndex {
+                value: index.value.saturating_sub(1),
+            }
+        })
+    }
+}
+
+/// # Tried to push a frame to a call stack that is already full
+///
+/// See [`CallStack::push`].
+#[derive(Debug)]
+pub struct CallStackOverflow;
+
+impl fmt::Display for CallStackOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "tried to push a frame to a full call stack")
+    }
+}
+
+impl std::error::Error for CallStackOverflow {}
+
+impl From<CallStackOverflow> for Effect {
+    fn from(CallStackOverflow: CallStackOverflow) -> Self {
+        Effect::CallStackOverflow
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::OperatorIndex;
+
+    use super::CallStack;
+
+    #[test]
+    fn push_fails_at_max_depth() {
+        let mut call_stack = CallStack::with_max_depth(Some(2));
+
+        assert!(call_stack.push(OperatorIndex::new(3)).is_ok());
+        assert!(call_stack.push(OperatorIndex::new(7)).is_ok());
+        assert!(call_stack.push(OperatorIndex::new(9)).is_err());
+
+        assert_eq!(
+            call_stack.call_sites().collect::<Vec<_>>(),
+            [6, 2].map(OperatorIndex::new),
+        );
+        assert_eq!(call_stack.pop(), Some(OperatorIndex::new(7)));
+    }
+}
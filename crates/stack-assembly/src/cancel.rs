@@ -0,0 +1,33 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// # Cancels an evaluation, possibly from another thread
+///
+/// Returned by [`Eval::cancellation_handle`]. Please refer to its documentation
+/// for more information.
+///
+/// [`Eval::cancellation_handle`]: crate::Eval::cancellation_handle
+#[derive(Clone, Debug, Default)]
+pub struct CancellationHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationHandle {
+    /// # Cancel the evaluation
+    ///
+    /// The evaluation triggers [`Effect::Cancelled`] before its next step.
+    ///
+    /// [`Effect::Cancelled`]: crate::Effect::Cancelled
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// # Determine whether the evaluation has been cancelled
+    ///
+    /// If so, reset the cancellation, so it only takes effect once.
+    pub(crate) fn take(&self) -> bool {
+        self.cancelled.swap(false, Ordering::Relaxed)
+    }
+}
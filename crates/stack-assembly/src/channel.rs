@@ -0,0 +1,35 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::Value;
+
+/// # The channels that a script and its host communicate through
+///
+/// Each channel is a queue of values, identified by a number. Values are
+/// received in the order they've been sent. Channels don't need to be created
+/// before they're used.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Channels {
+    queues: BTreeMap<u32, VecDeque<Value>>,
+}
+
+impl Channels {
+    pub(crate) fn send(&mut self, channel: u32, value: Value) {
+        self.queues.entry(channel).or_default().push_back(value);
+    }
+
+    pub(crate) fn receive(&mut self, channel: u32) -> Option<Value> {
+        self.queues.get_mut(&channel)?.pop_front()
+    }
+
+    /// # Undo the most recent [`Channels::send`] to the provided channel
+    pub(crate) fn unsend(&mut self, channel: u32) {
+        if let Some(queue) = self.queues.get_mut(&channel) {
+            queue.pop_back();
+        }
+    }
+
+    /// # Undo a [`Channels::receive`] that returned the provided value
+    pub(crate) fn unreceive(&mut self, channel: u32, value: Value) {
+        self.queues.entry(channel).or_default().push_front(value);
+    }
+}
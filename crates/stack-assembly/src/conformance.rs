@@ -0,0 +1,263 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{Effect, Eval, OperatorIndex, Script};
+
+/// # A script file that specifies the result of evaluating it
+///
+/// A conformance test states what evaluating it should result in, using
+/// `.meta` directives (see [`Script`]'s section on metadata):
+///
+/// ```text
+/// .meta expect_effect AssertionFailed
+/// .meta expect_stack 1 2
+///
+/// 1 2 0 assert
+/// ```
+///
+/// - `expect_effect` is the name of the [`Effect`] that the evaluation must
+///   trigger, as written in Rust. If this is missing, the evaluation must
+///   trigger [`Effect::OutOfOperators`].
+/// - `expect_stack` lists the values, separated by whitespace, that must be on
+///   the operand stack afterwards, bottom to top. If this is missing, the
+///   operand stack is not checked.
+///
+/// Conformance tests allow specifying the behavior of the language using
+/// script files, instead of Rust code.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConformanceTest {
+    /// # The path of the script file
+    pub path: PathBuf,
+
+    /// # The source code of the script
+    pub source: String,
+}
+
+impl ConformanceTest {
+    /// # The fuel that the evaluation of a test has available
+    ///
+    /// This keeps a test that doesn't terminate from hanging the harness. A
+    /// test can expect [`Effect::OutOfFuel`], to specify that it doesn't
+    /// terminate.
+    pub const FUEL: u64 = 1_000_000;
+
+    /// # Find all conformance tests in the provided directory
+    ///
+    /// Reads every file with the extension `stack`, including those in
+    /// subdirectories. Returns the tests sorted by path.
+    pub fn discover(dir: impl AsRef<Path>) -> io::Result<Vec<Self>> {
+        let mut tests = Vec::new();
+        let mut dirs = vec![dir.as_ref().to_path_buf()];
+
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "stack") {
+                    let source = fs::read_to_string(&path)?;
+                    tests.push(Self { path, source });
+                }
+            }
+        }
+
+        tests.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(tests)
+    }
+
+    /// # Evaluate the script and compare the result to the expectations
+    pub fn run(&self) -> Result<(), ConformanceFailure> {
+        let script = Script::compile(&self.source);
+
+        let expected_effect = match script.metadata_value("expect_effect") {
+            Some(name) => parse_effect(name).ok_or_else(|| {
+                ConformanceFailure::InvalidExpectation {
+                    key: "expect_effect",
+                    value: name.to_string(),
+                }
+            })?,
+            None => Effect::OutOfOperators,
+        };
+        let expected_stack = script
+            .metadata_value("expect_stack")
+            .map(|values| {
+                values
+                    .split_whitespace()
+                    .map(|value| value.parse::<i32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| ConformanceFailure::InvalidExpectation {
+                        key: "expect_stack",
+                        value: values.to_string(),
+                    })
+            })
+            .transpose()?;
+
+        let mut eval = Eval::start_at(script.start());
+        eval.set_fuel(Some(Self::FUEL));
+        eval.load_data(&script);
+
+        let (effect, operator) = eval.run(&script);
+
+        if effect != expected_effect {
+            return Err(ConformanceFailure::UnexpectedEffect {
+                expected: expected_effect,
+                actual: effect,
+                operator,
+            });
+        }
+        if let Some(expected) = expected_stack {
+            let actual = eval.operand_stack.to_i32_slice();
+
+            if actual != expected {
+                return Err(ConformanceFailure::UnexpectedStack {
+                    expected,
+                    actual: actual.to_vec(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// # The reason a [`ConformanceTest`] has failed
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConformanceFailure {
+    /// # An expectation could not be parsed
+    InvalidExpectation {
+        /// # The key of the metadata entry
+        key: &'static str,
+
+        /// # The value that could not be parsed
+        value: String,
+    },
+
+    /// # The evaluation triggered a different effect than expected
+    UnexpectedEffect {
+        /// # The effect that the test expected
+        expected: Effect,
+
+        /// # The effect that the evaluation triggered
+        actual: Effect,
+
+        /// # The operator that triggered the effect
+        operator: OperatorIndex,
+    },
+
+    /// # The operand stack didn't contain the expected values
+    UnexpectedStack {
+        /// # The values that the test expected, bottom to top
+        expected: Vec<i32>,
+
+        /// # The values that were on the operand stack, bottom to top
+        actual: Vec<i32>,
+    },
+}
+
+impl fmt::Display for ConformanceFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidExpectation { key, value } => {
+                write!(f, "invalid value for `{key}`: `{value}`")
+            }
+            Self::UnexpectedEffect {
+                expected,
+                actual,
+                operator,
+            } => {
+                write!(
+                    f,
+                    "expected effect `{expected:?}`, but operator {operator} \
+                    triggered `{actual:?}` ({actual})",
+                )
+            }
+            Self::UnexpectedStack { expected, actual } => {
+                write!(
+                    f,
+                    "expected operand stack {expected:?}, but found {actual:?}",
+                )
+            }
+        }
+    }
+}
+
+/// # Look up an effect by the name of its variant
+fn parse_effect(name: &str) -> Option<Effect> {
+    // Codes are assigned without gaps, so this visits every effect.
+    (0..)
+        .map_while(Effect::from_code)
+        .find(|effect| format!("{effect:?}") == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::Effect;
+
+    use super::{ConformanceFailure, ConformanceTest};
+
+    fn test(source: &str) -> ConformanceTest {
+        ConformanceTest {
+            path: PathBuf::from("test.stack"),
+            source: source.to_string(),
+        }
+    }
+
+    #[test]
+    fn passes_if_expectations_are_met() {
+        let test = test(
+            "
+            .meta expect_effect AssertionFailed
+            .meta expect_stack 1 -2
+            1 -2 0 assert
+            ",
+        );
+
+        assert_eq!(test.run(), Ok(()));
+    }
+
+    #[test]
+    fn fails_on_unexpected_effect() {
+        let test = test("1 yield");
+
+        let Err(ConformanceFailure::UnexpectedEffect {
+            expected, actual, ..
+        }) = test.run()
+        else {
+            unreachable!("Test expects a different effect.");
+        };
+        assert_eq!(expected, Effect::OutOfOperators);
+        assert_eq!(actual, Effect::Yield);
+    }
+
+    #[test]
+    fn fails_on_unexpected_stack() {
+        let test = test(".meta expect_stack 3\n1 2 + 3\n");
+
+        assert_eq!(
+            test.run(),
+            Err(ConformanceFailure::UnexpectedStack {
+                expected: vec![3],
+                actual: vec![3, 3],
+            }),
+        );
+    }
+
+    #[test]
+    fn fails_on_invalid_expectation() {
+        let test = test(".meta expect_effect Explosion\n");
+
+        assert_eq!(
+            test.run(),
+            Err(ConformanceFailure::InvalidExpectation {
+                key: "expect_effect",
+                value: "Explosion".to_string(),
+            }),
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::{Eval, OperatorIndex, Script, Tracer};
+
+/// # Records which operators have been evaluated
+///
+/// Coverage recording is opt-in. Call [`Eval::enable_coverage`] to start
+/// recording, then access the result via [`Eval::coverage`].
+///
+/// [`Eval::enable_coverage`]: crate::Eval::enable_coverage
+/// [`Eval::coverage`]: crate::Eval::coverage
+#[derive(Clone, Debug, Default)]
+pub struct Coverage {
+    evaluated: BTreeSet<OperatorIndex>,
+}
+
+impl Coverage {
+    /// # Determine whether the provided operator has been evaluated
+    pub fn is_covered(&self, operator: OperatorIndex) -> bool {
+        self.evaluated.contains(&operator)
+    }
+
+    /// # Iterate over all operators that have been evaluated
+    ///
+    /// Yields the operators ordered by index.
+    pub fn covered(&self) -> impl Iterator<Item = OperatorIndex> {
+        self.evaluated.iter().copied()
+    }
+
+    /// # Annotate the source code with coverage information
+    ///
+    /// Expects the script that was evaluated, and the source code that it was
+    /// compiled from. Returns the source code, with a marker in front of each
+    /// line:
+    ///
+    /// - `+`, if all operators on the line have been evaluated.
+    /// - `~`, if some, but not all, operators on the line have been evaluated.
+    /// - `-`, if none of the operators on the line have been evaluated.
+    /// - ` `, if there are no operators on the line.
+    pub fn annotate_source(&self, script: &Script, source: &str) -> String {
+        let line_starts = source
+            .char_indices()
+            .filter(|&(_, ch)| ch == '\n')
+            .map(|(i, _)| i + 1);
+        let line_starts =
+            [0].into_iter().chain(line_starts).collect::<Vec<_>>();
+
+        // For each line, the number of operators on it, and how many of those
+        // have been evaluated.
+        let mut lines = vec![(0, 0); line_starts.len()];
+
+        for (operator, _) in script.operators() {
+            let Ok(range) = script.map_operator_to_source(&operator) else {
+                // The operator doesn't come from the source code, so there's
+                // no line we could attribute it to.
+                continue;
+            };
+
+            let line =
+                line_starts.partition_point(|&start| start <= range.start) - 1;
+
+            let (total, covered) = &mut lines[line];
+            *total += 1;
+            if self.is_covered(operator) {
+                *covered += 1;
+            }
+        }
+
+        let mut annotated = String::new();
+
+        for (text, (total, covered)) in source.lines().zip(lines) {
+            let marker = if total == 0 {
+                ' '
+            } else if covered == total {
+                '+'
+            } else if covered == 0 {
+                '-'
+            } else {
+                '~'
+            };
+
+            let Ok(()) = writeln!(annotated, "{marker} | {text}") else {
+                unreachable!("Writing to a `String` can't fail.");
+            };
+        }
+
+        annotated
+    }
+}
+
+impl Tracer for Coverage {
+    fn before_step(&mut self, operator: OperatorIndex, _: &Eval) {
+        self.evaluated.insert(operator);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Eval, Script};
+
+    #[test]
+    fn annotate_source() {
+        let source = "\
+            # Comment\n\
+            1 @end jump\n\
+            2\n\
+            end: 3 return 4\n\
+        ";
+        let script = Script::compile(source);
+
+        let mut eval = Eval::new();
+        eval.enable_coverage();
+        eval.run(&script);
+
+        let Some(coverage) = eval.coverage() else {
+            unreachable!("Coverage has been enabled.");
+        };
+
+        assert_eq!(
+            coverage.annotate_source(&script, source),
+            "  | # Comment\n\
+            + | 1 @end jump\n\
+            - | 2\n\
+            ~ | end: 3 return 4\n",
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//! # Regions of memory that a script reserves, using directives
+//!
+//! The compiler places regions in memory one after the other, starting at
+//! address `0`, in the order the script defines them. An `.align` directive in
+//! between can leave a gap. A reference to the name of a region compiles into
+//! its address.
+//!
+//! A region starts out zeroed, unless it was defined by a `.data` directive,
+//! or by a `.var` directive with an initial value. Then its words start out
+//! with the values the directive provides. Those can include references to
+//! labels, which are resolved along with the references in the code.
+//!
+//! A `.string` directive reserves a region for a string, which starts out
+//! with the length of the string in bytes, followed by one byte of UTF-8 per
+//! word. If the script defines the same string more than once, the regions
+//! share their addresses.
+
+use std::{
+    collections::HashMap,
+    iter::{self, Peekable},
+    ops::Range,
+};
+
+use crate::{
+    OperatorIndex, Token, TokenKind,
+    lex::{parse_integer, parse_string},
+    script::Operator,
+    structured::is_keyword,
+};
+
+/// # A region of memory that the script reserves
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Region {
+    pub name: String,
+
+    /// # The addresses of the words that make up the region
+    pub addresses: Range<u32>,
+
+    /// # The values that the words of the region start out with
+    ///
+    /// This is empty, unless the region was defined by `.data`, `.string`, or
+    /// by `.var` with an initial value. Then there's one value for each word.
+    /// A `.string` region that shares the addresses of an earlier one has no
+    /// values of its own.
+    pub values: Vec<Datum>,
+}
+
+/// # A value that a word of memory starts out with
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Datum {
+    Integer {
+        value: i32,
+    },
+    Reference {
+        name: String,
+        target: Option<OperatorIndex>,
+
+        /// # The range of the reference in the source text
+        ///
+        /// This is `None` for a script that was loaded from an artifact.
+        range: Option<Range<usize>>,
+    },
+}
+
+/// # The layout of the regions in memory
+#[derive(Default)]
+pub(crate) struct Layout {
+    pub regions: Vec<Region>,
+
+    /// # The address at which the next region starts
+    next_address: u32,
+
+    /// # The addresses of the strings defined so far, by their text
+    strings: HashMap<String, Range<u32>>,
+}
+
+/// # Determine whether a token is a directive
+///
+/// This includes all directives that the layout handles, plus `.start`, which
+/// the compiler handles itself. It doesn't include `.meta`, which the lexer
+/// recognizes as a separate kind of token.
+pub(crate) fn is_directive(token: &str) -> bool {
+    matches!(
+        token,
+        ".zero" | ".data" | ".var" | ".string" | ".align" | ".start"
+    )
+}
+
+impl Layout {
+    /// # Continue a layout that already contains the provided regions
+    ///
+    /// Any region defined from now on is placed after all of them.
+    pub(crate) fn continuing(regions: Vec<Region>) -> Self {
+        let next_address = regions
+            .iter()
+            .map(|region| region.addresses.end)
+            .max()
+            .unwrap_or(0);
+
+        Self {
+            regions,
+            next_address,
+            strings: HashMap::new(),
+        }
+    }
+
+    /// # Compile the parts of a directive that follow the directive itself
+    ///
+    /// Consumes the tokens that are part of the directive, like
+    /// [`parse_procedure_header`] does. Returns `false`, if the directive is
+    /// malformed, or if it would define a region whose name is already taken.
+    ///
+    /// [`parse_procedure_header`]: crate::structured::parse_procedure_header
+    pub(crate) fn compile_directive(
+        &mut self,
+        directive: &str,
+        script: &str,
+        tokens: &mut Peekable<impl Iterator<Item = Token>>,
+    ) -> bool {
+        match directive {
+            ".zero" => {
+                let Some(name) = next_name(script, tokens) else {
+                    return false;
+                };
+                let Some(size) = next_count(script, tokens, 0) else {
+                    return false;
+                };
+
+                self.reserve(&script[name.range], size, Vec::new())
+            }
+            ".data" => {
+                let Some(name) = next_name(script, tokens) else {
+                    return false;
+                };
+                let Some(values) = next_values(script, tokens, name.range.end)
+                else {
+                    return false;
+                };
+                let Ok(size) = values.len().try_into() else {
+                    return false;
+                };
+
+                self.reserve(&script[name.range], size, values)
+            }
+            ".var" => {
+                let Some(name) = next_name(script, tokens) else {
+                    return false;
+                };
+                let Some(values) = next_values(script, tokens, name.range.end)
+                else {
+                    return false;
+                };
+                if values.len() > 1 {
+                    return false;
+                }
+
+                self.reserve(&script[name.range], 1, values)
+            }
+            ".string" => {
+                let Some(name) = next_name(script, tokens) else {
+                    return false;
+                };
+                let Some(string) =
+                    next(script, tokens, |kind, _| kind == TokenKind::String)
+                        .and_then(|token| parse_string(&script[token.range]))
+                else {
+                    return false;
+                };
+
+                self.reserve_string(&script[name.range], string)
+            }
+            ".align" => {
+                let Some(alignment) = next_count(script, tokens, 1) else {
+                    return false;
+                };
+
+                self.align(alignment)
+            }
+            _ => {
+                unreachable!("Only directives are passed to this method.");
+            }
+        }
+    }
+
+    fn reserve(&mut self, name: &str, size: u32, values: Vec<Datum>) -> bool {
+        if self.is_taken(name) {
+            return false;
+        }
+
+        let start = self.next_address;
+        let Some(end) = start.checked_add(size) else {
+            return false;
+        };
+
+        self.regions.push(Region {
+            name: name.to_string(),
+            addresses: start..end,
+            values,
+        });
+        self.next_address = end;
+
+        true
+    }
+
+    /// # Reserve a region for a string, unless the same string exists already
+    ///
+    /// In that case, the new region shares the addresses of the existing one.
+    fn reserve_string(&mut self, name: &str, string: String) -> bool {
+        if let Some(addresses) = self.strings.get(&string) {
+            if self.is_taken(name) {
+                return false;
+            }
+
+            self.regions.push(Region {
+                name: name.to_string(),
+                addresses: addresses.clone(),
+                values: Vec::new(),
+            });
+
+            return true;
+        }
+
+        let Ok(length) = i32::try_from(string.len()) else {
+            return false;
+        };
+        let values = iter::once(length)
+            .chain(string.bytes().map(i32::from))
+            .map(|value| Datum::Integer { value })
+            .collect::<Vec<_>>();
+        let Ok(size) = values.len().try_into() else {
+            return false;
+        };
+
+        if !self.reserve(name, size, values) {
+            return false;
+        }
+
+        let start = self.next_address - size;
+        self.strings.insert(string, start..self.next_address);
+
+        true
+    }
+
+    fn is_taken(&self, name: &str) -> bool {
+        self.regions.iter().any(|region| region.name == name)
+    }
+
+    /// # Move the start of the next region to a multiple of the alignment
+    ///
+    /// The addresses in between are not part of any region.
+    fn align(&mut self, alignment: u32) -> bool {
+        let Some(address) =
+            self.next_address.checked_next_multiple_of(alignment)
+        else {
+            return false;
+        };
+
+        self.next_address = address;
+
+        true
+    }
+}
+
+/// # Consume the next token that isn't a comment, if it's the expected one
+fn next(
+    script: &str,
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+    expected: impl Fn(TokenKind, &str) -> bool,
+) -> Option<Token> {
+    while tokens
+        .next_if(|token| token.kind == TokenKind::Comment)
+        .is_some()
+    {}
+
+    tokens.next_if(|token| expected(token.kind, &script[token.range.clone()]))
+}
+
+/// # Consume the next token, if it's a valid name for a region or label
+pub(crate) fn next_name(
+    script: &str,
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+) -> Option<Token> {
+    next(script, tokens, |kind, token| {
+        kind == TokenKind::Identifier
+            && !is_keyword(token)
+            && !token.starts_with('.')
+    })
+}
+
+/// # Consume the next token, if it's an integer that is at least `min`
+fn next_count(
+    script: &str,
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+    min: i32,
+) -> Option<u32> {
+    next(script, tokens, |kind, token| {
+        kind == TokenKind::Integer
+            && parse_integer(token).is_some_and(|value| value >= min)
+    })
+    .and_then(|token| parse_integer(&script[token.range]))
+    .map(i32::cast_unsigned)
+}
+
+/// # Consume the values of a `.data` or `.var` directive
+///
+/// The values extend to the end of the line, or to the start of a comment.
+/// `end` is the position in the source text, right after the name of the
+/// region. Returns `None`, if a value is neither an integer nor a reference.
+/// That token is not consumed, so it compiles like it would without the
+/// directive.
+fn next_values(
+    script: &str,
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+    mut end: usize,
+) -> Option<Vec<Datum>> {
+    let mut values = Vec::new();
+
+    while let Some(token) = tokens.peek() {
+        if token.kind == TokenKind::Comment
+            || script[end..token.range.start].contains('\n')
+        {
+            break;
+        }
+
+        let text = &script[token.range.clone()];
+        let value = match token.kind {
+            TokenKind::Integer => Datum::Integer {
+                value: parse_integer(text)?,
+            },
+            TokenKind::Reference => Datum::Reference {
+                name: text['@'.len_utf8()..].to_string(),
+                target: None,
+                range: Some(token.range.clone()),
+            },
+            _ => {
+                return None;
+            }
+        };
+
+        end = token.range.end;
+        values.push(value);
+        tokens.next();
+    }
+
+    Some(values)
+}
+
+/// # Compile the references to regions into their addresses
+///
+/// This includes the references in the values of other regions. A region
+/// takes precedence over a label of the same name. This includes the labels of
+/// the prelude.
+pub(crate) fn resolve_region_references(
+    operators: &mut [Operator],
+    regions: &mut [Region],
+) {
+    let addresses = regions
+        .iter()
+        .map(|region| (region.name.clone(), region.addresses.start))
+        .collect::<Vec<_>>();
+    let address = |name: &str| {
+        addresses
+            .iter()
+            .find(|(region, _)| region == name)
+            .map(|&(_, address)| address.cast_signed())
+    };
+
+    for operator in operators {
+        let Operator::Reference { name, target: _ } = operator else {
+            continue;
+        };
+        let Some(value) = address(name) else {
+            continue;
+        };
+
+        *operator = Operator::Integer { value };
+    }
+
+    for datum in regions.iter_mut().flat_map(|region| &mut region.values) {
+        let Datum::Reference {
+            name,
+            target: _,
+            range: _,
+        } = datum
+        else {
+            continue;
+        };
+        let Some(value) = address(name) else {
+            continue;
+        };
+
+        *datum = Datum::Integer { value };
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    fmt,
+    ops::Range,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{Effect, Value};
+
+/// # Services the memory accesses of a script, for a range of addresses
+///
+/// Map a device into the memory of an evaluation using [`Eval::map_device`].
+/// From then on, a `read` or `write` of an address within its range is
+/// forwarded to the device, instead of accessing the memory. This allows hosts
+/// to provide device-style interfaces, like a serial port or a timer, that a
+/// script can use without evaluating `yield`.
+///
+/// Each method receives the offset of the accessed address from the start of
+/// the range. If it returns an error, the evaluation triggers that effect,
+/// like it would for a built-in operator.
+///
+/// [`Eval::map_device`]: crate::Eval::map_device
+///
+/// ## Example
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+///
+/// use stack_assembly::{Effect, Eval, Device, Script, Value};
+///
+/// #[derive(Default)]
+/// struct Counter {
+///     count: u32,
+/// }
+///
+/// impl Device for Counter {
+///     fn read(&mut self, _: u32) -> Result<Value, Effect> {
+///         self.count += 1;
+///         Ok(Value::from(self.count))
+///     }
+///
+///     fn write(&mut self, _: u32, value: Value) -> Result<(), Effect> {
+///         self.count = value.to_u32();
+///         Ok(())
+///     }
+/// }
+///
+/// let script = Script::compile("2000 10 write 2000 read 2000 read");
+///
+/// let mut eval = Eval::new();
+/// eval.map_device(2000..2001, Arc::new(Mutex::new(Counter::default())));
+/// eval.run(&script);
+///
+/// assert_eq!(eval.operand_stack.to_u32_slice(), &[11, 12]);
+/// ```
+pub trait Device: Send {
+    /// # Called when the script reads from an address within the range
+    fn read(&mut self, offset: u32) -> Result<Value, Effect>;
+
+    /// # Called when the script writes to an address within the range
+    fn write(&mut self, offset: u32, value: Value) -> Result<(), Effect>;
+}
+
+/// # The devices that are mapped into the memory of an evaluation
+///
+/// Cloning this shares the devices between the clones.
+#[derive(Clone, Default)]
+pub(crate) struct Devices {
+    inner: Vec<(Range<u32>, SharedDevice)>,
+}
+
+type SharedDevice = Arc<Mutex<dyn Device>>;
+
+impl Devices {
+    pub(crate) fn map(&mut self, addresses: Range<u32>, device: SharedDevice) {
+        self.inner.push((addresses, device));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// # Find the device that is mapped to the provided address, if any
+    ///
+    /// Returns the device, along with the offset of the address within its
+    /// range. If ranges overlap, the device that was mapped last wins.
+    pub(crate) fn find(
+        &self,
+        address: u32,
+    ) -> Option<(MutexGuard<'_, dyn Device + 'static>, u32)> {
+        let (addresses, device) = self
+            .inner
+            .iter()
+            .rev()
+            .find(|(addresses, _)| addresses.contains(&address))?;
+
+        // If a device panicked before, it's up to the device to deal with any
+        // inconsistent state that might have resulted from that.
+        let device = device.lock().unwrap_or_else(PoisonError::into_inner);
+
+        Some((device, address - addresses.start))
+    }
+}
+
+impl fmt::Debug for Devices {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.inner.iter().map(|(addresses, _)| addresses))
+            .finish()
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 /// # An event triggered by scripts, to signal a specific condition
 ///
 /// Effects moderate the communication between script and host. The effect
@@ -54,6 +56,15 @@
 /// assert_eq!(effect, Effect::Yield);
 /// assert_eq!(eval.operand_stack.to_u32_slice(), &[2]);
 /// ```
+///
+/// ## Displaying effects
+///
+/// `Effect`'s [`Display`] implementation provides a short, human-readable
+/// description, which hosts can use in error messages. To identify an effect
+/// outside of Rust, for example in bindings to other languages, use
+/// [`Effect::code`].
+///
+/// [`Display`]: fmt::Display
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Effect {
     /// # An assertion failed
@@ -61,20 +72,54 @@ pub enum Effect {
     /// Can trigger when evaluating `assert`, if its input is zero.
     AssertionFailed,
 
+    /// # Exceeded the maximum depth of nested calls
+    ///
+    /// Can trigger when evaluating `call` or `call_either`, if the call stack
+    /// already holds as many frames as the host allows (see
+    /// [`Limits::max_call_depth`]). The call has not been made.
+    ///
+    /// [`Limits::max_call_depth`]: crate::Limits::max_call_depth
+    CallStackOverflow,
+
+    /// # The host has cancelled the evaluation
+    ///
+    /// Triggers before the next step, after [`CancellationHandle::cancel`] has
+    /// been called. The operator that would have been evaluated next has not
+    /// been evaluated yet.
+    ///
+    /// [`CancellationHandle::cancel`]: crate::CancellationHandle::cancel
+    Cancelled,
+
+    /// # Tried to receive a value from an empty channel
+    ///
+    /// Triggers when evaluating `receive`, if the channel it takes as input has
+    /// no values. The host can send a value to that channel, using
+    /// [`Eval::send`].
+    ///
+    /// Like with [`Effect::OutOfFuel`], the `receive` that triggered this
+    /// effect is evaluated again, once the effect has been cleared.
+    ///
+    /// [`Eval::send`]: crate::Eval::send
+    ChannelEmpty,
+
     /// # Tried to divide by zero
     ///
     /// Can trigger when evaluating the `/` operator, if its second input is
     /// `0`.
     DivisionByZero,
 
-    /// # Division resulted in integer overflow
+    /// # Arithmetic resulted in integer overflow
     ///
-    /// Can only trigger when evaluating the `/` operator, if its first input is
-    /// the lowest signed (two's complement) 32-bit integer, and its second
-    /// input is `-1`.
+    /// Can trigger when evaluating the `/` operator, if its first input is the
+    /// lowest signed (two's complement) 32-bit integer, and its second input
+    /// is `-1`.
     ///
-    /// All other arithmetic operators wrap on overflow and don't trigger this
-    /// effect.
+    /// All other arithmetic operators wrap on overflow by default and don't
+    /// trigger this effect. If the host has enabled
+    /// [`Eval::set_trap_on_overflow`], `+`, `-`, and `*` trigger it too, if
+    /// their result doesn't fit into a signed 32-bit integer.
+    ///
+    /// [`Eval::set_trap_on_overflow`]: crate::Eval::set_trap_on_overflow
     IntegerOverflow,
 
     /// # A memory address is out of bounds
@@ -87,21 +132,67 @@ pub enum Effect {
     /// # Index doesn't refer to valid value on the operand stack
     ///
     /// Can trigger when evaluating the `copy` or `drop` operators, if their
-    /// _index_ input is too large to refer to a value on the operand stack.
+    /// _index_ input is too large (or, for a negative index counting from the
+    /// bottom, too small) to refer to a value on the operand stack. Also
+    /// triggers when evaluating `reverse_n`, if there are fewer values on the
+    /// operand stack than it's supposed to reverse.
     InvalidOperandStackIndex,
 
+    /// # Index doesn't refer to a local variable
+    ///
+    /// Can trigger when evaluating the `local_get` or `local_set` operators, if
+    /// their _index_ input doesn't refer to a local variable that the current
+    /// routine has reserved using `locals`. Can also trigger when evaluating
+    /// `locals`, if the current strand would end up with more than 65536 local
+    /// variables in total.
+    InvalidLocal,
+
     /// # Evaluated a reference that is not paired with a matching label
     ///
     /// Can trigger when evaluating a reference, if that reference does not
     /// refer to a label.
     InvalidReference,
 
+    /// # Strand ID doesn't refer to a strand
+    ///
+    /// Can trigger when evaluating the `resume` operator, if its _strand_ input
+    /// does not refer to a strand that has been spawned.
+    InvalidStrand,
+
+    /// # Jumped or called to a target that doesn't come from a reference
+    ///
+    /// Can only trigger, if the host has enabled provenance tracking via
+    /// [`Eval::enable_provenance`]. Then it triggers when evaluating a jump,
+    /// `call`, or `call_either`, if the target it would transfer control to
+    /// doesn't carry [`Tag::Reference`]. The operator has not been evaluated.
+    ///
+    /// [`Eval::enable_provenance`]: crate::Eval::enable_provenance
+    /// [`Tag::Reference`]: crate::Tag::Reference
+    NonReferenceTarget,
+
+    /// # Exceeded the maximum number of values on the operand stack
+    ///
+    /// Triggers after evaluating an operator, if that left more values on the
+    /// operand stack than the host allows (see [`Limits::max_operand_stack`]).
+    ///
+    /// [`Limits::max_operand_stack`]: crate::Limits::max_operand_stack
+    OperandStackOverflow,
+
     /// # Tried popping a value from an empty operand stack
     ///
     /// Can trigger when evaluating any operator that has more inputs than the
     /// number of values currently on the operand stack.
     OperandStackUnderflow,
 
+    /// # Ran out of fuel
+    ///
+    /// Triggers when the evaluation has used up all of the fuel that the host
+    /// provided via [`Eval::set_fuel`]. The operator that would have been
+    /// evaluated next has not been evaluated yet.
+    ///
+    /// [`Eval::set_fuel`]: crate::Eval::set_fuel
+    OutOfFuel,
+
     /// # Ran out of operators to evaluate
     ///
     /// Triggers when evaluation reaches the end of the script, where no more
@@ -116,14 +207,182 @@ pub enum Effect {
     /// regular end of evaluation, alongside [`Effect::OutOfOperators`].
     Return,
 
+    /// # Exceeded the maximum number of strands
+    ///
+    /// Can trigger when evaluating `spawn`, if there are already as many
+    /// strands as the host allows (see [`Limits::max_strands`]). The strand
+    /// has not been spawned.
+    ///
+    /// [`Limits::max_strands`]: crate::Limits::max_strands
+    TooManyStrands,
+
+    /// # Evaluated a code path that hasn't been written yet
+    ///
+    /// Triggers when evaluating the `todo` operator. This allows compiling and
+    /// running a script that is still incomplete, while making it obvious when
+    /// the evaluation reaches one of its unfinished parts.
+    Todo,
+
+    /// # Evaluated a code path that the script declares to be impossible
+    ///
+    /// Triggers when evaluating the `unreachable` operator. Like
+    /// [`Effect::AssertionFailed`], this signals a bug in the script.
+    Unreachable,
+
+    /// # Evaluated a built-in operator that the host has disabled
+    ///
+    /// Triggers when evaluating an operator that belongs to an extension, which
+    /// the host has disabled using [`Eval::disable_extension`]. The operator
+    /// has not been evaluated.
+    ///
+    /// [`Eval::disable_extension`]: crate::Eval::disable_extension
+    DisabledOperator,
+
     /// # Evaluated an identifier that the language does not recognize
     ///
     /// Can trigger when evaluating an identifier, if that identifier does not
-    /// refer to a known operation.
+    /// refer to a known operation, nor to a native operator that the host has
+    /// registered (see [`Eval::register_native`]).
+    ///
+    /// [`Eval::register_native`]: crate::Eval::register_native
     UnknownIdentifier,
 
     /// # The evaluating script yields control to the host
     ///
     /// Triggers when evaluating the `yield` operator.
+    ///
+    /// What the script expects from the host at this point, is up to the two
+    /// of them. Usually, the script passes inputs on the operand stack, and
+    /// expects the host to replace those with outputs. If any of those are
+    /// strings, they should follow the [convention for strings] in memory.
+    ///
+    /// [convention for strings]: crate::Memory#strings
     Yield,
 }
+
+impl Effect {
+    /// # A numeric code that identifies the effect
+    ///
+    /// The codes are stable. An effect keeps its code across versions, and new
+    /// effects get new codes. Use [`Effect::from_code`] to convert a code back.
+    pub fn code(self) -> u32 {
+        match self {
+            Self::AssertionFailed => 0,
+            Self::DivisionByZero => 1,
+            Self::IntegerOverflow => 2,
+            Self::InvalidAddress => 3,
+            Self::InvalidOperandStackIndex => 4,
+            Self::InvalidReference => 5,
+            Self::OperandStackUnderflow => 6,
+            Self::OutOfOperators => 7,
+            Self::Return => 8,
+            Self::UnknownIdentifier => 9,
+            Self::InvalidStrand => 10,
+            Self::OutOfFuel => 11,
+            Self::Yield => 12,
+            Self::ChannelEmpty => 13,
+            Self::Cancelled => 14,
+            Self::InvalidLocal => 15,
+            Self::Unreachable => 16,
+            Self::Todo => 17,
+            Self::DisabledOperator => 18,
+            Self::OperandStackOverflow => 19,
+            Self::CallStackOverflow => 20,
+            Self::NonReferenceTarget => 21,
+            Self::TooManyStrands => 22,
+        }
+    }
+
+    /// # Convert a code returned by [`Effect::code`] back into an effect
+    ///
+    /// Returns `None`, if the code doesn't identify an effect.
+    pub fn from_code(code: u32) -> Option<Self> {
+        let effect = match code {
+            0 => Self::AssertionFailed,
+            1 => Self::DivisionByZero,
+            2 => Self::IntegerOverflow,
+            3 => Self::InvalidAddress,
+            4 => Self::InvalidOperandStackIndex,
+            5 => Self::InvalidReference,
+            6 => Self::OperandStackUnderflow,
+            7 => Self::OutOfOperators,
+            8 => Self::Return,
+            9 => Self::UnknownIdentifier,
+            10 => Self::InvalidStrand,
+            11 => Self::OutOfFuel,
+            12 => Self::Yield,
+            13 => Self::ChannelEmpty,
+            14 => Self::Cancelled,
+            15 => Self::InvalidLocal,
+            16 => Self::Unreachable,
+            17 => Self::Todo,
+            18 => Self::DisabledOperator,
+            19 => Self::OperandStackOverflow,
+            20 => Self::CallStackOverflow,
+            21 => Self::NonReferenceTarget,
+            22 => Self::TooManyStrands,
+            _ => {
+                return None;
+            }
+        };
+
+        Some(effect)
+    }
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            Self::AssertionFailed => "assertion failed",
+            Self::CallStackOverflow => "call stack overflow",
+            Self::Cancelled => "evaluation was cancelled",
+            Self::ChannelEmpty => "tried to receive from an empty channel",
+            Self::DisabledOperator => "operator has been disabled",
+            Self::DivisionByZero => "division by zero",
+            Self::IntegerOverflow => "integer overflow",
+            Self::InvalidAddress => "memory address out of bounds",
+            Self::InvalidLocal => "invalid local variable",
+            Self::InvalidOperandStackIndex => "invalid operand stack index",
+            Self::InvalidReference => "reference to a label that doesn't exist",
+            Self::InvalidStrand => "resumed a strand that doesn't exist",
+            Self::NonReferenceTarget => {
+                "jump or call target doesn't come from a reference"
+            }
+            Self::OperandStackOverflow => "operand stack overflow",
+            Self::OperandStackUnderflow => "operand stack underflow",
+            Self::OutOfFuel => "out of fuel",
+            Self::OutOfOperators => "reached the end of the script",
+            Self::Return => "returned with an empty call stack",
+            Self::Todo => "reached code that hasn't been written yet",
+            Self::TooManyStrands => "spawned more strands than allowed",
+            Self::Unreachable => "reached code that was declared unreachable",
+            Self::UnknownIdentifier => "unknown identifier",
+            Self::Yield => "yielded to the host",
+        };
+
+        write!(f, "{description}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Effect;
+
+    #[test]
+    fn code_round_trips() {
+        let mut num_effects = 0;
+
+        for code in 0..=u8::MAX.into() {
+            let Some(effect) = Effect::from_code(code) else {
+                continue;
+            };
+
+            assert_eq!(effect.code(), code);
+            num_effects += 1;
+        }
+
+        // If this fails, a new effect has been added without a code, or
+        // `from_code` hasn't been updated.
+        assert_eq!(num_effects, 23);
+    }
+}
@@ -1,6 +1,29 @@
+mod dispatch;
+mod locals;
+mod owned;
+mod strand;
+
+pub use self::owned::OwnedEval;
+pub(crate) use self::{dispatch::Instruction, locals::Locals, strand::Strand};
+
+use std::{
+    collections::{BTreeSet, VecDeque},
+    mem,
+    ops::Range,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
 use crate::{
-    Effect, Memory, OperandStack,
-    script::{Operator, OperatorIndex, Script},
+    CallStack, CancellationHandle, Coverage, Device, Effect, Extension, Limits,
+    Memory, Metrics, OperandStack, Profile, Provenance, Snapshot, Tag, Taint,
+    TaintListener, Tracer, Value,
+    channel::Channels,
+    device::Devices,
+    history::{ChannelAccess, History, Step},
+    native::{Native, Natives},
+    script::{OperatorIndex, Script},
+    tracer::Tracers,
 };
 
 /// # The ongoing evaluation of a script
@@ -21,12 +44,58 @@ use crate::{
 ///
 /// assert_eq!(eval.operand_stack.to_i32_slice(), &[3]);
 /// ```
-#[derive(Debug, Default)]
+///
+/// ## Forking an evaluation
+///
+/// Cloning an `Eval` forks the evaluation. Both clones can then be advanced
+/// independently. This is cheap, as the clones share their [`memory`] until
+/// one of them writes to it.
+///
+/// If the memory has been converted via [`Memory::into_shared`], the clones
+/// keep sharing it, even after writing to it.
+///
+/// [`memory`]: #structfield.memory
+#[derive(Clone, Debug, Default)]
 pub struct Eval {
     next_operator: OperatorIndex,
-    call_stack: Vec<OperatorIndex>,
+    locals: Locals,
     effect: Option<(Effect, OperatorIndex)>,
 
+    /// # Effects that become active, once the active one has been cleared
+    ///
+    /// This is only ever non-empty, if an effect is active. Keeping the active
+    /// effect separate means that the evaluation only needs to check that.
+    pending_effects: VecDeque<(Effect, OperatorIndex)>,
+    fuel: Option<u64>,
+    max_operand_stack: Option<usize>,
+    max_strands: Option<usize>,
+    trap_on_overflow: bool,
+    profile: Option<Profile>,
+    metrics: Option<Metrics>,
+    coverage: Option<Coverage>,
+    provenance: Option<Provenance>,
+    taint: Option<Taint>,
+    tracers: Tracers,
+    devices: Devices,
+    natives: Natives,
+    disabled_extensions: BTreeSet<Extension>,
+    exec: bool,
+
+    /// # Whether the most recent effect was triggered by generated code
+    ///
+    /// See [`Eval::enable_exec`]. Such an effect must not rewind to `exec`, as
+    /// that would evaluate the generated code again, after it had already
+    /// modified the operand stack.
+    effect_in_generated_code: bool,
+
+    /// # The number of nested evaluations of generated code
+    exec_depth: usize,
+    history: Option<History>,
+    current_strand: u32,
+    strands: Vec<Option<Strand>>,
+    channels: Channels,
+    cancellation: Option<CancellationHandle>,
+
     /// # The operand stack
     ///
     /// StackAssembly's evaluation model is based on an implicit stack which
@@ -47,6 +116,16 @@ pub struct Eval {
     /// [`memory`]: #structfield.memory
     pub operand_stack: OperandStack,
 
+    /// # The call stack
+    ///
+    /// Each call pushes the operator to return to, and each `return` pops it.
+    /// Like the operand stack, this refers to the current strand.
+    ///
+    /// Hosts that implement continuations or their own scheduling may need to
+    /// access this directly. Any other modifications make reasoning about the
+    /// script's behavior very difficult, and should be avoided.
+    pub call_stack: CallStack,
+
     /// # The memory
     ///
     /// StackAssembly provides a linear memory that is freely addressable per
@@ -68,6 +147,16 @@ pub struct Eval {
 }
 
 impl Eval {
+    /// # The number of steps between checks of the time budget
+    ///
+    /// See [`Eval::run_for`].
+    pub const STEPS_BETWEEN_TIME_CHECKS: u64 = 1024;
+
+    /// # The maximum number of nested evaluations of generated code
+    ///
+    /// See [`Eval::enable_exec`].
+    pub const MAX_EXEC_DEPTH: usize = 64;
+
     /// # Start evaluating the provided script
     ///
     /// Compile the provided script and return an `Eval` instance that is ready
@@ -77,24 +166,584 @@ impl Eval {
         Self::default()
     }
 
-    /// # Access the current call stack
-    ///
-    /// The returned iterator Yields the operators on the call stack, starting
-    /// with the top-most one.
-    pub fn call_stack(&self) -> impl Iterator<Item = OperatorIndex> {
-        self.call_stack.iter().copied().rev().map(|index| {
-            let Some(value) = index.value.checked_sub(1) else {
-                unreachable!(
-                    "Operator indices on call stack identify the operator to \
-                    return to after the call, which is the operator _after_ \
-                    the calling operator. Hence, an operator before that one \
-                    exists, and subtracting from the index of the call stack \
-                    cannot overflow."
-                );
+    /// # Start evaluating at the provided operator
+    ///
+    /// Works like [`Eval::new`], but instead of starting with the first
+    /// operator of the script, the evaluation starts with the provided one.
+    ///
+    /// Use this with [`Script::start`], to start where the script's `.start`
+    /// directive says. It can also be used to evaluate a routine in isolation,
+    /// for example to run a test (see [`Script::tests`]). Since the call stack
+    /// starts out empty, a `return` at the end of the routine triggers
+    /// [`Effect::Return`].
+    pub fn start_at(operator: OperatorIndex) -> Self {
+        Self {
+            next_operator: operator,
+            ..Self::default()
+        }
+    }
+
+    /// # Start evaluating with limits on the resources it can use
+    ///
+    /// Works like [`Eval::new`], but the evaluation is restricted by the
+    /// provided limits. See [`Limits`].
+    pub fn with_limits(limits: Limits) -> Self {
+        let Limits {
+            memory_words,
+            max_operand_stack,
+            max_call_depth,
+            max_strands,
+            fuel,
+        } = limits;
+
+        Self {
+            fuel,
+            max_operand_stack,
+            max_strands,
+            call_stack: CallStack::with_max_depth(max_call_depth),
+            memory: Memory::new(memory_words),
+            ..Self::default()
+        }
+    }
+
+    /// # Write the values that the script's memory starts out with
+    ///
+    /// A script can provide these values using the `.data` directive (see
+    /// [`Script`]). The host should call this before starting the evaluation,
+    /// after setting up the [`memory`].
+    ///
+    /// If a value doesn't fit into the memory, the evaluation triggers
+    /// [`Effect::InvalidAddress`]. If it's a reference to a label that doesn't
+    /// exist, or if the script's `.start` directive refers to such a label,
+    /// the evaluation triggers [`Effect::InvalidReference`]. Either happens
+    /// right away, without evaluating any operators, and no more values are
+    /// written.
+    ///
+    /// [`memory`]: #structfield.memory
+    pub fn load_data(&mut self, script: &Script) {
+        if script.has_invalid_start() {
+            self.trigger_effect(Effect::InvalidReference, self.next_operator);
+            return;
+        }
+
+        if let Some(provenance) = &mut self.provenance {
+            for address in script.reference_addresses() {
+                provenance.tag_memory(address, Tag::Reference);
+            }
+        }
+
+        for (address, value) in script.initial_values() {
+            let result = match value {
+                Some(value) => self
+                    .memory
+                    .write(address, value)
+                    .map_err(|_| Effect::InvalidAddress),
+                None => Err(Effect::InvalidReference),
             };
 
-            OperatorIndex { value }
-        })
+            if let Err(effect) = result {
+                self.trigger_effect(effect, self.next_operator);
+                return;
+            }
+        }
+    }
+
+    /// # Access the index of the operator that is going to be evaluated next
+    ///
+    /// This is the operator that the next call to [`Eval::step`] is going to
+    /// evaluate, unless an effect is active.
+    pub fn next_operator(&self) -> OperatorIndex {
+        self.next_operator
+    }
+
+    /// # Move the evaluation to the provided operator
+    ///
+    /// The next call to [`Eval::step`] is going to evaluate this operator,
+    /// unless an effect is active. Nothing else about the evaluation changes,
+    /// and the history doesn't record this (see [`Eval::enable_history`]).
+    ///
+    /// This lets the host take control of where the evaluation continues. For
+    /// example, after handling an effect, it can restart from a label (see
+    /// [`Script::labels`]), skip the operator that triggered the effect, or
+    /// continue with a routine that handles it. Moving to an operator that
+    /// doesn't exist is not an error in itself. The evaluation triggers
+    /// [`Effect::OutOfOperators`] once it gets there.
+    ///
+    /// This is also useful for a host that creates its `Eval` before compiling
+    /// the script, to start where the script's `.start` directive says (see
+    /// [`Script::start`]). Otherwise, [`Eval::start_at`] does the same.
+    pub fn set_next_operator(&mut self, operator: OperatorIndex) {
+        self.next_operator = operator;
+    }
+
+    /// # Access the ID of the strand that is currently being evaluated
+    ///
+    /// A script can spawn additional strands using `spawn`, and switch between
+    /// them using `resume`. Each strand has its own operand stack and call
+    /// stack, but they all share the memory. The [`operand_stack`] and
+    /// [`call_stack`] fields, as well as [`Eval::next_operator`], always refer
+    /// to the current strand.
+    ///
+    /// The strand that the evaluation starts with has the ID `0`.
+    ///
+    /// [`operand_stack`]: #structfield.operand_stack
+    /// [`call_stack`]: #structfield.call_stack
+    pub fn current_strand(&self) -> u32 {
+        self.current_strand
+    }
+
+    /// # Capture the current state of the operand stack and memory
+    ///
+    /// Call [`Snapshot::diff`] later, to find out what has changed since.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::new(self)
+    }
+
+    /// # Start recording how many times each operator is evaluated
+    ///
+    /// Profiling is disabled by default, as it slows down the evaluation. Once
+    /// enabled, you can access the recorded profile via [`Eval::profile`].
+    ///
+    /// If profiling is already enabled, this does nothing.
+    pub fn enable_profiling(&mut self) {
+        self.profile.get_or_insert_default();
+    }
+
+    /// # Access the recorded profile
+    ///
+    /// Returns `None`, unless profiling has been enabled via
+    /// [`Eval::enable_profiling`].
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// # Start measuring how fast the evaluation is
+    ///
+    /// Once enabled, you can access the measurements via [`Eval::metrics`].
+    /// See [`Metrics`].
+    ///
+    /// If measuring is already enabled, this does nothing.
+    pub fn enable_metrics(&mut self) {
+        self.metrics.get_or_insert_default();
+    }
+
+    /// # Access the measurements of how fast the evaluation is
+    ///
+    /// Returns `None`, unless measuring has been enabled via
+    /// [`Eval::enable_metrics`].
+    pub fn metrics(&self) -> Option<&Metrics> {
+        self.metrics.as_ref()
+    }
+
+    /// # Start recording which operators are evaluated
+    ///
+    /// Coverage recording is disabled by default, as it slows down the
+    /// evaluation. Once enabled, you can access the recorded coverage via
+    /// [`Eval::coverage`].
+    ///
+    /// If coverage recording is already enabled, this does nothing.
+    pub fn enable_coverage(&mut self) {
+        self.coverage.get_or_insert_default();
+    }
+
+    /// # Access the recorded coverage
+    ///
+    /// Returns `None`, unless coverage recording has been enabled via
+    /// [`Eval::enable_coverage`].
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    /// # Start tracking where each value came from
+    ///
+    /// This is an opt-in research mode, which slows down the evaluation
+    /// considerably. Once enabled, jumps and calls trigger
+    /// [`Effect::NonReferenceTarget`], unless their target comes from a
+    /// reference. See [`Provenance`] for details.
+    ///
+    /// Call this before [`Eval::load_data`], for references in the script's
+    /// `.data` directives to be recognized. If provenance tracking is already
+    /// enabled, this does nothing.
+    ///
+    /// ```
+    /// use stack_assembly::{Effect, Eval, Script};
+    ///
+    /// let script = Script::compile("@f 1 + call f: 1 yield");
+    ///
+    /// let mut eval = Eval::new();
+    /// eval.enable_provenance();
+    ///
+    /// let (effect, _) = eval.run(&script);
+    /// assert_eq!(effect, Effect::NonReferenceTarget);
+    /// ```
+    pub fn enable_provenance(&mut self) {
+        self.provenance.get_or_insert_default();
+    }
+
+    /// # Access the tracked provenance of each value
+    ///
+    /// Returns `None`, unless provenance tracking has been enabled via
+    /// [`Eval::enable_provenance`].
+    pub fn provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_ref()
+    }
+
+    /// # Start tracking which values are tainted
+    ///
+    /// This is an instrumentation mode for security-analysis experiments,
+    /// which slows down the evaluation considerably. Once enabled, mark values
+    /// as tainted using [`Eval::taint_operand`] and [`Eval::taint_memory`].
+    /// See [`Taint`] for details.
+    ///
+    /// If taint tracking is already enabled, this does nothing.
+    ///
+    /// ```
+    /// use stack_assembly::{Effect, Eval, Script};
+    ///
+    /// let script = Script::compile("yield 1 + 2");
+    ///
+    /// let mut eval = Eval::new();
+    /// eval.enable_taint_tracking();
+    /// eval.run(&script);
+    ///
+    /// // Pretend that the host provides some untrusted input.
+    /// eval.operand_stack.push(41);
+    /// eval.taint_operand(0);
+    /// eval.clear_effect();
+    /// eval.run(&script);
+    ///
+    /// let Some(taint) = eval.taint() else {
+    ///     unreachable!("Taint tracking has been enabled.");
+    /// };
+    /// assert_eq!(taint.operand_stack(0), &[true, false]);
+    /// ```
+    pub fn enable_taint_tracking(&mut self) {
+        self.taint.get_or_insert_default();
+    }
+
+    /// # Access which values are tainted
+    ///
+    /// Returns `None`, unless taint tracking has been enabled via
+    /// [`Eval::enable_taint_tracking`].
+    pub fn taint(&self) -> Option<&Taint> {
+        self.taint.as_ref()
+    }
+
+    /// # Mark a value on the operand stack as tainted
+    ///
+    /// Expects an index like `copy` does: `0` refers to the top value,
+    /// while `-1` refers to the bottom one.
+    ///
+    /// Returns `false`, if taint tracking has not been enabled via
+    /// [`Eval::enable_taint_tracking`], or if the index doesn't refer to a
+    /// value on the operand stack.
+    pub fn taint_operand(&mut self, index: i32) -> bool {
+        let Ok(index) =
+            convert_operand_stack_index(&self.operand_stack.values, index)
+        else {
+            return false;
+        };
+        let Some(mut taint) = self.taint.take() else {
+            return false;
+        };
+
+        taint.taint_operand(self, index);
+        self.taint = Some(taint);
+
+        true
+    }
+
+    /// # Mark the value at the provided memory address as tainted
+    ///
+    /// Returns `false`, if taint tracking has not been enabled via
+    /// [`Eval::enable_taint_tracking`], or if the address is out of bounds.
+    pub fn taint_memory(&mut self, address: u32) -> bool {
+        if self.memory.read(address).is_err() {
+            return false;
+        }
+        let Some(taint) = &mut self.taint else {
+            return false;
+        };
+
+        taint.taint_memory(address);
+
+        true
+    }
+
+    /// # Attach a listener that is notified about tainted jumps and writes
+    ///
+    /// See [`TaintListener`]. This enables taint tracking, if it isn't
+    /// already. Any number of listeners can be attached. They are notified in
+    /// the order they were attached, and shared between forks of the
+    /// evaluation, like tracers (see [`Eval::add_tracer`]).
+    pub fn add_taint_listener(
+        &mut self,
+        listener: Arc<Mutex<impl TaintListener + 'static>>,
+    ) {
+        self.taint.get_or_insert_default().add_listener(listener);
+    }
+
+    /// # Attach a tracer to the evaluation
+    ///
+    /// From now on, the tracer is notified before each operator is evaluated.
+    /// See [`Tracer`]. Any number of tracers can be attached. They are notified
+    /// in the order they were attached.
+    ///
+    /// The evaluation only holds on to the tracer, so the caller can keep a
+    /// clone of the [`Arc`] to access it later. When forking the evaluation,
+    /// the clones share the tracers.
+    ///
+    /// Like profiling, tracing slows down the evaluation. In addition, the
+    /// evaluation no longer evaluates multiple operators at once, not even
+    /// where [`CompileOptions::optimize`] allows it, so that the tracer can
+    /// observe each one of them.
+    ///
+    /// [`CompileOptions::optimize`]: crate::CompileOptions::optimize
+    pub fn add_tracer(&mut self, tracer: Arc<Mutex<impl Tracer + 'static>>) {
+        self.tracers.add(tracer);
+    }
+
+    /// # Detach all tracers that have been attached via [`Eval::add_tracer`]
+    pub fn clear_tracers(&mut self) {
+        self.tracers.clear();
+    }
+
+    /// # Map a device into the memory of the evaluation
+    ///
+    /// From now on, `read` and `write` forward any access to an address in the
+    /// provided range to the device, instead of accessing the memory. See
+    /// [`Device`]. If the ranges of multiple devices overlap, the device that
+    /// was mapped last takes precedence.
+    ///
+    /// Like tracers (see [`Eval::add_tracer`]), devices are shared by clones
+    /// of the evaluation. Mapping a device slows down all memory accesses, and
+    /// [`Eval::step_back`] doesn't undo the changes that it makes. The host
+    /// accesses the memory directly, so it doesn't see the devices.
+    pub fn map_device(
+        &mut self,
+        addresses: Range<u32>,
+        device: Arc<Mutex<impl Device + 'static>>,
+    ) {
+        self.devices.map(addresses, device);
+    }
+
+    /// # Remove all devices that have been mapped via [`Eval::map_device`]
+    pub fn unmap_devices(&mut self) {
+        self.devices.clear();
+    }
+
+    /// # Make an operator that is implemented in Rust available to the script
+    ///
+    /// From now on, evaluating an identifier with the provided name calls the
+    /// provided function, instead of triggering [`Effect::UnknownIdentifier`].
+    /// The function has access to the operand stack and memory. If it returns
+    /// an error, the evaluation triggers that effect, like it would for a
+    /// built-in operator.
+    ///
+    /// This is much faster than having the script trigger [`Effect::Yield`],
+    /// for the host to do the same work. The name can't be that of a built-in
+    /// operator, as those take precedence. Registering another operator with
+    /// the same name replaces the previous one.
+    ///
+    /// The compiler doesn't know about native operators. So strict mode (see
+    /// [`Script::try_compile`]) rejects a script that uses them, and
+    /// [`Script::to_wasm`] compiles them like any other unknown identifier.
+    /// [`Eval::step_back`] doesn't undo changes that they make to memory.
+    ///
+    /// ```
+    /// use stack_assembly::{Eval, Script};
+    ///
+    /// let script = Script::compile("9 isqrt");
+    ///
+    /// let mut eval = Eval::new();
+    /// eval.register_native("isqrt", |stack, _| {
+    ///     let value = stack.pop()?.to_u32();
+    ///     stack.push(value.isqrt());
+    ///     Ok(())
+    /// });
+    /// eval.run(&script);
+    ///
+    /// assert_eq!(eval.operand_stack.to_u32_slice(), &[3]);
+    /// ```
+    ///
+    /// [`Script::try_compile`]: crate::Script::try_compile
+    /// [`Script::to_wasm`]: crate::Script::to_wasm
+    pub fn register_native(
+        &mut self,
+        name: impl Into<String>,
+        native: impl Fn(&mut OperandStack, &mut Memory) -> Result<(), Effect>
+        + Send
+        + Sync
+        + 'static,
+    ) {
+        let native: Arc<Native> = Arc::new(native);
+        self.natives.insert(name.into(), native);
+    }
+
+    /// # Disable the built-in operators of an extension
+    ///
+    /// From now on, evaluating any of the operators that belong to the
+    /// extension triggers [`Effect::DisabledOperator`] instead. This allows
+    /// evaluating a script with a restricted set of operators. All extensions
+    /// are enabled by default.
+    ///
+    /// Like native operators (see [`Eval::register_native`]), this is not
+    /// known to the compiler. [`Script::to_wasm`] compiles all operators.
+    ///
+    /// ```
+    /// use stack_assembly::{Effect, Eval, Extension, Script};
+    ///
+    /// let script = Script::compile("0 read");
+    ///
+    /// let mut eval = Eval::new();
+    /// eval.disable_extension(Extension::Memory);
+    ///
+    /// let (effect, _) = eval.run(&script);
+    /// assert_eq!(effect, Effect::DisabledOperator);
+    /// ```
+    ///
+    /// [`Script::to_wasm`]: crate::Script::to_wasm
+    pub fn disable_extension(&mut self, extension: Extension) {
+        self.disabled_extensions.insert(extension);
+    }
+
+    /// # Enable the built-in operators of an extension again
+    ///
+    /// See [`Eval::disable_extension`].
+    pub fn enable_extension(&mut self, extension: Extension) {
+        self.disabled_extensions.remove(&extension);
+    }
+
+    /// # Determine whether the operators of an extension can be evaluated
+    pub fn is_extension_enabled(&self, extension: Extension) -> bool {
+        !self.disabled_extensions.contains(&extension)
+    }
+
+    /// # Allow the script to evaluate code that it generated at runtime
+    ///
+    /// This is a research mode for self-modifying and JIT-compiling scripts,
+    /// and is disabled by default. Once enabled, the `exec` operator pops a
+    /// length, then an address, and reads a string from that range of memory
+    /// (see [`Memory::read_str`]). It compiles that string into a script and
+    /// evaluates it to completion, with the same operand stack, memory, and
+    /// fuel, before the evaluation continues after `exec`.
+    ///
+    /// The generated code finishes by reaching its end, or by evaluating
+    /// `return` with an empty call stack. Any other effect that it triggers is
+    /// attributed to `exec`, and the rest of the generated code is discarded.
+    /// This includes [`Effect::Yield`], so generated code can't yield to the
+    /// host. Unlike elsewhere, [`Effect::OutOfFuel`] and
+    /// [`Effect::ChannelEmpty`] don't cause `exec` to be evaluated again, once
+    /// the evaluation continues. It continues after `exec` instead.
+    ///
+    /// If the string is not within memory, or not valid UTF-8, `exec` triggers
+    /// [`Effect::InvalidAddress`].
+    ///
+    /// Generated code can evaluate `exec` itself. Each such evaluation is
+    /// nested within the previous one, and counts like a call towards the
+    /// maximum call depth (see [`Limits::max_call_depth`]). Regardless of
+    /// that, no more than [`Eval::MAX_EXEC_DEPTH`] evaluations can be nested.
+    /// Exceeding either triggers [`Effect::CallStackOverflow`].
+    ///
+    /// While the generated code is being evaluated, no history, profile, or
+    /// coverage is recorded, and tracers are not notified, as operator indices
+    /// refer to the generated script. For the same reason, generated code can't
+    /// switch strands. Evaluating `spawn` or `resume` within it triggers
+    /// [`Effect::DisabledOperator`]. While this mode is disabled, `exec`
+    /// triggers that effect too.
+    ///
+    /// ```
+    /// use stack_assembly::{Eval, Script};
+    ///
+    /// let script = Script::compile("1 0 5 exec");
+    ///
+    /// let mut eval = Eval::new();
+    /// eval.enable_exec();
+    /// let _ = eval.memory.write_str(0, "2 + 3");
+    /// eval.run(&script);
+    ///
+    /// assert_eq!(eval.operand_stack.to_i32_slice(), &[3, 3]);
+    /// ```
+    pub fn enable_exec(&mut self) {
+        self.exec = true;
+    }
+
+    /// # Determine whether the script can evaluate generated code
+    ///
+    /// See [`Eval::enable_exec`].
+    pub fn is_exec_enabled(&self) -> bool {
+        self.exec
+    }
+
+    /// # Start retaining the information required to step backwards
+    ///
+    /// Once enabled, each call to [`Eval::step`] that evaluates an operator
+    /// records the information required to undo it. Only the most recent
+    /// `capacity` steps are retained. See [`Eval::step_back`].
+    ///
+    /// This is disabled by default, as it slows down the evaluation and
+    /// requires additional memory. Calling this method again discards any
+    /// steps that have been recorded so far.
+    pub fn enable_history(&mut self, capacity: usize) {
+        self.history = Some(History::new(capacity));
+    }
+
+    /// # Undo the most recent step
+    ///
+    /// Restores the operand stack, call stack, local variables, memory,
+    /// remaining fuel, and next operator to what they were before the most
+    /// recent step, and clears any effect that this step triggered.
+    ///
+    /// Changes that the host made between steps are not undone, except for
+    /// changes to the operand stack. Neither is any recorded profile or
+    /// coverage, and tracers are not notified.
+    ///
+    /// Returns `false`, if there is no step to undo. This is the case, if
+    /// history has not been enabled via [`Eval::enable_history`], or all
+    /// retained steps have already been undone.
+    pub fn step_back(&mut self) -> bool {
+        let Some(step) = self.history.as_mut().and_then(History::pop) else {
+            return false;
+        };
+
+        let Step {
+            next_operator,
+            call_stack,
+            locals,
+            operand_stack,
+            fuel,
+            memory_write,
+            channel_access,
+            current_strand,
+            strands,
+        } = step;
+
+        self.next_operator = next_operator;
+        self.call_stack.frames = call_stack;
+        self.locals = locals;
+        self.operand_stack.values = operand_stack;
+        self.fuel = fuel;
+        self.effect = None;
+        self.pending_effects.clear();
+        self.current_strand = current_strand;
+        self.strands = strands;
+
+        if let Some((address, value)) = memory_write {
+            // We've read from this address before the step wrote to it, so
+            // it's definitely valid.
+            let _ = self.memory.write(address, value);
+        }
+        match channel_access {
+            Some(ChannelAccess::Send { channel }) => {
+                self.channels.unsend(channel);
+            }
+            Some(ChannelAccess::Receive { channel, value }) => {
+                self.channels.unreceive(channel, value);
+            }
+            None => {}
+        }
+
+        true
     }
 
     /// # Advance the evaluation until it triggers an effect
@@ -109,9 +758,59 @@ impl Eval {
     /// [`effect`]: #structfield.effect
     /// [`next_operator`]: #structfield.next_operator
     pub fn run(&mut self, script: &Script) -> (Effect, OperatorIndex) {
-        loop {
+        let start = self.start_measuring();
+
+        let effect = loop {
             if let Some(effect) = self.step(script) {
-                return effect;
+                break effect;
+            }
+        };
+
+        self.stop_measuring(start);
+
+        effect
+    }
+
+    /// # Advance the evaluation until it triggers an effect, or time runs out
+    ///
+    /// Works like [`Eval::run`], but returns `None`, if the evaluation is
+    /// still going after the provided time budget has been used up. The
+    /// evaluation can then be continued by calling this method (or any other
+    /// that advances the evaluation) again.
+    ///
+    /// This allows a host to interleave the evaluation with other work, like
+    /// rendering frames, without needing another thread. The elapsed time is
+    /// only checked every [`Eval::STEPS_BETWEEN_TIME_CHECKS`] steps, so the
+    /// budget can be exceeded slightly.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use stack_assembly::{Eval, Script};
+    ///
+    /// let script = Script::compile("loop: @loop jump");
+    ///
+    /// let mut eval = Eval::new();
+    /// let effect = eval.run_for(&script, Duration::from_millis(1));
+    ///
+    /// assert_eq!(effect, None);
+    /// ```
+    pub fn run_for(
+        &mut self,
+        script: &Script,
+        budget: Duration,
+    ) -> Option<(Effect, OperatorIndex)> {
+        let start = Instant::now();
+
+        loop {
+            if let Some(effect) =
+                self.step_n(script, Self::STEPS_BETWEEN_TIME_CHECKS)
+            {
+                return Some(effect);
+            }
+
+            if start.elapsed() >= budget {
+                return None;
             }
         }
     }
@@ -129,254 +828,512 @@ impl Eval {
     /// [`effect`]: #structfield.effect
     /// [`next_operator`]: #structfield.next_operator
     pub fn step(&mut self, script: &Script) -> Option<(Effect, OperatorIndex)> {
+        if self.effect.is_some() {
+            return self.effect;
+        }
+        if let Some(cancellation) = &self.cancellation
+            && cancellation.take()
+        {
+            self.effect = Some((Effect::Cancelled, self.next_operator));
+            return self.effect;
+        }
+
+        if let Some(history) = &mut self.history {
+            history.record(Step {
+                next_operator: self.next_operator,
+                call_stack: self.call_stack.frames.clone(),
+                locals: self.locals.clone(),
+                operand_stack: self.operand_stack.values.clone(),
+                fuel: self.fuel,
+                memory_write: None,
+                channel_access: None,
+                current_strand: self.current_strand,
+                strands: self.strands.clone(),
+            });
+        }
+
         let operator = self.next_operator;
+        let instruction = self.prepare_operator(operator, script);
         self.next_operator.value += 1;
 
-        if self.effect.is_none()
-            && let Err(effect) = self.evaluate_operator(operator, script)
-        {
+        let result = instruction.and_then(|instruction| {
+            self.evaluate_tracked(instruction, operator, script)
+        });
+        let result = result.and_then(|()| self.check_limits());
+
+        if let Err(effect) = result {
+            let in_generated_code =
+                mem::take(&mut self.effect_in_generated_code);
+
+            if let Effect::OutOfFuel
+            | Effect::ChannelEmpty
+            | Effect::OutOfOperators = effect
+                && !in_generated_code
+            {
+                // The operator has not been evaluated. Once the host provides
+                // more fuel, a value to receive, or more operators (see
+                // `Script::append`), evaluation must continue with it.
+                self.next_operator = operator;
+            }
+
             self.effect = Some((effect, operator));
         }
 
         self.effect
     }
 
+    /// # Advance the evaluation by up to `n` steps
+    ///
+    /// Works like calling [`Eval::step`] `n` times, but stops as soon as an
+    /// effect triggers. Returns the active effect, if any, like [`Eval::step`]
+    /// does.
+    ///
+    /// This is useful for hosts that need to regain control regularly, for
+    /// example to keep a user interface responsive, without paying the cost of
+    /// calling [`Eval::step`] for every single operator.
+    pub fn step_n(
+        &mut self,
+        script: &Script,
+        n: u64,
+    ) -> Option<(Effect, OperatorIndex)> {
+        for _ in 0..n {
+            if let Some(effect) = self.step(script) {
+                return Some(effect);
+            }
+        }
+
+        self.effect
+    }
+
+    /// # Access the remaining fuel
+    ///
+    /// Returns `None`, if the evaluation has unlimited fuel, which is the
+    /// default. See [`Eval::set_fuel`].
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// # Limit the number of operators that can be evaluated
+    ///
+    /// Evaluating an operator consumes one unit of fuel. Once the fuel has been
+    /// used up, the next step triggers [`Effect::OutOfFuel`] instead of
+    /// evaluating an operator.
+    ///
+    /// The host can then provide more fuel by calling this method again and
+    /// clearing the effect. Evaluation then continues with the operator that
+    /// had not been evaluated, due to the lack of fuel.
+    ///
+    /// Pass `None` to allow an unlimited number of operators to be evaluated.
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel = fuel;
+    }
+
+    /// # Determine whether arithmetic traps on overflow
+    ///
+    /// See [`Eval::set_trap_on_overflow`].
+    pub fn trap_on_overflow(&self) -> bool {
+        self.trap_on_overflow
+    }
+
+    /// # Control whether arithmetic traps on overflow
+    ///
+    /// By default, `+`, `-`, and `*` wrap on overflow. If this is enabled,
+    /// they treat their inputs as signed and trigger
+    /// [`Effect::IntegerOverflow`] instead, if the result doesn't fit into a
+    /// signed 32-bit integer. This makes it possible to evaluate the same
+    /// script under both semantics and compare the results.
+    ///
+    /// ```
+    /// use stack_assembly::{Effect, Eval, Script};
+    ///
+    /// let script = Script::compile("2147483647 1 +");
+    ///
+    /// let mut eval = Eval::new();
+    /// eval.set_trap_on_overflow(true);
+    ///
+    /// let (effect, _) = eval.run(&script);
+    /// assert_eq!(effect, Effect::IntegerOverflow);
+    /// ```
+    pub fn set_trap_on_overflow(&mut self, trap_on_overflow: bool) {
+        self.trap_on_overflow = trap_on_overflow;
+    }
+
+    /// # Send a value to a channel, for the script to receive
+    ///
+    /// A script sends and receives values through channels, using the `send`
+    /// and `receive` operators. Each channel is a queue of values, identified
+    /// by a number, and shared by all strands of the evaluation, as well as the
+    /// host. Values are received in the order in which they were sent.
+    ///
+    /// If the script triggered [`Effect::ChannelEmpty`], the host can use this
+    /// method to provide the value that the script is waiting for.
+    pub fn send(&mut self, channel: u32, value: impl Into<Value>) {
+        self.channels.send(channel, value.into());
+    }
+
+    /// # Receive a value that has been sent to a channel
+    ///
+    /// Returns `None`, if no value is available. See [`Eval::send`] for more
+    /// information on channels.
+    ///
+    /// A host can connect multiple evaluations, by receiving the values that
+    /// one of them sends, and sending them to another.
+    pub fn receive(&mut self, channel: u32) -> Option<Value> {
+        self.channels.receive(channel)
+    }
+
+    /// # Access a handle that can cancel the evaluation
+    ///
+    /// [`Eval::run`] keeps evaluating until the script triggers an effect,
+    /// which might never happen. Calling [`CancellationHandle::cancel`], for
+    /// example from another thread, makes the evaluation trigger
+    /// [`Effect::Cancelled`] before its next step. Once that effect has been
+    /// cleared, the evaluation can continue as normal.
+    ///
+    /// Checking for cancellation slightly slows down the evaluation, so it
+    /// only happens after this method has been called for the first time. All
+    /// handles returned by this method refer to the same evaluation, as do the
+    /// handles of any clones of it.
+    pub fn cancellation_handle(&mut self) -> CancellationHandle {
+        self.cancellation.get_or_insert_default().clone()
+    }
+
+    /// # Access the active effect, if any
+    ///
+    /// Returns the effect, together with the index of the operator that
+    /// triggered it.
+    pub fn effect(&self) -> Option<(Effect, OperatorIndex)> {
+        self.effect
+    }
+
     /// # Clear the active effect, if any
     ///
     /// If no effect is active, this call does nothing. Return the effect that
     /// has been cleared.
+    ///
+    /// If more effects are pending (see [`Eval::trigger_effect`]), the next
+    /// one becomes active. The evaluation only continues, once all of them
+    /// have been cleared.
     pub fn clear_effect(&mut self) -> Option<(Effect, OperatorIndex)> {
-        self.effect.take()
+        let effect = self.effect.take();
+        self.effect = self.pending_effects.pop_front();
+        effect
     }
 
-    fn evaluate_operator(
+    /// # Trigger an effect on behalf of the script
+    ///
+    /// If no effect is active, the provided one becomes active. Otherwise, it
+    /// is queued behind the active one, and any others that are already
+    /// pending. Clearing the active effect makes the next one active.
+    ///
+    /// This allows instrumentation that is built on top of the evaluation,
+    /// like watchpoints, to deliver its own notifications, even if the script
+    /// has triggered an effect in the same step.
+    ///
+    /// ```
+    /// use stack_assembly::{Effect, Eval, Script};
+    ///
+    /// let script = Script::compile("yield 1");
+    ///
+    /// let mut eval = Eval::new();
+    /// let (_, operator) = eval.run(&script);
+    /// eval.trigger_effect(Effect::Cancelled, operator);
+    ///
+    /// assert_eq!(eval.clear_effect(), Some((Effect::Yield, operator)));
+    /// assert_eq!(eval.effect(), Some((Effect::Cancelled, operator)));
+    /// ```
+    pub fn trigger_effect(&mut self, effect: Effect, operator: OperatorIndex) {
+        if self.effect.is_none() {
+            self.effect = Some((effect, operator));
+        } else {
+            self.pending_effects.push_back((effect, operator));
+        }
+    }
+
+    /// # Iterate over all pending effects
+    ///
+    /// Starts with the active effect, if any, followed by the effects that
+    /// become active, once it has been cleared.
+    pub fn pending_effects(
+        &self,
+    ) -> impl Iterator<Item = (Effect, OperatorIndex)> {
+        self.effect
+            .into_iter()
+            .chain(self.pending_effects.iter().copied())
+    }
+
+    /// # Clear all pending effects, including the active one
+    ///
+    /// Returns the effects that have been cleared, in the order they would
+    /// have become active.
+    pub fn drain_effects(&mut self) -> Vec<(Effect, OperatorIndex)> {
+        let effects = self.pending_effects().collect();
+
+        self.effect = None;
+        self.pending_effects.clear();
+
+        effects
+    }
+
+    /// # Continue the evaluation, after the host has handled an effect
+    ///
+    /// Clears the active effect, if any, then keeps evaluating until the next
+    /// one, like [`Eval::run`].
+    ///
+    /// The host doesn't have to do this right away. It can keep the `Eval`
+    /// around for as long as it needs to, for example while the script waits
+    /// for a timer that it requested by yielding, and evaluate other scripts
+    /// in the meantime. `Eval` is [`Send`], so the evaluation can even be
+    /// resumed on another thread. To keep the script together with the
+    /// evaluation, see [`OwnedEval`].
+    pub fn resume(&mut self, script: &Script) -> (Effect, OperatorIndex) {
+        self.clear_effect();
+        self.run(script)
+    }
+
+    /// # Determine whether the evaluation can skip the next operators
+    ///
+    /// This is the case, if nothing would observe the individual evaluation of
+    /// those operators.
+    #[cfg(feature = "jit")]
+    pub(crate) fn can_skip(&self, num_operators: u32) -> bool {
+        self.effect.is_none()
+            && !self.trap_on_overflow
+            && self.history.is_none()
+            && self.profile.is_none()
+            && self.coverage.is_none()
+            && self.provenance.is_none()
+            && self.taint.is_none()
+            && self.tracers.is_empty()
+            && self.devices.is_empty()
+            && self.disabled_extensions.is_empty()
+            && self.max_operand_stack.is_none()
+            && self
+                .fuel
+                .is_none_or(|fuel| fuel >= u64::from(num_operators))
+    }
+
+    /// # Skip the next operators, after evaluating them elsewhere
+    #[cfg(feature = "jit")]
+    pub(crate) fn skip(&mut self, num_operators: u32) {
+        self.next_operator.value += num_operators;
+
+        if let Some(fuel) = &mut self.fuel {
+            *fuel -= u64::from(num_operators);
+        }
+        if let Some(metrics) = &mut self.metrics {
+            metrics.count_operators(u64::from(num_operators));
+        }
+    }
+
+    /// # Evaluate code that the script generated, for `exec`
+    ///
+    /// See [`Eval::enable_exec`].
+    fn evaluate_generated(&mut self, script: &Script) -> Result<(), Effect> {
+        // Each nested evaluation recurses on the native stack. Without a limit,
+        // code that evaluates itself would overflow that.
+        if self.exec_depth >= Self::MAX_EXEC_DEPTH
+            || self
+                .call_stack
+                .max_depth()
+                .is_some_and(|max| self.exec_depth >= max)
+        {
+            return Err(Effect::CallStackOverflow);
+        }
+
+        let next_operator = mem::take(&mut self.next_operator);
+        let frames = mem::take(&mut self.call_stack.frames);
+        let history = self.history.take();
+        let profile = self.profile.take();
+        let coverage = self.coverage.take();
+        let tracers = mem::take(&mut self.tracers);
+
+        self.exec_depth += 1;
+        let effect = loop {
+            if let Some((effect, _)) = self.step(script) {
+                break effect;
+            }
+        };
+        self.exec_depth -= 1;
+        self.effect = None;
+
+        self.next_operator = next_operator;
+        self.call_stack.frames = frames;
+        self.history = history;
+        self.profile = profile;
+        self.coverage = coverage;
+        self.tracers = tracers;
+
+        match effect {
+            Effect::OutOfOperators | Effect::Return => Ok(()),
+            effect => {
+                self.effect_in_generated_code = true;
+                Err(effect)
+            }
+        }
+    }
+
+    /// # Evaluate an instruction, while tracking provenance and taint
+    ///
+    /// See [`Eval::enable_provenance`] and [`Eval::enable_taint_tracking`].
+    fn evaluate_tracked(
         &mut self,
-        operator: OperatorIndex,
+        instruction: &Instruction,
+        index: OperatorIndex,
         script: &Script,
     ) -> Result<(), Effect> {
-        let operator = script.get_operator(operator)?;
-
-        match operator {
-            Operator::Identifier { value: identifier } => {
-                if identifier == "*" {
-                    let b = self.operand_stack.pop()?.to_i32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a.wrapping_mul(b));
-                } else if identifier == "+" {
-                    let b = self.operand_stack.pop()?.to_i32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a.wrapping_add(b));
-                } else if identifier == "-" {
-                    let b = self.operand_stack.pop()?.to_i32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a.wrapping_sub(b));
-                } else if identifier == "/" {
-                    let b = self.operand_stack.pop()?.to_i32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    if b == 0 {
-                        return Err(Effect::DivisionByZero);
-                    }
-                    if a == i32::MIN && b == -1 {
-                        return Err(Effect::IntegerOverflow);
-                    }
-
-                    self.operand_stack.push(a / b);
-                    self.operand_stack.push(a % b);
-                } else if identifier == "<" {
-                    let b = self.operand_stack.pop()?.to_i32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a < b);
-                } else if identifier == "<=" {
-                    let b = self.operand_stack.pop()?.to_i32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a <= b);
-                } else if identifier == "=" {
-                    let b = self.operand_stack.pop()?.to_i32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a == b);
-                } else if identifier == ">" {
-                    let b = self.operand_stack.pop()?.to_i32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a > b);
-                } else if identifier == ">=" {
-                    let b = self.operand_stack.pop()?.to_i32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a >= b);
-                } else if identifier == "and" {
-                    let b = self.operand_stack.pop()?.to_i32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a & b);
-                } else if identifier == "or" {
-                    let b = self.operand_stack.pop()?.to_i32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a | b);
-                } else if identifier == "xor" {
-                    let b = self.operand_stack.pop()?.to_i32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a ^ b);
-                } else if identifier == "count_ones" {
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a.count_ones());
-                } else if identifier == "leading_zeros" {
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a.leading_zeros());
-                } else if identifier == "trailing_zeros" {
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a.trailing_zeros());
-                } else if identifier == "rotate_left" {
-                    let num_positions = self.operand_stack.pop()?.to_u32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a.rotate_left(num_positions));
-                } else if identifier == "rotate_right" {
-                    let num_positions = self.operand_stack.pop()?.to_u32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a.rotate_right(num_positions));
-                } else if identifier == "shift_left" {
-                    let num_positions = self.operand_stack.pop()?.to_i32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a << num_positions);
-                } else if identifier == "shift_right" {
-                    let num_positions = self.operand_stack.pop()?.to_i32();
-                    let a = self.operand_stack.pop()?.to_i32();
-
-                    self.operand_stack.push(a >> num_positions);
-                } else if identifier == "copy" {
-                    let index_from_top = self.operand_stack.pop()?.to_u32();
-                    let index_from_bottom = convert_operand_stack_index(
-                        &self.operand_stack,
-                        index_from_top,
-                    )?;
-
-                    let Some(value) = self
-                        .operand_stack
-                        .values
-                        .get(index_from_bottom)
-                        .copied()
-                    else {
-                        unreachable!(
-                            "We computed the index from the top, based on the \
-                            number of values on the stack. Since that did not \
-                            result in an integer overflow, it's not possible \
-                            that we ended up with an out-of-range index."
-                        );
-                    };
-
-                    self.operand_stack.push(value);
-                } else if identifier == "drop" {
-                    let index_from_top = self.operand_stack.pop()?.to_u32();
-                    let index_from_bottom = convert_operand_stack_index(
-                        &self.operand_stack,
-                        index_from_top,
-                    )?;
-
-                    // This could theoretically panic, but actually won't, for
-                    // the same reason that the index must be valid in the
-                    // implementation of `copy`.
-                    self.operand_stack.values.remove(index_from_bottom);
-                } else if identifier == "jump" {
-                    let index = self.operand_stack.pop()?.to_u32();
-
-                    self.next_operator.value = index;
-                } else if identifier == "jump_if" {
-                    let index = self.operand_stack.pop()?.to_u32();
-                    let condition = self.operand_stack.pop()?.to_bool();
-
-                    if condition {
-                        self.next_operator.value = index;
-                    }
-                } else if identifier == "call" {
-                    self.call_stack.push(self.next_operator);
-
-                    let index = self.operand_stack.pop()?.to_u32();
-
-                    self.next_operator.value = index;
-                } else if identifier == "call_either" {
-                    self.call_stack.push(self.next_operator);
-
-                    let else_ = self.operand_stack.pop()?.to_u32();
-                    let then = self.operand_stack.pop()?.to_u32();
-                    let condition = self.operand_stack.pop()?.to_bool();
-
-                    self.next_operator = {
-                        let value = if condition { then } else { else_ };
-                        OperatorIndex { value }
-                    };
-                } else if identifier == "return" {
-                    let Some(index) = self.call_stack.pop() else {
-                        return Err(Effect::Return);
-                    };
-
-                    self.next_operator = index;
-                } else if identifier == "assert" {
-                    let condition = self.operand_stack.pop()?.to_bool();
-
-                    if !condition {
-                        return Err(Effect::AssertionFailed);
-                    }
-                } else if identifier == "yield" {
-                    return Err(Effect::Yield);
-                } else if identifier == "read" {
-                    let address = self.operand_stack.pop()?.to_u32();
-
-                    let value = self.memory.read(address)?;
-
-                    self.operand_stack.push(value);
-                } else if identifier == "write" {
-                    let value = self.operand_stack.pop()?;
-                    let address = self.operand_stack.pop()?.to_u32();
-
-                    self.memory.write(address, value)?;
-                } else {
-                    return Err(Effect::UnknownIdentifier);
-                }
+        match (self.provenance.take(), self.taint.take()) {
+            (None, None) => self.evaluate(instruction, index, script),
+            (Some(mut provenance), taint) => {
+                self.taint = taint;
+
+                let result = provenance.evaluate(index, script, self, |eval| {
+                    eval.evaluate_tracked(instruction, index, script)
+                });
+                self.provenance = Some(provenance);
+
+                result
             }
-            Operator::Integer { value } => {
-                self.operand_stack.push(*value);
+            (None, Some(mut taint)) => {
+                let result = taint.evaluate(index, script, self, |eval| {
+                    eval.evaluate(instruction, index, script)
+                });
+                self.taint = Some(taint);
+
+                result
             }
-            Operator::Reference { name } => {
-                let operator = script.resolve_reference(name)?;
-                self.operand_stack.push(operator.value);
+        }
+    }
+
+    /// # Evaluate an instruction, falling back to a native operator
+    fn evaluate(
+        &mut self,
+        instruction: &Instruction,
+        index: OperatorIndex,
+        script: &Script,
+    ) -> Result<(), Effect> {
+        match instruction.evaluate(self) {
+            Err(Effect::UnknownIdentifier) => {
+                self.evaluate_native(index, script)
             }
+            result => result,
+        }
+    }
+
+    /// # Make sure that the last operator didn't exceed any limits
+    ///
+    /// See [`Limits`].
+    fn check_limits(&self) -> Result<(), Effect> {
+        if self
+            .max_operand_stack
+            .is_some_and(|max| self.operand_stack.values.len() > max)
+        {
+            return Err(Effect::OperandStackOverflow);
         }
 
         Ok(())
     }
+
+    /// # Evaluate an identifier as a native operator, if one is registered
+    ///
+    /// See [`Eval::register_native`].
+    fn evaluate_native(
+        &mut self,
+        index: OperatorIndex,
+        script: &Script,
+    ) -> Result<(), Effect> {
+        let Some(native) = script
+            .identifier_at(index)
+            .and_then(|name| self.natives.get(name))
+        else {
+            return Err(Effect::UnknownIdentifier);
+        };
+
+        native(&mut self.operand_stack, &mut self.memory)
+    }
+
+    /// # Do everything that needs to happen, before an operator is evaluated
+    ///
+    /// Returns the instruction that the operator has been decoded into.
+    fn prepare_operator<'r>(
+        &mut self,
+        index: OperatorIndex,
+        script: &'r Script,
+    ) -> Result<&'r Instruction, Effect> {
+        let instruction = script.get_instruction(index)?;
+
+        if !self.disabled_extensions.is_empty()
+            && let Some(opcode) = script.opcode_at(index)
+            && self.disabled_extensions.contains(&opcode.extension())
+        {
+            return Err(Effect::DisabledOperator);
+        }
+
+        if let Some(fuel) = &mut self.fuel {
+            *fuel = fuel.checked_sub(1).ok_or(Effect::OutOfFuel)?;
+        }
+        self.before_step(index);
+
+        Ok(instruction)
+    }
+
+    /// # Note the time at which `run` started, if measuring is enabled
+    ///
+    /// See [`Eval::enable_metrics`].
+    pub(crate) fn start_measuring(&self) -> Option<Instant> {
+        self.metrics.is_some().then(Instant::now)
+    }
+
+    /// # Account for the time that `run` took
+    pub(crate) fn stop_measuring(&mut self, start: Option<Instant>) {
+        if let (Some(metrics), Some(start)) = (&mut self.metrics, start) {
+            metrics.add_time_since(start);
+        }
+    }
+
+    /// # Notify all tracers, that the provided operator is evaluated next
+    ///
+    /// This includes the built-in ones, that record the profile and coverage.
+    pub(crate) fn before_step(&mut self, index: OperatorIndex) {
+        if let Some(metrics) = &mut self.metrics {
+            metrics.count_operators(1);
+        }
+
+        // The built-in tracers are fields of `self`, so they need to be moved
+        // out while they get access to it.
+        if let Some(mut profile) = self.profile.take() {
+            profile.before_step(index, self);
+            self.profile = Some(profile);
+        }
+        if let Some(mut coverage) = self.coverage.take() {
+            coverage.before_step(index, self);
+            self.coverage = Some(coverage);
+        }
+
+        self.tracers.before_step(index, self);
+    }
 }
 
-fn convert_operand_stack_index(
-    operand_stack: &OperandStack,
-    index_from_top: u32,
+/// # Convert an index, as `copy` and `drop` expect it, into one into `values`
+///
+/// Non-negative indices count from the top of the stack, negative ones from the
+/// bottom. So `0` refers to the top value, while `-1` refers to the bottom one.
+pub(crate) fn convert_operand_stack_index(
+    values: &[Value],
+    index: i32,
 ) -> Result<usize, Effect> {
-    let Ok(index_from_top): Result<usize, _> = index_from_top.try_into() else {
-        // It is not possible to have a stack larger than what `usize` can
-        // address. So by definition, any index that's too large to convert to
-        // `usize`, can not be valid.
-        return Err(Effect::InvalidOperandStackIndex);
+    // It is not possible to have a stack larger than what `usize` can address.
+    // So by definition, any index that's too large to convert to `usize`, can
+    // not be valid.
+    let index_from_bottom = if index < 0 {
+        usize::try_from(!index).ok()
+    } else {
+        usize::try_from(index).ok().and_then(|index_from_top| {
+            values.len().checked_sub(1)?.checked_sub(index_from_top)
+        })
     };
 
-    let index_from_bottom = operand_stack
-        .values
-        .len()
-        .checked_sub(1)
-        .and_then(|index| index.checked_sub(index_from_top));
-
-    index_from_bottom.ok_or(Effect::InvalidOperandStackIndex)
+    index_from_bottom
+        .filter(|index| *index < values.len())
+        .ok_or(Effect::InvalidOperandStackIndex)
 }
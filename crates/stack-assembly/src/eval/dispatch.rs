@@ -0,0 +1,811 @@
+//! # Operators, decoded ahead of time for fast dispatch
+//!
+//! When compiling a script, each operator is decoded into an [`Instruction`]:
+//! a pointer to the function that evaluates it, plus an immediate value that
+//! this function receives as an argument. This means that evaluating an
+//! operator doesn't require any branching on the kind of operator. The
+//! evaluation calls the function directly.
+
+use std::cmp::Ordering;
+
+use crate::{
+    Effect, Value,
+    fuse::Superinstruction,
+    history::ChannelAccess,
+    opcode::Opcode,
+    script::{Operator, OperatorIndex, Script},
+};
+
+use super::{Eval, convert_operand_stack_index};
+
+/// # An operator, decoded for fast dispatch
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Instruction {
+    handler: Handler,
+    immediate: Value,
+}
+
+impl Instruction {
+    /// # Decode the provided operator
+    pub(crate) fn decode(operator: &Operator) -> Self {
+        let (handler, immediate): (Handler, Value) = match operator {
+            Operator::End => (end, Value::from(0)),
+            Operator::Fused { superinstruction } => match *superinstruction {
+                Superinstruction::AddImmediate { value } => {
+                    (add_immediate, Value::from(value))
+                }
+                Superinstruction::CopyJumpIf => (copy_jump_if, Value::from(0)),
+                Superinstruction::WriteImmediate { value } => {
+                    (write_immediate, Value::from(value))
+                }
+            },
+            Operator::Identifier { value: _ } => {
+                (unknown_identifier, Value::from(0))
+            }
+            Operator::Integer { value } => (integer, Value::from(*value)),
+            Operator::Opcode { opcode } => {
+                let handler: Handler = match opcode {
+                    Opcode::Multiply => multiply,
+                    Opcode::Add => add,
+                    Opcode::Subtract => subtract,
+                    Opcode::Divide => divide,
+                    Opcode::DivFloor => div_floor,
+                    Opcode::ModFloor => mod_floor,
+                    Opcode::DivmodU => divmod_u,
+                    Opcode::Less => less,
+                    Opcode::LessOrEqual => less_or_equal,
+                    Opcode::Equal => equal,
+                    Opcode::Greater => greater,
+                    Opcode::GreaterOrEqual => greater_or_equal,
+                    Opcode::And => and,
+                    Opcode::Or => or,
+                    Opcode::Xor => xor,
+                    Opcode::CountOnes => count_ones,
+                    Opcode::LeadingZeros => leading_zeros,
+                    Opcode::TrailingZeros => trailing_zeros,
+                    Opcode::RotateLeft => rotate_left,
+                    Opcode::RotateRight => rotate_right,
+                    Opcode::ShiftLeft => shift_left,
+                    Opcode::ShiftRight => shift_right,
+                    Opcode::Copy => copy,
+                    Opcode::Drop => drop,
+                    Opcode::ReverseN => reverse_n,
+                    Opcode::Jump => jump,
+                    Opcode::JumpIf => jump_if,
+                    Opcode::JumpIfEqual => jump_if_eq,
+                    Opcode::JumpIfNotEqual => jump_if_ne,
+                    Opcode::JumpIfLess => jump_if_lt,
+                    Opcode::JumpIfLessOrEqual => jump_if_le,
+                    Opcode::JumpIfGreater => jump_if_gt,
+                    Opcode::JumpIfGreaterOrEqual => jump_if_ge,
+                    Opcode::Call => call,
+                    Opcode::CallEither => call_either,
+                    Opcode::Return => return_,
+                    Opcode::Pc => pc,
+                    Opcode::Locals => locals,
+                    Opcode::LocalGet => local_get,
+                    Opcode::LocalSet => local_set,
+                    Opcode::Assert => assert,
+                    Opcode::Unreachable => unreachable,
+                    Opcode::Todo => todo,
+                    Opcode::Yield => yield_,
+                    Opcode::Read => read,
+                    Opcode::Write => write,
+                    Opcode::Spawn => spawn,
+                    Opcode::Resume => resume,
+                    Opcode::Current => current,
+                    Opcode::Send => send,
+                    Opcode::Receive => receive,
+                    Opcode::Exec => exec,
+                };
+
+                (handler, Value::from(0))
+            }
+            Operator::Reference { name: _, target } => match target {
+                Some(operator) => (integer, Value::from(operator.value)),
+                None => (invalid_reference, Value::from(0)),
+            },
+        };
+
+        Self { handler, immediate }
+    }
+
+    /// # Evaluate the instruction
+    pub(crate) fn evaluate(&self, eval: &mut Eval) -> Result<(), Effect> {
+        (self.handler)(eval, self.immediate)
+    }
+}
+
+/// # A function that evaluates an operator
+///
+/// Receives the immediate value of the [`Instruction`] as its second argument.
+/// Operators that don't have an immediate value ignore it.
+type Handler = fn(&mut Eval, Value) -> Result<(), Effect>;
+
+fn end(_: &mut Eval, _: Value) -> Result<(), Effect> {
+    Err(Effect::OutOfOperators)
+}
+
+fn unknown_identifier(_: &mut Eval, _: Value) -> Result<(), Effect> {
+    Err(Effect::UnknownIdentifier)
+}
+
+fn invalid_reference(_: &mut Eval, _: Value) -> Result<(), Effect> {
+    Err(Effect::InvalidReference)
+}
+
+fn integer(eval: &mut Eval, value: Value) -> Result<(), Effect> {
+    eval.operand_stack.push(value);
+    Ok(())
+}
+
+fn multiply(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?;
+    let a = eval.operand_stack.pop()?;
+
+    let product =
+        arithmetic(eval, a, b, Value::wrapping_mul, Value::checked_mul)?;
+
+    eval.operand_stack.push(product);
+    Ok(())
+}
+
+fn add(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?;
+    let a = eval.operand_stack.pop()?;
+
+    let sum = arithmetic(eval, a, b, Value::wrapping_add, Value::checked_add)?;
+
+    eval.operand_stack.push(sum);
+    Ok(())
+}
+
+fn subtract(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?;
+    let a = eval.operand_stack.pop()?;
+
+    let difference =
+        arithmetic(eval, a, b, Value::wrapping_sub, Value::checked_sub)?;
+
+    eval.operand_stack.push(difference);
+    Ok(())
+}
+
+/// # Apply an arithmetic operation, wrapping or trapping on overflow
+///
+/// See [`Eval::set_trap_on_overflow`].
+fn arithmetic(
+    eval: &Eval,
+    a: Value,
+    b: Value,
+    wrapping: fn(Value, Value) -> Value,
+    checked: fn(Value, Value) -> Option<Value>,
+) -> Result<Value, Effect> {
+    if eval.trap_on_overflow {
+        checked(a, b).ok_or(Effect::IntegerOverflow)
+    } else {
+        Ok(wrapping(a, b))
+    }
+}
+
+fn divide(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?.to_i32();
+    let a = eval.operand_stack.pop()?.to_i32();
+
+    if b == 0 {
+        return Err(Effect::DivisionByZero);
+    }
+    if a == i32::MIN && b == -1 {
+        return Err(Effect::IntegerOverflow);
+    }
+
+    eval.operand_stack.push(a / b);
+    eval.operand_stack.push(a % b);
+    Ok(())
+}
+
+fn div_floor(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?.to_i32();
+    let a = eval.operand_stack.pop()?.to_i32();
+
+    if b == 0 {
+        return Err(Effect::DivisionByZero);
+    }
+    if a == i32::MIN && b == -1 {
+        return Err(Effect::IntegerOverflow);
+    }
+
+    let (quotient, _) = divide_flooring(a, b);
+
+    eval.operand_stack.push(quotient);
+    Ok(())
+}
+
+fn mod_floor(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?.to_i32();
+    let a = eval.operand_stack.pop()?.to_i32();
+
+    if b == 0 {
+        return Err(Effect::DivisionByZero);
+    }
+
+    // The quotient overflows for `i32::MIN` and `-1`, but the modulo is `0`,
+    // as for any other divisor of `-1`. So unlike `div_floor`, this never
+    // triggers an effect on overflow.
+    let (_, modulo) = divide_flooring(a, b);
+
+    eval.operand_stack.push(modulo);
+    Ok(())
+}
+
+fn divmod_u(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?.to_u32();
+    let a = eval.operand_stack.pop()?.to_u32();
+
+    if b == 0 {
+        return Err(Effect::DivisionByZero);
+    }
+
+    eval.operand_stack.push(a / b);
+    eval.operand_stack.push(a % b);
+    Ok(())
+}
+
+/// # Divide, rounding the quotient towards negative infinity
+///
+/// Returns the quotient and the modulo, which has the same sign as the
+/// divisor. The quotient wraps on overflow.
+fn divide_flooring(a: i32, b: i32) -> (i32, i32) {
+    let quotient = a.wrapping_div(b);
+    let remainder = a.wrapping_rem(b);
+
+    if remainder != 0 && (remainder < 0) != (b < 0) {
+        (quotient - 1, remainder + b)
+    } else {
+        (quotient, remainder)
+    }
+}
+
+fn less(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?;
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a.cmp_signed(b).is_lt());
+    Ok(())
+}
+
+fn less_or_equal(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?;
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a.cmp_signed(b).is_le());
+    Ok(())
+}
+
+fn equal(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?;
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a == b);
+    Ok(())
+}
+
+fn greater(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?;
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a.cmp_signed(b).is_gt());
+    Ok(())
+}
+
+fn greater_or_equal(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?;
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a.cmp_signed(b).is_ge());
+    Ok(())
+}
+
+fn and(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?;
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a & b);
+    Ok(())
+}
+
+fn or(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?;
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a | b);
+    Ok(())
+}
+
+fn xor(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let b = eval.operand_stack.pop()?;
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a ^ b);
+    Ok(())
+}
+
+fn count_ones(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a.count_ones());
+    Ok(())
+}
+
+fn leading_zeros(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a.leading_zeros());
+    Ok(())
+}
+
+fn trailing_zeros(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a.trailing_zeros());
+    Ok(())
+}
+
+fn rotate_left(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let num_positions = eval.operand_stack.pop()?.to_u32();
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a.rotate_left(num_positions));
+    Ok(())
+}
+
+fn rotate_right(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let num_positions = eval.operand_stack.pop()?.to_u32();
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a.rotate_right(num_positions));
+    Ok(())
+}
+
+fn shift_left(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let num_positions = eval.operand_stack.pop()?.to_u32();
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a.shift_left(num_positions));
+    Ok(())
+}
+
+fn shift_right(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let num_positions = eval.operand_stack.pop()?.to_u32();
+    let a = eval.operand_stack.pop()?;
+
+    eval.operand_stack.push(a.shift_right_signed(num_positions));
+    Ok(())
+}
+
+fn copy(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let index = eval.operand_stack.pop()?.to_i32();
+    let index_from_bottom =
+        convert_operand_stack_index(&eval.operand_stack.values, index)?;
+
+    let Some(value) = eval.operand_stack.values.get(index_from_bottom).copied()
+    else {
+        unreachable!(
+            "Converting the index checked, that it's within the bounds of the \
+            stack."
+        );
+    };
+
+    eval.operand_stack.push(value);
+    Ok(())
+}
+
+fn drop(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let index = eval.operand_stack.pop()?.to_i32();
+    let index_from_bottom =
+        convert_operand_stack_index(&eval.operand_stack.values, index)?;
+
+    // This could theoretically panic, but actually won't, for the same reason
+    // that the index must be valid in the implementation of `copy`.
+    eval.operand_stack.values.remove(index_from_bottom);
+    Ok(())
+}
+
+fn reverse_n(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let num_values = eval.operand_stack.pop()?.to_u32();
+    let values = &mut eval.operand_stack.values;
+
+    let Some(start) = usize::try_from(num_values)
+        .ok()
+        .and_then(|num_values| values.len().checked_sub(num_values))
+    else {
+        return Err(Effect::InvalidOperandStackIndex);
+    };
+
+    values[start..].reverse();
+    Ok(())
+}
+
+fn jump(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let index = eval.operand_stack.pop()?.to_u32();
+
+    eval.next_operator.value = index;
+    Ok(())
+}
+
+fn jump_if(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let index = eval.operand_stack.pop()?.to_u32();
+    let condition = eval.operand_stack.pop()?.to_bool();
+
+    if condition {
+        eval.next_operator.value = index;
+    }
+    Ok(())
+}
+
+fn jump_if_eq(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    jump_if_comparison(eval, Ordering::is_eq)
+}
+
+fn jump_if_ne(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    jump_if_comparison(eval, Ordering::is_ne)
+}
+
+fn jump_if_lt(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    jump_if_comparison(eval, Ordering::is_lt)
+}
+
+fn jump_if_le(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    jump_if_comparison(eval, Ordering::is_le)
+}
+
+fn jump_if_gt(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    jump_if_comparison(eval, Ordering::is_gt)
+}
+
+fn jump_if_ge(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    jump_if_comparison(eval, Ordering::is_ge)
+}
+
+/// # Jump, if comparing two values has the expected result
+///
+/// Compares the values like `<` and the other comparison operators do,
+/// treating them as signed.
+fn jump_if_comparison(
+    eval: &mut Eval,
+    expected: fn(Ordering) -> bool,
+) -> Result<(), Effect> {
+    let index = eval.operand_stack.pop()?.to_u32();
+    let b = eval.operand_stack.pop()?;
+    let a = eval.operand_stack.pop()?;
+
+    if expected(a.cmp_signed(b)) {
+        eval.next_operator.value = index;
+    }
+    Ok(())
+}
+
+fn call(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    eval.call_stack.push(eval.next_operator)?;
+    eval.locals.enter_frame();
+
+    let index = eval.operand_stack.pop()?.to_u32();
+
+    eval.next_operator.value = index;
+    Ok(())
+}
+
+fn call_either(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    eval.call_stack.push(eval.next_operator)?;
+    eval.locals.enter_frame();
+
+    let else_ = eval.operand_stack.pop()?.to_u32();
+    let then = eval.operand_stack.pop()?.to_u32();
+    let condition = eval.operand_stack.pop()?.to_bool();
+
+    eval.next_operator = {
+        let value = if condition { then } else { else_ };
+        OperatorIndex { value }
+    };
+    Ok(())
+}
+
+fn return_(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let Some(index) = eval.call_stack.pop() else {
+        return Err(Effect::Return);
+    };
+
+    eval.locals.leave_frame();
+
+    eval.next_operator = index;
+    Ok(())
+}
+
+fn pc(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    // The index has already been advanced past the current operator.
+    let index = eval.next_operator.value - 1;
+
+    eval.operand_stack.push(index);
+    Ok(())
+}
+
+fn locals(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let num = eval.operand_stack.pop()?.to_u32();
+
+    eval.locals.reserve(num)
+}
+
+fn local_get(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let index = eval.operand_stack.pop()?.to_u32();
+
+    let value = eval.locals.get(index)?;
+
+    eval.operand_stack.push(value);
+    Ok(())
+}
+
+fn local_set(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let value = eval.operand_stack.pop()?;
+    let index = eval.operand_stack.pop()?.to_u32();
+
+    eval.locals.set(index, value)
+}
+
+fn assert(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let condition = eval.operand_stack.pop()?.to_bool();
+
+    if !condition {
+        return Err(Effect::AssertionFailed);
+    }
+    Ok(())
+}
+
+fn unreachable(_: &mut Eval, _: Value) -> Result<(), Effect> {
+    Err(Effect::Unreachable)
+}
+
+fn todo(_: &mut Eval, _: Value) -> Result<(), Effect> {
+    Err(Effect::Todo)
+}
+
+fn yield_(_: &mut Eval, _: Value) -> Result<(), Effect> {
+    Err(Effect::Yield)
+}
+
+fn read(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let address = eval.operand_stack.pop()?.to_u32();
+
+    let value = match eval.devices.find(address) {
+        Some((mut device, offset)) => device.read(offset)?,
+        None => eval.memory.read(address)?,
+    };
+
+    eval.operand_stack.push(value);
+    Ok(())
+}
+
+fn write(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let value = eval.operand_stack.pop()?;
+    let address = eval.operand_stack.pop()?.to_u32();
+
+    if let Some((mut device, offset)) = eval.devices.find(address) {
+        return device.write(offset, value);
+    }
+
+    if let Some(history) = &mut eval.history
+        && let Ok(previous) = eval.memory.read(address)
+    {
+        history.record_memory_write(address, previous);
+    }
+
+    eval.memory.write(address, value)?;
+    Ok(())
+}
+
+fn spawn(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    if eval.exec_depth > 0 {
+        // Generated code must not switch strands. See `Eval::enable_exec`.
+        return Err(Effect::DisabledOperator);
+    }
+
+    let start = eval.operand_stack.pop()?.to_u32();
+
+    let id = eval.spawn_strand(OperatorIndex { value: start })?;
+
+    eval.operand_stack.push(id);
+    Ok(())
+}
+
+fn resume(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    if eval.exec_depth > 0 {
+        return Err(Effect::DisabledOperator);
+    }
+
+    let id = eval.operand_stack.pop()?.to_u32();
+
+    eval.resume_strand(id)
+}
+
+fn current(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    eval.operand_stack.push(eval.current_strand);
+    Ok(())
+}
+
+fn send(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let value = eval.operand_stack.pop()?;
+    let channel = eval.operand_stack.pop()?.to_u32();
+
+    if let Some(history) = &mut eval.history {
+        history.record_channel_access(ChannelAccess::Send { channel });
+    }
+
+    eval.channels.send(channel, value);
+    Ok(())
+}
+
+fn receive(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    let channel = eval.operand_stack.pop()?.to_u32();
+
+    let Some(value) = eval.channels.receive(channel) else {
+        // `receive` is evaluated again, once a value is available. So it must
+        // find its input where it left it.
+        eval.operand_stack.push(channel);
+        return Err(Effect::ChannelEmpty);
+    };
+
+    if let Some(history) = &mut eval.history {
+        history
+            .record_channel_access(ChannelAccess::Receive { channel, value });
+    }
+
+    eval.operand_stack.push(value);
+    Ok(())
+}
+
+fn exec(eval: &mut Eval, _: Value) -> Result<(), Effect> {
+    if !eval.exec {
+        return Err(Effect::DisabledOperator);
+    }
+
+    let length = eval.operand_stack.pop()?.to_u32();
+    let address = eval.operand_stack.pop()?.to_u32();
+
+    let source = eval
+        .memory
+        .read_str(address..address.saturating_add(length))
+        .map_err(|_| Effect::InvalidAddress)?;
+    let script = Script::compile(&source);
+
+    eval.evaluate_generated(&script)
+}
+
+// A superinstruction must behave exactly like the operators it replaces. Rather
+// than replicating every way those could trigger an effect, the handlers below
+// only evaluate the superinstruction, if it's guaranteed to succeed. Otherwise,
+// they fall back to evaluating the first of the unfused operators.
+
+fn add_immediate(eval: &mut Eval, value: Value) -> Result<(), Effect> {
+    let Some(a) = eval.operand_stack.values.last().copied() else {
+        return integer(eval, value);
+    };
+
+    // If the evaluation traps on overflow, the addition can fail. Then the
+    // effect must trigger at `+`, so that's left to the unfused operators.
+    let Ok(sum) =
+        arithmetic(eval, a, value, Value::wrapping_add, Value::checked_add)
+    else {
+        return integer(eval, value);
+    };
+    if !eval.can_fuse() {
+        return integer(eval, value);
+    }
+
+    eval.evaluate_second_fused_operator();
+
+    let Ok(_) = eval.operand_stack.pop() else {
+        unreachable!("Checked above, that there's a value on the stack.");
+    };
+    eval.operand_stack.push(sum);
+
+    Ok(())
+}
+
+fn copy_jump_if(eval: &mut Eval, value: Value) -> Result<(), Effect> {
+    let values = &eval.operand_stack.values;
+
+    let Some((index, rest)) = values.split_last() else {
+        return copy(eval, value);
+    };
+
+    // `copy` needs a value at the index, `jump_if` needs an additional value as
+    // its condition. If the index is valid, the latter is there too.
+    let is_valid = convert_operand_stack_index(rest, index.to_i32()).is_ok();
+    if !is_valid || !eval.can_fuse() {
+        return copy(eval, value);
+    }
+
+    eval.evaluate_second_fused_operator();
+
+    let Ok(index) = eval.operand_stack.pop() else {
+        unreachable!("Checked above, that there's a value on the stack.");
+    };
+    let Ok(index_from_bottom) =
+        convert_operand_stack_index(&eval.operand_stack.values, index.to_i32())
+    else {
+        unreachable!("Checked above, that the index is valid.");
+    };
+    let target = eval.operand_stack.values[index_from_bottom];
+    let Ok(condition) = eval.operand_stack.pop() else {
+        unreachable!("Checked above, that there's a condition on the stack.");
+    };
+
+    if condition.to_bool() {
+        eval.next_operator.value = target.to_u32();
+    }
+
+    Ok(())
+}
+
+fn write_immediate(eval: &mut Eval, value: Value) -> Result<(), Effect> {
+    let Some(address) = eval.operand_stack.values.last().copied() else {
+        return integer(eval, value);
+    };
+    if !eval.can_fuse() || eval.memory.read(address.to_u32()).is_err() {
+        return integer(eval, value);
+    }
+
+    eval.evaluate_second_fused_operator();
+
+    let Ok(address) = eval.operand_stack.pop() else {
+        unreachable!("Checked above, that there's a value on the stack.");
+    };
+    let Ok(()) = eval.memory.write(address.to_u32(), value) else {
+        unreachable!("Checked above, that the address is valid.");
+    };
+
+    Ok(())
+}
+
+impl Eval {
+    /// # Determine whether a superinstruction can be evaluated
+    ///
+    /// By the time a superinstruction is evaluated, the fuel for the first of
+    /// its operators has already been consumed. The second one needs another
+    /// unit.
+    ///
+    /// Superinstructions are also not evaluated, if the history is enabled,
+    /// as it must be possible to undo each operator individually. Or if any
+    /// tracers are attached, as they must be able to observe each operator.
+    /// Or if any devices are mapped, as writes must be forwarded to them. Or if
+    /// the size of the operand stack is limited, as the limit must be checked
+    /// after each operator.
+    fn can_fuse(&self) -> bool {
+        self.history.is_none()
+            && self.provenance.is_none()
+            && self.taint.is_none()
+            && self.tracers.is_empty()
+            && self.devices.is_empty()
+            && self.disabled_extensions.is_empty()
+            && self.max_operand_stack.is_none()
+            && self.fuel.is_none_or(|fuel| fuel >= 1)
+    }
+
+    /// # Account for the second operator of a superinstruction
+    ///
+    /// The second operator stays in place, and is evaluated as part of the
+    /// superinstruction. So it needs to be skipped, but still consume fuel and
+    /// show up in the profile and coverage.
+    ///
+    /// No other tracers are attached at this point, or the superinstruction
+    /// wouldn't be evaluated. See [`Eval::can_fuse`].
+    fn evaluate_second_fused_operator(&mut self) {
+        let second = self.next_operator;
+
+        if let Some(fuel) = &mut self.fuel {
+            *fuel -= 1;
+        }
+        self.before_step(second);
+
+        self.next_operator.value += 1;
+    }
+}
@@ -0,0 +1,79 @@
+//! # Local variables, reserved by routines using `locals`
+//!
+//! Each call to a routine starts a new frame, which holds the local variables
+//! that the routine reserves. Returning from the routine discards the frame.
+//! Code that isn't part of a called routine has a frame too, which lasts for
+//! the whole evaluation.
+
+use crate::{Effect, Value};
+
+/// # The maximum number of local variables per strand
+///
+/// This includes the local variables of all frames.
+const MAX_LOCALS: usize = 64 * 1024;
+
+/// # The local variables of a strand
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Locals {
+    values: Vec<Value>,
+
+    /// # The index in `values` at which each frame starts
+    ///
+    /// The frame of the code that isn't part of a called routine always starts
+    /// at `0`, so it's not included here.
+    frames: Vec<usize>,
+}
+
+impl Locals {
+    /// # Start the frame of a called routine
+    pub(crate) fn enter_frame(&mut self) {
+        self.frames.push(self.values.len());
+    }
+
+    /// # Discard the frame of the routine that returns
+    pub(crate) fn leave_frame(&mut self) {
+        if let Some(start) = self.frames.pop() {
+            self.values.truncate(start);
+        }
+    }
+
+    /// # Reserve additional local variables in the current frame
+    ///
+    /// Each new local variable starts out as `0`.
+    pub(crate) fn reserve(&mut self, num: u32) -> Result<(), Effect> {
+        let len = usize::try_from(num)
+            .ok()
+            .and_then(|num| self.values.len().checked_add(num))
+            .filter(|len| *len <= MAX_LOCALS)
+            .ok_or(Effect::InvalidLocal)?;
+
+        self.values.resize(len, Value::from(0));
+        Ok(())
+    }
+
+    pub(crate) fn get(&self, index: u32) -> Result<Value, Effect> {
+        let index = self.index(index)?;
+        Ok(self.values[index])
+    }
+
+    pub(crate) fn set(
+        &mut self,
+        index: u32,
+        value: Value,
+    ) -> Result<(), Effect> {
+        let index = self.index(index)?;
+        self.values[index] = value;
+        Ok(())
+    }
+
+    /// # Convert an index within the current frame into an index into `values`
+    fn index(&self, index: u32) -> Result<usize, Effect> {
+        let start = self.frames.last().copied().unwrap_or(0);
+
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| start.checked_add(index))
+            .filter(|index| *index < self.values.len())
+            .ok_or(Effect::InvalidLocal)
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use crate::{Effect, Eval, OperatorIndex, Script};
+
+/// # An evaluation that owns the script it evaluates
+///
+/// [`Eval`] doesn't keep a reference to the script. Every call that advances
+/// it expects the host to pass the same script again. That keeps `Eval` free
+/// of lifetimes, but a host that suspends evaluations, to resume them later,
+/// has to keep track of which script belongs to which evaluation.
+///
+/// `OwnedEval` keeps the script together with the evaluation, making a
+/// suspended evaluation self-contained. The script is held by an [`Arc`], so
+/// multiple evaluations of the same script can share it.
+///
+/// `OwnedEval` dereferences to [`Eval`], so everything else, like accessing
+/// the operand stack or providing fuel, works as it does there.
+///
+/// ## Example
+///
+/// ```
+/// use stack_assembly::{Effect, OwnedEval, Script};
+///
+/// let mut eval = OwnedEval::new(Script::compile("1 yield 2"));
+///
+/// let (effect, _) = eval.run();
+/// assert_eq!(effect, Effect::Yield);
+///
+/// // The evaluation can be resumed on another thread, without having to send
+/// // the script along.
+/// let thread = std::thread::spawn(move || {
+///     eval.resume();
+///     eval
+/// });
+/// let Ok(eval) = thread.join() else {
+///     unreachable!("The thread doesn't panic.");
+/// };
+///
+/// assert_eq!(eval.operand_stack.to_i32_slice(), &[1, 2]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct OwnedEval {
+    eval: Eval,
+    script: Arc<Script>,
+}
+
+impl OwnedEval {
+    /// # Start evaluating the provided script
+    ///
+    /// Works like [`Eval::new`]. Use [`Eval::with_script`] instead, to start
+    /// from an `Eval` that has already been configured.
+    pub fn new(script: impl Into<Arc<Script>>) -> Self {
+        Eval::new().with_script(script)
+    }
+
+    /// # Access the script that is being evaluated
+    pub fn script(&self) -> &Arc<Script> {
+        &self.script
+    }
+
+    /// # Advance the evaluation until it triggers an effect
+    ///
+    /// See [`Eval::run`].
+    pub fn run(&mut self) -> (Effect, OperatorIndex) {
+        self.eval.run(&self.script)
+    }
+
+    /// # Advance the evaluation by one step
+    ///
+    /// See [`Eval::step`].
+    pub fn step(&mut self) -> Option<(Effect, OperatorIndex)> {
+        self.eval.step(&self.script)
+    }
+
+    /// # Resume the evaluation after handling an effect
+    ///
+    /// See [`Eval::resume`].
+    pub fn resume(&mut self) -> (Effect, OperatorIndex) {
+        self.eval.resume(&self.script)
+    }
+
+    /// # Split this into the evaluation and the script
+    pub fn into_parts(self) -> (Eval, Arc<Script>) {
+        (self.eval, self.script)
+    }
+}
+
+impl Eval {
+    /// # Let the evaluation take ownership of the script it evaluates
+    ///
+    /// See [`OwnedEval`].
+    pub fn with_script(self, script: impl Into<Arc<Script>>) -> OwnedEval {
+        OwnedEval {
+            eval: self,
+            script: script.into(),
+        }
+    }
+}
+
+impl Deref for OwnedEval {
+    type Target = Eval;
+
+    fn deref(&self) -> &Self::Target {
+        &self.eval
+    }
+}
+
+impl DerefMut for OwnedEval {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.eval
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{Effect, Eval, OperatorIndex, Script};
+
+    #[test]
+    fn clones_share_script() {
+        let script = Arc::new(Script::compile("1 yield 2 yield 3"));
+
+        let mut a = Eval::start_at(OperatorIndex::new(2)).with_script(script);
+        let (effect, _) = a.run();
+        assert_eq!(effect, Effect::Yield);
+
+        let mut b = a.clone();
+        assert!(Arc::ptr_eq(a.script(), b.script()));
+
+        a.resume();
+        b.operand_stack.push(4);
+        b.resume();
+
+        assert_eq!(a.operand_stack.to_i32_slice(), &[2, 3]);
+        assert_eq!(b.operand_stack.to_i32_slice(), &[2, 4, 3]);
+    }
+}
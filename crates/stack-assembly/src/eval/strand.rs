@@ -0,0 +1,114 @@
+//! # Strands, independent threads of evaluation within a single `Eval`
+//!
+//! Each strand has its own operand stack, call stack, and local variables, but
+//! all strands share the memory. Only one strand is evaluated at a time.
+//! Switching between them is cooperative: the current strand keeps being
+//! evaluated, until it resumes another one.
+
+use std::mem;
+
+use crate::{CallStack, Effect, OperandStack, script::OperatorIndex};
+
+use super::{Eval, Locals};
+
+/// # A strand that is not currently being evaluated
+#[derive(Clone, Debug)]
+pub(crate) struct Strand {
+    next_operator: OperatorIndex,
+    call_stack: CallStack,
+    locals: Locals,
+    operand_stack: OperandStack,
+}
+
+impl Eval {
+    /// # Spawn a strand that starts evaluating at the provided operator
+    ///
+    /// Returns the ID of the new strand. The current strand keeps being
+    /// evaluated. Triggers [`Effect::TooManyStrands`], if that would exceed
+    /// [`Limits::max_strands`].
+    ///
+    /// [`Limits::max_strands`]: crate::Limits::max_strands
+    pub(super) fn spawn_strand(
+        &mut self,
+        start: OperatorIndex,
+    ) -> Result<u32, Effect> {
+        // Before the first strand is spawned, the current strand is the only
+        // one, even though it doesn't have an entry yet.
+        let num_strands = self.strands.len().max(1);
+        if self.max_strands.is_some_and(|max| num_strands >= max) {
+            return Err(Effect::TooManyStrands);
+        }
+
+        if self.strands.is_empty() {
+            // Before the first strand is spawned, the current strand is the
+            // only one. It needs an entry, so the IDs of the other strands
+            // don't collide with its own.
+            self.strands.push(None);
+        }
+
+        let Ok(id) = u32::try_from(self.strands.len()) else {
+            unreachable!(
+                "Every strand takes up memory. It's not possible to spawn so \
+                many of them, that their IDs no longer fit into a `u32`."
+            );
+        };
+
+        self.strands.push(Some(Strand {
+            next_operator: start,
+            call_stack: CallStack::with_max_depth(self.call_stack.max_depth()),
+            locals: Locals::default(),
+            operand_stack: OperandStack::default(),
+        }));
+
+        Ok(id)
+    }
+
+    /// # Suspend the current strand and continue with the provided one
+    ///
+    /// Resuming the current strand does nothing.
+    pub(super) fn resume_strand(&mut self, id: u32) -> Result<(), Effect> {
+        if id == self.current_strand {
+            return Ok(());
+        }
+
+        let Some(entry) = usize::try_from(id)
+            .ok()
+            .and_then(|index| self.strands.get_mut(index))
+        else {
+            return Err(Effect::InvalidStrand);
+        };
+        let Some(next) = entry.take() else {
+            unreachable!(
+                "Only the current strand has no entry, and we checked above \
+                that this is not the current strand."
+            );
+        };
+
+        let previous = Strand {
+            next_operator: mem::replace(
+                &mut self.next_operator,
+                next.next_operator,
+            ),
+            call_stack: mem::replace(&mut self.call_stack, next.call_stack),
+            locals: mem::replace(&mut self.locals, next.locals),
+            operand_stack: mem::replace(
+                &mut self.operand_stack,
+                next.operand_stack,
+            ),
+        };
+
+        let Some(entry) = usize::try_from(self.current_strand)
+            .ok()
+            .and_then(|index| self.strands.get_mut(index))
+        else {
+            unreachable!(
+                "The current strand has an entry, as soon as a second strand \
+                has been spawned."
+            );
+        };
+        *entry = Some(previous);
+        self.current_strand = id;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+use crate::opcode::Opcode;
+
+/// # A named set of built-in operators
+///
+/// Every built-in operator belongs to exactly one extension. All extensions
+/// are enabled by default. A host can disable some of them, to evaluate a
+/// script with a restricted set of operators (see
+/// [`Eval::disable_extension`]).
+///
+/// Integers and references are not operators in that sense, and are always
+/// available. The same goes for native operators that the host has
+/// registered.
+///
+/// [`Eval::disable_extension`]: crate::Eval::disable_extension
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Extension {
+    /// # Manipulating the operand stack, assertions, and yielding
+    ///
+    /// `copy`, `drop`, `reverse_n`, `assert`, `unreachable`, `todo`, and
+    /// `yield`.
+    Core,
+
+    /// # Arithmetic and comparisons
+    ///
+    /// `*`, `+`, `-`, `/`, `div_floor`, `mod_floor`, `divmod_u`, `<`, `<=`,
+    /// `=`, `>`, and `>=`.
+    Arithmetic,
+
+    /// # Bitwise operations
+    ///
+    /// `and`, `or`, `xor`, `count_ones`, `leading_zeros`, `trailing_zeros`,
+    /// `rotate_left`, `rotate_right`, `shift_left`, and `shift_right`.
+    Bitwise,
+
+    /// # Jumps, calls, and returns
+    ///
+    /// `jump`, all of the conditional jumps, `call`, `call_either`, `return`,
+    /// `pc`, and `exec`.
+    ControlFlow,
+
+    /// # Local variables
+    ///
+    /// `locals`, `local_get`, and `local_set`.
+    Locals,
+
+    /// # Accessing memory
+    ///
+    /// `read` and `write`.
+    Memory,
+
+    /// # Strands
+    ///
+    /// `spawn`, `resume`, and `current`.
+    Strands,
+
+    /// # Channels
+    ///
+    /// `send` and `receive`.
+    Channels,
+}
+
+impl Extension {
+    /// # All extensions
+    pub const ALL: [Self; 8] = [
+        Self::Core,
+        Self::Arithmetic,
+        Self::Bitwise,
+        Self::ControlFlow,
+        Self::Locals,
+        Self::Memory,
+        Self::Strands,
+        Self::Channels,
+    ];
+
+    /// # The name of the extension
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Core => "core",
+            Self::Arithmetic => "arithmetic",
+            Self::Bitwise => "bitwise",
+            Self::ControlFlow => "control_flow",
+            Self::Locals => "locals",
+            Self::Memory => "memory",
+            Self::Strands => "strands",
+            Self::Channels => "channels",
+        }
+    }
+
+    /// # Look up an extension by its name
+    ///
+    /// This is the inverse of [`Extension::name`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|extension| extension.name() == name)
+    }
+
+    /// # Determine whether the extension includes the provided identifier
+    ///
+    /// Returns `false`, if the identifier doesn't refer to a built-in
+    /// operator.
+    pub fn includes(&self, identifier: &str) -> bool {
+        Opcode::from_identifier(identifier)
+            .is_some_and(|opcode| opcode.extension() == *self)
+    }
+}
+
+impl Opcode {
+    /// # The extension that the built-in operator belongs to
+    pub(crate) fn extension(&self) -> Extension {
+        match self {
+            Self::Copy
+            | Self::Drop
+            | Self::ReverseN
+            | Self::Assert
+            | Self::Unreachable
+            | Self::Todo
+            | Self::Yield => Extension::Core,
+            Self::Multiply
+            | Self::Add
+            | Self::Subtract
+            | Self::Divide
+            | Self::DivFloor
+            | Self::ModFloor
+            | Self::DivmodU
+            | Self::Less
+            | Self::LessOrEqual
+            | Self::Equal
+            | Self::Greater
+            | Self::GreaterOrEqual => Extension::Arithmetic,
+            Self::And
+            | Self::Or
+            | Self::Xor
+            | Self::CountOnes
+            | Self::LeadingZeros
+            | Self::TrailingZeros
+            | Self::RotateLeft
+            | Self::RotateRight
+            | Self::ShiftLeft
+            | Self::ShiftRight => Extension::Bitwise,
+            Self::Jump
+            | Self::JumpIf
+            | Self::JumpIfEqual
+            | Self::JumpIfNotEqual
+            | Self::JumpIfLess
+            | Self::JumpIfLessOrEqual
+            | Self::JumpIfGreater
+            | Self::JumpIfGreaterOrEqual
+            | Self::Call
+            | Self::CallEither
+            | Self::Return
+            | Self::Pc
+            | Self::Exec => Extension::ControlFlow,
+            Self::Locals | Self::LocalGet | Self::LocalSet => Extension::Locals,
+            Self::Read | Self::Write => Extension::Memory,
+            Self::Spawn | Self::Resume | Self::Current => Extension::Strands,
+            Self::Send | Self::Receive => Extension::Channels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Effect, Eval, Extension, Script};
+
+    #[test]
+    fn disabled_operator_triggers_effect() {
+        let script = Script::compile("1 2 + 3 and");
+
+        let mut eval = Eval::new();
+        eval.disable_extension(Extension::Bitwise);
+        assert!(!eval.is_extension_enabled(Extension::Bitwise));
+
+        let (effect, operator) = eval.run(&script);
+        assert_eq!(effect, Effect::DisabledOperator);
+        assert_eq!(operator.value(), 4);
+        assert_eq!(eval.operand_stack.to_i32_slice(), &[3, 3]);
+    }
+
+    #[test]
+    fn disabled_operator_is_not_fused() {
+        // `1 +` compiles into a superinstruction. Its second operator must
+        // still be checked on its own.
+        let script = Script::compile("2 1 +");
+
+        let mut eval = Eval::new();
+        eval.disable_extension(Extension::Arithmetic);
+
+        let (effect, operator) = eval.run(&script);
+        assert_eq!(effect, Effect::DisabledOperator);
+        assert_eq!(operator.value(), 2);
+    }
+
+    #[test]
+    fn extension_can_be_enabled_again() {
+        let script = Script::compile("0 1 write");
+
+        let mut eval = Eval::new();
+        eval.disable_extension(Extension::Memory);
+        eval.enable_extension(Extension::Memory);
+
+        let (effect, _) = eval.run(&script);
+        assert_eq!(effect, Effect::OutOfOperators);
+    }
+
+    #[test]
+    fn extension_names_round_trip() {
+        for extension in Extension::ALL {
+            assert_eq!(Extension::from_name(extension.name()), Some(extension));
+        }
+
+        assert!(Extension::Memory.includes("read"));
+        assert!(!Extension::Memory.includes("+"));
+        assert!(!Extension::Memory.includes("unknown"));
+    }
+}
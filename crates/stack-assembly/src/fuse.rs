@@ -0,0 +1,136 @@
+use crate::{opcode::Opcode, script::Operator};
+
+/// # A sequence of two operators, fused into one
+///
+/// Evaluating a superinstruction has the same result as evaluating the two
+/// operators it replaces, but requires only one dispatch.
+///
+/// The superinstruction replaces the first of the two operators. The second
+/// operator stays where it is, so the indices of all operators are unchanged,
+/// and any jump to the second operator still works as expected. Evaluating the
+/// superinstruction skips the second operator.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Superinstruction {
+    /// # An integer, followed by `+`
+    AddImmediate { value: i32 },
+
+    /// # `copy`, followed by `jump_if`
+    CopyJumpIf,
+
+    /// # An integer, followed by `write`
+    WriteImmediate { value: i32 },
+}
+
+impl Superinstruction {
+    /// # The first of the operators that the superinstruction replaces
+    ///
+    /// The evaluation falls back to evaluating this operator, whenever the
+    /// superinstruction can't be used. For example, because evaluating it
+    /// would trigger an effect.
+    pub fn unfused(&self) -> Operator {
+        match *self {
+            Self::AddImmediate { value } | Self::WriteImmediate { value } => {
+                Operator::Integer { value }
+            }
+            Self::CopyJumpIf => Operator::Opcode {
+                opcode: Opcode::Copy,
+            },
+        }
+    }
+}
+
+/// # Fuse common sequences of operators into superinstructions
+pub fn fuse(operators: &mut [Operator]) {
+    for i in 0..operators.len() {
+        let [first, second, ..] = &operators[i..] else {
+            break;
+        };
+
+        let superinstruction = match (first, second) {
+            (
+                Operator::Integer { value },
+                Operator::Opcode {
+                    opcode: Opcode::Add,
+                },
+            ) => Superinstruction::AddImmediate { value: *value },
+            (
+                Operator::Opcode {
+                    opcode: Opcode::Copy,
+                },
+                Operator::Opcode {
+                    opcode: Opcode::JumpIf,
+                },
+            ) => Superinstruction::CopyJumpIf,
+            (
+                Operator::Integer { value },
+                Operator::Opcode {
+                    opcode: Opcode::Write,
+                },
+            ) => Superinstruction::WriteImmediate { value: *value },
+            _ => {
+                continue;
+            }
+        };
+
+        operators[i] = Operator::Fused { superinstruction };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompileOptions, Effect, Eval, Script};
+
+    #[test]
+    fn fused_script_behaves_like_unfused_script() {
+        let scripts = [
+            // Sequences that get fused, evaluated in a loop.
+            "@loop 0 loop: 1 + 0 copy 100 + 9 write 0 copy 5 < 2 copy jump_if",
+            // Fused sequence with not enough inputs.
+            "1 +",
+            "0 1 write",
+            "1 copy jump_if",
+            // Jump into the middle of a fused sequence.
+            "@target jump 1 target: + 2",
+            // Invalid address.
+            "1024 1 write",
+        ];
+
+        for source in scripts {
+            let [unfused, fused] = [false, true].map(|optimize| {
+                let options = CompileOptions {
+                    optimize,
+                    ..CompileOptions::default()
+                };
+                let script = Script::compile_with_options(source, options);
+
+                let mut eval = Eval::new();
+                let effect = eval.run(&script);
+
+                (
+                    effect,
+                    eval.operand_stack.values,
+                    eval.memory.values().to_vec(),
+                )
+            });
+
+            assert_eq!(unfused, fused, "Script: {source}");
+        }
+    }
+
+    #[test]
+    fn fused_operators_consume_fuel_individually() {
+        let options = CompileOptions {
+            optimize: true,
+            ..CompileOptions::default()
+        };
+        let script = Script::compile_with_options("1 2 + 3", options);
+
+        let mut eval = Eval::new();
+        eval.set_fuel(Some(2));
+
+        let (effect, operator) = eval.run(&script);
+        assert_eq!(effect, Effect::OutOfFuel);
+        assert_eq!(operator.value(), 2);
+        assert_eq!(eval.operand_stack.to_u32_slice(), &[1, 2]);
+    }
+}
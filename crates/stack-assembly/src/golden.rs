@@ -0,0 +1,250 @@
+use std::{env, fmt, fs, io, path::Path};
+
+use crate::{Effect, Eval, MemoryChange, OperatorIndex, Script, Value};
+
+/// # The sequence of effects that a script triggered, with the state at each
+///
+/// This is meant for golden tests of scripts that interact with a host. Use
+/// [`YieldLog::record`] to evaluate a script against a fake host, then
+/// [`YieldLog::check_golden`] to compare the result to a file that stores the
+/// expected log. Any change in behavior shows up as a difference.
+///
+/// The [`Display`] implementation formats the log as text, which is also the
+/// format of the golden file.
+///
+/// [`Display`]: fmt::Display
+///
+/// ## Example
+///
+/// ```
+/// use stack_assembly::{Eval, Script, YieldLog};
+///
+/// let script = Script::compile("1 yield 2 + 3 write yield");
+///
+/// let mut eval = Eval::new();
+/// let log = YieldLog::record(&script, &mut eval, |eval| {
+///     // The fake host doubles the value on top of the stack.
+///     if let Ok(value) = eval.operand_stack.pop() {
+///         eval.operand_stack.push(value.to_i32() * 2);
+///     }
+/// });
+///
+/// assert_eq!(
+///     log.to_string(),
+///     "\
+/// Yield at operator 1
+///     stack: 1
+/// Yield at operator 6
+///     stack:
+///     memory 4: 0 -> 3
+/// OutOfOperators at operator 7
+///     stack:
+/// ",
+/// );
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct YieldLog {
+    /// # The entries of the log, one per effect
+    ///
+    /// Every entry but the last is for [`Effect::Yield`]. The last entry is for
+    /// the effect that ended the evaluation.
+    pub entries: Vec<YieldLogEntry>,
+}
+
+impl YieldLog {
+    /// # The environment variable that makes golden files get updated
+    ///
+    /// See [`YieldLog::check_golden`].
+    pub const UPDATE_VARIABLE: &str = "STACK_ASSEMBLY_UPDATE_GOLDEN";
+
+    /// # Evaluate a script, recording every effect it triggers
+    ///
+    /// Whenever the script triggers [`Effect::Yield`], `host` gets to handle
+    /// it, before the evaluation continues. Any other effect ends the
+    /// evaluation. If the script could yield forever, limit the evaluation
+    /// using [`Eval::set_fuel`], so it ends with [`Effect::OutOfFuel`].
+    ///
+    /// The memory changes that each entry lists are those the script has made
+    /// since the previous effect was handled.
+    pub fn record(
+        script: &Script,
+        eval: &mut Eval,
+        mut host: impl FnMut(&mut Eval),
+    ) -> Self {
+        let mut entries = Vec::new();
+        let mut snapshot = eval.snapshot();
+
+        loop {
+            let (effect, operator) = eval.run(script);
+
+            entries.push(YieldLogEntry {
+                effect,
+                operator,
+                operand_stack: eval.operand_stack.values.clone(),
+                memory: snapshot.diff(eval).memory,
+            });
+
+            if effect != Effect::Yield {
+                break;
+            }
+
+            host(eval);
+            eval.clear_effect();
+            snapshot = eval.snapshot();
+        }
+
+        Self { entries }
+    }
+
+    /// # Compare the log to the one stored in the provided golden file
+    ///
+    /// If the environment variable [`YieldLog::UPDATE_VARIABLE`] is set, this
+    /// writes the log to the file instead, creating it, if necessary. Set it
+    /// to create a golden file initially, or to accept an intentional change
+    /// in behavior.
+    pub fn check_golden(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<(), GoldenError> {
+        let path = path.as_ref();
+        let actual = self.to_string();
+
+        if env::var_os(Self::UPDATE_VARIABLE).is_some() {
+            fs::write(path, actual).map_err(GoldenError::Io)?;
+            return Ok(());
+        }
+
+        let expected = fs::read_to_string(path).map_err(GoldenError::Io)?;
+        if expected != actual {
+            return Err(GoldenError::Mismatch { expected, actual });
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for YieldLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            write!(f, "{entry}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// # An entry in a [`YieldLog`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct YieldLogEntry {
+    /// # The effect that the script triggered
+    pub effect: Effect,
+
+    /// # The operator that triggered the effect
+    pub operator: OperatorIndex,
+
+    /// # The values on the operand stack, when the effect triggered
+    pub operand_stack: Vec<Value>,
+
+    /// # The changes to memory since the previous effect was handled
+    pub memory: Vec<MemoryChange>,
+}
+
+impl fmt::Display for YieldLogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:?} at operator {}", self.effect, self.operator)?;
+
+        write!(f, "    stack:")?;
+        for value in &self.operand_stack {
+            write!(f, " {value:?}")?;
+        }
+        writeln!(f)?;
+
+        for MemoryChange { address, old, new } in &self.memory {
+            writeln!(f, "    memory {address}: {old:?} -> {new:?}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// # The reason that [`YieldLog::check_golden`] has failed
+#[derive(Debug)]
+pub enum GoldenError {
+    /// # The golden file could not be read or written
+    Io(io::Error),
+
+    /// # The log differs from the one in the golden file
+    Mismatch {
+        /// # The log, as stored in the golden file
+        expected: String,
+
+        /// # The log that was recorded
+        actual: String,
+    },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not access golden file: {err}"),
+            Self::Mismatch { expected, actual } => {
+                writeln!(f, "log differs from golden file")?;
+                writeln!(f, "expected:")?;
+                write!(f, "{expected}")?;
+                writeln!(f, "actual:")?;
+                write!(f, "{actual}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use crate::{Eval, Script};
+
+    use super::{GoldenError, YieldLog};
+
+    #[test]
+    fn log_matches_golden_file() {
+        let script = Script::compile("1 yield yield");
+
+        let mut eval = Eval::new();
+        let log = YieldLog::record(&script, &mut eval, |eval| {
+            eval.operand_stack.push(2);
+        });
+
+        let path = env::temp_dir()
+            .join(format!("stack-assembly-golden-{}.log", process::id()));
+
+        let Ok(()) = fs::write(&path, log.to_string()) else {
+            unreachable!("Temporary directory is writable.");
+        };
+        assert!(log.check_golden(&path).is_ok());
+
+        let Ok(()) = fs::write(&path, "OutOfOperators at operator 3\n") else {
+            unreachable!("Temporary directory is writable.");
+        };
+        assert!(matches!(
+            log.check_golden(&path),
+            Err(GoldenError::Mismatch { .. }),
+        ));
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn fuel_ends_endless_yielding() {
+        let script = Script::compile("loop: yield @loop jump");
+
+        let mut eval = Eval::new();
+        eval.set_fuel(Some(7));
+
+        let log = YieldLog::record(&script, &mut eval, |_| {});
+
+        assert_eq!(log.entries.len(), 4);
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::VecDeque;
+
+use crate::{
+    OperatorIndex, Value,
+    eval::{Locals, Strand},
+};
+
+/// # The information required to undo the most recent steps
+///
+/// Only a limited number of steps is retained. Once that number is reached,
+/// recording another step discards the oldest one.
+#[derive(Clone, Debug)]
+pub(crate) struct History {
+    capacity: usize,
+    steps: VecDeque<Step>,
+}
+
+impl History {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            steps: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(crate) fn record(&mut self, step: Step) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.steps.len() == self.capacity {
+            self.steps.pop_front();
+        }
+
+        self.steps.push_back(step);
+    }
+
+    pub(crate) fn record_memory_write(
+        &mut self,
+        address: u32,
+        previous: Value,
+    ) {
+        if let Some(step) = self.steps.back_mut() {
+            step.memory_write = Some((address, previous));
+        }
+    }
+
+    pub(crate) fn record_channel_access(&mut self, access: ChannelAccess) {
+        if let Some(step) = self.steps.back_mut() {
+            step.channel_access = Some(access);
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<Step> {
+        self.steps.pop_back()
+    }
+}
+
+/// # The state of the evaluation before a step
+///
+/// The operand stack, call stack, and local variables are usually small, so we
+/// store them completely. The memory is large, but a step writes to at most one
+/// address. So for the memory, we only store the previous value at that
+/// address. The same goes for channels, where a step sends or receives at most
+/// one value.
+///
+/// Strands other than the current one are only affected by steps that spawn or
+/// resume a strand. But there usually are few of them, if any, so we store
+/// them completely too.
+#[derive(Clone, Debug)]
+pub(crate) struct Step {
+    pub next_operator: OperatorIndex,
+    pub call_stack: Vec<OperatorIndex>,
+    pub locals: Locals,
+    pub operand_stack: Vec<Value>,
+    pub fuel: Option<u64>,
+    pub memory_write: Option<(u32, Value)>,
+    pub channel_access: Option<ChannelAccess>,
+    pub current_strand: u32,
+    pub strands: Vec<Option<Strand>>,
+}
+
+/// # A value sent to, or received from, a channel by a step
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ChannelAccess {
+    Send { channel: u32 },
+    Receive { channel: u32, value: Value },
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Eval, Script};
+
+    #[test]
+    fn step_back_restores_previous_state() {
+        let script = Script::compile("0 1 write 2 3 + 0 read");
+
+        let mut eval = Eval::new();
+        eval.enable_history(16);
+        eval.run(&script);
+
+        assert_eq!(eval.operand_stack.to_u32_slice(), &[5, 1]);
+
+        let mut num_steps = 0;
+        while eval.step_back() {
+            num_steps += 1;
+
+            if num_steps == 4 {
+                // Stepped back past the end of the script, `read`, `0`, and
+                // `+`.
+                assert_eq!(eval.operand_stack.to_u32_slice(), &[2, 3]);
+                assert_eq!(eval.next_operator().value(), 5);
+            }
+        }
+
+        // The script consists of 8 operators. The step that tried to evaluate
+        // an operator past the end of the script can be undone too.
+        assert_eq!(num_steps, 9);
+        assert_eq!(eval.next_operator().value(), 0);
+        assert_eq!(eval.effect(), None);
+        assert_eq!(eval.operand_stack.to_u32_slice(), &[]);
+        assert_eq!(
+            eval.memory.read(0).map(|value| value.to_u32()).ok(),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn step_back_is_limited_by_capacity() {
+        let script = Script::compile("1 2 3 4");
+
+        let mut eval = Eval::new();
+        eval.enable_history(2);
+        eval.run(&script);
+
+        assert!(eval.step_back());
+        assert!(eval.step_back());
+        assert!(!eval.step_back());
+
+        assert_eq!(eval.operand_stack.to_u32_slice(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn step_back_restores_previous_strand() {
+        let script = Script::compile("3 @strand spawn resume strand: 5");
+
+        let mut eval = Eval::new();
+        eval.enable_history(16);
+        eval.run(&script);
+
+        assert_eq!(eval.current_strand(), 1);
+        assert_eq!(eval.operand_stack.to_u32_slice(), &[5]);
+
+        // Step back past the end of the script, `5`, and `resume`.
+        for _ in 0..3 {
+            assert!(eval.step_back());
+        }
+
+        assert_eq!(eval.current_strand(), 0);
+        assert_eq!(eval.operand_stack.to_u32_slice(), &[3, 1]);
+    }
+
+    #[test]
+    fn step_back_restores_channels() {
+        let script = Script::compile("0 3 send 0 5 send 0 receive");
+
+        let mut eval = Eval::new();
+        eval.enable_history(16);
+        eval.run(&script);
+
+        assert_eq!(eval.operand_stack.to_u32_slice(), &[3]);
+
+        // Step back past the end of the script, `receive`, `0`, and the second
+        // `send`.
+        for _ in 0..4 {
+            assert!(eval.step_back());
+        }
+
+        assert_eq!(eval.receive(0).map(|value| value.to_u32()), Some(3));
+        assert_eq!(eval.receive(0), None);
+    }
+}
@@ -0,0 +1,525 @@
+use std::{collections::BTreeMap, fmt, mem};
+
+use cranelift_codegen::{
+    Context,
+    ir::{AbiParam, InstBuilder, MemFlagsData, condcodes::IntCC, types},
+    settings::{self, Configurable},
+};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{Module, default_libcall_names};
+
+use crate::{
+    Effect, Eval, OperatorIndex, Script, Value, opcode::Opcode,
+    script::Operator,
+};
+
+/// # Native code compiled from a script
+///
+/// Compiles sequences of operators that can't trigger an effect (as long as
+/// enough values are on the operand stack), like integers and arithmetic, into
+/// native code. Everything else is still evaluated by the interpreter.
+///
+/// Use [`Jit::run`] to evaluate a script with the help of the compiled code.
+///
+/// This is only available, if the `jit` feature is enabled.
+pub struct Jit {
+    blocks: BTreeMap<OperatorIndex, Block>,
+
+    // Owns the memory that the compiled code lives in. Must not be dropped,
+    // while any of the blocks might still be called.
+    _module: JITModule,
+}
+
+impl Jit {
+    /// # Compile the provided script into native code
+    ///
+    /// Returns an error, if the native code generator isn't available on the
+    /// current platform, or if it fails to compile the script.
+    pub fn compile(script: &Script) -> Result<Self, JitError> {
+        let mut flags = settings::builder();
+        flags.set("use_colocated_libcalls", "false")?;
+        flags.set("is_pic", "false")?;
+        flags.set("opt_level", "speed")?;
+
+        let isa = cranelift_native::builder()
+            .map_err(|message| JitError {
+                message: message.to_string(),
+            })?
+            .finish(settings::Flags::new(flags))?;
+
+        let mut module =
+            JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+
+        let mut context = module.make_context();
+        let mut builder_context = FunctionBuilderContext::new();
+
+        let mut blocks = Vec::new();
+
+        for start in block_starts(script) {
+            let operators = script
+                .operators()
+                .skip(start.value as usize)
+                .map_while(|(_, operator)| Instruction::from_operator(operator))
+                .collect::<Vec<_>>();
+
+            if operators.len() < MIN_BLOCK_LENGTH {
+                continue;
+            }
+
+            let (required, provided) = compile_block(
+                &operators,
+                &mut module,
+                &mut context,
+                &mut builder_context,
+            );
+
+            let id =
+                module.declare_anonymous_function(&context.func.signature)?;
+            module.define_function(id, &mut context)?;
+            module.clear_context(&mut context);
+
+            let Ok(num_operators) = operators.len().try_into() else {
+                unreachable!(
+                    "Number of operators in a block can't be larger than the \
+                    number of operators in a script, which fits into `u32`."
+                );
+            };
+
+            blocks.push((start, id, num_operators, required, provided));
+        }
+
+        module.finalize_definitions()?;
+
+        let blocks = blocks
+            .into_iter()
+            .map(|(start, id, num_operators, required, provided)| {
+                let code = module.get_finalized_function(id);
+
+                // SAFETY:
+                //
+                // We defined this function with a signature that matches
+                // `BlockFn`.
+                let function =
+                    unsafe { mem::transmute::<*const u8, BlockFn>(code) };
+
+                let block = Block {
+                    function,
+                    num_operators,
+                    required,
+                    provided,
+                };
+
+                (start, block)
+            })
+            .collect();
+
+        Ok(Self {
+            blocks,
+            _module: module,
+        })
+    }
+
+    /// # Advance the evaluation until it triggers an effect
+    ///
+    /// Works like [`Eval::run`], but evaluates the operators that have been
+    /// compiled into native code by calling that code.
+    ///
+    /// The native code is not used, while profiling, coverage recording, the
+    /// history, or trapping on overflow are enabled, nor if the fuel would run
+    /// out while evaluating it. In that case, or if there are not enough values
+    /// on the operand stack, the operators are evaluated by the interpreter
+    /// instead, which triggers any effects in the same way as it would without
+    /// this.
+    ///
+    /// The script must be the one that was passed to [`Jit::compile`].
+    pub fn run(
+        &self,
+        eval: &mut Eval,
+        script: &Script,
+    ) -> (Effect, OperatorIndex) {
+        let start = eval.start_measuring();
+
+        let effect = loop {
+            if let Some(block) = self.blocks.get(&eval.next_operator())
+                && eval.operand_stack.values.len() >= block.required
+                && eval.can_skip(block.num_operators)
+            {
+                let values = &mut eval.operand_stack.values;
+                values.reserve(block.provided);
+
+                let len = values.len();
+
+                // SAFETY:
+                //
+                // The block reads the `required` values below `len`, which we
+                // checked are there. It writes `provided` values, starting at
+                // `len - required`, for which we reserved the capacity.
+                //
+                // After the call, all values up to the returned length have
+                // been initialized.
+                unsafe {
+                    let len = (block.function)(values.as_mut_ptr(), len);
+                    values.set_len(len);
+                }
+
+                eval.skip(block.num_operators);
+
+                continue;
+            }
+
+            if let Some(effect) = eval.step(script) {
+                break effect;
+            }
+        };
+
+        eval.stop_measuring(start);
+
+        effect
+    }
+}
+
+impl fmt::Debug for Jit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Jit")
+            .field("blocks", &self.blocks.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// # An error that occurred while compiling a script into native code
+#[derive(Debug)]
+pub struct JitError {
+    message: String,
+}
+
+impl fmt::Display for JitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for JitError {}
+
+impl From<settings::SetError> for JitError {
+    fn from(err: settings::SetError) -> Self {
+        Self {
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<cranelift_codegen::CodegenError> for JitError {
+    fn from(err: cranelift_codegen::CodegenError) -> Self {
+        Self {
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<cranelift_module::ModuleError> for JitError {
+    fn from(err: cranelift_module::ModuleError) -> Self {
+        Self {
+            message: err.to_string(),
+        }
+    }
+}
+
+/// # Sequences shorter than this are not worth compiling
+const MIN_BLOCK_LENGTH: usize = 2;
+
+/// # The signature of a compiled block
+///
+/// Takes a pointer to the values on the operand stack and their number.
+/// Returns the number of values after the block has been evaluated.
+type BlockFn = unsafe extern "C" fn(*mut Value, usize) -> usize;
+
+struct Block {
+    function: BlockFn,
+    num_operators: u32,
+    required: usize,
+    provided: usize,
+}
+
+/// # An operator that can be compiled into native code
+#[derive(Clone, Copy)]
+enum Instruction {
+    Push { value: i32 },
+    Opcode { opcode: Opcode },
+}
+
+impl Instruction {
+    fn from_operator(operator: &Operator) -> Option<Self> {
+        let instruction = match operator {
+            Operator::Fused { superinstruction } => {
+                return Self::from_operator(&superinstruction.unfused());
+            }
+            Operator::Integer { value } => Self::Push { value: *value },
+            Operator::Opcode { opcode } => {
+                // These operators can only trigger an effect, if there are not
+                // enough values on the operand stack. We check for that before
+                // calling the native code.
+                let can_compile = matches!(
+                    opcode,
+                    Opcode::Multiply
+                        | Opcode::Add
+                        | Opcode::Subtract
+                        | Opcode::Less
+                        | Opcode::LessOrEqual
+                        | Opcode::Equal
+                        | Opcode::Greater
+                        | Opcode::GreaterOrEqual
+                        | Opcode::And
+                        | Opcode::Or
+                        | Opcode::Xor
+                        | Opcode::CountOnes
+                        | Opcode::LeadingZeros
+                        | Opcode::TrailingZeros
+                        | Opcode::RotateLeft
+                        | Opcode::RotateRight
+                );
+
+                if !can_compile {
+                    return None;
+                }
+
+                Self::Opcode { opcode: *opcode }
+            }
+            Operator::Reference {
+                name: _,
+                target: Some(target),
+            } => Self::Push {
+                value: i32::from_le_bytes(target.value.to_le_bytes()),
+            },
+            _ => {
+                return None;
+            }
+        };
+
+        Some(instruction)
+    }
+}
+
+/// # Determine where compiled blocks should start
+///
+/// A block starts at the beginning of each sequence of operators that can be
+/// compiled, as well as at each label, since the evaluation can jump there.
+fn block_starts(script: &Script) -> Vec<OperatorIndex> {
+    let mut starts = script.label_targets().collect::<Vec<_>>();
+
+    let mut previous_can_compile = false;
+    for (index, operator) in script.operators() {
+        let can_compile = Instruction::from_operator(operator).is_some();
+
+        if can_compile && !previous_can_compile {
+            starts.push(index);
+        }
+
+        previous_can_compile = can_compile;
+    }
+
+    starts.sort();
+    starts.dedup();
+
+    starts
+}
+
+/// # Compile a block of operators into the provided context
+///
+/// Returns the number of values that the block requires on the operand stack,
+/// and the number of values it leaves in their place.
+fn compile_block(
+    instructions: &[Instruction],
+    module: &mut JITModule,
+    context: &mut Context,
+    builder_context: &mut FunctionBuilderContext,
+) -> (usize, usize) {
+    let config = module.target_config();
+    let pointer = config.pointer_type();
+
+    context.func.signature.params.push(AbiParam::new(pointer));
+    context.func.signature.params.push(AbiParam::new(pointer));
+    context.func.signature.returns.push(AbiParam::new(pointer));
+
+    let mut builder = FunctionBuilder::new(&mut context.func, builder_context);
+
+    let entry = builder.create_block();
+    builder.append_block_params_for_function_params(entry);
+    builder.switch_to_block(entry);
+    builder.seal_block(entry);
+
+    let [values, len] = *builder.block_params(entry) else {
+        unreachable!("Function has exactly two parameters.");
+    };
+
+    let offset = builder.ins().imul_imm_s(len, 4);
+    let top = builder.ins().iadd(values, offset);
+
+    // The values that the block has pushed so far, and the number of values
+    // that it has popped from below those. We keep the former in registers,
+    // and only write them to memory at the end.
+    let mut stack = Vec::new();
+    let mut required = 0;
+
+    let mut pop = |builder: &mut FunctionBuilder, stack: &mut Vec<_>| {
+        stack.pop().unwrap_or_else(|| {
+            required += 1;
+
+            let Ok(offset) = i32::try_from(required * 4) else {
+                unreachable!(
+                    "A block can't pop more values than it has operators."
+                );
+            };
+            builder.ins().load(
+                types::I32,
+                MemFlagsData::trusted(),
+                top,
+                -offset,
+            )
+        })
+    };
+
+    for instruction in instructions {
+        let value = match *instruction {
+            Instruction::Push { value } => {
+                builder.ins().iconst(types::I32, i64::from(value))
+            }
+            Instruction::Opcode { opcode } => match opcode {
+                Opcode::CountOnes
+                | Opcode::LeadingZeros
+                | Opcode::TrailingZeros => {
+                    let a = pop(&mut builder, &mut stack);
+
+                    match opcode {
+                        Opcode::CountOnes => builder.ins().popcnt(a),
+                        Opcode::LeadingZeros => builder.ins().clz(a),
+                        _ => builder.ins().ctz(a),
+                    }
+                }
+                opcode => {
+                    let b = pop(&mut builder, &mut stack);
+                    let a = pop(&mut builder, &mut stack);
+
+                    let compare = |builder: &mut FunctionBuilder, cc| {
+                        let result = builder.ins().icmp(cc, a, b);
+                        builder.ins().uextend(types::I32, result)
+                    };
+
+                    match opcode {
+                        Opcode::Multiply => builder.ins().imul(a, b),
+                        Opcode::Add => builder.ins().iadd(a, b),
+                        Opcode::Subtract => builder.ins().isub(a, b),
+                        Opcode::Less => {
+                            compare(&mut builder, IntCC::SignedLessThan)
+                        }
+                        Opcode::LessOrEqual => {
+                            compare(&mut builder, IntCC::SignedLessThanOrEqual)
+                        }
+                        Opcode::Equal => compare(&mut builder, IntCC::Equal),
+                        Opcode::Greater => {
+                            compare(&mut builder, IntCC::SignedGreaterThan)
+                        }
+                        Opcode::GreaterOrEqual => compare(
+                            &mut builder,
+                            IntCC::SignedGreaterThanOrEqual,
+                        ),
+                        Opcode::And => builder.ins().band(a, b),
+                        Opcode::Or => builder.ins().bor(a, b),
+                        Opcode::Xor => builder.ins().bxor(a, b),
+                        Opcode::RotateLeft => builder.ins().rotl(a, b),
+                        Opcode::RotateRight => builder.ins().rotr(a, b),
+                        opcode => {
+                            unreachable!(
+                                "Opcode `{opcode:?}` can't be compiled."
+                            );
+                        }
+                    }
+                }
+            },
+        };
+
+        stack.push(value);
+    }
+
+    // Write the values that the block pushed, in place of the ones it popped.
+    let base = i64::try_from(required).unwrap_or(i64::MAX);
+    for (i, &value) in stack.iter().enumerate() {
+        let Ok(offset) = i32::try_from((i as i64 - base) * 4) else {
+            unreachable!(
+                "A block can't push more values than it has operators."
+            );
+        };
+
+        builder
+            .ins()
+            .store(MemFlagsData::trusted(), value, top, offset);
+    }
+
+    let provided = stack.len();
+    let change = provided as i64 - base;
+    let len = builder.ins().iadd_imm_s(len, change);
+    builder.ins().return_(&[len]);
+
+    builder.finalize(config);
+
+    (required, provided)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Effect, Eval, Script};
+
+    use super::Jit;
+
+    #[test]
+    fn compiled_script_behaves_like_interpreted_script() {
+        let scripts = [
+            "1 2 + 3 * 4 - 5 <",
+            "-1 3 rotate_left 255 and 7 xor 1 or count_ones",
+            "16 leading_zeros 16 trailing_zeros >= 3 3 <= 2 1 > =",
+            // Not enough values on the operand stack.
+            "1 2 + +",
+            // Loop with a compiled block at a label.
+            "0 loop: 1 + 0 copy 10 < @loop jump_if",
+            // Computation that overflows.
+            "2147483647 1 + -2147483648 1 - 65536 65536 *",
+        ];
+
+        for source in scripts {
+            let script = Script::compile(source);
+
+            let mut interpreted = Eval::new();
+            let interpreted_effect = interpreted.run(&script);
+
+            let Ok(jit) = Jit::compile(&script) else {
+                // The native code generator isn't available on the current
+                // platform. Nothing to test here.
+                return;
+            };
+            let mut compiled = Eval::new();
+            let compiled_effect = jit.run(&mut compiled, &script);
+
+            assert_eq!(interpreted_effect, compiled_effect, "{source}");
+            assert_eq!(
+                interpreted.operand_stack.values, compiled.operand_stack.values,
+                "{source}",
+            );
+        }
+    }
+
+    #[test]
+    fn compiled_blocks_respect_fuel() {
+        let script = Script::compile("1 2 3 + +");
+
+        let Ok(jit) = Jit::compile(&script) else {
+            return;
+        };
+        let mut eval = Eval::new();
+        eval.set_fuel(Some(3));
+
+        let (effect, operator) = jit.run(&mut eval, &script);
+        assert_eq!(effect, Effect::OutOfFuel);
+        assert_eq!(operator.value(), 3);
+        assert_eq!(eval.operand_stack.to_i32_slice(), &[1, 2, 3]);
+    }
+}
@@ -0,0 +1,309 @@
+use std::ops::Range;
+
+/// # Split the source text of a script into tokens
+///
+/// This is the first step of compiling a script, and [`Script::compile`] uses
+/// it internally. It's available separately, so tools like syntax
+/// highlighters or formatters can process source text exactly like the
+/// compiler does.
+///
+/// Yields the tokens in the order they appear in. Whitespace between tokens is
+/// skipped. Everything else is part of exactly one token.
+///
+/// ```
+/// use stack_assembly::{TokenKind, lex};
+///
+/// let source = "loop: 1 + @loop jump # forever";
+/// let kinds = lex(source).map(|token| token.kind).collect::<Vec<_>>();
+///
+/// assert_eq!(
+///     kinds,
+///     [
+///         TokenKind::Label,
+///         TokenKind::Integer,
+///         TokenKind::Identifier,
+///         TokenKind::Reference,
+///         TokenKind::Identifier,
+///         TokenKind::Comment,
+///     ],
+/// );
+/// ```
+///
+/// [`Script::compile`]: crate::Script::compile
+pub fn lex(source: &str) -> impl Iterator<Item = Token> {
+    Lexer {
+        source,
+        position: 0,
+    }
+}
+
+/// # A token in the source text of a script
+///
+/// Produced by [`lex`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Token {
+    /// # The kind of token
+    pub kind: TokenKind,
+
+    /// # The range in the source text that the token covers
+    pub range: Range<usize>,
+}
+
+/// # The kind of a [`Token`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TokenKind {
+    /// # An integer, like `3`, `-1`, or `0xff`
+    Integer,
+
+    /// # An identifier, like `+`, `jump`, or `unknown`
+    ///
+    /// This includes the identifiers of built-in operators, the keywords of
+    /// structured control flow (like `if`, `loop`, and `end`), and directives
+    /// like `.zero`, `.data`, `.var`, and `.align`, but also any other token
+    /// that doesn't fit into one of the other kinds. If an identifier doesn't
+    /// refer to a built-in operator, the script triggers an effect when
+    /// evaluating it.
+    Identifier,
+
+    /// # A label, like `loop:`
+    Label,
+
+    /// # A reference to a label, like `@loop`
+    Reference,
+
+    /// # A comment, like `# forever`
+    ///
+    /// Extends from the `#` to the end of the line, not including the line
+    /// break.
+    Comment,
+
+    /// # A `.meta` directive, like `.meta name Counter`
+    ///
+    /// Extends to the end of the line, or to the start of a comment. See
+    /// [`Script`] for more information.
+    ///
+    /// [`Script`]: crate::Script
+    Metadata,
+
+    /// # A string, like `"Hello, world!"`
+    ///
+    /// Extends from the opening `"` to the closing one, which must be on the
+    /// same line. Within the string, `\"`, `\\`, and `\n` stand for a quote, a
+    /// backslash, and a line break. A `"` without a closing one on the same
+    /// line starts an identifier instead.
+    ///
+    /// Strings are only valid as part of a `.string` directive. See [`Script`]
+    /// for more information.
+    ///
+    /// [`Script`]: crate::Script
+    String,
+}
+
+struct Lexer<'r> {
+    source: &'r str,
+    position: usize,
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.source[self.position..];
+        let start = self.position + rest.len() - rest.trim_start().len();
+        let rest = &self.source[start..];
+
+        let end_of =
+            |pattern: &[char]| rest.find(pattern).unwrap_or(rest.len());
+
+        let (kind, length) = if rest.is_empty() {
+            return None;
+        } else if rest.starts_with('#') {
+            (TokenKind::Comment, end_of(&['\n']))
+        } else if let Some(length) = string_length(rest) {
+            (TokenKind::String, length)
+        } else {
+            let length = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let token = &rest[..length];
+
+            if token == ".meta"
+                && rest[length..].starts_with(|ch: char| ch != '\n')
+            {
+                // The rest of the line belongs to the directive.
+                (TokenKind::Metadata, end_of(&['\n', '#']))
+            } else {
+                (classify(token), length)
+            }
+        };
+
+        self.position = start + length;
+
+        Some(Token {
+            kind,
+            range: start..self.position,
+        })
+    }
+}
+
+/// # Determine the length of the string at the start of the provided text
+///
+/// Returns `None`, if the text doesn't start with a string that is closed on
+/// the same line.
+fn string_length(text: &str) -> Option<usize> {
+    let mut chars = text.strip_prefix('"')?.char_indices().peekable();
+
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '"' => {
+                return Some('"'.len_utf8() + i + ch.len_utf8());
+            }
+            '\n' => {
+                return None;
+            }
+            '\\' => {
+                // Whatever comes next is escaped, so it can't close the string.
+                // Unless it's a line break, which we leave to the next
+                // iteration.
+                chars.next_if(|&(_, ch)| ch != '\n');
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// # Parse a string token into the text it stands for
+///
+/// Returns `None`, if the token contains an unknown escape sequence.
+pub(crate) fn parse_string(token: &str) -> Option<String> {
+    let content = token.strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut string = String::new();
+    let mut chars = content.chars();
+
+    while let Some(ch) = chars.next() {
+        let ch = if ch == '\\' {
+            match chars.next()? {
+                '"' => '"',
+                '\\' => '\\',
+                'n' => '\n',
+                _ => {
+                    return None;
+                }
+            }
+        } else {
+            ch
+        };
+
+        string.push(ch);
+    }
+
+    Some(string)
+}
+
+fn classify(token: &str) -> TokenKind {
+    if token.ends_with(':') {
+        TokenKind::Label
+    } else if token.starts_with('@') {
+        TokenKind::Reference
+    } else if parse_integer(token).is_some() {
+        TokenKind::Integer
+    } else {
+        TokenKind::Identifier
+    }
+}
+
+/// # Parse an integer token
+///
+/// Accepts decimal and hexadecimal (`0x`-prefixed) integers, in the range of
+/// either `i32` or `u32`. Values in the `u32` range are reinterpreted as
+/// `i32`.
+pub(crate) fn parse_integer(token: &str) -> Option<i32> {
+    if let Some(value) = token.strip_prefix("0x") {
+        if let Ok(value) = i32::from_str_radix(value, 16) {
+            return Some(value);
+        }
+        if let Ok(value) = u32::from_str_radix(value, 16) {
+            return Some(i32::from_le_bytes(value.to_le_bytes()));
+        }
+    }
+
+    if let Ok(value) = token.parse::<i32>() {
+        return Some(value);
+    }
+    if let Ok(value) = token.parse::<u32>() {
+        return Some(i32::from_le_bytes(value.to_le_bytes()));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Token, TokenKind, lex, parse_string};
+
+    fn tokens(source: &str) -> Vec<(TokenKind, &str)> {
+        lex(source)
+            .map(|Token { kind, range }| (kind, &source[range]))
+            .collect()
+    }
+
+    #[test]
+    fn kinds() {
+        assert_eq!(
+            tokens("start: 1 0xff 4294967295 4294967296 + @start\n"),
+            [
+                (TokenKind::Label, "start:"),
+                (TokenKind::Integer, "1"),
+                (TokenKind::Integer, "0xff"),
+                (TokenKind::Integer, "4294967295"),
+                (TokenKind::Identifier, "4294967296"),
+                (TokenKind::Identifier, "+"),
+                (TokenKind::Reference, "@start"),
+            ],
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            tokens("# one\n1 # two\n  a#b"),
+            [
+                (TokenKind::Comment, "# one"),
+                (TokenKind::Integer, "1"),
+                (TokenKind::Comment, "# two"),
+                (TokenKind::Identifier, "a#b"),
+            ],
+        );
+    }
+
+    #[test]
+    fn metadata() {
+        assert_eq!(
+            tokens(".meta name Counter # comment\n.meta\n1"),
+            [
+                (TokenKind::Metadata, ".meta name Counter "),
+                (TokenKind::Comment, "# comment"),
+                (TokenKind::Identifier, ".meta"),
+                (TokenKind::Integer, "1"),
+            ],
+        );
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            tokens("\"a # b\" \"\\\"c\\\\\"1 \"open\n\" "),
+            [
+                (TokenKind::String, r#""a # b""#),
+                (TokenKind::String, r#""\"c\\""#),
+                (TokenKind::Integer, "1"),
+                (TokenKind::Identifier, r#""open"#),
+                (TokenKind::Identifier, r#"""#),
+            ],
+        );
+
+        assert_eq!(parse_string(r#""\"a\\b\n""#).as_deref(), Some("\"a\\b\n"),);
+        assert_eq!(parse_string(r#""\t""#), None);
+    }
+}
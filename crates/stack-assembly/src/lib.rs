@@ -95,26 +95,96 @@
 //! This is just a simple example. A more full-featured host would provide more
 //! services in addition to printing values. Such a host could determine which
 //! service the script means to request by inspecting which other values it put
-//! on the stack, or into memory.
+//! on the stack, or into memory. [`Syscall`] implements a convention for that.
 
 #![warn(missing_debug_implementations)]
 #![warn(missing_docs)]
 
+mod analysis;
+mod artifact;
+mod cache;
+mod call_stack;
+mod cancel;
+mod channel;
+mod conformance;
+mod coverage;
+mod data;
+mod device;
 mod effect;
 mod eval;
+mod extension;
+mod fuse;
+mod golden;
+mod history;
+#[cfg(feature = "jit")]
+mod jit;
+mod lex;
+mod limits;
+mod link;
+mod lint;
 mod memory;
+mod metrics;
+mod native;
+mod opcode;
 mod operand_stack;
+mod profile;
+mod provenance;
+mod reachability;
+mod region;
+mod report;
 mod script;
+mod shadow;
+mod snapshot;
+mod stack_depth;
+mod statistics;
+mod structured;
+mod syscall;
+mod taint;
+mod tracer;
 mod value;
+#[cfg(feature = "wasm")]
+mod wasm;
 
 #[cfg(test)]
 mod tests;
 
 pub use self::{
+    analysis::{AbstractStack, Analysis, Interval},
+    artifact::InvalidArtifact,
+    cache::ScriptCache,
+    call_stack::{CallStack, CallStackOverflow},
+    cancel::CancellationHandle,
+    conformance::{ConformanceFailure, ConformanceTest},
+    coverage::Coverage,
+    device::Device,
     effect::Effect,
-    eval::Eval,
-    memory::Memory,
+    eval::{Eval, OwnedEval},
+    extension::Extension,
+    golden::{GoldenError, YieldLog, YieldLogEntry},
+    lex::{Token, TokenKind, lex},
+    limits::Limits,
+    link::{LinkError, Linker},
+    lint::Warning,
+    memory::{
+        InvalidAddress, InvalidString, Memory, MemoryValues, MemoryValuesMut,
+    },
+    metrics::Metrics,
     operand_stack::{OperandStack, OperandStackUnderflow},
-    script::{OperatorIndex, Script},
+    profile::Profile,
+    provenance::{Provenance, Tag},
+    region::{MemoryRegion, RegionView},
+    report::{ErrorReport, Excerpt, Location},
+    script::{
+        Arity, CompileError, CompileOptions, InvalidOperatorIndex,
+        OperatorIndex, Script,
+    },
+    snapshot::{Diff, MemoryChange, Snapshot},
+    statistics::Statistics,
+    syscall::Syscall,
+    taint::{Taint, TaintListener},
+    tracer::Tracer,
     value::Value,
 };
+
+#[cfg(feature = "jit")]
+pub use self::jit::{Jit, JitError};
@@ -0,0 +1,179 @@
+use crate::Memory;
+
+/// # Limits on the resources that an evaluation can use
+///
+/// Pass this to [`Eval::with_limits`], to make an evaluation safe against
+/// scripts that would otherwise exhaust the resources of the host. Each limit
+/// that is exceeded triggers its own effect.
+///
+/// The default value has the default memory size, and no other limits, which
+/// matches an evaluation created by [`Eval::new`].
+///
+/// [`Eval::with_limits`]: crate::Eval::with_limits
+/// [`Eval::new`]: crate::Eval::new
+///
+/// ## Example
+///
+/// ```
+/// use stack_assembly::{Effect, Eval, Limits, Script};
+///
+/// let script = Script::compile("
+///     push:
+///         1
+///         @push jump
+/// ");
+///
+/// let mut eval = Eval::with_limits(Limits {
+///     max_operand_stack: Some(16),
+///     ..Limits::default()
+/// });
+///
+/// let (effect, _) = eval.run(&script);
+/// assert_eq!(effect, Effect::OperandStackOverflow);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Limits {
+    /// # The size of the memory, in words
+    ///
+    /// Accessing an address beyond that triggers [`Effect::InvalidAddress`].
+    ///
+    /// [`Effect::InvalidAddress`]: crate::Effect::InvalidAddress
+    pub memory_words: usize,
+
+    /// # The maximum number of values on the operand stack
+    ///
+    /// Evaluating an operator that leaves more values on the operand stack
+    /// triggers [`Effect::OperandStackOverflow`]. `None` means unlimited.
+    ///
+    /// [`Effect::OperandStackOverflow`]: crate::Effect::OperandStackOverflow
+    pub max_operand_stack: Option<usize>,
+
+    /// # The maximum number of nested calls
+    ///
+    /// Evaluating a call that exceeds this depth triggers
+    /// [`Effect::CallStackOverflow`]. `None` means unlimited. See
+    /// [`CallStack::with_max_depth`].
+    ///
+    /// [`Effect::CallStackOverflow`]: crate::Effect::CallStackOverflow
+    /// [`CallStack::with_max_depth`]: crate::CallStack::with_max_depth
+    pub max_call_depth: Option<usize>,
+
+    /// # The maximum number of strands
+    ///
+    /// This includes the strand that the evaluation starts with. Evaluating
+    /// `spawn` while there are already this many strands triggers
+    /// [`Effect::TooManyStrands`]. `None` means unlimited.
+    ///
+    /// [`Effect::TooManyStrands`]: crate::Effect::TooManyStrands
+    pub max_strands: Option<usize>,
+
+    /// # The number of operators that can be evaluated
+    ///
+    /// See [`Eval::set_fuel`]. `None` means unlimited.
+    ///
+    /// [`Eval::set_fuel`]: crate::Eval::set_fuel
+    pub fuel: Option<u64>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            memory_words: Memory::DEFAULT_SIZE,
+            max_operand_stack: None,
+            max_call_depth: None,
+            max_strands: None,
+            fuel: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompileOptions, Effect, Eval, Limits, Script};
+
+    #[test]
+    fn exceeding_call_depth_triggers_effect() {
+        let script = Script::compile("f: @f call");
+
+        let mut eval = Eval::with_limits(Limits {
+            max_call_depth: Some(3),
+            ..Limits::default()
+        });
+
+        let (effect, _) = eval.run(&script);
+        assert_eq!(effect, Effect::CallStackOverflow);
+        assert_eq!(eval.call_stack.len(), 3);
+    }
+
+    #[test]
+    fn exceeding_strands_triggers_effect() {
+        let script = Script::compile("spawn: @spawn spawn @spawn jump");
+
+        let mut eval = Eval::with_limits(Limits {
+            max_strands: Some(3),
+            ..Limits::default()
+        });
+
+        let (effect, _) = eval.run(&script);
+        assert_eq!(effect, Effect::TooManyStrands);
+        assert_eq!(eval.operand_stack.to_u32_slice(), &[1, 2]);
+    }
+
+    #[test]
+    fn operand_stack_may_reach_its_limit() {
+        let script = Script::compile("1 2 3 + 4 5");
+
+        let mut eval = Eval::with_limits(Limits {
+            max_operand_stack: Some(3),
+            ..Limits::default()
+        });
+
+        let (effect, operator) = eval.run(&script);
+        assert_eq!(effect, Effect::OperandStackOverflow);
+        assert_eq!(operator.value(), 5);
+    }
+
+    #[test]
+    fn operand_stack_limit_applies_if_optimized() {
+        for optimize in [false, true] {
+            let options = CompileOptions {
+                optimize,
+                ..CompileOptions::default()
+            };
+            let script = Script::compile_with_options("1 2 1 +", options);
+
+            let mut eval = Eval::with_limits(Limits {
+                max_operand_stack: Some(2),
+                ..Limits::default()
+            });
+
+            let (effect, operator) = eval.run(&script);
+            assert_eq!(effect, Effect::OperandStackOverflow, "{optimize}");
+            assert_eq!(operator.value(), 2, "{optimize}");
+        }
+    }
+
+    #[test]
+    fn memory_and_fuel_are_limited() {
+        let script = Script::compile("15 0 write 16 1 write");
+
+        let mut eval = Eval::with_limits(Limits {
+            memory_words: 16,
+            fuel: Some(6),
+            ..Limits::default()
+        });
+
+        let (effect, operator) = eval.run(&script);
+        assert_eq!(effect, Effect::InvalidAddress);
+        assert_eq!(operator.value(), 5);
+
+        let mut eval = Eval::with_limits(Limits {
+            fuel: Some(2),
+            ..Limits::default()
+        });
+
+        let (effect, operator) = eval.run(&script);
+        assert_eq!(effect, Effect::OutOfFuel);
+        assert_eq!(operator.value(), 2);
+    }
+}
@@ -0,0 +1,535 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    ops::Range,
+};
+
+use crate::{
+    OperatorIndex, Script,
+    data::{Datum, Region},
+    script::{Label, Operator, Procedure, Start},
+};
+
+/// # Combines separately compiled scripts into one
+///
+/// Larger programs can be split into units, like one per file, or a library
+/// that many programs share. Each unit is compiled on its own, then added to
+/// the linker, alongside the names of the labels it exports. [`Linker::link`]
+/// then produces a single script, in which each reference to a label that a
+/// unit doesn't define itself (an import) refers to the export of that name.
+///
+/// The units are placed one after the other, in the order they were added. The
+/// evaluation of the linked script starts with the first operator of the first
+/// unit, unless a unit has a `.start` directive (see [`Script::start`]).
+/// Reaching the end of a unit ends the evaluation, like reaching the end of a
+/// script does.
+///
+/// Labels that a unit doesn't export are private to it. In the linked script,
+/// their names are qualified with the name of the unit, like `unit::label`, so
+/// they don't collide with the labels of other units. As a result,
+/// [`Script::tests`] only finds the tests of a unit, if it exports them.
+///
+/// ## Limitations
+///
+/// The linked script has no source map, as its operators were compiled from
+/// different source texts. [`Script::map_operator_to_source`] always returns an
+/// error.
+///
+/// References to regions of memory compile into their addresses, so the
+/// linker can't move regions. Each unit's regions stay at the addresses they
+/// were compiled to, starting at address `0`, and the units must not reserve
+/// overlapping addresses. In practice, this means that only one unit can
+/// reserve memory. Regions are private to their unit, and can't be exported.
+///
+/// ## Example
+///
+/// ```
+/// use stack_assembly::{Eval, Linker, Script};
+///
+/// let main = Script::compile("3 @square call");
+/// let math = Script::compile("
+///     square:
+///         0 copy *
+///         return
+/// ");
+///
+/// let mut linker = Linker::new();
+/// linker.add("main", main, []);
+/// linker.add("math", math, ["square"]);
+///
+/// let Ok(script) = linker.link() else {
+///     unreachable!("All imports are exported by another unit.");
+/// };
+///
+/// let mut eval = Eval::new();
+/// eval.run(&script);
+///
+/// assert_eq!(eval.operand_stack.to_i32_slice(), &[9]);
+/// ```
+#[derive(Debug, Default)]
+pub struct Linker {
+    units: Vec<Unit>,
+}
+
+impl Linker {
+    /// # Create a linker without any units
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Add a unit to the linker
+    ///
+    /// The name identifies the unit in errors, and qualifies the names of its
+    /// private labels in the linked script. `exports` lists the labels that
+    /// the other units can refer to.
+    pub fn add<'r>(
+        &mut self,
+        name: impl Into<String>,
+        script: Script,
+        exports: impl IntoIterator<Item = &'r str>,
+    ) {
+        self.units.push(Unit {
+            name: name.into(),
+            script,
+            exports: exports.into_iter().map(String::from).collect(),
+        });
+    }
+
+    /// # Link the units into a single script
+    ///
+    /// Returns an error, if a unit exports a label it doesn't define, if
+    /// multiple units export the same label or have a `.start` directive, if
+    /// a unit imports a label that no unit exports, or if the regions of
+    /// multiple units overlap.
+    pub fn link(self) -> Result<Script, LinkError> {
+        let exports = self.find_exports()?;
+        self.check_regions()?;
+
+        let mut operators = Vec::new();
+        let mut labels = Vec::new();
+        let mut procedures = Vec::new();
+        let mut regions = Vec::new();
+        let mut metadata = Vec::new();
+        let mut start = None;
+
+        for (i, unit) in self.units.iter().enumerate() {
+            if i > 0 {
+                // Reaching the end of the previous unit must not continue
+                // into this one.
+                operators.push(Operator::End);
+            }
+
+            let offset = u32::try_from(operators.len())
+                .map_err(|_| LinkError::TooManyOperators)?;
+            let relocate = |index: OperatorIndex| {
+                index.checked_add(offset).ok_or(LinkError::TooManyOperators)
+            };
+
+            // Resolves a reference, which either refers to a label of the unit
+            // itself, or is an import.
+            let link = |name: &str, target: Option<OperatorIndex>| match target
+            {
+                Some(target) => Ok((unit.qualify(name), relocate(target)?)),
+                None => match exports.get(name) {
+                    Some(&(_, target)) => Ok((name.to_string(), target)),
+                    None => Err(LinkError::UnresolvedImport {
+                        unit: unit.name.clone(),
+                        name: name.to_string(),
+                    }),
+                },
+            };
+
+            for (_, operator) in unit.script.operators() {
+                let operator = match operator {
+                    Operator::Reference { name, target } => {
+                        let (name, target) = link(name, *target)?;
+                        Operator::Reference {
+                            name,
+                            target: Some(target),
+                        }
+                    }
+                    operator => operator.clone(),
+                };
+
+                operators.push(operator);
+            }
+
+            for (name, operator) in unit.script.labels() {
+                labels.push(Label {
+                    name: unit.qualify(name),
+                    operator: relocate(operator)?,
+                    range: None,
+                });
+            }
+            for (name, operator, arity) in unit.script.procedures() {
+                procedures.push(Procedure {
+                    name: unit.qualify(name),
+                    operator: relocate(operator)?,
+                    arity,
+                });
+            }
+
+            for region in unit.script.region_definitions() {
+                let values = region
+                    .values
+                    .iter()
+                    .map(|datum| match datum {
+                        Datum::Integer { value } => {
+                            Ok(Datum::Integer { value: *value })
+                        }
+                        Datum::Reference {
+                            name,
+                            target,
+                            range: _,
+                        } => {
+                            let (name, target) = link(name, *target)?;
+                            Ok(Datum::Reference {
+                                name,
+                                target: Some(target),
+                                range: None,
+                            })
+                        }
+                    })
+                    .collect::<Result<_, _>>()?;
+
+                regions.push(Region {
+                    name: region.name.clone(),
+                    addresses: region.addresses.clone(),
+                    values,
+                });
+            }
+
+            metadata.extend(
+                unit.script
+                    .metadata()
+                    .map(|(key, value)| (key.to_string(), value.to_string())),
+            );
+
+            if let Some(name) = unit.script.start_label() {
+                if start.is_some() {
+                    return Err(LinkError::DuplicateStart {
+                        unit: unit.name.clone(),
+                    });
+                }
+
+                let target = (!unit.script.has_invalid_start())
+                    .then(|| unit.script.start());
+                let (name, _) = link(name, target)?;
+
+                start = Some(Start {
+                    name,
+                    target: None,
+                    range: None,
+                });
+            }
+        }
+
+        let mut script = Script::from_parts(
+            operators,
+            labels,
+            procedures,
+            regions,
+            BTreeMap::new(),
+            metadata,
+            start,
+        );
+        script.decode_instructions();
+
+        Ok(script)
+    }
+
+    /// # Find the labels that the units export, by name
+    ///
+    /// Yields the name of the exporting unit, alongside the operator that the
+    /// label refers to in the linked script.
+    fn find_exports(
+        &self,
+    ) -> Result<HashMap<&str, (&str, OperatorIndex)>, LinkError> {
+        let mut exports = HashMap::new();
+        let mut offset = 0;
+
+        for unit in &self.units {
+            for name in &unit.exports {
+                let Some((_, operator)) =
+                    unit.script.labels().find(|(label, _)| label == name)
+                else {
+                    return Err(LinkError::UnknownExport {
+                        unit: unit.name.clone(),
+                        name: name.clone(),
+                    });
+                };
+                let operator = operator
+                    .checked_add(offset)
+                    .ok_or(LinkError::TooManyOperators)?;
+
+                if let Some((other, _)) = exports
+                    .insert(name.as_str(), (unit.name.as_str(), operator))
+                {
+                    return Err(LinkError::DuplicateExport {
+                        units: [other.to_string(), unit.name.clone()],
+                        name: name.clone(),
+                    });
+                }
+            }
+
+            // Each unit is followed by the `End` that separates it from the
+            // next one.
+            let num_operators = u32::try_from(unit.script.operators().count())
+                .map_err(|_| LinkError::TooManyOperators)?;
+            offset = offset
+                .checked_add(num_operators)
+                .and_then(|offset| offset.checked_add(1))
+                .ok_or(LinkError::TooManyOperators)?;
+        }
+
+        Ok(exports)
+    }
+
+    /// # Make sure that the regions of different units don't overlap
+    fn check_regions(&self) -> Result<(), LinkError> {
+        let mut reserved: Vec<(&str, Range<u32>)> = Vec::new();
+
+        for unit in &self.units {
+            let addresses = unit
+                .script
+                .regions()
+                .map(|(_, addresses)| addresses)
+                .filter(|addresses| !addresses.is_empty())
+                .collect::<Vec<_>>();
+
+            for a in &addresses {
+                for (other, b) in &reserved {
+                    if a.start < b.end && b.start < a.end {
+                        return Err(LinkError::OverlappingRegions {
+                            units: [other.to_string(), unit.name.clone()],
+                        });
+                    }
+                }
+            }
+
+            reserved.extend(
+                addresses
+                    .into_iter()
+                    .map(|addresses| (unit.name.as_str(), addresses)),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Unit {
+    name: String,
+    script: Script,
+    exports: Vec<String>,
+}
+
+impl Unit {
+    /// # Determine the name of a label of this unit in the linked script
+    fn qualify(&self, name: &str) -> String {
+        if self.exports.iter().any(|export| export == name) {
+            name.to_string()
+        } else {
+            format!("{}::{name}", self.name)
+        }
+    }
+}
+
+/// # The units passed to a [`Linker`] can't be linked
+///
+/// See [`Linker::link`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LinkError {
+    /// # A unit exports a label that it doesn't define
+    UnknownExport {
+        /// # The name of the unit
+        unit: String,
+
+        /// # The name of the label
+        name: String,
+    },
+
+    /// # Multiple units export a label of the same name
+    DuplicateExport {
+        /// # The names of the units, in the order they were added
+        units: [String; 2],
+
+        /// # The name of the label
+        name: String,
+    },
+
+    /// # A unit refers to a label that neither it nor any export defines
+    UnresolvedImport {
+        /// # The name of the unit
+        unit: String,
+
+        /// # The name of the label
+        name: String,
+    },
+
+    /// # Multiple units have a `.start` directive
+    DuplicateStart {
+        /// # The name of the unit with the second directive
+        unit: String,
+    },
+
+    /// # The regions of two units reserve some of the same addresses
+    OverlappingRegions {
+        /// # The names of the units, in the order they were added
+        units: [String; 2],
+    },
+
+    /// # The linked script would have more operators than can be indexed
+    TooManyOperators,
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownExport { unit, name } => {
+                write!(f, "unit `{unit}` exports unknown label `{name}`")
+            }
+            Self::DuplicateExport {
+                units: [a, b],
+                name,
+            } => {
+                write!(f, "units `{a}` and `{b}` both export `{name}`")
+            }
+            Self::UnresolvedImport { unit, name } => {
+                write!(f, "unit `{unit}` refers to unknown label `{name}`")
+            }
+            Self::DuplicateStart { unit } => {
+                write!(
+                    f,
+                    "unit `{unit}` has a `.start` directive, but an earlier \
+                    unit has one too"
+                )
+            }
+            Self::OverlappingRegions { units: [a, b] } => {
+                write!(f, "regions of units `{a}` and `{b}` overlap")
+            }
+            Self::TooManyOperators => {
+                write!(f, "linked script has too many operators")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Effect, Eval, Script};
+
+    use super::{LinkError, Linker};
+
+    #[test]
+    fn private_labels_do_not_collide() {
+        // Both units define `helper`, but neither exports it. Each unit's
+        // references keep referring to its own label.
+
+        let mut linker = Linker::new();
+        linker.add(
+            "main",
+            Script::compile(
+                ".start main helper: 1 return main: @helper call @f call",
+            ),
+            [],
+        );
+        linker.add(
+            "lib",
+            Script::compile("f: @helper jump helper: 2 return"),
+            ["f"],
+        );
+
+        let Ok(script) = linker.link() else {
+            unreachable!("Units can be linked.");
+        };
+
+        assert!(script.labels().any(|(name, _)| name == "lib::helper"));
+        assert!(script.labels().any(|(name, _)| name == "f"));
+
+        let mut eval = Eval::start_at(script.start());
+        eval.load_data(&script);
+        let (effect, _) = eval.run(&script);
+
+        assert_eq!(effect, Effect::OutOfOperators);
+        assert_eq!(eval.operand_stack.to_i32_slice(), &[1, 2]);
+    }
+
+    #[test]
+    fn data_can_refer_to_exports() {
+        let mut linker = Linker::new();
+        linker.add(
+            "main",
+            Script::compile(".data table @f\n @table read call"),
+            [],
+        );
+        linker.add("lib", Script::compile("f: 3 return"), ["f"]);
+
+        let Ok(script) = linker.link() else {
+            unreachable!("Units can be linked.");
+        };
+
+        let mut eval = Eval::new();
+        eval.load_data(&script);
+        let (effect, _) = eval.run(&script);
+
+        assert_eq!(effect, Effect::OutOfOperators);
+        assert_eq!(eval.operand_stack.to_i32_slice(), &[3]);
+    }
+
+    #[test]
+    fn reject_invalid_units() {
+        let link = |units: &[(&str, &str, &[&str])]| {
+            let mut linker = Linker::new();
+            for &(name, source, exports) in units {
+                linker.add(
+                    name,
+                    Script::compile(source),
+                    exports.iter().copied(),
+                );
+            }
+            linker.link().err()
+        };
+
+        assert_eq!(
+            link(&[("a", "1", &["f"])]),
+            Some(LinkError::UnknownExport {
+                unit: "a".to_string(),
+                name: "f".to_string(),
+            }),
+        );
+        assert_eq!(
+            link(&[("a", "f:", &["f"]), ("b", "f:", &["f"])]),
+            Some(LinkError::DuplicateExport {
+                units: ["a".to_string(), "b".to_string()],
+                name: "f".to_string(),
+            }),
+        );
+        assert_eq!(
+            link(&[("a", "@f call", &[]), ("b", "f:", &[])]),
+            Some(LinkError::UnresolvedImport {
+                unit: "a".to_string(),
+                name: "f".to_string(),
+            }),
+        );
+        assert_eq!(
+            link(&[("a", ".start f f:", &[]), ("b", ".start g g:", &[])]),
+            Some(LinkError::DuplicateStart {
+                unit: "b".to_string(),
+            }),
+        );
+        assert_eq!(
+            link(&[("a", ".zero x 2", &[]), ("b", ".zero y 1", &[])]),
+            Some(LinkError::OverlappingRegions {
+                units: ["a".to_string(), "b".to_string()],
+            }),
+        );
+        assert_eq!(
+            link(&[("a", ".zero x 2", &[]), ("b", ".zero y 0", &[])]),
+            None
+        );
+    }
+}
@@ -0,0 +1,422 @@
+//! # Detection of code that is likely a mistake
+//!
+//! See [`Script::lint`].
+
+use std::{collections::BTreeSet, fmt};
+
+use crate::{
+    OperatorIndex, Script,
+    opcode::Opcode,
+    reachability::find_reachable,
+    script::Operator,
+    stack_depth::{find_arity_mismatches, find_stack_underflows},
+    structured::is_generated_label,
+};
+
+impl Script {
+    /// # Check the script for code that is likely a mistake
+    ///
+    /// Returns a warning for each label that is never referenced or defined
+    /// more than once, for each range of operators that the evaluation can
+    /// never reach, and for each operator that can find too few values on the
+    /// operand stack. Only the code that was compiled from the source text is
+    /// checked, not the prelude.
+    ///
+    /// Labels whose name starts with `test_` are considered to be referenced,
+    /// as they are the entry points of tests (see [`Script::tests`]). Labels
+    /// that the compiler generates for structured control flow are never
+    /// reported. See [`Script::reachable_operators`] for which operators are
+    /// considered reachable. Since that analysis can't take into account
+    /// addresses that a script computes, the result is only a hint.
+    ///
+    /// The number of values on the operand stack is only tracked, where it
+    /// doesn't depend on the path that the evaluation took, or on routines
+    /// that the script calls. So many operators that can trigger
+    /// [`Effect::OperandStackUnderflow`] won't result in a warning. Procedures
+    /// (see [`Script::procedures`]) help with that, as the analysis can rely
+    /// on their declared arity for calls to them. In turn, it checks that each
+    /// procedure leaves as many values on the stack, as it declares.
+    ///
+    /// [`Effect::OperandStackUnderflow`]: crate::Effect::OperandStackUnderflow
+    pub fn lint(&self) -> Vec<Warning> {
+        // Operators from the prelude are not present in the source map. And
+        // since the prelude comes after the source text, all operators from
+        // the source text are at the start.
+        let num_operators = self
+            .operators()
+            .take_while(|(operator, _)| {
+                self.map_operator_to_source(operator).is_ok()
+            })
+            .count();
+
+        let referenced = self
+            .operators()
+            .filter_map(|(_, operator)| match operator {
+                Operator::Reference { name, target: _ } => Some(name.as_str()),
+                _ => None,
+            })
+            .chain(self.data_references().map(|(name, _)| name))
+            .chain(self.start_label())
+            .collect::<BTreeSet<_>>();
+
+        let mut warnings = Vec::new();
+        let mut defined = BTreeSet::new();
+
+        for (name, operator) in self.labels() {
+            // The label is defined in the source text, if it refers to one of
+            // its operators. A label at its very end refers to the operator
+            // right after it.
+            if operator.value() as usize > num_operators {
+                continue;
+            }
+
+            // If multiple labels have the same name, references resolve to the
+            // first one. So any label that comes after can't be in use.
+            if !defined.insert(name) {
+                warnings.push(Warning::DuplicateLabel {
+                    name: name.to_string(),
+                    operator,
+                });
+            } else if !referenced.contains(name)
+                && !name.starts_with("test_")
+                && !is_generated_label(name)
+            {
+                warnings.push(Warning::UnusedLabel {
+                    name: name.to_string(),
+                    operator,
+                });
+            }
+        }
+
+        let mut unreachable = None;
+
+        let mut reachable = find_reachable(self);
+        let operators = self
+            .operators()
+            .map(|(_, operator)| operator)
+            .collect::<Vec<_>>();
+        for (i, pair) in operators.windows(2).enumerate() {
+            // If the `if` branch ends with `jump` or `return`, the jump that
+            // skips the `else` branch can't be reached. That's not a mistake
+            // though, as that jump doesn't appear in the source text.
+            if let (
+                Operator::Reference { name, target: _ },
+                Operator::Opcode {
+                    opcode: Opcode::Jump,
+                },
+            ) = (pair[0], pair[1])
+                && is_generated_label(name)
+            {
+                reachable[i] = true;
+                reachable[i + 1] = true;
+            }
+        }
+
+        for ((index, _), is_reachable) in
+            self.operators().zip(reachable).take(num_operators)
+        {
+            match (is_reachable, unreachable) {
+                (false, None) => {
+                    unreachable = Some(index);
+                }
+                (true, Some(first)) => {
+                    warnings.push(Warning::UnreachableCode {
+                        first,
+                        last: OperatorIndex::new(index.value() - 1),
+                    });
+                    unreachable = None;
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(first) = unreachable {
+            warnings.push(Warning::UnreachableCode {
+                first,
+                last: OperatorIndex::new(num_operators as u32 - 1),
+            });
+        }
+
+        warnings.extend(
+            find_stack_underflows(self)
+                .into_iter()
+                .filter(|operator| (operator.value() as usize) < num_operators)
+                .map(|operator| Warning::StackUnderflow { operator }),
+        );
+        warnings.extend(
+            find_arity_mismatches(self)
+                .into_iter()
+                .filter(|(_, operator)| {
+                    (operator.value() as usize) < num_operators
+                })
+                .map(|(name, operator)| Warning::ArityMismatch {
+                    name,
+                    operator,
+                }),
+        );
+
+        warnings
+    }
+}
+
+/// # A hint about code that is likely a mistake
+///
+/// See [`Script::lint`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Warning {
+    /// # A label that is never referenced
+    UnusedLabel {
+        /// # The name of the label
+        name: String,
+
+        /// # The operator that the label refers to
+        operator: OperatorIndex,
+    },
+
+    /// # A label with the same name as one defined before it
+    ///
+    /// References resolve to the first of those labels, so this one is never
+    /// used.
+    DuplicateLabel {
+        /// # The name of the label
+        name: String,
+
+        /// # The operator that the label refers to
+        operator: OperatorIndex,
+    },
+
+    /// # A range of operators that the evaluation can never reach
+    UnreachableCode {
+        /// # The first operator of the range
+        first: OperatorIndex,
+
+        /// # The last operator of the range
+        last: OperatorIndex,
+    },
+
+    /// # An operator that can find too few values on the operand stack
+    ///
+    /// Evaluating it would trigger [`Effect::OperandStackUnderflow`].
+    ///
+    /// [`Effect::OperandStackUnderflow`]: crate::Effect::OperandStackUnderflow
+    StackUnderflow {
+        /// # The operator
+        operator: OperatorIndex,
+    },
+
+    /// # A procedure that can return a different number of values than declared
+    ///
+    /// See [`Script::procedures`].
+    ArityMismatch {
+        /// # The name of the procedure
+        name: String,
+
+        /// # The `return` through which the procedure does that
+        operator: OperatorIndex,
+    },
+}
+
+impl Warning {
+    /// # The operator that the warning refers to
+    ///
+    /// For a label, that's the operator that it refers to. For a range of
+    /// operators, that's the first one.
+    pub fn operator(&self) -> OperatorIndex {
+        match *self {
+            Self::UnusedLabel { name: _, operator }
+            | Self::DuplicateLabel { name: _, operator }
+            | Self::StackUnderflow { operator }
+            | Self::ArityMismatch { name: _, operator } => operator,
+            Self::UnreachableCode { first, last: _ } => first,
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnusedLabel { name, operator: _ } => {
+                write!(f, "label `{name}:` is never referenced")
+            }
+            Self::DuplicateLabel { name, operator: _ } => {
+                write!(f, "label `{name}:` is already defined")
+            }
+            Self::UnreachableCode { first: _, last: _ } => {
+                write!(f, "code is unreachable")
+            }
+            Self::StackUnderflow { operator: _ } => {
+                write!(f, "operator can find too few values on the stack")
+            }
+            Self::ArityMismatch { name, operator: _ } => {
+                write!(
+                    f,
+                    "procedure `{name}` can return a different number of \
+                    values than it declares"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompileOptions, OperatorIndex, Script, lint::Warning};
+
+    #[test]
+    fn warn_about_unused_label() {
+        let script = Script::compile("loop: 1 @used jump used: unused: 2");
+
+        assert_eq!(
+            script.lint(),
+            [
+                Warning::UnusedLabel {
+                    name: "loop".to_string(),
+                    operator: OperatorIndex::new(0),
+                },
+                Warning::UnusedLabel {
+                    name: "unused".to_string(),
+                    operator: OperatorIndex::new(3),
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn warn_about_duplicate_label() {
+        let script = Script::compile("@a jump a: 1 a: 2");
+
+        assert_eq!(
+            script.lint(),
+            [Warning::DuplicateLabel {
+                name: "a".to_string(),
+                operator: OperatorIndex::new(3),
+            }],
+        );
+    }
+
+    #[test]
+    fn warn_about_unreachable_code() {
+        let script = Script::compile(
+            "
+            @a jump
+                1 2
+            a:
+                return
+                3
+            ",
+        );
+
+        assert_eq!(
+            script.lint(),
+            [
+                Warning::UnreachableCode {
+                    first: OperatorIndex::new(2),
+                    last: OperatorIndex::new(3),
+                },
+                Warning::UnreachableCode {
+                    first: OperatorIndex::new(5),
+                    last: OperatorIndex::new(5),
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn ignore_unreachable_jump_generated_by_else() {
+        let script = Script::compile(
+            "
+            1 if
+                1 return
+            else
+                2 return
+            end
+            ",
+        );
+
+        assert_eq!(script.lint(), []);
+    }
+
+    #[test]
+    fn ignore_generated_labels() {
+        // The end of the loop is never referenced, since nothing leaves it.
+        let script = Script::compile("loop 1 yield end");
+        assert_eq!(script.lint(), []);
+    }
+
+    #[test]
+    fn warn_about_stack_underflow() {
+        let script = Script::compile("1 2 + *");
+
+        assert_eq!(
+            script.lint(),
+            [Warning::StackUnderflow {
+                operator: OperatorIndex::new(3),
+            }],
+        );
+    }
+
+    #[test]
+    fn consider_tests_to_be_reachable() {
+        let script = Script::compile("return test_a: 1 assert return");
+        assert_eq!(script.lint(), []);
+    }
+
+    #[test]
+    fn ignore_prelude() {
+        let script = Script::compile_with_options(
+            "1 2",
+            CompileOptions {
+                prelude: true,
+                ..CompileOptions::default()
+            },
+        );
+
+        assert_eq!(script.lint(), []);
+    }
+
+    #[test]
+    fn check_calls_against_declared_arity() {
+        let script = Script::compile(
+            "
+            1 @add call
+            return
+
+            proc add in 2 out 1
+                +
+            end
+            ",
+        );
+
+        assert_eq!(
+            script.lint(),
+            [Warning::StackUnderflow {
+                operator: OperatorIndex::new(2),
+            }],
+        );
+    }
+
+    #[test]
+    fn warn_about_arity_mismatch() {
+        let script = Script::compile(
+            "
+            1 @f call
+            return
+
+            proc f in 1 out 1
+                0 copy if
+                    return
+                end
+                1
+            end
+            ",
+        );
+
+        assert_eq!(
+            script.lint(),
+            [Warning::ArityMismatch {
+                name: "f".to_string(),
+                operator: OperatorIndex::new(12),
+            }],
+        );
+    }
+}
@@ -1,4 +1,9 @@
-use std::fmt;
+use std::{
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut, Range},
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
 
 use crate::{Effect, Value};
 
@@ -13,22 +18,144 @@ use crate::{Effect, Value};
 ///
 /// ## Constructing a `Memory` instance
 ///
-/// By default, `Memory` has a size of 1024 words and is initially empty. This
-/// is controlled by its [`Default` implementation].
+/// By default, `Memory` has a size of [`Memory::DEFAULT_SIZE`] words and is
+/// initially empty. This is controlled by its [`Default` implementation].
 ///
-/// If you want to override this size, you can do so by overwriting the
-/// [`values`] field with a [`Vec`] of the desired length.
+/// If you want to override this size, you can construct a memory of the
+/// desired size using [`Memory::new`], and assign it to [`Eval`]'s [`memory`]
+/// field.
+///
+/// The memory is allocated once, when it's constructed. Its size never changes
+/// during the evaluation, and neither reading from it nor writing to it
+/// allocates, unless it has been cloned.
+///
+/// ## Cloning
+///
+/// Cloning a `Memory` is cheap, as the clones share their values. Only once a
+/// clone is written to, it copies the values, so the write is not visible to
+/// the other clones. This makes it affordable to clone an [`Eval`] many times,
+/// for example to explore different ways of handling an effect.
+///
+/// ## Sharing memory between evaluations
+///
+/// A memory can be converted into a shared one, using [`Memory::into_shared`].
+/// Cloning a shared memory does not copy its values. Instead, the clones refer
+/// to the same values, and a write through any of them is visible to all. This
+/// allows multiple instances of [`Eval`] to communicate through a common
+/// memory, even if they are evaluated on different threads.
+///
+/// ## Strings
+///
+/// Scripts and hosts exchange text as UTF-8, stored in memory one byte per
+/// word, at consecutive addresses. A string is passed around as the address of
+/// its first byte and its length in bytes. If those are passed on the operand
+/// stack, the length is on top.
+///
+/// The language itself doesn't care about strings, so this is only a
+/// convention. But following it means that scripts can work with any host that
+/// does, and that hosts don't need to document their own encoding.
+/// [`Memory::read_str`] and [`Memory::write_str`] implement it.
 ///
 /// [`Eval`]: crate::Eval
 /// [`memory`]: struct.Eval.html#structfield.memory
 /// [`Default` implementation]: #impl-Default-for-Memory
-/// [`values`]: #structfield.values
+#[derive(Clone)]
 pub struct Memory {
-    /// # The values in the memory
-    pub values: Vec<Value>,
+    storage: Storage,
+}
+
+#[derive(Clone)]
+enum Storage {
+    /// # MemoryValues that are copied on write, if shared with a clone
+    Owned { values: Arc<[Value]> },
+
+    /// # MemoryValues that are shared with all clones, even when written
+    Shared { values: Arc<RwLock<Box<[Value]>>> },
 }
 
 impl Memory {
+    /// # The size of the memory, in words, unless configured otherwise
+    pub const DEFAULT_SIZE: usize = 1024;
+
+    /// # Create an empty memory with the provided size, in words
+    ///
+    /// Only the first `u32::MAX + 1` words are addressable by a script. Any
+    /// words beyond that are still allocated, but can only be accessed by the
+    /// host.
+    pub fn new(size: usize) -> Self {
+        Self {
+            storage: Storage::Owned {
+                values: vec![Value::from(0); size].into(),
+            },
+        }
+    }
+
+    /// # Convert this memory into one that is shared with its clones
+    ///
+    /// See the [section on sharing memory]. If this memory is already shared,
+    /// return it unchanged.
+    ///
+    /// [section on sharing memory]: #sharing-memory-between-evaluations
+    pub fn into_shared(self) -> Self {
+        let values = match self.storage {
+            Storage::Owned { values } => values.to_vec().into_boxed_slice(),
+            storage @ Storage::Shared { .. } => {
+                return Self { storage };
+            }
+        };
+
+        Self {
+            storage: Storage::Shared {
+                values: Arc::new(RwLock::new(values)),
+            },
+        }
+    }
+
+    /// # Determine whether this memory is shared with its clones
+    ///
+    /// See [`Memory::into_shared`].
+    pub fn is_shared(&self) -> bool {
+        matches!(self.storage, Storage::Shared { .. })
+    }
+
+    /// # Access the values in the memory
+    ///
+    /// If the memory is shared, writes through any of its clones are blocked,
+    /// for as long as the returned value exists.
+    pub fn values(&self) -> MemoryValues<'_, Value> {
+        let guard = match &self.storage {
+            Storage::Owned { values } => MemoryValuesGuard::Owned { values },
+            Storage::Shared { values } => MemoryValuesGuard::Shared {
+                values: values.read().unwrap_or_else(PoisonError::into_inner),
+            },
+        };
+
+        MemoryValues {
+            guard,
+            _type: PhantomData,
+        }
+    }
+
+    /// # Access the values in the memory mutably
+    ///
+    /// If this memory shares its values with a clone, and has not been
+    /// converted via [`Memory::into_shared`], this copies them first.
+    ///
+    /// If the memory is shared, any access through any of its clones is
+    /// blocked, for as long as the returned value exists.
+    pub fn values_mut(&mut self) -> MemoryValuesMut<'_> {
+        let guard = match &mut self.storage {
+            Storage::Owned { values } => MemoryValuesMutGuard::Owned {
+                values: Arc::make_mut(values),
+            },
+            Storage::Shared { values } => MemoryValuesMutGuard::Shared {
+                values: values.write().unwrap_or_else(PoisonError::into_inner),
+            },
+        };
+
+        MemoryValuesMut { guard }
+    }
+
     /// # Read the value at the provided address
     pub fn read(&self, address: u32) -> Result<Value, InvalidAddress> {
         let Ok(address): Result<usize, _> = address.try_into() else {
@@ -38,7 +165,7 @@ impl Memory {
             return Err(InvalidAddress);
         };
 
-        let Some(value) = self.values.get(address).copied() else {
+        let Some(value) = self.values().get(address).copied() else {
             return Err(InvalidAddress);
         };
 
@@ -58,31 +185,140 @@ impl Memory {
             return Err(InvalidAddress);
         };
 
-        if address >= self.values.len() {
+        let mut values = self.values_mut();
+        let Some(slot) = values.get_mut(address) else {
             return Err(InvalidAddress);
-        }
+        };
 
-        self.values[address] = value;
+        *slot = value;
 
         Ok(())
     }
 
+    /// # Write a sequence of values, prefixed by their number
+    ///
+    /// Writes the number of values to the provided address, followed by the
+    /// values themselves, to the addresses right after that. This is useful
+    /// for passing variable-length data, like command-line arguments, to a
+    /// script.
+    ///
+    /// Returns an error, if the sequence doesn't fit into the memory at the
+    /// provided address. Nothing is written in that case.
+    pub fn write_length_prefixed(
+        &mut self,
+        address: u32,
+        values: &[Value],
+    ) -> Result<(), InvalidAddress> {
+        let Ok(length) = u32::try_from(values.len()) else {
+            // The memory can't be larger than `u32::MAX` words, or it wouldn't
+            // be addressable. So this many values definitely don't fit.
+            return Err(InvalidAddress);
+        };
+
+        let Some(last_address) = address.checked_add(length) else {
+            return Err(InvalidAddress);
+        };
+
+        // Make sure the whole sequence fits, before writing anything.
+        self.read(last_address)?;
+
+        self.write(address, Value::from(length))?;
+        for (address, &value) in (address + 1..).zip(values) {
+            self.write(address, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// # Read the string stored in the provided range of addresses
+    ///
+    /// Expects one byte of UTF-8 per word, as described in the [section on
+    /// strings]. Returns an error, if the range is not within the memory, if
+    /// any word in it doesn't hold a byte, or if the bytes are not valid
+    /// UTF-8.
+    ///
+    /// [section on strings]: #strings
+    pub fn read_str(&self, range: Range<u32>) -> Result<String, InvalidString> {
+        let (Ok(start), Ok(end)) =
+            (usize::try_from(range.start), usize::try_from(range.end))
+        else {
+            return Err(InvalidString::OutOfBounds);
+        };
+
+        let values = self.values();
+        let Some(words) = values.get(start..end) else {
+            return Err(InvalidString::OutOfBounds);
+        };
+
+        let bytes = words
+            .iter()
+            .zip(range)
+            .map(|(value, address)| {
+                u8::try_from(value.to_u32())
+                    .map_err(|_| InvalidString::NotAByte { address })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        String::from_utf8(bytes).map_err(|_| InvalidString::NotUtf8)
+    }
+
+    /// # Write a string, starting at the provided address
+    ///
+    /// Writes one byte of UTF-8 per word, as described in the [section on
+    /// strings]. Returns the range of addresses that the string has been
+    /// written to, which [`Memory::read_str`] accepts.
+    ///
+    /// Returns an error, if the string doesn't fit into the memory at the
+    /// provided address. Nothing is written in that case.
+    ///
+    /// [section on strings]: #strings
+    pub fn write_str(
+        &mut self,
+        address: u32,
+        string: &str,
+    ) -> Result<Range<u32>, InvalidAddress> {
+        let Some(end) = u32::try_from(string.len())
+            .ok()
+            .and_then(|length| address.checked_add(length))
+        else {
+            return Err(InvalidAddress);
+        };
+        let (Ok(start_index), Ok(end_index)) =
+            (usize::try_from(address), usize::try_from(end))
+        else {
+            return Err(InvalidAddress);
+        };
+
+        let mut values = self.values_mut();
+        let Some(words) = values.get_mut(start_index..end_index) else {
+            return Err(InvalidAddress);
+        };
+
+        for (word, byte) in words.iter_mut().zip(string.bytes()) {
+            *word = Value::from(u32::from(byte));
+        }
+
+        Ok(address..end)
+    }
+
     /// # Access the memory as a slice of `i32` values
-    pub fn to_i32_slice(&self) -> &[i32] {
-        bytemuck::cast_slice(&self.values)
+    ///
+    /// Blocks writes to a shared memory, like [`Memory::values`].
+    pub fn to_i32_slice(&self) -> MemoryValues<'_, i32> {
+        self.values().cast()
     }
 
     /// # Access the memory as a slice of `u32` values
-    pub fn to_u32_slice(&self) -> &[u32] {
-        bytemuck::cast_slice(&self.values)
+    ///
+    /// Blocks writes to a shared memory, like [`Memory::values`].
+    pub fn to_u32_slice(&self) -> MemoryValues<'_, u32> {
+        self.values().cast()
     }
 }
 
 impl Default for Memory {
     fn default() -> Self {
-        Self {
-            values: vec![Value::from(0); 1024],
-        }
+        Self::new(Self::DEFAULT_SIZE)
     }
 }
 
@@ -91,7 +327,8 @@ impl fmt::Debug for Memory {
         // This is not perfect, but it's way more compact than the derived
         // implementation.
 
-        let mut values = self.values.iter().peekable();
+        let values = self.values();
+        let mut values = values.iter().peekable();
 
         write!(f, "[")?;
 
@@ -109,11 +346,240 @@ impl fmt::Debug for Memory {
     }
 }
 
+/// # Read access to the values in a [`Memory`]
+///
+/// Dereferences to a slice of the values, interpreted as `T`. Returned by
+/// [`Memory::values`], [`Memory::to_i32_slice`], and [`Memory::to_u32_slice`].
+pub struct MemoryValues<'r, T> {
+    guard: MemoryValuesGuard<'r>,
+    _type: PhantomData<T>,
+}
+
+impl<'r> MemoryValues<'r, Value> {
+    fn cast<T>(self) -> MemoryValues<'r, T> {
+        MemoryValues {
+            guard: self.guard,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<T> Deref for MemoryValues<'_, T>
+where
+    T: bytemuck::Pod,
+{
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        let values: &[Value] = match &self.guard {
+            MemoryValuesGuard::Owned { values } => values,
+            MemoryValuesGuard::Shared { values } => values,
+        };
+
+        bytemuck::cast_slice(values)
+    }
+}
+
+impl<T> fmt::Debug for MemoryValues<'_, T>
+where
+    T: bytemuck::Pod + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+enum MemoryValuesGuard<'r> {
+    Owned {
+        values: &'r [Value],
+    },
+    Shared {
+        values: RwLockReadGuard<'r, Box<[Value]>>,
+    },
+}
+
+/// # Write access to the values in a [`Memory`]
+///
+/// Dereferences to a mutable slice of the values. Returned by
+/// [`Memory::values_mut`].
+pub struct MemoryValuesMut<'r> {
+    guard: MemoryValuesMutGuard<'r>,
+}
+
+impl Deref for MemoryValuesMut<'_> {
+    type Target = [Value];
+
+    fn deref(&self) -> &Self::Target {
+        match &self.guard {
+            MemoryValuesMutGuard::Owned { values } => values,
+            MemoryValuesMutGuard::Shared { values } => values,
+        }
+    }
+}
+
+impl DerefMut for MemoryValuesMut<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match &mut self.guard {
+            MemoryValuesMutGuard::Owned { values } => values,
+            MemoryValuesMutGuard::Shared { values } => values,
+        }
+    }
+}
+
+impl fmt::Debug for MemoryValuesMut<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+enum MemoryValuesMutGuard<'r> {
+    Owned {
+        values: &'r mut [Value],
+    },
+    Shared {
+        values: RwLockWriteGuard<'r, Box<[Value]>>,
+    },
+}
+
+/// # Tried to access memory at an address that is out of bounds
+///
+/// See [`Memory::read`] and [`Memory::write`].
 #[derive(Debug)]
 pub struct InvalidAddress;
 
+impl fmt::Display for InvalidAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "memory address out of bounds")
+    }
+}
+
+impl std::error::Error for InvalidAddress {}
+
 impl From<InvalidAddress> for Effect {
     fn from(InvalidAddress: InvalidAddress) -> Self {
         Effect::InvalidAddress
     }
 }
+
+/// # Tried to read a string from memory that isn't stored there correctly
+///
+/// See [`Memory::read_str`].
+#[derive(Debug, Eq, PartialEq)]
+pub enum InvalidString {
+    /// # The range of addresses is not within the memory
+    OutOfBounds,
+
+    /// # A word within the range doesn't hold a byte
+    NotAByte {
+        /// # The address of the word
+        address: u32,
+    },
+
+    /// # The bytes are not valid UTF-8
+    NotUtf8,
+}
+
+impl fmt::Display for InvalidString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OutOfBounds => {
+                write!(f, "string is not within memory")
+            }
+            Self::NotAByte { address } => {
+                write!(f, "value at address `{address}` is not a byte")
+            }
+            Self::NotUtf8 => {
+                write!(f, "string is not valid UTF-8")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvalidString {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Memory, Value};
+
+    use super::{InvalidAddress, InvalidString};
+
+    #[test]
+    fn write_and_read_str() {
+        let mut memory = Memory::new(8);
+
+        let Ok(range) = memory.write_str(1, "ÿes") else {
+            unreachable!("String fits into memory.");
+        };
+        assert_eq!(range, 1..5);
+        assert_eq!(
+            *memory.to_u32_slice(),
+            [0, 0xc3, 0xbf, b'e'.into(), b's'.into(), 0, 0, 0]
+        );
+        assert_eq!(memory.read_str(range).as_deref(), Ok("ÿes"));
+
+        // If the string doesn't fit, nothing is written.
+        assert!(memory.write_str(6, "abc").is_err());
+        assert_eq!(memory.to_u32_slice()[6..], [0, 0]);
+    }
+
+    #[test]
+    fn read_str_rejects_invalid_strings() -> Result<(), InvalidAddress> {
+        let mut memory = Memory::new(4);
+        memory.write(1, Value::from(256))?;
+        memory.write(2, Value::from(0xff))?;
+
+        assert_eq!(memory.read_str(3..5), Err(InvalidString::OutOfBounds));
+        assert_eq!(
+            memory.read_str(0..2),
+            Err(InvalidString::NotAByte { address: 1 }),
+        );
+        assert_eq!(memory.read_str(2..3), Err(InvalidString::NotUtf8));
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_length_prefixed() {
+        let mut memory = Memory::new(5);
+
+        let values = [3, 5].map(Value::from);
+        assert!(memory.write_length_prefixed(1, &values).is_ok());
+        assert_eq!(*memory.to_u32_slice(), [0, 2, 3, 5, 0]);
+
+        // If the values don't fit, nothing is written.
+        let values = [8, 13].map(Value::from);
+        assert!(memory.write_length_prefixed(3, &values).is_err());
+        assert_eq!(*memory.to_u32_slice(), [0, 2, 3, 5, 0]);
+    }
+
+    #[test]
+    fn clones_share_values_until_written() -> Result<(), InvalidAddress> {
+        let mut a = Memory::new(3);
+        a.write(0, Value::from(1))?;
+
+        let mut b = a.clone();
+        assert_eq!(a.values().as_ptr(), b.values().as_ptr());
+
+        b.write(1, Value::from(2))?;
+        assert_ne!(a.values().as_ptr(), b.values().as_ptr());
+        assert_eq!(*a.to_u32_slice(), [1, 0, 0]);
+        assert_eq!(*b.to_u32_slice(), [1, 2, 0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn shared_memory_is_written_through_all_clones()
+    -> Result<(), InvalidAddress> {
+        let mut a = Memory::new(3).into_shared();
+        let mut b = a.clone();
+
+        a.write(0, Value::from(1))?;
+        b.write(1, Value::from(2))?;
+
+        assert_eq!(*a.to_u32_slice(), [1, 2, 0]);
+        assert_eq!(*b.to_u32_slice(), [1, 2, 0]);
+
+        Ok(())
+    }
+}
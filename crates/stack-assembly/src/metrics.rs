@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+/// # Measures how fast the evaluation is
+///
+/// Measuring is opt-in. Call [`Eval::enable_metrics`] to start, then access
+/// the measurements via [`Eval::metrics`]. This is meant for evaluating changes
+/// to the evaluation itself, like superinstructions (see
+/// [`CompileOptions::optimize`]) or the `Jit`, with numbers.
+///
+/// Operators are counted however they are evaluated, but time is only
+/// measured within [`Eval::run`] (or `Jit::run`). For the throughput to be
+/// accurate, use those to advance the evaluation.
+///
+/// [`Eval::enable_metrics`]: crate::Eval::enable_metrics
+/// [`Eval::metrics`]: crate::Eval::metrics
+/// [`Eval::run`]: crate::Eval::run
+/// [`CompileOptions::optimize`]: crate::CompileOptions::optimize
+///
+/// ## Example
+///
+/// ```
+/// use stack_assembly::{Eval, Script};
+///
+/// let script = Script::compile("
+///     100000
+///
+///     loop:
+///         1 -
+///         0 copy @loop jump_if
+/// ");
+///
+/// let mut eval = Eval::new();
+/// eval.enable_metrics();
+/// eval.run(&script);
+///
+/// let Some(metrics) = eval.metrics() else {
+///     unreachable!("Metrics have been enabled.");
+/// };
+/// assert_eq!(metrics.operators(), 1 + 100000 * 6);
+///
+/// println!("{:.0} operators/s", metrics.operators_per_second());
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Metrics {
+    operators: u64,
+    time_in_run: Duration,
+}
+
+impl Metrics {
+    /// # The number of operators that have been evaluated
+    pub fn operators(&self) -> u64 {
+        self.operators
+    }
+
+    /// # The time that has been spent inside of `run`
+    pub fn time_in_run(&self) -> Duration {
+        self.time_in_run
+    }
+
+    /// # The number of operators evaluated per second spent inside of `run`
+    ///
+    /// Returns `0.0`, if no time has been spent inside of `run` yet.
+    pub fn operators_per_second(&self) -> f64 {
+        let seconds = self.time_in_run.as_secs_f64();
+
+        if seconds > 0.0 {
+            self.operators as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    pub(crate) fn count_operators(&mut self, num_operators: u64) {
+        self.operators += num_operators;
+    }
+
+    pub(crate) fn add_time_since(&mut self, start: Instant) {
+        self.time_in_run += start.elapsed();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompileOptions, Eval, Script};
+
+    #[test]
+    fn superinstructions_count_as_multiple_operators() {
+        let options = CompileOptions {
+            optimize: true,
+            ..CompileOptions::default()
+        };
+        let script =
+            Script::compile_with_options("0 3 write 1 2 + 0 copy", options);
+
+        let mut eval = Eval::new();
+        eval.enable_metrics();
+        eval.run(&script);
+
+        let Some(metrics) = eval.metrics() else {
+            unreachable!("Metrics have been enabled.");
+        };
+        assert_eq!(metrics.operators(), 8);
+    }
+
+    #[test]
+    fn stepping_counts_operators_but_not_time() {
+        let script = Script::compile("1 2 3");
+
+        let mut eval = Eval::new();
+        eval.enable_metrics();
+        eval.step(&script);
+        eval.step(&script);
+
+        let Some(metrics) = eval.metrics() else {
+            unreachable!("Metrics have been enabled.");
+        };
+        assert_eq!(metrics.operators(), 2);
+        assert!(metrics.time_in_run().is_zero());
+        assert_eq!(metrics.operators_per_second(), 0.0);
+    }
+}
@@ -0,0 +1,85 @@
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use crate::{Effect, Memory, OperandStack};
+
+/// # An operator that the host implements in Rust
+///
+/// See [`Eval::register_native`].
+///
+/// [`Eval::register_native`]: crate::Eval::register_native
+pub(crate) type Native =
+    dyn Fn(&mut OperandStack, &mut Memory) -> Result<(), Effect> + Send + Sync;
+
+/// # The native operators that a host has registered with an evaluation
+///
+/// Cloning this shares the operators between the clones.
+#[derive(Clone, Default)]
+pub(crate) struct Natives {
+    inner: BTreeMap<String, Arc<Native>>,
+}
+
+impl Natives {
+    pub(crate) fn insert(&mut self, name: String, native: Arc<Native>) {
+        self.inner.insert(name, native);
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<Arc<Native>> {
+        self.inner.get(name).cloned()
+    }
+}
+
+impl fmt::Debug for Natives {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.inner.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Effect, Eval, Script, Value};
+
+    #[test]
+    fn native_operator_is_evaluated_like_a_built_in_one() {
+        let script = Script::compile("3 4 hypot_squared 7 double_write");
+
+        let mut eval = Eval::new();
+        eval.register_native("hypot_squared", |stack, _| {
+            let b = stack.pop()?.to_i32();
+            let a = stack.pop()?.to_i32();
+            stack.push(a * a + b * b);
+            Ok(())
+        });
+        eval.register_native("double_write", |stack, memory| {
+            let address = stack.pop()?.to_u32();
+            let value = stack.pop()?.to_i32();
+            memory.write(address, Value::from(value * 2))?;
+            Ok(())
+        });
+
+        let (effect, _) = eval.run(&script);
+
+        assert_eq!(effect, Effect::OutOfOperators);
+        assert_eq!(eval.operand_stack.to_i32_slice(), &[]);
+
+        let Ok(value) = eval.memory.read(7) else {
+            unreachable!("Address is within the bounds of the memory.");
+        };
+        assert_eq!(value.to_i32(), 50);
+    }
+
+    #[test]
+    fn native_operator_can_trigger_effect() {
+        let script = Script::compile("1 fails 2 unknown");
+
+        let mut eval = Eval::new();
+        eval.register_native("fails", |_, _| Err(Effect::AssertionFailed));
+
+        let (effect, operator) = eval.run(&script);
+        assert_eq!(effect, Effect::AssertionFailed);
+        assert_eq!(operator.value(), 1);
+
+        let (effect, operator) = eval.resume(&script);
+        assert_eq!(effect, Effect::UnknownIdentifier);
+        assert_eq!(operator.value(), 3);
+    }
+}
@@ -0,0 +1,186 @@
+/// # A built-in operator
+///
+/// Identifiers that refer to built-in operators are resolved to an opcode when
+/// the script is compiled. This means the evaluation doesn't need to compare
+/// strings to figure out which operator to evaluate.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Opcode {
+    Multiply,
+    Add,
+    Subtract,
+    Divide,
+    DivFloor,
+    ModFloor,
+    DivmodU,
+    Less,
+    LessOrEqual,
+    Equal,
+    Greater,
+    GreaterOrEqual,
+    And,
+    Or,
+    Xor,
+    CountOnes,
+    LeadingZeros,
+    TrailingZeros,
+    RotateLeft,
+    RotateRight,
+    ShiftLeft,
+    ShiftRight,
+    Copy,
+    Drop,
+    ReverseN,
+    Jump,
+    JumpIf,
+    JumpIfEqual,
+    JumpIfNotEqual,
+    JumpIfLess,
+    JumpIfLessOrEqual,
+    JumpIfGreater,
+    JumpIfGreaterOrEqual,
+    Call,
+    CallEither,
+    Return,
+    Pc,
+    Locals,
+    LocalGet,
+    LocalSet,
+    Assert,
+    Unreachable,
+    Todo,
+    Yield,
+    Read,
+    Write,
+    Spawn,
+    Resume,
+    Current,
+    Send,
+    Receive,
+    Exec,
+}
+
+impl Opcode {
+    /// # Resolve an identifier to the built-in operator it refers to
+    ///
+    /// Returns `None`, if the identifier does not refer to a built-in
+    /// operator.
+    pub fn from_identifier(identifier: &str) -> Option<Self> {
+        let opcode = match identifier {
+            "*" => Self::Multiply,
+            "+" => Self::Add,
+            "-" => Self::Subtract,
+            "/" => Self::Divide,
+            "div_floor" => Self::DivFloor,
+            "mod_floor" => Self::ModFloor,
+            "divmod_u" => Self::DivmodU,
+            "<" => Self::Less,
+            "<=" => Self::LessOrEqual,
+            "=" => Self::Equal,
+            ">" => Self::Greater,
+            ">=" => Self::GreaterOrEqual,
+            "and" => Self::And,
+            "or" => Self::Or,
+            "xor" => Self::Xor,
+            "count_ones" => Self::CountOnes,
+            "leading_zeros" => Self::LeadingZeros,
+            "trailing_zeros" => Self::TrailingZeros,
+            "rotate_left" => Self::RotateLeft,
+            "rotate_right" => Self::RotateRight,
+            "shift_left" => Self::ShiftLeft,
+            "shift_right" => Self::ShiftRight,
+            "copy" => Self::Copy,
+            "drop" => Self::Drop,
+            "reverse_n" => Self::ReverseN,
+            "jump" => Self::Jump,
+            "jump_if" => Self::JumpIf,
+            "jump_if_eq" => Self::JumpIfEqual,
+            "jump_if_ne" => Self::JumpIfNotEqual,
+            "jump_if_lt" => Self::JumpIfLess,
+            "jump_if_le" => Self::JumpIfLessOrEqual,
+            "jump_if_gt" => Self::JumpIfGreater,
+            "jump_if_ge" => Self::JumpIfGreaterOrEqual,
+            "call" => Self::Call,
+            "call_either" => Self::CallEither,
+            "return" => Self::Return,
+            "pc" => Self::Pc,
+            "locals" => Self::Locals,
+            "local_get" => Self::LocalGet,
+            "local_set" => Self::LocalSet,
+            "assert" => Self::Assert,
+            "unreachable" => Self::Unreachable,
+            "todo" => Self::Todo,
+            "yield" => Self::Yield,
+            "read" => Self::Read,
+            "write" => Self::Write,
+            "spawn" => Self::Spawn,
+            "resume" => Self::Resume,
+            "current" => Self::Current,
+            "send" => Self::Send,
+            "receive" => Self::Receive,
+            "exec" => Self::Exec,
+            _ => return None,
+        };
+
+        Some(opcode)
+    }
+
+    /// # The identifier that refers to the built-in operator
+    ///
+    /// This is the inverse of [`Opcode::from_identifier`].
+    pub fn identifier(&self) -> &'static str {
+        match self {
+            Self::Multiply => "*",
+            Self::Add => "+",
+            Self::Subtract => "-",
+            Self::Divide => "/",
+            Self::DivFloor => "div_floor",
+            Self::ModFloor => "mod_floor",
+            Self::DivmodU => "divmod_u",
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Equal => "=",
+            Self::Greater => ">",
+            Self::GreaterOrEqual => ">=",
+            Self::And => "and",
+            Self::Or => "or",
+            Self::Xor => "xor",
+            Self::CountOnes => "count_ones",
+            Self::LeadingZeros => "leading_zeros",
+            Self::TrailingZeros => "trailing_zeros",
+            Self::RotateLeft => "rotate_left",
+            Self::RotateRight => "rotate_right",
+            Self::ShiftLeft => "shift_left",
+            Self::ShiftRight => "shift_right",
+            Self::Copy => "copy",
+            Self::Drop => "drop",
+            Self::ReverseN => "reverse_n",
+            Self::Jump => "jump",
+            Self::JumpIf => "jump_if",
+            Self::JumpIfEqual => "jump_if_eq",
+            Self::JumpIfNotEqual => "jump_if_ne",
+            Self::JumpIfLess => "jump_if_lt",
+            Self::JumpIfLessOrEqual => "jump_if_le",
+            Self::JumpIfGreater => "jump_if_gt",
+            Self::JumpIfGreaterOrEqual => "jump_if_ge",
+            Self::Call => "call",
+            Self::CallEither => "call_either",
+            Self::Return => "return",
+            Self::Pc => "pc",
+            Self::Locals => "locals",
+            Self::LocalGet => "local_get",
+            Self::LocalSet => "local_set",
+            Self::Assert => "assert",
+            Self::Unreachable => "unreachable",
+            Self::Todo => "todo",
+            Self::Yield => "yield",
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Spawn => "spawn",
+            Self::Resume => "resume",
+            Self::Current => "current",
+            Self::Send => "send",
+            Self::Receive => "receive",
+            Self::Exec => "exec",
+        }
+    }
+}
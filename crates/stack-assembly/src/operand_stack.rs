@@ -1,3 +1,5 @@
+use std::fmt;
+
 use crate::{Effect, Value};
 
 /// # The operand stack
@@ -9,15 +11,46 @@ use crate::{Effect, Value};
 /// script and host. Please refer to [`Eval`]'s [`operand_stack`] field for more
 /// information on that.
 ///
+/// ## Allocation
+///
+/// The stack grows as needed, which requires allocation. If you want to bound
+/// or predict that, you can construct a stack with a sufficient capacity using
+/// [`OperandStack::with_capacity`], and assign it to [`Eval`]'s
+/// [`operand_stack`] field. Or reserve capacity in an existing stack, using
+/// [`OperandStack::reserve`]. Pushing a value does not allocate, as long as
+/// the stack has capacity left.
+///
 /// [`Eval`]: crate::Eval
 /// [`operand_stack`]: struct.Eval.html#structfield.operand_stack
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct OperandStack {
     /// # The values on the stack
     pub values: Vec<Value>,
 }
 
 impl OperandStack {
+    /// # Create an empty stack with the provided capacity
+    ///
+    /// The stack can hold `capacity` values, before it needs to allocate.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            values: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// # The number of values the stack can hold without allocating
+    pub fn capacity(&self) -> usize {
+        self.values.capacity()
+    }
+
+    /// # Reserve capacity for at least `additional` more values
+    ///
+    /// After this call, the stack can hold at least `additional` more values
+    /// than it currently does, before it needs to allocate again.
+    pub fn reserve(&mut self, additional: usize) {
+        self.values.reserve(additional);
+    }
+
     /// # Push a value to top of the stack
     pub fn push(&mut self, value: impl Into<Value>) {
         self.values.push(value.into());
@@ -48,6 +81,14 @@ impl OperandStack {
 #[derive(Debug)]
 pub struct OperandStackUnderflow;
 
+impl fmt::Display for OperandStackUnderflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "tried to pop a value from an empty operand stack")
+    }
+}
+
+impl std::error::Error for OperandStackUnderflow {}
+
 impl From<OperandStackUnderflow> for Effect {
     fn from(OperandStackUnderflow: OperandStackUnderflow) -> Self {
         Effect::OperandStackUnderflow
@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+
+use crate::{Eval, OperatorIndex, Tracer};
+
+/// # Counts how many times each operator has been evaluated
+///
+/// Profiling is opt-in. Call [`Eval::enable_profiling`] to start recording a
+/// profile, then access it via [`Eval::profile`].
+///
+/// You can use [`Script::map_operator_to_source`] to find out which part of
+/// the source code each operator corresponds to.
+///
+/// [`Eval::enable_profiling`]: crate::Eval::enable_profiling
+/// [`Eval::profile`]: crate::Eval::profile
+/// [`Script::map_operator_to_source`]: crate::Script::map_operator_to_source
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    counts: BTreeMap<OperatorIndex, u64>,
+}
+
+impl Profile {
+    /// # Access the number of times the provided operator has been evaluated
+    pub fn count(&self, operator: OperatorIndex) -> u64 {
+        self.counts.get(&operator).copied().unwrap_or(0)
+    }
+
+    /// # Iterate over all operators that have been evaluated at least once
+    ///
+    /// Yields each operator alongside the number of times it has been
+    /// evaluated, ordered by operator index.
+    pub fn counts(&self) -> impl Iterator<Item = (OperatorIndex, u64)> {
+        self.counts
+            .iter()
+            .map(|(&operator, &count)| (operator, count))
+    }
+
+    /// # Iterate over the evaluated operators, starting with the most frequent
+    ///
+    /// Operators that have been evaluated the same number of times are ordered
+    /// by operator index.
+    pub fn hottest(&self) -> impl Iterator<Item = (OperatorIndex, u64)> {
+        let mut counts = self.counts().collect::<Vec<_>>();
+        counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+        counts.into_iter()
+    }
+}
+
+impl Tracer for Profile {
+    fn before_step(&mut self, operator: OperatorIndex, _: &Eval) {
+        *self.counts.entry(operator).or_default() += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Eval, OperatorIndex, Script};
+
+    #[test]
+    fn profile_counts_evaluated_operators() {
+        let script = Script::compile(
+            "
+            0
+            loop:
+                1 +
+                0 copy 3 <
+                @loop jump_if
+            ",
+        );
+
+        let mut eval = Eval::new();
+        eval.enable_profiling();
+        eval.run(&script);
+
+        let Some(profile) = eval.profile() else {
+            unreachable!("Profiling has been enabled.");
+        };
+
+        let counts = profile
+            .counts()
+            .map(|(operator, count)| (operator.value(), count))
+            .collect::<Vec<_>>();
+        let mut expected = vec![(0, 1)];
+        expected.extend((1..=8).map(|operator| (operator, 3)));
+        assert_eq!(counts, expected);
+
+        assert_eq!(profile.count(OperatorIndex::new(0)), 1);
+        assert_eq!(profile.hottest().next(), Some((OperatorIndex::new(1), 3)));
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{
+    Effect, Eval, OperatorIndex, Script,
+    shadow::{Label, Shadow, num_targets},
+};
+
+/// # Where a value came from
+///
+/// See [`Provenance`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Tag {
+    /// # The value was written into the script as an integer
+    ///
+    /// This includes integers in `.data` directives, as well as memory that
+    /// hasn't been written to.
+    Literal,
+
+    /// # The value was produced by a reference to a label, or by `pc`
+    Reference,
+
+    /// # The value was computed, or came from somewhere that isn't tracked
+    Computed,
+}
+
+/// # Records where each value on the operand stack and in memory came from
+///
+/// This is an opt-in research mode, for experimenting with a stricter
+/// separation of code and data. Call [`Eval::enable_provenance`] to enable it,
+/// then access the tags via [`Eval::provenance`].
+///
+/// Each value carries a [`Tag`]. Jumps, `call`, and `call_either` only accept
+/// a target tagged [`Tag::Reference`], and trigger
+/// [`Effect::NonReferenceTarget`] otherwise. That finds any transfer of control
+/// to a computed target, like an offset into a jump table.
+///
+/// Tags propagate like this:
+///
+/// - Integers produce [`Tag::Literal`]; references and `pc` produce
+///   [`Tag::Reference`].
+/// - `copy`, `drop`, and `reverse_n` move the tags along with the values.
+/// - `write` stores the tag of its value, and `read` restores it. References
+///   in `.data` directives are tagged [`Tag::Reference`], if provenance is
+///   enabled before calling [`Eval::load_data`].
+/// - All other operators produce [`Tag::Computed`]. That includes values that
+///   pass through local variables, channels, native operators, or `exec`.
+///
+/// Values that the host adds to the operand stack are tagged
+/// [`Tag::Computed`]. Other changes that the host makes to the operand stack or
+/// memory are not tracked, and neither does [`Eval::step_back`] restore any
+/// tags.
+///
+/// [`Eval::enable_provenance`]: crate::Eval::enable_provenance
+/// [`Eval::provenance`]: crate::Eval::provenance
+/// [`Eval::load_data`]: crate::Eval::load_data
+/// [`Eval::step_back`]: crate::Eval::step_back
+#[derive(Clone, Debug, Default)]
+pub struct Provenance {
+    shadow: Shadow<Tag>,
+}
+
+impl Provenance {
+    /// # Access the tags of the values on a strand's operand stack
+    ///
+    /// The last tag belongs to the value on top of the stack. The tags reflect
+    /// the operand stack, as of the most recently evaluated operator.
+    pub fn operand_stack(&self, strand: u32) -> &[Tag] {
+        self.shadow.operand_stack(strand)
+    }
+
+    /// # Access the tag of the value at the provided memory address
+    pub fn memory(&self, address: u32) -> Tag {
+        self.shadow.memory(address)
+    }
+
+    /// # Record the tag of a value that has been written to memory
+    pub(crate) fn tag_memory(&mut self, address: u32, tag: Tag) {
+        self.shadow.label_memory(address, tag);
+    }
+
+    /// # Evaluate an operator, while keeping track of the tags
+    ///
+    /// Checks the targets of jumps and calls before evaluating them.
+    pub(crate) fn evaluate(
+        &mut self,
+        index: OperatorIndex,
+        script: &Script,
+        eval: &mut Eval,
+        evaluate: impl FnOnce(&mut Eval) -> Result<(), Effect>,
+    ) -> Result<(), Effect> {
+        let check = |opcode, tags: &[Tag], _: &[_]| {
+            // If there are too few values, the evaluation triggers an effect
+            // for that.
+            if let Some(targets) = tags.len().checked_sub(num_targets(opcode))
+                && tags[targets..].iter().any(|&tag| tag != Tag::Reference)
+            {
+                return Err(Effect::NonReferenceTarget);
+            }
+
+            Ok(())
+        };
+
+        self.shadow.evaluate(index, script, eval, check, evaluate)
+    }
+}
+
+impl Label for Tag {
+    const LITERAL: Self = Self::Literal;
+    const REFERENCE: Self = Self::Reference;
+    const UNKNOWN: Self = Self::Computed;
+
+    fn computed(_: &[Self]) -> Self {
+        Self::Computed
+    }
+
+    fn read(value: Self, _: Self) -> Self {
+        value
+    }
+}
@@ -0,0 +1,148 @@
+//! # Analysis of which operators the evaluation can reach
+//!
+//! See [`Script::reachable_operators`].
+
+use crate::{OperatorIndex, Script, opcode::Opcode, script::Operator};
+
+impl Script {
+    /// # Iterate over the operators that the evaluation can reach
+    ///
+    /// The evaluation can reach an operator, if it is the one the evaluation
+    /// starts at (see [`Script::start`]), if a reachable reference resolves to
+    /// it, or if it follows a reachable operator other than `jump` or
+    /// `return`. The operators that labels whose name starts with `test_`
+    /// refer to are reachable too, as they are the entry points of tests (see
+    /// [`Script::tests`]). So are the operators that references in the values
+    /// of regions resolve to, as the script can read those from memory and
+    /// jump to them.
+    ///
+    /// This can't take into account addresses that a script computes, or that
+    /// the host starts an evaluation at (see [`Eval::start_at`]). Any operator
+    /// that can only be reached that way, is not considered reachable.
+    ///
+    /// Yields the operators ordered by index. [`Script::lint`] uses this
+    /// analysis to warn about unreachable code. Compiling with the
+    /// `strip_unreachable` field of [`CompileOptions`] set uses it to remove
+    /// that code.
+    ///
+    /// [`Eval::start_at`]: crate::Eval::start_at
+    /// [`CompileOptions`]: crate::CompileOptions
+    pub fn reachable_operators(&self) -> impl Iterator<Item = OperatorIndex> {
+        self.operators()
+            .zip(find_reachable(self))
+            .filter(|(_, is_reachable)| *is_reachable)
+            .map(|((index, _), _)| index)
+    }
+}
+
+/// # Determine for each operator, whether the evaluation can reach it
+///
+/// See [`Script::reachable_operators`].
+pub(crate) fn find_reachable(script: &Script) -> Vec<bool> {
+    let operators = script.operators().collect::<Vec<_>>();
+    let mut reachable = vec![false; operators.len()];
+
+    let mut entry_points = vec![script.start()];
+    entry_points.extend(script.tests().map(|(_, operator)| operator));
+    entry_points
+        .extend(script.data_references().filter_map(|(_, target)| target));
+
+    while let Some(entry_point) = entry_points.pop() {
+        // Starting at the entry point, follow the evaluation until it jumps
+        // away, or reaches an operator we already know about.
+        for &(index, operator) in &operators[entry_point.value() as usize..] {
+            let is_reachable = &mut reachable[index.value() as usize];
+            if *is_reachable {
+                break;
+            }
+            *is_reachable = true;
+
+            match operator {
+                Operator::Reference {
+                    name: _,
+                    target: Some(target),
+                } => {
+                    entry_points.push(*target);
+                }
+                Operator::End
+                | Operator::Opcode {
+                    opcode:
+                        Opcode::Jump
+                        | Opcode::Return
+                        | Opcode::Unreachable
+                        | Opcode::Todo,
+                } => {
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    reachable
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompileOptions, Eval, OperatorIndex, Script, Value};
+
+    #[test]
+    fn reachable_operators() {
+        let script = Script::compile(
+            "
+            @a jump
+                1 2
+            a:
+                @b call
+                return
+                3
+            b:
+                return
+            test_c:
+                return
+            ",
+        );
+
+        let reachable = script
+            .reachable_operators()
+            .map(|operator| operator.value())
+            .collect::<Vec<_>>();
+
+        assert_eq!(reachable, [0, 1, 4, 5, 6, 8, 9]);
+    }
+
+    #[test]
+    fn strip_unreachable_operators() {
+        let options = CompileOptions {
+            prelude: true,
+            strip_unreachable: true,
+            ..CompileOptions::default()
+        };
+        let source = "
+            @a jump
+                1 2
+            a:
+                -3 @abs call
+            ";
+        let script = Script::compile_with_options(source, options);
+
+        // Most of the prelude is gone, leaving only the routine that the
+        // script calls.
+        assert!(script.operators().count() < 20);
+        assert_eq!(
+            script.reachable_operators().count(),
+            script.operators().count(),
+        );
+
+        let mut eval = Eval::new();
+        eval.run(&script);
+        assert_eq!(eval.operand_stack.values, [Value::from(3)]);
+
+        // The source map refers to the remaining operators.
+        let Ok(range) = script.map_operator_to_source(&OperatorIndex::new(2))
+        else {
+            unreachable!("Operator is present in the source map.");
+        };
+        assert_eq!(&source[range], "-3");
+    }
+}
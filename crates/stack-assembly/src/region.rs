@@ -0,0 +1,260 @@
+use std::{fmt, ops::Deref};
+
+use crate::{InvalidAddress, Memory, MemoryValues, Script, Value};
+
+/// # A named range of addresses in memory
+///
+/// Host code that exchanges data with a script through memory can use this,
+/// instead of indexing raw addresses. A region can be defined by the host,
+/// using [`MemoryRegion::new`], or looked up from the regions that a script
+/// reserves, using [`MemoryRegion::from_script`].
+///
+/// A region can optionally name its words, using
+/// [`MemoryRegion::with_fields`], for regions that hold a record, like the
+/// header of a request. All accesses are checked against the bounds of the
+/// region, as well as those of the memory.
+///
+/// ## Example
+///
+/// ```
+/// use stack_assembly::{Eval, MemoryRegion, Script};
+///
+/// let script = Script::compile("
+///     .zero request 3
+///
+///     @request 1 + 5 write
+/// ");
+///
+/// let mut eval = Eval::new();
+/// eval.run(&script);
+///
+/// let Some(request) = MemoryRegion::from_script(&script, "request") else {
+///     unreachable!("The script reserves the region.");
+/// };
+/// let request = request.with_fields(["service", "address", "length"]);
+///
+/// let Ok(view) = request.view(&eval.memory) else {
+///     unreachable!("The region fits into the memory.");
+/// };
+/// assert_eq!(view.field("address"), Some(5));
+/// assert_eq!(&*view, &[0, 5, 0]);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MemoryRegion {
+    name: String,
+    start: u32,
+    len: u32,
+    fields: Vec<String>,
+}
+
+impl MemoryRegion {
+    /// # Define a region that starts at `start` and covers `len` words
+    pub fn new(name: impl Into<String>, start: u32, len: u32) -> Self {
+        Self {
+            name: name.into(),
+            start,
+            len,
+            fields: Vec::new(),
+        }
+    }
+
+    /// # Look up a region that the script reserves
+    ///
+    /// Returns `None`, if the script doesn't reserve a region of that name.
+    /// See [`Script::regions`].
+    pub fn from_script(script: &Script, name: &str) -> Option<Self> {
+        let (name, addresses) =
+            script.regions().find(|(region, _)| *region == name)?;
+
+        let len = addresses.end - addresses.start;
+        Some(Self::new(name, addresses.start, len))
+    }
+
+    /// # Name the words of the region, starting with the first one
+    ///
+    /// Fields beyond the end of the region can't be accessed.
+    pub fn with_fields(
+        mut self,
+        fields: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.fields = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// # Access the name of the region
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// # Access the address of the first word in the region
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    /// # Access the number of words in the region
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// # Determine whether the region covers no words
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// # Access the values in the region
+    ///
+    /// Returns an error, if the region is not within the memory. Like
+    /// [`Memory::values`], this blocks writes to a shared memory, for as long
+    /// as the returned view exists.
+    pub fn view<'r>(
+        &'r self,
+        memory: &'r Memory,
+    ) -> Result<RegionView<'r>, InvalidAddress> {
+        let values = memory.to_u32_slice();
+
+        let start = self.start as usize;
+        let end = start.checked_add(self.len as usize).ok_or(InvalidAddress)?;
+        if end > values.len() {
+            return Err(InvalidAddress);
+        }
+
+        Ok(RegionView {
+            region: self,
+            values,
+            start,
+            end,
+        })
+    }
+
+    /// # Write a value to the word at the provided offset within the region
+    ///
+    /// Returns an error, if the offset is not within the region, or the
+    /// region is not within the memory.
+    pub fn write(
+        &self,
+        memory: &mut Memory,
+        offset: u32,
+        value: impl Into<Value>,
+    ) -> Result<(), InvalidAddress> {
+        if offset >= self.len {
+            return Err(InvalidAddress);
+        }
+        let address = self.start.checked_add(offset).ok_or(InvalidAddress)?;
+
+        memory.write(address, value.into())
+    }
+
+    /// # Write a value to the field with the provided name
+    ///
+    /// Returns an error, if the region has no such field, or the field is not
+    /// within the memory.
+    pub fn write_field(
+        &self,
+        memory: &mut Memory,
+        field: &str,
+        value: impl Into<Value>,
+    ) -> Result<(), InvalidAddress> {
+        let offset = self.field_offset(field).ok_or(InvalidAddress)?;
+        self.write(memory, offset, value)
+    }
+
+    fn field_offset(&self, field: &str) -> Option<u32> {
+        let offset = self.fields.iter().position(|name| name == field)?;
+        u32::try_from(offset).ok()
+    }
+}
+
+impl fmt::Display for MemoryRegion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let end = u64::from(self.start) + u64::from(self.len);
+        write!(f, "`{}` ({}..{end})", self.name, self.start)
+    }
+}
+
+/// # Read access to the values in a [`MemoryRegion`]
+///
+/// Dereferences to a slice of the values in the region, interpreted as `u32`.
+/// Returned by [`MemoryRegion::view`].
+pub struct RegionView<'r> {
+    region: &'r MemoryRegion,
+    values: MemoryValues<'r, u32>,
+    start: usize,
+    end: usize,
+}
+
+impl RegionView<'_> {
+    /// # Access the values in the region as `i32`
+    pub fn to_i32_slice(&self) -> &[i32] {
+        bytemuck::cast_slice(self)
+    }
+
+    /// # Interpret each value in the region as a byte
+    ///
+    /// Returns `None`, if any value doesn't fit into a byte. See the [section
+    /// on strings] for this convention.
+    ///
+    /// [section on strings]: Memory#strings
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        self.iter().map(|&value| u8::try_from(value).ok()).collect()
+    }
+
+    /// # Read the field with the provided name
+    ///
+    /// Returns `None`, if the region has no such field. See
+    /// [`MemoryRegion::with_fields`].
+    pub fn field(&self, field: &str) -> Option<u32> {
+        let offset = self.region.field_offset(field)?;
+        self.get(offset as usize).copied()
+    }
+}
+
+impl Deref for RegionView<'_> {
+    type Target = [u32];
+
+    fn deref(&self) -> &Self::Target {
+        &self.values[self.start..self.end]
+    }
+}
+
+impl fmt::Debug for RegionView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Memory;
+
+    use super::MemoryRegion;
+
+    #[test]
+    fn accesses_are_checked_against_region() {
+        let mut memory = Memory::new(8);
+        let region = MemoryRegion::new("header", 6, 2).with_fields(["a", "b"]);
+
+        assert!(region.write_field(&mut memory, "b", 3).is_ok());
+        assert!(region.write(&mut memory, 2, 4).is_err());
+        assert!(region.write_field(&mut memory, "c", 5).is_err());
+
+        let Ok(view) = region.view(&memory) else {
+            unreachable!("Region fits into memory.");
+        };
+        assert_eq!(view.field("b"), Some(3));
+        assert_eq!(view.to_bytes(), Some(vec![0, 3]));
+
+        let region = MemoryRegion::new("beyond", 7, 2);
+        assert!(region.view(&memory).is_err());
+    }
+
+    #[test]
+    fn region_at_end_of_address_space_is_invalid() {
+        let mut memory = Memory::new(8);
+        let region = MemoryRegion::new("x", u32::MAX, 2);
+
+        // The end of this region doesn't fit into 32 bits. That must not
+        // overflow, even where `usize` has no more bits than that.
+        assert!(region.view(&memory).is_err());
+        assert!(region.write(&mut memory, 1, 1).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+use std::{fmt, ops::Range};
+
+use crate::{Effect, Eval, OperatorIndex, Script, Value};
+
+/// # Describes an effect that the evaluation has triggered
+///
+/// Create one using [`Eval::error_report`]. This bundles everything that a
+/// host would want to show to the user, when a script triggers an effect that
+/// signals an error. Its [`Display`] implementation formats it over multiple
+/// lines, each ending with a newline.
+///
+/// The report only refers to the source code by ranges, since [`Script`]
+/// doesn't keep the source text around. Use [`ErrorReport::with_source`] to
+/// include excerpts.
+///
+/// [`Display`]: fmt::Display
+///
+/// ## Example
+///
+/// ```
+/// use stack_assembly::{Effect, Eval, Script};
+///
+/// let source = "
+///     1 2 @check call
+///
+///     check:
+///         =
+///         assert
+/// ";
+/// let script = Script::compile(source);
+///
+/// let mut eval = Eval::new();
+/// eval.run(&script);
+///
+/// let Some(report) = eval.error_report(&script) else {
+///     unreachable!("The script triggers an effect.");
+/// };
+/// let report = report.with_source(source);
+///
+/// assert_eq!(report.effect, Effect::AssertionFailed);
+/// assert_eq!(report.location.routine.as_deref(), Some("check"));
+/// assert_eq!(report.call_stack.len(), 1);
+///
+/// println!("{report}");
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ErrorReport {
+    /// # The effect that has triggered
+    pub effect: Effect,
+
+    /// # The operator that triggered the effect
+    pub location: Location,
+
+    /// # The values on top of the operand stack
+    ///
+    /// Contains at most [`ErrorReport::MAX_OPERAND_STACK_VALUES`] values, with
+    /// the top value last.
+    pub operand_stack: Vec<Value>,
+
+    /// # The total number of values on the operand stack
+    pub operand_stack_len: usize,
+
+    /// # The calls that led to the operator that triggered the effect
+    ///
+    /// Starts with the most recent call.
+    pub call_stack: Vec<Location>,
+}
+
+impl ErrorReport {
+    /// # The number of values from the top of the operand stack to include
+    pub const MAX_OPERAND_STACK_VALUES: usize = 8;
+
+    /// # Add excerpts of the provided source code to all locations
+    ///
+    /// Expects the source code that the script was compiled from.
+    pub fn with_source(mut self, source: &str) -> Self {
+        for location in
+            [&mut self.location].into_iter().chain(&mut self.call_stack)
+        {
+            location.excerpt = location
+                .range
+                .clone()
+                .and_then(|range| Excerpt::new(source, range));
+        }
+
+        self
+    }
+}
+
+impl fmt::Display for ErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Script triggered effect: {}", self.effect)?;
+        writeln!(f, "    at {}", self.location)?;
+
+        write!(f, "Operand stack")?;
+        let num_omitted = self.operand_stack_len - self.operand_stack.len();
+        if num_omitted > 0 {
+            write!(f, " ({num_omitted} more values below)")?;
+        }
+        write!(f, ":")?;
+        for value in &self.operand_stack {
+            write!(f, " {value:?}")?;
+        }
+        writeln!(f)?;
+
+        if !self.call_stack.is_empty() {
+            writeln!(f, "Call stack:")?;
+
+            for location in &self.call_stack {
+                writeln!(f, "    {location}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// # The location of an operator, as part of an [`ErrorReport`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Location {
+    /// # The index of the operator
+    pub operator: OperatorIndex,
+
+    /// # The name of the routine that the operator is part of
+    ///
+    /// See [`Script::label_at`].
+    pub routine: Option<String>,
+
+    /// # The range of the operator in the source code
+    ///
+    /// This is `None`, if the operator is not part of the source code, for
+    /// example because the evaluation has reached the end of the script.
+    pub range: Option<Range<usize>>,
+
+    /// # An excerpt of the source code at the operator
+    ///
+    /// This is `None`, unless the source code has been provided using
+    /// [`ErrorReport::with_source`].
+    pub excerpt: Option<Excerpt>,
+}
+
+impl Location {
+    fn new(operator: OperatorIndex, script: &Script) -> Self {
+        Self {
+            operator,
+            routine: script
+                .label_at(operator)
+                .map(|(name, _)| name.to_string()),
+            range: script.map_operator_to_source(&operator).ok(),
+            excerpt: None,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "operator {}", self.operator)?;
+
+        if let Some(routine) = &self.routine {
+            write!(f, " in `{routine}:`")?;
+        }
+
+        match (&self.excerpt, &self.range) {
+            (Some(excerpt), _) => write!(f, " at {excerpt}"),
+            (None, Some(range)) => {
+                write!(f, " at {}..{}", range.start, range.end)
+            }
+            (None, None) => write!(f, " (end of script)"),
+        }
+    }
+}
+
+/// # An excerpt of the source code, as part of an [`ErrorReport`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Excerpt {
+    /// # The 1-based line that the excerpt starts on
+    pub line: u32,
+
+    /// # The 1-based column that the excerpt starts at
+    pub column: u32,
+
+    /// # The text of the excerpt
+    pub text: String,
+}
+
+impl Excerpt {
+    fn new(source: &str, range: Range<usize>) -> Option<Self> {
+        let text = source.get(range.clone())?.to_string();
+
+        let mut line = 1;
+        let mut column = 1;
+
+        for ch in source[..range.start].chars() {
+            if ch == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+
+        Some(Self { line, column, text })
+    }
+}
+
+impl fmt::Display for Excerpt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: `{}`", self.line, self.column, self.text)
+    }
+}
+
+impl Eval {
+    /// # Describe the effect that the evaluation has triggered
+    ///
+    /// Returns `None`, if no effect has triggered. See [`ErrorReport`].
+    pub fn error_report(&self, script: &Script) -> Option<ErrorReport> {
+        let (effect, operator) = self.effect()?;
+
+        let values = &self.operand_stack.values;
+        let num_shown = values.len().min(ErrorReport::MAX_OPERAND_STACK_VALUES);
+
+        Some(ErrorReport {
+            effect,
+            location: Location::new(operator, script),
+            operand_stack: values[values.len() - num_shown..].to_vec(),
+            operand_stack_len: values.len(),
+            call_stack: self
+                .call_stack
+                .call_sites()
+                .map(|operator| Location::new(operator, script))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Effect, Eval, OperatorIndex, Script};
+
+    #[test]
+    fn report_describes_effect() {
+        let source = "0 1 2 3 4 5 6 7 8 9\n@f call\nf: 0 assert";
+        let script = Script::compile(source);
+
+        let mut eval = Eval::new();
+        eval.run(&script);
+
+        let Some(report) = eval.error_report(&script) else {
+            unreachable!("Script triggers an effect.");
+        };
+        let report = report.with_source(source);
+
+        assert_eq!(report.effect, Effect::AssertionFailed);
+        assert_eq!(report.location.operator, OperatorIndex::new(13));
+        assert_eq!(report.operand_stack_len, 10);
+        assert_eq!(
+            report
+                .operand_stack
+                .iter()
+                .map(|value| value.to_i32())
+                .collect::<Vec<_>>(),
+            [2, 3, 4, 5, 6, 7, 8, 9],
+        );
+
+        assert_eq!(
+            report.to_string(),
+            "Script triggered effect: assertion failed\n    \
+            at operator 13 in `f:` at 3:6: `assert`\n\
+            Operand stack (2 more values below): 2 3 4 5 6 7 8 9\n\
+            Call stack:\n    \
+            operator 11 at 2:4: `call`\n",
+        );
+    }
+
+    #[test]
+    fn no_report_without_effect() {
+        let script = Script::compile("1 yield");
+
+        let mut eval = Eval::new();
+        eval.run(&script);
+        eval.clear_effect();
+
+        assert_eq!(eval.error_report(&script), None);
+    }
+}
@@ -1,6 +1,25 @@
-use std::{collections::BTreeMap, fmt, iter, ops::Range};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    iter::{self, Peekable},
+    ops::Range,
+};
 
-use crate::Effect;
+use crate::{
+    Effect, Value, Warning,
+    data::{
+        Datum, Layout, Region, is_directive, next_name,
+        resolve_region_references,
+    },
+    eval::Instruction,
+    fuse::{Superinstruction, fuse},
+    lex::{Token, TokenKind, lex, parse_integer},
+    opcode::Opcode,
+    reachability::find_reachable,
+    structured::{
+        Blocks, is_generated_label, is_keyword, parse_procedure_header,
+    },
+};
 
 /// # A compiled script
 ///
@@ -8,87 +27,738 @@ use crate::Effect;
 /// an instance of this struct, using [`Script::compile`]. Afterwards, you can
 /// evaluate the script using [`Eval`].
 ///
+/// ## Metadata
+///
+/// A script can provide information about itself to the host, using the
+/// `.meta` directive. The directive is followed by a key and a value, and
+/// extends to the end of the line (or to the start of a comment):
+///
+/// ```text
+/// .meta name Counter
+/// .meta memory 16
+/// ```
+///
+/// The key is the first word after `.meta`. The value is the rest of the line,
+/// with any surrounding whitespace removed. Which keys exist and what their
+/// values mean is up to the host. A host could use them to refuse running a
+/// script whose requirements it can't satisfy, for example.
+///
+/// Use [`Script::metadata`] and [`Script::metadata_value`] to access the
+/// entries.
+///
+/// ## Reserving memory
+///
+/// A script can reserve a region of memory using the `.zero` directive, which
+/// is followed by a name and the size of the region in words:
+///
+/// ```text
+/// .zero buffer 16
+///
+/// @buffer 3 + 1 write
+/// ```
+///
+/// The compiler places the regions in memory one after the other, starting at
+/// address `0`, in the order the script defines them. A reference to a region,
+/// like `@buffer`, compiles into its address. If a label has the same name as
+/// a region, the reference refers to the region.
+///
+/// To match a layout that a host expects, the `.align` directive moves the
+/// start of the next region to the next multiple of the provided number of
+/// words, leaving a gap if necessary:
+///
+/// ```text
+/// .zero flag 1
+/// .align 4
+/// .zero vector 4 # starts at address `4`
+/// ```
+///
+/// Memory starts out zeroed, so the directive only reserves the addresses.
+/// That way, buffers don't overlap by accident. Use [`Script::regions`] to
+/// find out how much memory a script needs. A host that reserves addresses
+/// for its own use, for example to pass arguments to the script, should use it
+/// to check that no region overlaps them.
+///
+/// The `.data` directive also reserves a region, but provides the values that
+/// its words start out with. They extend to the end of the line, and can be
+/// integers or references:
+///
+/// ```text
+/// .data handlers @on_key @on_tick
+///
+/// @handlers 1 + read call # calls `on_tick`
+/// ```
+///
+/// A reference to a label stores the index of the operator that the label
+/// refers to, so a script can `jump` to it, or `call` it, after reading it
+/// from memory. The host writes those values to memory, before the evaluation
+/// starts, using [`Eval::load_data`].
+///
+/// For the common case of a single word, the `.var` directive reserves a
+/// region of size `1`. It can be followed by the value that the word starts
+/// out with, which is zero otherwise:
+///
+/// ```text
+/// .var count
+/// .var step 2
+///
+/// @count  @count read @step read +  write
+/// ```
+///
+/// The `.string` directive reserves a region for a string, which is written
+/// in quotes. The first word of the region holds the length of the string in
+/// bytes, followed by one byte of UTF-8 per word, as described in the
+/// [section on strings] of [`Memory`]:
+///
+/// ```text
+/// .string greeting "Hello, world!\n"
+///
+/// @greeting 1 +  @greeting read  # address and length of the string
+/// ```
+///
+/// If the script defines the same string more than once, only the first
+/// definition reserves memory. The other names refer to the same addresses.
+///
+/// ## Entry point
+///
+/// By default, the evaluation starts with the first operator of the script.
+/// The `.start` directive, followed by the name of a label, selects a
+/// different one. That allows a script to define its routines before the code
+/// that uses them, without having to jump over them:
+///
+/// ```text
+/// .start main
+///
+/// square:
+///     0 copy *
+///     return
+///
+/// main:
+///     3 @square call
+/// ```
+///
+/// The host starts the evaluation there, using [`Eval::start_at`] with the
+/// index that [`Script::start`] returns.
+///
 /// [`Eval`]: crate::Eval
+/// [`Eval::load_data`]: crate::Eval::load_data
+/// [`Eval::start_at`]: crate::Eval::start_at
+/// [`Memory`]: crate::Memory
+/// [section on strings]: crate::Memory#strings
 #[derive(Debug)]
 pub struct Script {
     operators: Vec<Operator>,
+    instructions: Vec<Instruction>,
     labels: Vec<Label>,
+    labels_by_name: HashMap<String, OperatorIndex>,
+    procedures: Vec<Procedure>,
+    regions: Vec<Region>,
     source_map: BTreeMap<OperatorIndex, Range<usize>>,
+    metadata: Vec<(String, String)>,
+    start: Option<Start>,
+
+    /// # The length of the source text that the script was compiled from
+    ///
+    /// This includes any source text that has been appended since. See
+    /// [`Script::append`].
+    source_len: usize,
 }
 
 impl Script {
     /// # Compile the source text of a script into an instance of `Script`
+    ///
+    /// Uses the default [`CompileOptions`]. Use
+    /// [`Script::compile_with_options`], if you need to override those.
     pub fn compile(script: &str) -> Self {
-        let mut next_index = OperatorIndex::default();
+        Self::compile_with_options(script, CompileOptions::default())
+    }
+
+    /// # Compile a script, using the provided options
+    ///
+    /// Like [`Script::compile`], this never fails. Use [`Script::try_compile`],
+    /// if you want problems to be rejected instead.
+    pub fn compile_with_options(script: &str, options: CompileOptions) -> Self {
+        Self::assemble(script, options).finish(options)
+    }
 
+    /// # Compile a script in strict mode, rejecting any problems
+    ///
+    /// By default, compilation never fails. Tokens that are neither integers
+    /// nor labels, references, or built-in operators compile into identifiers
+    /// that trigger [`Effect::UnknownIdentifier`] when evaluated. That
+    /// includes integers that don't fit into 32 bits, and keywords of
+    /// structured control flow that don't match up. References to labels
+    /// that don't exist trigger [`Effect::InvalidReference`].
+    ///
+    /// In strict mode, each of those cases is a [`CompileError`] instead. So
+    /// is every warning that [`Script::lint`] would return. Otherwise, this
+    /// works just like [`Script::compile_with_options`].
+    pub fn try_compile(
+        script: &str,
+        options: CompileOptions,
+    ) -> Result<Self, CompileError> {
+        let script = Self::assemble(script, options);
+        script.check_strict()?;
+
+        Ok(script.finish(options))
+    }
+
+    /// # Compile a script, without checking it or transforming its operators
+    fn assemble(script: &str, options: CompileOptions) -> Self {
+        let script_len = script.len();
         let mut operators = Vec::new();
         let mut labels = Vec::new();
+        let mut procedures = Vec::new();
+        let mut layout = Layout::default();
         let mut source_map = BTreeMap::new();
+        let mut directives = Directives::default();
+
+        compile_source(
+            script,
+            &mut operators,
+            &mut labels,
+            &mut procedures,
+            &mut layout,
+            &mut source_map,
+            &mut directives,
+        );
+
+        if options.prelude {
+            let num_labels = labels.len();
 
-        enum State {
-            Initial,
-            Comment,
-            Token { start: usize },
+            // If the evaluation reaches the end of the script, it must not
+            // continue into the prelude.
+            operators.push(Operator::End);
+
+            // The prelude is not part of the source text that the caller
+            // provided. So we don't record its operators in the source map.
+            compile_source(
+                PRELUDE,
+                &mut operators,
+                &mut labels,
+                &mut procedures,
+                &mut layout,
+                &mut BTreeMap::new(),
+                &mut Directives::default(),
+            );
+
+            // Same goes for its labels. Their ranges refer to the prelude's
+            // source text, which would be confusing.
+            for label in &mut labels[num_labels..] {
+                label.range = None;
+            }
         }
-        let mut state = State::Initial;
 
-        for (i, ch) in script.char_indices() {
-            match (&state, ch) {
-                (State::Initial, '#') => {
-                    state = State::Comment;
-                }
-                (State::Initial, ch) if !ch.is_whitespace() => {
-                    state = State::Token { start: i };
+        resolve_region_references(&mut operators, &mut layout.regions);
+
+        let mut script = Self::from_parts(
+            operators,
+            labels,
+            procedures,
+            layout.regions,
+            source_map,
+            directives.metadata,
+            directives.start,
+        );
+
+        script.source_len = script_len;
+
+        script
+    }
+
+    /// # Apply the options that transform the operators of a compiled script
+    fn finish(mut self, options: CompileOptions) -> Self {
+        if options.strip_unreachable {
+            self.strip_unreachable();
+        }
+
+        if options.optimize {
+            fuse(&mut self.operators);
+        }
+
+        self.decode_instructions();
+
+        self
+    }
+
+    /// # Assemble a script from its parts, resolving its references
+    ///
+    /// The returned script has no instructions yet. Call
+    /// [`Script::decode_instructions`], once the operators are final.
+    pub(crate) fn from_parts(
+        operators: Vec<Operator>,
+        labels: Vec<Label>,
+        procedures: Vec<Procedure>,
+        regions: Vec<Region>,
+        source_map: BTreeMap<OperatorIndex, Range<usize>>,
+        metadata: Vec<(String, String)>,
+        start: Option<Start>,
+    ) -> Self {
+        // If multiple labels have the same name, the first one wins. This is
+        // also what allows the script to shadow labels from the prelude.
+        let mut labels_by_name = HashMap::new();
+        for label in &labels {
+            labels_by_name
+                .entry(label.name.clone())
+                .or_insert(label.operator);
+        }
+
+        let mut script = Self {
+            operators,
+            instructions: Vec::new(),
+            labels,
+            labels_by_name,
+            procedures,
+            regions,
+            source_map,
+            metadata,
+            start,
+            source_len: 0,
+        };
+        script.resolve_references();
+
+        script
+    }
+
+    /// # Compile additional source text, and add it to the end of the script
+    ///
+    /// Returns the range of the operators that the source text compiled into.
+    /// The new operators can refer to the labels and regions of the script,
+    /// and vice versa. References that didn't resolve before are resolved, if
+    /// the new source text defines their labels. If a label was already
+    /// defined, references keep referring to the original one. New regions
+    /// are placed in memory after the existing ones.
+    ///
+    /// This allows a long-lived evaluation to receive code over time, like in
+    /// a REPL. If the evaluation has triggered [`Effect::OutOfOperators`] at
+    /// the end of the script, it continues with the new operators, once the
+    /// effect has been cleared. That doesn't work, if the script was compiled
+    /// with [`CompileOptions::prelude`], since the new operators are placed
+    /// after it. And if the new source text has `.data` directives, the host
+    /// needs to write their values to memory (see [`Eval::load_data`]).
+    ///
+    /// The new source text is compiled with the default [`CompileOptions`].
+    /// The source map treats it as if it was appended to the source text that
+    /// the script was compiled from. So a host that concatenates all source
+    /// texts in the same way can keep using [`Script::map_operator_to_source`].
+    ///
+    /// ```
+    /// use stack_assembly::{Effect, Eval, Script};
+    ///
+    /// let mut script = Script::compile("1 2 +");
+    ///
+    /// let mut eval = Eval::new();
+    /// assert_eq!(eval.run(&script).0, Effect::OutOfOperators);
+    ///
+    /// script.append(" @triple call triple: 3 *");
+    /// assert_eq!(eval.resume(&script).0, Effect::OutOfOperators);
+    ///
+    /// assert_eq!(eval.operand_stack.to_i32_slice(), &[9]);
+    /// ```
+    ///
+    /// [`Eval::load_data`]: crate::Eval::load_data
+    pub fn append(&mut self, source: &str) -> Range<OperatorIndex> {
+        let start = next_index(&self.operators);
+        let num_labels = self.labels.len();
+        let num_regions = self.regions.len();
+        let had_start = self.start.is_some();
+        let mut directives = Directives {
+            metadata: std::mem::take(&mut self.metadata),
+            start: self.start.take(),
+        };
+
+        let mut layout = Layout::continuing(std::mem::take(&mut self.regions));
+        let mut source_map = BTreeMap::new();
+
+        compile_source(
+            source,
+            &mut self.operators,
+            &mut self.labels,
+            &mut self.procedures,
+            &mut layout,
+            &mut source_map,
+            &mut directives,
+        );
+
+        self.metadata = directives.metadata;
+        self.start = directives.start;
+
+        // The ranges refer to the new source text. Shift them, so they refer
+        // to it as part of all source text.
+        let offset = self.source_len;
+        let shift = |range: &mut Range<usize>| {
+            range.start += offset;
+            range.end += offset;
+        };
+
+        for (index, mut range) in source_map {
+            shift(&mut range);
+            self.source_map.insert(index, range);
+        }
+        for label in &mut self.labels[num_labels..] {
+            if let Some(range) = &mut label.range {
+                shift(range);
+            }
+        }
+        for datum in layout.regions[num_regions..]
+            .iter_mut()
+            .flat_map(|region| &mut region.values)
+        {
+            if let Datum::Reference {
+                name: _,
+                target: _,
+                range: Some(range),
+            } = datum
+            {
+                shift(range);
+            }
+        }
+        if let Some(Start {
+            name: _,
+            target: _,
+            range: Some(range),
+        }) = &mut self.start
+            && !had_start
+        {
+            shift(range);
+        }
+        self.source_len += source.len();
+
+        resolve_region_references(&mut self.operators, &mut layout.regions);
+        self.regions = layout.regions;
+
+        for label in &self.labels[num_labels..] {
+            self.labels_by_name
+                .entry(label.name.clone())
+                .or_insert(label.operator);
+        }
+        self.resolve_references();
+        self.decode_instructions();
+
+        start..next_index(&self.operators)
+    }
+
+    pub(crate) fn decode_instructions(&mut self) {
+        self.instructions =
+            self.operators.iter().map(Instruction::decode).collect();
+    }
+
+    /// # Remove all operators that the evaluation can't reach
+    ///
+    /// See [`CompileOptions::strip_unreachable`].
+    fn strip_unreachable(&mut self) {
+        let reachable = find_reachable(self);
+
+        // For each operator, the index it is going to have after stripping.
+        // If the operator is removed, that's the index of the next operator
+        // that remains, which is where any label that refers to it is going
+        // to point to.
+        let mut new_indices = Vec::with_capacity(reachable.len() + 1);
+        let mut next_index = OperatorIndex::default();
+
+        for &is_reachable in &reachable {
+            new_indices.push(next_index);
+
+            if is_reachable {
+                next_index.value += 1;
+            }
+        }
+
+        // A label at the end of the script refers to the index right after the
+        // last operator.
+        new_indices.push(next_index);
+
+        let new_index =
+            |index: OperatorIndex| new_indices[index.value as usize];
+
+        let operators = std::mem::take(&mut self.operators);
+        self.operators = operators
+            .into_iter()
+            .zip(&reachable)
+            .filter(|(_, is_reachable)| **is_reachable)
+            .map(|(mut operator, _)| {
+                if let Operator::Reference {
+                    name: _,
+                    target: Some(target),
+                } = &mut operator
+                {
+                    *target = new_index(*target);
                 }
-                (State::Initial, _) => {
-                    // Token won't start until we're past the whitespace.
+
+                operator
+            })
+            .collect();
+
+        for label in &mut self.labels {
+            label.operator = new_index(label.operator);
+        }
+        for operator in self.labels_by_name.values_mut() {
+            *operator = new_index(*operator);
+        }
+        for procedure in &mut self.procedures {
+            procedure.operator = new_index(procedure.operator);
+        }
+        for datum in self.regions.iter_mut().flat_map(|r| &mut r.values) {
+            if let Datum::Reference {
+                name: _,
+                target: Some(target),
+                range: _,
+            } = datum
+            {
+                *target = new_index(*target);
+            }
+        }
+        if let Some(Start {
+            name: _,
+            target: Some(target),
+            range: _,
+        }) = &mut self.start
+        {
+            *target = new_index(*target);
+        }
+
+        self.source_map = std::mem::take(&mut self.source_map)
+            .into_iter()
+            .filter(|(index, _)| reachable[index.value as usize])
+            .map(|(index, range)| (new_index(index), range))
+            .collect();
+    }
+
+    /// # Reject anything that a compilation in strict mode doesn't allow
+    ///
+    /// See [`Script::try_compile`].
+    fn check_strict(&self) -> Result<(), CompileError> {
+        match self.check().into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// # Find all problems that a compilation in strict mode rejects
+    ///
+    /// Returns every error that [`Script::try_compile`] would return, not just
+    /// the first one. This is useful for tools that report all
+    /// problems at once, like editors. Errors about specific operators come
+    /// first, in the order of those operators, followed by invalid references
+    /// in the values of regions and in the `.start` directive, followed by any
+    /// warnings (see [`Script::lint`]).
+    ///
+    /// Only the code that was compiled from the source text is checked, not
+    /// the prelude. Since strict mode rejects a script before unreachable
+    /// operators are stripped, calling this on a script compiled with
+    /// [`CompileOptions::strip_unreachable`] might not find all problems.
+    pub fn check(&self) -> Vec<CompileError> {
+        let mut errors = Vec::new();
+
+        for (index, operator) in self.operators() {
+            let Ok(range) = self.map_operator_to_source(&index) else {
+                // Only the operators that were compiled from the source text
+                // are checked, not the prelude.
+                continue;
+            };
+
+            match operator {
+                Operator::Identifier { value } if value == "proc" => {
+                    errors.push(CompileError::InvalidProcedure { range });
                 }
-                (State::Comment, '\n') => {
-                    state = State::Initial;
+                Operator::Identifier { value } if is_directive(value) => {
+                    errors.push(CompileError::InvalidDirective { range });
                 }
-                (State::Comment, _) => {
-                    // Ignoring characters in comments.
+                Operator::Identifier { value } if is_keyword(value) => {
+                    errors.push(CompileError::UnmatchedKeyword { range });
                 }
-                (State::Token { start }, ch) if ch.is_whitespace() => {
-                    parse_token(
-                        script,
-                        *start..i,
-                        &mut operators,
-                        &mut labels,
-                        &mut next_index,
-                        &mut source_map,
-                    );
-                    state = State::Initial;
+                Operator::Identifier { value } => {
+                    let digits = value.strip_prefix('-').unwrap_or(value);
+
+                    if digits.starts_with(|ch: char| ch.is_ascii_digit()) {
+                        errors.push(CompileError::IntegerOutOfRange { range });
+                    } else {
+                        errors.push(CompileError::UnknownIdentifier { range });
+                    }
                 }
-                (State::Token { start: _ }, _) => {
-                    // We already remembered the start of the token. Nothing
-                    // else to do until it's over.
+                Operator::Reference {
+                    name: _,
+                    target: None,
+                } => {
+                    errors.push(CompileError::InvalidReference { range });
                 }
+                _ => {}
             }
         }
 
-        if let State::Token { start } = state {
-            parse_token(
-                script,
-                start..script.len(),
-                &mut operators,
-                &mut labels,
-                &mut next_index,
-                &mut source_map,
-            );
+        for datum in self.regions.iter().flat_map(|region| &region.values) {
+            if let Datum::Reference {
+                name: _,
+                target: None,
+                range: Some(range),
+            } = datum
+            {
+                errors.push(CompileError::InvalidReference {
+                    range: range.clone(),
+                });
+            }
+        }
+        if let Some(Start {
+            name: _,
+            target: None,
+            range: Some(range),
+        }) = &self.start
+        {
+            errors.push(CompileError::InvalidReference {
+                range: range.clone(),
+            });
         }
 
-        Self {
-            operators,
-            labels,
-            source_map,
+        for warning in self.lint() {
+            let range = self.map_operator_to_source(&warning.operator()).ok();
+
+            errors.push(CompileError::Warning { warning, range });
         }
+
+        errors
     }
 
-    pub(crate) fn get_operator(
+    /// # Resolve all references to the operators they refer to
+    ///
+    /// This happens once, after compilation, so evaluating a reference doesn't
+    /// require looking up its label. References to labels that don't exist
+    /// remain unresolved, and trigger [`Effect::InvalidReference`] when
+    /// evaluated.
+    ///
+    /// The references in the values of regions, and the label of the `.start`
+    /// directive, are resolved too. Those that remain unresolved trigger the
+    /// effect when loading the values (see [`Eval::load_data`]).
+    ///
+    /// [`Eval::load_data`]: crate::Eval::load_data
+    fn resolve_references(&mut self) {
+        for i in 0..self.operators.len() {
+            let Operator::Reference { name, target: _ } = &self.operators[i]
+            else {
+                continue;
+            };
+
+            let resolved = self.resolve_reference(name);
+
+            if let Operator::Reference { name: _, target } =
+                &mut self.operators[i]
+            {
+                *target = resolved;
+            }
+        }
+
+        for datum in self.regions.iter_mut().flat_map(|r| &mut r.values) {
+            if let Datum::Reference {
+                name,
+                target,
+                range: _,
+            } = datum
+            {
+                *target = self.labels_by_name.get(name).copied();
+            }
+        }
+
+        if let Some(start) = &mut self.start {
+            start.target = self.labels_by_name.get(&start.name).copied();
+        }
+    }
+
+    /// # Iterate over the values that the script's memory starts out with
+    ///
+    /// Yields the address of each word that a `.data` directive provides a
+    /// value for, alongside that value. The value is `None`, if it's a
+    /// reference to a label that doesn't exist.
+    pub(crate) fn initial_values(
+        &self,
+    ) -> impl Iterator<Item = (u32, Option<Value>)> {
+        self.regions.iter().flat_map(|region| {
+            region
+                .addresses
+                .clone()
+                .zip(region.values.iter().map(|datum| match datum {
+                    Datum::Integer { value } => Some(Value::from(*value)),
+                    Datum::Reference {
+                        name: _,
+                        target,
+                        range: _,
+                    } => target.map(Value::from),
+                }))
+        })
+    }
+
+    /// # Iterate over the addresses that start out holding a reference
+    ///
+    /// These are the addresses of values provided by `.data` directives, that
+    /// are references to labels.
+    pub(crate) fn reference_addresses(&self) -> impl Iterator<Item = u32> {
+        self.regions.iter().flat_map(|region| {
+            region.addresses.clone().zip(&region.values).filter_map(
+                |(address, datum)| {
+                    matches!(datum, Datum::Reference { .. }).then_some(address)
+                },
+            )
+        })
+    }
+
+    /// # Iterate over the references in the values of regions
+    ///
+    /// Yields the name of the label that each reference refers to, alongside
+    /// the operator it resolves to, if any.
+    pub(crate) fn data_references(
+        &self,
+    ) -> impl Iterator<Item = (&str, Option<OperatorIndex>)> {
+        self.regions
+            .iter()
+            .flat_map(|region| &region.values)
+            .filter_map(|datum| match datum {
+                Datum::Integer { value: _ } => None,
+                Datum::Reference {
+                    name,
+                    target,
+                    range: _,
+                } => Some((name.as_str(), *target)),
+            })
+    }
+
+    /// # Access the regions, including the values they start out with
+    pub(crate) fn region_definitions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// # Access the operator at the provided index, if any
+    pub(crate) fn operator_at(
         &self,
         index: OperatorIndex,
-    ) -> Result<&Operator, InvalidOperatorIndex> {
+    ) -> Option<&Operator> {
+        self.operators.get(index.value as usize)
+    }
+
+    /// # Access the name of the identifier at the provided index, if any
+    pub(crate) fn identifier_at(&self, index: OperatorIndex) -> Option<&str> {
+        match self.operators.get(index.value as usize)? {
+            Operator::Identifier { value } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// # Access the built-in operator at the provided index, if any
+    ///
+    /// For a superinstruction, this is the operator that it replaced.
+    pub(crate) fn opcode_at(&self, index: OperatorIndex) -> Option<Opcode> {
+        match self.operators.get(index.value as usize)? {
+            Operator::Opcode { opcode } => Some(*opcode),
+            Operator::Fused { superinstruction } => {
+                match superinstruction.unfused() {
+                    Operator::Opcode { opcode } => Some(opcode),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn get_instruction(
+        &self,
+        index: OperatorIndex,
+    ) -> Result<&Instruction, InvalidOperatorIndex> {
         let Ok(index): Result<usize, _> = index.value.try_into() else {
             // We can at most store `usize::MAX` operators, so if we can't make
             // this conversion, then the index definitely doesn't point to an
@@ -96,24 +766,46 @@ impl Script {
             return Err(InvalidOperatorIndex);
         };
 
-        let Some(operator) = self.operators.get(index) else {
+        let Some(instruction) = self.instructions.get(index) else {
             return Err(InvalidOperatorIndex);
         };
 
-        Ok(operator)
+        Ok(instruction)
     }
 
-    pub(crate) fn resolve_reference(
-        &self,
-        name: &str,
-    ) -> Result<OperatorIndex, InvalidReference> {
-        let label = self.labels.iter().find(|label| label.name == name);
+    fn resolve_reference(&self, name: &str) -> Option<OperatorIndex> {
+        self.labels_by_name.get(name).copied()
+    }
 
-        let Some(&Label { name: _, operator }) = label else {
-            return Err(InvalidReference);
-        };
+    /// # Access the operator that the evaluation starts at
+    ///
+    /// This is the operator that the label of the script's `.start` directive
+    /// refers to, or the first operator, if the script has no such directive.
+    /// Pass it to [`Eval::start_at`] to start the evaluation there.
+    ///
+    /// If the label doesn't exist, this is the first operator too. But
+    /// [`Eval::load_data`] triggers [`Effect::InvalidReference`] in that case,
+    /// and [`Script::check`] reports it.
+    ///
+    /// [`Eval::start_at`]: crate::Eval::start_at
+    /// [`Eval::load_data`]: crate::Eval::load_data
+    pub fn start(&self) -> OperatorIndex {
+        self.start
+            .as_ref()
+            .and_then(|start| start.target)
+            .unwrap_or_default()
+    }
+
+    /// # Indicate whether the label of the `.start` directive doesn't exist
+    pub(crate) fn has_invalid_start(&self) -> bool {
+        self.start
+            .as_ref()
+            .is_some_and(|start| start.target.is_none())
+    }
 
-        Ok(operator)
+    /// # Access the name of the label that the `.start` directive refers to
+    pub(crate) fn start_label(&self) -> Option<&str> {
+        self.start.as_ref().map(|start| start.name.as_str())
     }
 
     /// # Map the operator identified by the provided index to the source code
@@ -135,6 +827,187 @@ impl Script {
         Ok(range)
     }
 
+    /// # Iterate over all tests defined in the script
+    ///
+    /// A test is a routine that is identified by a label whose name starts
+    /// with `test_`. To run a test, evaluate the script starting at the
+    /// operator that the label refers to (see [`Eval::start_at`]).
+    ///
+    /// The test has passed, if evaluating it triggers [`Effect::Return`]. This
+    /// happens, if the test routine ends with `return`, which is what a routine
+    /// that is meant to be called should do anyway. If the evaluation triggers
+    /// any other effect, like [`Effect::AssertionFailed`], the test has failed.
+    ///
+    /// Yields the name of each test, alongside the index of its first
+    /// operator, in the order the tests are defined in the script.
+    ///
+    /// [`Eval::start_at`]: crate::Eval::start_at
+    pub fn tests(&self) -> impl Iterator<Item = (&str, OperatorIndex)> {
+        self.labels
+            .iter()
+            .filter(|label| label.name.starts_with("test_"))
+            .map(|label| (label.name.as_str(), label.operator))
+    }
+
+    /// # Iterate over all labels defined in the script
+    ///
+    /// Yields the name of each label, alongside the index of the operator it
+    /// refers to, in the order the labels are defined in the script. If the
+    /// script has been compiled with the prelude, its labels come last.
+    ///
+    /// Multiple labels may have the same name. References resolve to the first
+    /// of those.
+    ///
+    /// This includes the labels that the compiler generates for structured
+    /// control flow, like `if` and `end`. Their names contain whitespace, so
+    /// they can't collide with labels defined in the source text.
+    pub fn labels(&self) -> impl Iterator<Item = (&str, OperatorIndex)> {
+        self.labels
+            .iter()
+            .map(|label| (label.name.as_str(), label.operator))
+    }
+
+    /// # Iterate over the labels defined in the source text
+    ///
+    /// Works like [`Script::labels`], but also yields the range of each label
+    /// in the source text, which includes the trailing `:`. Leaves out the
+    /// labels of the prelude, as they are not defined in the source text.
+    pub fn label_definitions(
+        &self,
+    ) -> impl Iterator<Item = (&str, OperatorIndex, Range<usize>)> {
+        self.labels.iter().filter_map(|label| {
+            let range = label.range.clone()?;
+            Some((label.name.as_str(), label.operator, range))
+        })
+    }
+
+    /// # Iterate over all references in the script
+    ///
+    /// Yields the name of the label that each reference refers to, alongside
+    /// the index of the reference's operator, in the order of those operators.
+    /// Use [`Script::map_operator_to_source`] to find a reference in the
+    /// source text.
+    pub fn references(&self) -> impl Iterator<Item = (&str, OperatorIndex)> {
+        self.operators()
+            .filter_map(|(index, operator)| match operator {
+                Operator::Reference { name, target: _ } => {
+                    Some((name.as_str(), index))
+                }
+                _ => None,
+            })
+    }
+
+    /// # Find the label that most closely precedes the provided operator
+    ///
+    /// Returns the label that refers to the provided operator, or to the
+    /// closest operator before it. If multiple labels refer to that operator,
+    /// returns the one defined last. Returns `None`, if there is no such
+    /// label.
+    ///
+    /// Labels that the compiler generates for structured control flow, like
+    /// `if` and `end`, are skipped. Only labels from the source text (or the
+    /// prelude) are useful for describing a location.
+    ///
+    /// This is useful for describing a location in the script symbolically,
+    /// for example as the routine it is part of.
+    pub fn label_at(
+        &self,
+        operator: OperatorIndex,
+    ) -> Option<(&str, OperatorIndex)> {
+        // Labels are defined in the order of the operators they refer to, so
+        // the list is sorted by operator.
+        let num_preceding = self
+            .labels
+            .partition_point(|label| label.operator <= operator);
+
+        let label = self.labels[..num_preceding]
+            .iter()
+            .rev()
+            .find(|label| !is_generated_label(&label.name))?;
+        Some((label.name.as_str(), label.operator))
+    }
+
+    /// # Iterate over all procedures defined in the script
+    ///
+    /// A procedure is defined using `proc`, which declares its name and its
+    /// arity:
+    ///
+    /// ```text
+    /// proc square in 1 out 1
+    ///     0 copy *
+    /// end
+    /// ```
+    ///
+    /// This compiles into a label with the name of the procedure, followed by
+    /// the operators of its body, followed by `return`. The arity is not
+    /// enforced during evaluation, but [`Script::lint`] checks it where it
+    /// can.
+    ///
+    /// Yields the name of each procedure, alongside the index of its first
+    /// operator and its arity, in the order the procedures are defined in the
+    /// script.
+    pub fn procedures(
+        &self,
+    ) -> impl Iterator<Item = (&str, OperatorIndex, Arity)> {
+        self.procedures.iter().map(|procedure| {
+            (procedure.name.as_str(), procedure.operator, procedure.arity)
+        })
+    }
+
+    /// # Access the arity of the procedure that starts at the provided operator
+    ///
+    /// Returns `None`, if no procedure starts there. Use this to check what a
+    /// call to that operator is expected to do with the operand stack.
+    pub fn arity(&self, operator: OperatorIndex) -> Option<Arity> {
+        self.procedures
+            .iter()
+            .find(|procedure| procedure.operator == operator)
+            .map(|procedure| procedure.arity)
+    }
+
+    /// # Iterate over the regions of memory that the script reserves
+    ///
+    /// Yields the name of each region, alongside the addresses it covers, in
+    /// the order the regions are defined in the script. Since regions are
+    /// placed one after the other, the end of the last one is the minimum size
+    /// of the memory that the script needs. See the [section on reserving
+    /// memory] for how to define regions.
+    ///
+    /// [section on reserving memory]: Script#reserving-memory
+    pub fn regions(&self) -> impl Iterator<Item = (&str, Range<u32>)> {
+        self.regions
+            .iter()
+            .map(|region| (region.name.as_str(), region.addresses.clone()))
+    }
+
+    /// # Iterate over the metadata entries defined in the script
+    ///
+    /// Yields the key and value of each entry, in the order they are defined
+    /// in the script. A key may appear multiple times. See the [section on
+    /// metadata] for how to define entries.
+    ///
+    /// [section on metadata]: Script#metadata
+    pub fn metadata(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.metadata
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// # Access the value of the metadata entry with the provided key
+    ///
+    /// If there are multiple entries with this key, returns the value of the
+    /// first one. Returns `None`, if there is no such entry.
+    pub fn metadata_value(&self, key: &str) -> Option<&str> {
+        self.metadata()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| value)
+    }
+
+    #[cfg(feature = "jit")]
+    pub(crate) fn label_targets(&self) -> impl Iterator<Item = OperatorIndex> {
+        self.labels.iter().map(|label| label.operator)
+    }
+
     /// # Iterate over all operators in the script
     pub fn operators(
         &self,
@@ -150,88 +1023,464 @@ impl Script {
     }
 }
 
+fn compile_source(
+    script: &str,
+    operators: &mut Vec<Operator>,
+    labels: &mut Vec<Label>,
+    procedures: &mut Vec<Procedure>,
+    layout: &mut Layout,
+    source_map: &mut BTreeMap<OperatorIndex, Range<usize>>,
+    directives: &mut Directives,
+) {
+    let mut blocks = Blocks::default();
+    let mut tokens = lex(script).peekable();
+
+    while let Some(token) = tokens.next() {
+        match token.kind {
+            TokenKind::Comment => {
+                // Comments don't affect the compiled script.
+            }
+            TokenKind::Metadata => {
+                directives
+                    .metadata
+                    .push(parse_metadata(&script[token.range]));
+            }
+            TokenKind::Identifier if &script[token.range.clone()] == "proc" => {
+                let header = parse_procedure_header(script, &mut tokens);
+                blocks.compile_procedure(
+                    header,
+                    token.range,
+                    operators,
+                    labels,
+                    procedures,
+                    source_map,
+                );
+            }
+            TokenKind::Identifier
+                if is_directive(&script[token.range.clone()]) =>
+            {
+                let directive = &script[token.range.clone()];
+
+                let is_valid = if directive == ".start" {
+                    compile_start(script, &mut tokens, &mut directives.start)
+                } else {
+                    layout.compile_directive(directive, script, &mut tokens)
+                };
+
+                if !is_valid {
+                    // Like any other token that the compiler doesn't
+                    // recognize, this triggers an effect when evaluated.
+                    push_operator(
+                        Operator::Identifier {
+                            value: directive.to_string(),
+                        },
+                        token.range,
+                        operators,
+                        source_map,
+                    );
+                }
+            }
+            TokenKind::Identifier
+                if is_keyword(&script[token.range.clone()]) =>
+            {
+                blocks.compile_keyword(
+                    &script[token.range.clone()],
+                    token.range,
+                    operators,
+                    labels,
+                    source_map,
+                );
+            }
+            _ => {
+                parse_token(script, token, operators, labels, source_map);
+            }
+        }
+    }
+
+    blocks.finish(operators, labels, source_map);
+}
+
+/// # What the directives of a script define, aside from regions of memory
+#[derive(Default)]
+struct Directives {
+    metadata: Vec<(String, String)>,
+    start: Option<Start>,
+}
+
+/// # Compile the parts of a `.start` directive that follow the directive
+///
+/// Returns `false`, if the directive is malformed, or if the script already
+/// has one.
+fn compile_start(
+    script: &str,
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+    start: &mut Option<Start>,
+) -> bool {
+    if start.is_some() {
+        return false;
+    }
+    let Some(name) = next_name(script, tokens) else {
+        return false;
+    };
+
+    *start = Some(Start {
+        name: script[name.range.clone()].to_string(),
+        target: None,
+        range: Some(name.range),
+    });
+
+    true
+}
+
+/// # Parse a `.meta` directive into a key and a value
+fn parse_metadata(directive: &str) -> (String, String) {
+    let entry = directive.trim_start_matches(".meta").trim();
+    let (key, value) =
+        entry.split_once(char::is_whitespace).unwrap_or((entry, ""));
+
+    (key.to_string(), value.trim().to_string())
+}
+
 fn parse_token(
     script: &str,
-    range: Range<usize>,
+    token: Token,
     operators: &mut Vec<Operator>,
     labels: &mut Vec<Label>,
-    next_index: &mut OperatorIndex,
     source_map: &mut BTreeMap<OperatorIndex, Range<usize>>,
 ) {
+    let Token { kind, range } = token;
     let token = &script[range.clone()];
 
-    let operator = if let Some((name, "")) = token.rsplit_once(":") {
-        let Ok(index) = operators.len().try_into() else {
-            panic!(
-                "Trying to create a label for an operator whose index can't be \
-                represented as `u32`. This is only possible on 64-bit \
-                platforms, when there are more than `u32::MAX` operators in a \
-                script.\n\
-                \n\
-                That this limit can practically be reached with the language \
-                as it currently is, seems highly unlikely. This makes this \
-                panic an acceptable outcome.\n\
-                \n\
-                Long-term, once the API supports compiler errors, this case \
-                should result in an such an error instead."
-            );
-        };
+    let operator = if kind == TokenKind::Label {
+        let name = &token[..token.len() - ':'.len_utf8()];
 
         labels.push(Label {
             name: name.to_string(),
-            operator: OperatorIndex { value: index },
+            operator: next_index(operators),
+            range: Some(range),
         });
 
         return;
-    } else if let Some(("", name)) = token.split_once("@") {
+    } else if kind == TokenKind::Reference {
+        let name = &token['@'.len_utf8()..];
         Operator::Reference {
             name: name.to_string(),
+            // Labels might be defined after the reference, so we can only
+            // resolve references once the whole script has been compiled.
+            target: None,
         }
-    } else if let Some(("", value)) = token.split_once("0x")
-        && let Ok(value) = i32::from_str_radix(value, 16)
+    } else if kind == TokenKind::Integer
+        && let Some(value) = parse_integer(token)
     {
         Operator::Integer { value }
-    } else if let Some(("", value)) = token.split_once("0x")
-        && let Ok(value) = u32::from_str_radix(value, 16)
-    {
-        Operator::integer_u32(value)
-    } else if let Ok(value) = token.parse::<i32>() {
-        Operator::Integer { value }
-    } else if let Ok(value) = token.parse::<u32>() {
-        Operator::integer_u32(value)
+    } else if let Some(opcode) = Opcode::from_identifier(token) {
+        Operator::Opcode { opcode }
     } else {
         Operator::Identifier {
             value: token.to_string(),
         }
     };
 
+    push_operator(operator, range, operators, source_map);
+}
+
+/// # Add an operator that was compiled from the provided range of the source
+pub(crate) fn push_operator(
+    operator: Operator,
+    range: Range<usize>,
+    operators: &mut Vec<Operator>,
+    source_map: &mut BTreeMap<OperatorIndex, Range<usize>>,
+) {
+    source_map.insert(next_index(operators), range);
     operators.push(operator);
+}
+
+/// # The index that the next operator added to the script is going to have
+///
+/// A label defined at this point refers to it.
+pub(crate) fn next_index(operators: &[Operator]) -> OperatorIndex {
+    let Ok(value) = operators.len().try_into() else {
+        panic!(
+            "Trying to add an operator or label whose index can't be \
+            represented as `u32`. This is only possible on 64-bit platforms, \
+            when there are more than `u32::MAX` operators in a script.\n\
+            \n\
+            That this limit can practically be reached with the language as it \
+            currently is, seems highly unlikely. This makes this panic an \
+            acceptable outcome.\n\
+            \n\
+            Long-term, once the API supports compiler errors, this case should \
+            result in an such an error instead."
+        );
+    };
 
-    source_map.insert(*next_index, range);
-    next_index.value += 1;
+    OperatorIndex { value }
 }
 
-#[derive(Debug)]
-pub enum Operator {
-    Identifier { value: String },
-    Integer { value: i32 },
-    Reference { name: String },
+/// # Options that control how a script is compiled
+///
+/// Pass this to [`Script::compile_with_options`] or [`Script::try_compile`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CompileOptions {
+    /// # Link the prelude into the script
+    ///
+    /// The prelude is a collection of routines that are useful in many
+    /// scripts, like math helpers (`abs`, `min`, `max`, `gcd`), stack
+    /// shuffles (`dup`, `swap`, `over`, `rot`, `nip`, and variants like `dup2`
+    /// that work on pairs of values), and memory utilities (`memory_copy`,
+    /// `memory_fill`). Please refer to [its source code] for full
+    /// documentation.
+    ///
+    /// The prelude is placed after the operators of the script, so the indices
+    /// of those are not affected by this option. Its operators are not present
+    /// in the source map.
+    ///
+    /// If the script defines a label of the same name as a routine in the
+    /// prelude, any reference to that name refers to the script's label.
+    ///
+    /// [its source code]: https://github.com/hannobraun/stack-assembly/blob/main/crates/stack-assembly/src/prelude.stack
+    pub prelude: bool,
+
+    /// # Fuse common sequences of operators into superinstructions
+    ///
+    /// This speeds up the evaluation, as fewer operators need to be
+    /// dispatched. Fused operators behave exactly like the unfused ones, and
+    /// the indices of all operators, as well as the source map, are unaffected
+    /// by this option.
+    ///
+    /// The only observable difference is that a single call to
+    /// [`Eval::step`] might evaluate two operators. This does not happen, if
+    /// the evaluation is limited by fuel that would run out in between, or if
+    /// the history is enabled (see [`Eval::enable_history`]).
+    ///
+    /// [`Eval::step`]: crate::Eval::step
+    /// [`Eval::enable_history`]: crate::Eval::enable_history
+    pub optimize: bool,
+
+    /// # Remove the operators that the evaluation can never reach
+    ///
+    /// This makes the script smaller, which is most useful together with
+    /// [`CompileOptions::prelude`], as it removes any routine of the prelude
+    /// that the script doesn't use. See [`Script::reachable_operators`] for
+    /// which operators are considered reachable.
+    ///
+    /// Unlike the other options, this changes the indices of operators. Labels,
+    /// references, and the source map are updated accordingly. But if the
+    /// script jumps to addresses that it computes, instead of ones provided by
+    /// references, or if the host starts the evaluation at an operator that is
+    /// not the target of a label, the evaluation may no longer work as
+    /// expected.
+    pub strip_unreachable: bool,
+}
+
+/// # An error that prevented a script from being compiled
+///
+/// Only a compilation in strict mode can fail. See [`Script::try_compile`].
+///
+/// Each error contains the range in the source text that caused it, which can
+/// be used to index into the source string provided to [`Script::try_compile`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CompileError {
+    /// # An integer that doesn't fit into 32 bits
+    IntegerOutOfRange {
+        /// # The range of the integer in the source text
+        range: Range<usize>,
+    },
+
+    /// # An identifier that doesn't refer to a built-in operator
+    UnknownIdentifier {
+        /// # The range of the identifier in the source text
+        range: Range<usize>,
+    },
+
+    /// # A reference to a label that doesn't exist
+    ///
+    /// This can be a reference in the code, or one in the values of a `.data`
+    /// directive.
+    InvalidReference {
+        /// # The range of the reference in the source text
+        range: Range<usize>,
+    },
+
+    /// # A keyword of structured control flow that doesn't match up
+    ///
+    /// This is an `else` without a preceding `if`, a `while`, `break`, or
+    /// `continue` outside of a loop, an `end` that doesn't close anything, or
+    /// an `if` or `loop` without an `end`. Same for a `{` without a `}`, or
+    /// the other way around.
+    UnmatchedKeyword {
+        /// # The range of the keyword in the source text
+        range: Range<usize>,
+    },
+
+    /// # A procedure that isn't defined correctly
+    ///
+    /// This is a `proc` that isn't followed by a name and an arity, like
+    /// `proc name in 1 out 1`, that is placed within another block, or that
+    /// has no `end`.
+    InvalidProcedure {
+        /// # The range of the `proc` keyword in the source text
+        range: Range<usize>,
+    },
+
+    /// # A directive that isn't used correctly
+    ///
+    /// This is a `.zero` that isn't followed by a name and a size, like
+    /// `.zero buffer 16`, or a `.data` that isn't followed by a name and values
+    /// that are integers or references, like `.data table 1 @a`. Either might
+    /// also reserve a region whose name is already taken by another one. Or
+    /// it's an `.align` that isn't followed by a positive alignment, like
+    /// `.align 4`.
+    InvalidDirective {
+        /// # The range of the directive in the source text
+        range: Range<usize>,
+    },
+
+    /// # A warning that strict mode treats as an error
+    ///
+    /// See [`Script::lint`].
+    Warning {
+        /// # The warning
+        warning: Warning,
+
+        /// # The range of the operator that the warning refers to
+        ///
+        /// This is `None`, if the warning refers to a label at the end of the
+        /// script, which doesn't name any operator.
+        range: Option<Range<usize>>,
+    },
 }
 
-impl Operator {
-    pub fn integer_u32(value: u32) -> Self {
-        Self::Integer {
-            value: i32::from_le_bytes(value.to_le_bytes()),
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::IntegerOutOfRange { range: _ } => {
+                write!(f, "integer doesn't fit into 32 bits")
+            }
+            Self::UnknownIdentifier { range: _ } => {
+                write!(f, "unknown identifier")
+            }
+            Self::InvalidReference { range: _ } => {
+                write!(f, "reference to a label that doesn't exist")
+            }
+            Self::UnmatchedKeyword { range: _ } => {
+                write!(f, "keyword doesn't match up with its block")
+            }
+            Self::InvalidProcedure { range: _ } => {
+                write!(
+                    f,
+                    "procedure needs a header like `proc name in 1 out 1`, \
+                    must not be nested, and needs an `end`"
+                )
+            }
+            Self::InvalidDirective { range: _ } => {
+                write!(
+                    f,
+                    "directive is malformed; expected something like `.zero \
+                    buffer 16`, `.var count 0` (with a unique name), or \
+                    `.align 4`"
+                )
+            }
+            Self::Warning { warning, range: _ } => {
+                write!(f, "{warning}")
+            }
         }
     }
 }
 
+impl std::error::Error for CompileError {}
+
+/// # The source code of the prelude
+///
+/// See [`CompileOptions::prelude`].
+const PRELUDE: &str = include_str!("prelude.stack");
+
+#[derive(Clone, Debug)]
+pub enum Operator {
+    End,
+    Fused {
+        superinstruction: Superinstruction,
+    },
+    Identifier {
+        value: String,
+    },
+    Integer {
+        value: i32,
+    },
+    Opcode {
+        opcode: Opcode,
+    },
+    Reference {
+        name: String,
+        target: Option<OperatorIndex>,
+    },
+}
+
 /// # Refers to an operator in a script
 #[derive(Clone, Copy, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
 pub struct OperatorIndex {
     pub(crate) value: u32,
 }
 
+impl OperatorIndex {
+    /// # Create an `OperatorIndex` from its raw value
+    ///
+    /// This does not check whether the resulting index refers to an operator
+    /// in any particular script. If it doesn't, using it will result in
+    /// [`Effect::OutOfOperators`].
+    pub fn new(value: u32) -> Self {
+        Self { value }
+    }
+
+    /// # Access the raw value of the index
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+
+    /// # Compute the index that is `offset` operators further ahead
+    ///
+    /// Returns `None`, if the result would not fit into a `u32`.
+    pub fn checked_add(self, offset: u32) -> Option<Self> {
+        self.value.checked_add(offset).map(Self::new)
+    }
+
+    /// # Compute the index that is `offset` operators further back
+    ///
+    /// Returns `None`, if the result would be negative.
+    pub fn checked_sub(self, offset: u32) -> Option<Self> {
+        self.value.checked_sub(offset).map(Self::new)
+    }
+
+    /// # Compute the index at a signed offset from this one
+    ///
+    /// Returns `None`, if the result would be negative or would not fit into a
+    /// `u32`.
+    pub fn checked_add_signed(self, offset: i32) -> Option<Self> {
+        self.value.checked_add_signed(offset).map(Self::new)
+    }
+}
+
+/// # Convert an operator index into the value that refers to it
+///
+/// This is the value that a reference to a label at that index pushes, and
+/// that operators like `jump` and `call` expect.
+impl From<OperatorIndex> for Value {
+    fn from(index: OperatorIndex) -> Self {
+        Self::from(index.value)
+    }
+}
+
+/// # Interpret a value as an operator index
+///
+/// The value is interpreted as an unsigned integer, like operators like `jump`
+/// and `call` do. A negative value, for example, becomes a large index that
+/// most likely doesn't refer to any operator.
+impl From<Value> for OperatorIndex {
+    fn from(value: Value) -> Self {
+        Self::new(value.to_u32())
+    }
+}
+
 impl fmt::Display for OperatorIndex {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.value)
@@ -242,29 +1491,77 @@ impl fmt::Display for OperatorIndex {
 pub struct Label {
     pub name: String,
     pub operator: OperatorIndex,
+
+    /// # The range of the label in the source text
+    ///
+    /// This is `None` for the labels of the prelude, for those that the
+    /// compiler generates for structured control flow, and for those of a
+    /// script that was loaded from an artifact.
+    pub range: Option<Range<usize>>,
 }
 
+/// # The label that a `.start` directive refers to
+#[derive(Debug)]
+pub struct Start {
+    pub name: String,
+    pub target: Option<OperatorIndex>,
+
+    /// # The range of the label's name in the source text
+    ///
+    /// This is `None` for a script that was loaded from an artifact.
+    pub range: Option<Range<usize>>,
+}
+
+#[derive(Debug)]
+pub struct Procedure {
+    pub name: String,
+    pub operator: OperatorIndex,
+    pub arity: Arity,
+}
+
+/// # The number of values a procedure takes from and leaves on the stack
+///
+/// See [`Script::procedures`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Arity {
+    /// # The number of values the procedure takes from the operand stack
+    pub inputs: u32,
+
+    /// # The number of values the procedure leaves on the operand stack
+    ///
+    /// They take the place of the inputs.
+    pub outputs: u32,
+}
+
+/// # An operator index that doesn't refer to an operator in the script
+///
+/// See [`Script::map_operator_to_source`].
 #[derive(Debug)]
 pub struct InvalidOperatorIndex;
 
-impl From<InvalidOperatorIndex> for Effect {
-    fn from(InvalidOperatorIndex: InvalidOperatorIndex) -> Self {
-        Effect::OutOfOperators
+impl fmt::Display for InvalidOperatorIndex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "operator index doesn't refer to an operator in the script"
+        )
     }
 }
 
-#[derive(Debug)]
-pub struct InvalidReference;
+impl std::error::Error for InvalidOperatorIndex {}
 
-impl From<InvalidReference> for Effect {
-    fn from(InvalidReference: InvalidReference) -> Self {
-        Effect::InvalidReference
+impl From<InvalidOperatorIndex> for Effect {
+    fn from(InvalidOperatorIndex: InvalidOperatorIndex) -> Self {
+        Effect::OutOfOperators
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::Script;
+    use crate::{
+        Arity, CompileError, CompileOptions, Eval, OperatorIndex, Script,
+        Warning,
+    };
 
     #[test]
     fn map_operator_to_source() {
@@ -286,4 +1583,259 @@ mod tests {
 
         assert_eq!(operators, vec!["0", "1", "+", "@loop", "jump"]);
     }
+
+    #[test]
+    fn append() {
+        let mut source = String::from("@f call .zero a 2\n");
+        let mut script = Script::compile(&source);
+
+        let appended = " f: @a @b\n.zero b 1";
+        let range = script.append(appended);
+        source.push_str(appended);
+
+        assert_eq!(range, OperatorIndex::new(2)..OperatorIndex::new(4));
+        assert_eq!(
+            script.labels().collect::<Vec<_>>(),
+            [("f", OperatorIndex::new(2))],
+        );
+        assert_eq!(
+            script.regions().collect::<Vec<_>>(),
+            [("a", 0..2), ("b", 2..3)],
+        );
+
+        let Ok(range) = script.map_operator_to_source(&OperatorIndex::new(3))
+        else {
+            unreachable!("Operator was compiled from the appended source.");
+        };
+        assert_eq!(&source[range], "@b");
+
+        let mut eval = Eval::new();
+        eval.run(&script);
+        assert_eq!(eval.operand_stack.to_i32_slice(), &[0, 2]);
+    }
+
+    #[test]
+    fn tests() {
+        let script = Script::compile(
+            "
+            return
+
+            test_a: 1 assert return
+            helper: return
+            test_b: 1 assert return
+            ",
+        );
+
+        let tests = script
+            .tests()
+            .map(|(name, operator)| (name, operator.value()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(tests, vec![("test_a", 1), ("test_b", 5)]);
+    }
+
+    #[test]
+    fn labels() {
+        let script = Script::compile("0 a: 1 b: c: 2 3");
+
+        let labels = script
+            .labels()
+            .map(|(name, operator)| (name, operator.value()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(labels, vec![("a", 1), ("b", 2), ("c", 2)]);
+    }
+
+    #[test]
+    fn label_definitions() {
+        let source = "a: 1 b: 2";
+        let options = CompileOptions {
+            prelude: true,
+            ..CompileOptions::default()
+        };
+        let script = Script::compile_with_options(source, options);
+
+        let labels = script
+            .label_definitions()
+            .map(|(name, operator, range)| {
+                (name, operator.value(), &source[range])
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(labels, vec![("a", 0, "a:"), ("b", 1, "b:")]);
+    }
+
+    #[test]
+    fn references() {
+        let script = Script::compile("@a jump a: 1 @b @a");
+
+        let references = script
+            .references()
+            .map(|(name, operator)| (name, operator.value()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(references, vec![("a", 0), ("b", 3), ("a", 4)]);
+    }
+
+    #[test]
+    fn label_at() {
+        let script = Script::compile("0 a: 1 b: c: 2 3");
+
+        let label_at = |operator| {
+            script
+                .label_at(OperatorIndex::new(operator))
+                .map(|(name, operator)| (name, operator.value()))
+        };
+
+        assert_eq!(label_at(0), None);
+        assert_eq!(label_at(1), Some(("a", 1)));
+        assert_eq!(label_at(2), Some(("c", 2)));
+        assert_eq!(label_at(3), Some(("c", 2)));
+    }
+
+    #[test]
+    fn procedures() {
+        let script = Script::compile(
+            "
+            proc f in 2 out 1 + end
+            proc g in 0 out 3 1 2 3 end
+            ",
+        );
+
+        let procedures = script
+            .procedures()
+            .map(|(name, operator, arity)| {
+                (name, operator.value(), arity.inputs, arity.outputs)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(procedures, vec![("f", 0, 2, 1), ("g", 2, 0, 3)]);
+        assert_eq!(
+            script.arity(OperatorIndex::new(2)),
+            Some(Arity {
+                inputs: 0,
+                outputs: 3,
+            }),
+        );
+        assert_eq!(script.arity(OperatorIndex::new(1)), None);
+    }
+
+    #[test]
+    fn label_at_skips_generated_labels() {
+        let script = Script::compile("f: 1 if 2 end 3");
+
+        let Some((name, _)) = script.label_at(OperatorIndex::new(6)) else {
+            unreachable!("Operator is preceded by label.");
+        };
+
+        assert_eq!(name, "f");
+    }
+
+    #[test]
+    fn strict_mode_rejects_problems() {
+        let options = CompileOptions::default();
+
+        for (source, expected) in [
+            (
+                "1 4294967296",
+                CompileError::IntegerOutOfRange { range: 2..12 },
+            ),
+            (
+                "-2147483649",
+                CompileError::IntegerOutOfRange { range: 0..11 },
+            ),
+            ("1 unknown", CompileError::UnknownIdentifier { range: 2..9 }),
+            (
+                "@missing jump",
+                CompileError::InvalidReference { range: 0..8 },
+            ),
+            ("1 if", CompileError::UnmatchedKeyword { range: 2..4 }),
+            ("1 end", CompileError::UnmatchedKeyword { range: 2..5 }),
+            ("loop", CompileError::UnmatchedKeyword { range: 0..4 }),
+            (
+                "1 if break end",
+                CompileError::UnmatchedKeyword { range: 5..10 },
+            ),
+            (
+                "proc f in 1 end",
+                CompileError::InvalidProcedure { range: 0..4 },
+            ),
+            (
+                "1 if proc f in 1 out 1 end end",
+                CompileError::InvalidProcedure { range: 5..9 },
+            ),
+            (
+                "proc f in 0 out 0",
+                CompileError::InvalidProcedure { range: 0..4 },
+            ),
+            (
+                ".zero buffer",
+                CompileError::InvalidDirective { range: 0..5 },
+            ),
+            (
+                ".zero a 1 .zero a 2",
+                CompileError::InvalidDirective { range: 10..15 },
+            ),
+            (".align 0", CompileError::InvalidDirective { range: 0..6 }),
+            (
+                "return 1",
+                CompileError::Warning {
+                    warning: Warning::UnreachableCode {
+                        first: OperatorIndex::new(1),
+                        last: OperatorIndex::new(1),
+                    },
+                    range: Some(7..8),
+                },
+            ),
+        ] {
+            assert_eq!(
+                Script::try_compile(source, options).err(),
+                Some(expected),
+                "Script: {source}",
+            );
+        }
+    }
+
+    #[test]
+    fn check_finds_all_problems() {
+        let script = Script::compile("unknown @missing return 1");
+
+        assert_eq!(
+            script.check(),
+            [
+                CompileError::UnknownIdentifier { range: 0..7 },
+                CompileError::InvalidReference { range: 8..16 },
+                CompileError::Warning {
+                    warning: Warning::UnreachableCode {
+                        first: OperatorIndex::new(3),
+                        last: OperatorIndex::new(3),
+                    },
+                    range: Some(24..25),
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn strict_mode_accepts_valid_script() {
+        let options = CompileOptions {
+            prelude: true,
+            ..CompileOptions::default()
+        };
+
+        let script = Script::try_compile(
+            "0x7fffffff -1 @end jump end: 1 @dup call",
+            options,
+        );
+        assert!(script.is_ok());
+    }
+
+    #[test]
+    fn lenient_mode_accepts_problems() {
+        let script = Script::compile_with_options(
+            "4294967296 unknown @missing return 1",
+            CompileOptions::default(),
+        );
+        assert_eq!(script.operators().count(), 5);
+    }
 }
@@ -0,0 +1,265 @@
+//! # Metadata that accompanies each value on the operand stack and in memory
+//!
+//! Both [`Provenance`] and [`Taint`] attach a label to each value, and follow
+//! those labels as the values move around. This module implements that once,
+//! while each of them decides what the labels are, and how they combine.
+//!
+//! [`Provenance`]: crate::Provenance
+//! [`Taint`]: crate::Taint
+
+use std::collections::BTreeMap;
+
+use crate::{
+    Effect, Eval, Value,
+    eval::convert_operand_stack_index,
+    opcode::Opcode,
+    script::{Operator, OperatorIndex, Script},
+    stack_depth::fixed_arity,
+};
+
+/// # A label that accompanies each value
+pub(crate) trait Label: Copy + Eq {
+    /// # The label of integers in the script, and of unwritten memory
+    const LITERAL: Self;
+
+    /// # The label of references, and of the output of `pc`
+    const REFERENCE: Self;
+
+    /// # The label of values that the host added to the operand stack
+    const UNKNOWN: Self;
+
+    /// # Determine the label of an operator's output, from its inputs
+    fn computed(inputs: &[Self]) -> Self;
+
+    /// # Determine the label of a value that is read from memory
+    fn read(value: Self, address: Self) -> Self;
+}
+
+/// # The labels of all values on the operand stacks and in memory
+#[derive(Clone, Debug)]
+pub(crate) struct Shadow<T> {
+    operand_stacks: BTreeMap<u32, Vec<T>>,
+    memory: BTreeMap<u32, T>,
+}
+
+impl<T> Default for Shadow<T> {
+    fn default() -> Self {
+        Self {
+            operand_stacks: BTreeMap::new(),
+            memory: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: Label> Shadow<T> {
+    pub(crate) fn operand_stack(&self, strand: u32) -> &[T] {
+        self.operand_stacks
+            .get(&strand)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// # Label a value on the operand stack of the current strand
+    ///
+    /// The index counts from the bottom of the stack. Values that don't have
+    /// a label yet, because the host added them, are labeled as unknown.
+    pub(crate) fn label_operand(
+        &mut self,
+        eval: &Eval,
+        index: usize,
+        label: T,
+    ) {
+        let labels = self
+            .operand_stacks
+            .entry(eval.current_strand())
+            .or_default();
+        sync(labels, eval.operand_stack.values.len());
+
+        if let Some(l) = labels.get_mut(index) {
+            *l = label;
+        }
+    }
+
+    pub(crate) fn memory(&self, address: u32) -> T {
+        self.memory.get(&address).copied().unwrap_or(T::LITERAL)
+    }
+
+    pub(crate) fn label_memory(&mut self, address: u32, label: T) {
+        if label == T::LITERAL {
+            self.memory.remove(&address);
+        } else {
+            self.memory.insert(address, label);
+        }
+    }
+
+    /// # Evaluate an operator, while keeping track of the labels
+    ///
+    /// Before the evaluation, `check` receives the operator, as well as the
+    /// values on the operand stack and their labels. If it returns an error,
+    /// the operator is not evaluated.
+    pub(crate) fn evaluate(
+        &mut self,
+        index: OperatorIndex,
+        script: &Script,
+        eval: &mut Eval,
+        check: impl FnOnce(Opcode, &[T], &[Value]) -> Result<(), Effect>,
+        evaluate: impl FnOnce(&mut Eval) -> Result<(), Effect>,
+    ) -> Result<(), Effect> {
+        let strand = eval.current_strand();
+        let labels = self.operand_stacks.entry(strand).or_default();
+        sync(labels, eval.operand_stack.values.len());
+
+        let values = &eval.operand_stack.values;
+        let operator = match script.operator_at(index) {
+            Some(Operator::Fused { superinstruction }) => {
+                // Superinstructions aren't evaluated while labels are tracked,
+                // so this is what's going to be evaluated instead.
+                Some(superinstruction.unfused())
+            }
+            operator => operator.cloned(),
+        };
+        let opcode = match operator {
+            Some(Operator::Opcode { opcode }) => Some(opcode),
+            _ => None,
+        };
+
+        if let Some(opcode) = opcode {
+            check(opcode, labels, values)?;
+        }
+
+        // Some operators move labels around, depending on their inputs. Those
+        // inputs are gone after the evaluation, so look at them now.
+        let moved = match opcode {
+            Some(Opcode::Copy | Opcode::Drop) => {
+                values.split_last().and_then(|(index, rest)| {
+                    convert_operand_stack_index(rest, index.to_i32()).ok()
+                })
+            }
+            Some(Opcode::ReverseN) => {
+                values.split_last().and_then(|(num_values, rest)| {
+                    usize::try_from(num_values.to_u32()).ok().and_then(
+                        |num_values| rest.len().checked_sub(num_values),
+                    )
+                })
+            }
+            _ => None,
+        };
+        let address = values.last().copied().map(Value::to_u32);
+        let written = values
+            .len()
+            .checked_sub(2)
+            .and_then(|index| values.get(index).copied())
+            .map(Value::to_u32);
+
+        // Native operators and `exec` can do anything with the stack. All we
+        // can do, is compare it before and after.
+        let unknown = match (&operator, opcode) {
+            (Some(Operator::Identifier { value: _ }), _)
+            | (_, Some(Opcode::Exec)) => Some(values.clone()),
+            _ => None,
+        };
+
+        let num_values = values.len();
+        evaluate(eval)?;
+
+        let labels = self.operand_stacks.entry(strand).or_default();
+        let mut memory_write = None;
+        match (operator, opcode, moved) {
+            (Some(Operator::Integer { value: _ }), _, _) => {
+                labels.push(T::LITERAL);
+            }
+            (Some(Operator::Reference { name: _, target: _ }), _, _)
+            | (_, Some(Opcode::Pc), _) => {
+                labels.push(T::REFERENCE);
+            }
+            (_, Some(Opcode::Copy), Some(index)) => {
+                labels.pop();
+                labels.push(labels[index]);
+            }
+            (_, Some(Opcode::Drop), Some(index)) => {
+                labels.pop();
+                labels.remove(index);
+            }
+            (_, Some(Opcode::ReverseN), Some(start)) => {
+                labels.pop();
+                labels[start..].reverse();
+            }
+            (_, Some(Opcode::Read), _) => {
+                let label = labels.pop().zip(address).map_or(
+                    T::UNKNOWN,
+                    |(label, address)| {
+                        let value = self
+                            .memory
+                            .get(&address)
+                            .copied()
+                            .unwrap_or(T::LITERAL);
+                        T::read(value, label)
+                    },
+                );
+                labels.push(label);
+            }
+            (_, Some(Opcode::Write), _) => {
+                memory_write = written.zip(labels.get(num_values - 1).copied());
+                labels.truncate(num_values - 2);
+            }
+            (_, Some(opcode), _) => {
+                if let Some((num_inputs, num_outputs)) = fixed_arity(opcode) {
+                    let start = num_values - num_inputs;
+                    let label = T::computed(&labels[start..]);
+
+                    labels.truncate(start);
+                    labels.extend((0..num_outputs).map(|_| label));
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(before) = unknown {
+            let unchanged = before
+                .iter()
+                .zip(&eval.operand_stack.values)
+                .take_while(|(a, b)| a == b)
+                .count()
+                .min(labels.len());
+            let label = T::computed(&labels[unchanged..]);
+
+            labels.truncate(unchanged);
+            labels.resize(eval.operand_stack.values.len(), label);
+        }
+        if eval.current_strand() == strand {
+            sync(labels, eval.operand_stack.values.len());
+        }
+        if let Some((address, label)) = memory_write {
+            self.label_memory(address, label);
+        }
+
+        Ok(())
+    }
+}
+
+/// # Determine how many jump or call targets an operator takes as inputs
+///
+/// The targets are always on top of the stack.
+pub(crate) fn num_targets(opcode: Opcode) -> usize {
+    match opcode {
+        Opcode::Jump
+        | Opcode::JumpIf
+        | Opcode::JumpIfEqual
+        | Opcode::JumpIfNotEqual
+        | Opcode::JumpIfLess
+        | Opcode::JumpIfLessOrEqual
+        | Opcode::JumpIfGreater
+        | Opcode::JumpIfGreaterOrEqual
+        | Opcode::Call => 1,
+        Opcode::CallEither => 2,
+        _ => 0,
+    }
+}
+
+/// # Make sure there's a label for each value on the operand stack
+///
+/// Values that the labels don't account for have been added by something that
+/// isn't tracked, most likely the host.
+fn sync<T: Label>(labels: &mut Vec<T>, num_values: usize) {
+    labels.resize(num_values, T::UNKNOWN);
+}
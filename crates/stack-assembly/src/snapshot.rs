@@ -0,0 +1,178 @@
+use crate::{Eval, Value};
+
+/// # A copy of the operand stack and memory at a specific point in time
+///
+/// Create a snapshot via [`Eval::snapshot`], then call [`Snapshot::diff`]
+/// later, to learn what has changed since. This is useful for writing tests
+/// about what a routine actually touches.
+///
+/// ## Example
+///
+/// ```
+/// use stack_assembly::{Eval, Script, Value};
+///
+/// let script = Script::compile("1 2 0 drop 7 3 write");
+///
+/// let mut eval = Eval::new();
+/// eval.operand_stack.push(5);
+///
+/// let snapshot = eval.snapshot();
+/// eval.run(&script);
+///
+/// let diff = snapshot.diff(&eval);
+///
+/// assert_eq!(diff.popped, []);
+/// assert_eq!(diff.pushed, [Value::from(1)]);
+///
+/// assert_eq!(diff.memory.len(), 1);
+/// assert_eq!(diff.memory[0].address, 7);
+/// assert_eq!(diff.memory[0].old, Value::from(0));
+/// assert_eq!(diff.memory[0].new, Value::from(3));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    operand_stack: Vec<Value>,
+    memory: Box<[Value]>,
+}
+
+impl Snapshot {
+    pub(crate) fn new(eval: &Eval) -> Self {
+        Self {
+            operand_stack: eval.operand_stack.values.clone(),
+            memory: Box::from(&*eval.memory.values()),
+        }
+    }
+
+    /// # Compare the snapshot to the current state of an evaluation
+    ///
+    /// The operand stack is compared from the bottom up. Everything above the
+    /// first value that differs counts as popped (if it was in the snapshot)
+    /// or pushed (if it is in the evaluation now). This means a value that was
+    /// popped and then pushed again unchanged isn't part of the diff.
+    ///
+    /// If the size of the memory has changed since the snapshot was taken,
+    /// only the addresses that are present in both are compared.
+    pub fn diff(&self, eval: &Eval) -> Diff {
+        let operand_stack = &eval.operand_stack.values;
+
+        let unchanged = self
+            .operand_stack
+            .iter()
+            .zip(operand_stack)
+            .take_while(|(old, new)| old == new)
+            .count();
+
+        let popped = self.operand_stack[unchanged..].to_vec();
+        let pushed = operand_stack[unchanged..].to_vec();
+
+        let memory = self
+            .memory
+            .iter()
+            .zip(eval.memory.values().iter())
+            .zip(0..)
+            .filter(|((old, new), _)| old != new)
+            .map(|((&old, &new), address)| MemoryChange { address, old, new })
+            .collect();
+
+        Diff {
+            popped,
+            pushed,
+            memory,
+        }
+    }
+}
+
+/// # The changes to operand stack and memory since a snapshot was taken
+///
+/// See [`Snapshot::diff`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diff {
+    /// # The values that have been popped from the operand stack
+    ///
+    /// Ordered from bottom to top, as they were on the stack.
+    pub popped: Vec<Value>,
+
+    /// # The values that have been pushed to the operand stack
+    ///
+    /// Ordered from bottom to top, as they are on the stack now.
+    pub pushed: Vec<Value>,
+
+    /// # The memory addresses whose values have changed
+    ///
+    /// Ordered by address.
+    pub memory: Vec<MemoryChange>,
+}
+
+impl Diff {
+    /// # Determine whether nothing has changed
+    pub fn is_empty(&self) -> bool {
+        self.popped.is_empty()
+            && self.pushed.is_empty()
+            && self.memory.is_empty()
+    }
+}
+
+/// # A change to a single memory address
+///
+/// See [`Diff`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MemoryChange {
+    /// # The address whose value has changed
+    pub address: u32,
+
+    /// # The value at the address, when the snapshot was taken
+    pub old: Value,
+
+    /// # The value at the address now
+    pub new: Value,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Eval, Script, Value, snapshot::MemoryChange};
+
+    #[test]
+    fn diff_is_empty_if_nothing_changed() {
+        let mut eval = Eval::new();
+        eval.operand_stack.push(1);
+
+        let snapshot = eval.snapshot();
+        eval.run(&Script::compile("0 copy 0 drop 3 0 read write"));
+
+        assert!(snapshot.diff(&eval).is_empty());
+    }
+
+    #[test]
+    fn diff_contains_popped_and_pushed_values() {
+        let mut eval = Eval::new();
+        eval.operand_stack.push(1);
+        eval.operand_stack.push(2);
+        eval.operand_stack.push(3);
+
+        let snapshot = eval.snapshot();
+        eval.run(&Script::compile("0 drop 0 drop 4 5"));
+
+        let diff = snapshot.diff(&eval);
+        assert_eq!(diff.popped, [Value::from(2), Value::from(3)]);
+        assert_eq!(diff.pushed, [Value::from(4), Value::from(5)]);
+        assert_eq!(diff.memory, []);
+    }
+
+    #[test]
+    fn diff_contains_changed_memory() {
+        let mut eval = Eval::new();
+
+        let snapshot = eval.snapshot();
+        eval.run(&Script::compile("3 1 write 2 5 write 2 0 write"));
+
+        let diff = snapshot.diff(&eval);
+        assert_eq!(
+            diff.memory,
+            [MemoryChange {
+                address: 3,
+                old: Value::from(0),
+                new: Value::from(1),
+            }],
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! # Analysis of how many values the operand stack holds
+//!
+//! See [`Script::lint`].
+
+use crate::{
+    Effect, OperatorIndex, Script, analysis::analyze, opcode::Opcode,
+    script::Operator,
+};
+
+/// # Find the operators that can find too few values on the operand stack
+///
+/// Builds on [`Script::analyze`], which follows the evaluation from the start
+/// of the script, from the start of each test, and from the start of each
+/// procedure. Where the number of values on the stack depends on the path the
+/// evaluation took, or on something the analysis can't know about (like what a
+/// routine that is called, or the host, does with the stack), this gives up on
+/// that path. Calls to procedures are the exception, as they declare what they
+/// do with the stack.
+///
+/// Consequently, this only finds a subset of the operators that can trigger
+/// [`Effect::OperandStackUnderflow`] or [`Effect::InvalidOperandStackIndex`].
+/// But each operator it finds, does so on every path through the script that
+/// the analysis follows to it. A call is also considered to find too few
+/// values, if there are fewer on the stack than the procedure it calls
+/// declares as inputs.
+pub(crate) fn find_stack_underflows(script: &Script) -> Vec<OperatorIndex> {
+    let analysis = script.analyze();
+
+    script
+        .operators()
+        .map(|(operator, _)| operator)
+        .filter(|&operator| {
+            matches!(
+                analysis.certain_effect(operator),
+                Some(
+                    Effect::OperandStackUnderflow
+                        | Effect::InvalidOperandStackIndex
+                )
+            )
+        })
+        .collect()
+}
+
+/// # Find the `return`s that don't leave the declared number of values
+///
+/// Follows the evaluation from the start of each procedure, like
+/// [`find_stack_underflows`] does. Yields the name of the procedure, alongside
+/// each `return` that the evaluation reaches with a different number of
+/// values on the stack than the procedure declares as outputs.
+pub(crate) fn find_arity_mismatches(
+    script: &Script,
+) -> Vec<(String, OperatorIndex)> {
+    let mut mismatches = Vec::new();
+
+    for (name, operator, arity) in script.procedures() {
+        let analysis = analyze(script, [(operator, arity.inputs)]);
+
+        for (index, operator) in script.operators() {
+            if let (
+                Some(depth),
+                Operator::Opcode {
+                    opcode: Opcode::Return,
+                },
+            ) = (
+                analysis.stack(index).and_then(|stack| stack.depth()),
+                operator,
+            ) && depth != arity.outputs as usize
+            {
+                mismatches.push((name.to_string(), index));
+            }
+        }
+    }
+
+    mismatches
+}
+
+/// # Determine how many values an operator pops and pushes
+///
+/// Returns `None` for the operators that don't always pop and push the same
+/// number of values. That includes those that transfer control elsewhere, as
+/// the operator that continues the evaluation can do anything with the stack.
+pub(crate) fn fixed_arity(opcode: Opcode) -> Option<(usize, usize)> {
+    let arity = match opcode {
+        Opcode::Copy
+        | Opcode::Drop
+        | Opcode::ReverseN
+        | Opcode::Jump
+        | Opcode::JumpIf
+        | Opcode::JumpIfEqual
+        | Opcode::JumpIfNotEqual
+        | Opcode::JumpIfLess
+        | Opcode::JumpIfLessOrEqual
+        | Opcode::JumpIfGreater
+        | Opcode::JumpIfGreaterOrEqual
+        | Opcode::Return
+        | Opcode::Unreachable
+        | Opcode::Todo
+        | Opcode::Call
+        | Opcode::CallEither
+        | Opcode::Resume
+        | Opcode::Exec
+        | Opcode::Yield => {
+            return None;
+        }
+        Opcode::Pc | Opcode::Current => (0, 1),
+        Opcode::Assert | Opcode::Locals => (1, 0),
+        Opcode::CountOnes
+        | Opcode::LeadingZeros
+        | Opcode::TrailingZeros
+        | Opcode::LocalGet
+        | Opcode::Read
+        | Opcode::Spawn
+        | Opcode::Receive => (1, 1),
+        Opcode::LocalSet | Opcode::Write | Opcode::Send => (2, 0),
+        Opcode::Multiply
+        | Opcode::Add
+        | Opcode::Subtract
+        | Opcode::Less
+        | Opcode::LessOrEqual
+        | Opcode::Equal
+        | Opcode::Greater
+        | Opcode::GreaterOrEqual
+        | Opcode::And
+        | Opcode::Or
+        | Opcode::Xor
+        | Opcode::RotateLeft
+        | Opcode::RotateRight
+        | Opcode::ShiftLeft
+        | Opcode::ShiftRight
+        | Opcode::DivFloor
+        | Opcode::ModFloor => (2, 1),
+        Opcode::Divide | Opcode::DivmodU => (2, 2),
+    };
+
+    Some(arity)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{OperatorIndex, Script};
+
+    use super::find_stack_underflows;
+
+    #[test]
+    fn find_underflow_in_straight_line_code() {
+        let script = Script::compile("1 +");
+        assert_eq!(find_stack_underflows(&script), [OperatorIndex::new(1)]);
+
+        let script = Script::compile("1 2 1 copy 0 copy 2 drop 5 copy");
+        assert_eq!(find_stack_underflows(&script), [OperatorIndex::new(9)]);
+
+        let script = Script::compile("1 2 -1 copy -2 drop -3 copy");
+        assert_eq!(find_stack_underflows(&script), [OperatorIndex::new(7)]);
+
+        let script = Script::compile("1 2 2 reverse_n 3 reverse_n");
+        assert_eq!(find_stack_underflows(&script), [OperatorIndex::new(5)]);
+    }
+
+    #[test]
+    fn follow_jumps() {
+        let script = Script::compile(
+            "
+            1 0 @skip jump_if
+                drop
+            skip:
+                2 @end jump
+                +
+            end:
+                *
+            ",
+        );
+
+        assert_eq!(find_stack_underflows(&script), [OperatorIndex::new(4)]);
+    }
+
+    #[test]
+    fn give_up_where_stack_is_unknown() {
+        // The stack depends on the number of iterations.
+        let script = Script::compile("loop: 1 1 @loop jump_if + +");
+        assert_eq!(find_stack_underflows(&script), []);
+
+        // The stack depends on the called routine.
+        let script = Script::compile("@f call + f: return");
+        assert_eq!(find_stack_underflows(&script), []);
+    }
+}
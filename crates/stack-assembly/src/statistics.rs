@@ -0,0 +1,158 @@
+//! # Summary of the shape of a script's code
+//!
+//! See [`Script::statistics`].
+
+use std::collections::BTreeMap;
+
+use crate::{Script, script::Operator};
+
+impl Script {
+    /// # Summarize the operators and labels of the script
+    ///
+    /// This covers the whole script, including the prelude, if it has been
+    /// linked into the script (see [`CompileOptions::prelude`]). Fused
+    /// operators are counted as the operators they replace (see
+    /// [`CompileOptions::optimize`]).
+    ///
+    /// [`CompileOptions::prelude`]: crate::CompileOptions::prelude
+    /// [`CompileOptions::optimize`]: crate::CompileOptions::optimize
+    pub fn statistics(&self) -> Statistics {
+        let mut statistics = Statistics {
+            num_labels: self.labels().count(),
+            ..Statistics::default()
+        };
+
+        for (_, operator) in self.operators() {
+            let operator = match operator {
+                Operator::End => {
+                    // Not compiled from any source text. Nothing to count.
+                    continue;
+                }
+                Operator::Fused { superinstruction } => {
+                    &superinstruction.unfused()
+                }
+                operator => operator,
+            };
+
+            statistics.num_operators += 1;
+
+            match operator {
+                Operator::Identifier { value: _ } => {
+                    statistics.num_identifiers += 1;
+                }
+                Operator::Integer { value } => {
+                    statistics.num_integers += 1;
+
+                    // The number of bits required to represent the integer as
+                    // a signed integer, including the sign bit.
+                    let magnitude = if *value < 0 { !value } else { *value };
+                    let width = i32::BITS + 1 - magnitude.leading_zeros();
+
+                    statistics.max_integer_width =
+                        statistics.max_integer_width.max(width);
+                }
+                Operator::Opcode { opcode } => {
+                    *statistics
+                        .num_built_ins
+                        .entry(opcode.identifier())
+                        .or_default() += 1;
+                }
+                Operator::Reference { name: _, target: _ } => {
+                    statistics.num_references += 1;
+                }
+                Operator::End
+                | Operator::Fused {
+                    superinstruction: _,
+                } => {
+                    unreachable!("Handled above.");
+                }
+            }
+        }
+
+        statistics
+    }
+}
+
+/// # A summary of the shape of a script's code
+///
+/// See [`Script::statistics`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Statistics {
+    /// # The total number of operators
+    pub num_operators: usize,
+
+    /// # The number of labels
+    pub num_labels: usize,
+
+    /// # The number of integers
+    pub num_integers: usize,
+
+    /// # The number of references to labels
+    pub num_references: usize,
+
+    /// # The number of identifiers that don't refer to a built-in operator
+    pub num_identifiers: usize,
+
+    /// # The number of uses of each built-in operator
+    ///
+    /// Maps the identifier of each built-in operator (like `+` or `jump`) to
+    /// the number of times it is used. Built-in operators that aren't used at
+    /// all are not present.
+    pub num_built_ins: BTreeMap<&'static str, usize>,
+
+    /// # The maximum width of any integer, in bits
+    ///
+    /// This is the number of bits that are required to represent the integer
+    /// as a signed integer, including the sign bit. For example, `1` and `-2`
+    /// both have a width of 2 bits. This is `0`, if there are no integers.
+    pub max_integer_width: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{CompileOptions, Script, statistics::Statistics};
+
+    #[test]
+    fn statistics() {
+        let script = Script::compile(
+            "
+            start:
+                1 2 +
+                -129 +
+                @start jump
+                unknown
+            end:
+            ",
+        );
+
+        assert_eq!(
+            script.statistics(),
+            Statistics {
+                num_operators: 8,
+                num_labels: 2,
+                num_integers: 3,
+                num_references: 1,
+                num_identifiers: 1,
+                num_built_ins: BTreeMap::from([("+", 2), ("jump", 1)]),
+                max_integer_width: 9,
+            },
+        );
+    }
+
+    #[test]
+    fn statistics_count_fused_operators_individually() {
+        let source = "1 2 + 3 write";
+
+        let [unfused, fused] = [false, true].map(|optimize| {
+            let options = CompileOptions {
+                optimize,
+                ..CompileOptions::default()
+            };
+            Script::compile_with_options(source, options).statistics()
+        });
+
+        assert_eq!(unfused, fused);
+    }
+}
@@ -0,0 +1,444 @@
+//! # Structured control flow, like `if` ... `else` ... `end`
+//!
+//! The compiler lowers structured control flow into operators that a script
+//! could also use directly: references, `jump`, and `jump_if`. Their targets
+//! are labels that the compiler generates. In the source map, each generated
+//! operator maps to the keyword it was compiled from.
+//!
+//! Procedures (`proc` ... `end`) are handled here too, as they are blocks that
+//! `end` closes, like the others. So are quotations (`{` ... `}`), which are
+//! closed by `}` instead.
+
+use std::{collections::BTreeMap, iter::Peekable, ops::Range};
+
+use crate::{
+    Arity, OperatorIndex, Token, TokenKind,
+    lex::parse_integer,
+    opcode::Opcode,
+    script::{Label, Operator, Procedure, next_index, push_operator},
+};
+
+/// # Determine whether a token is a keyword of structured control flow
+pub(crate) fn is_keyword(token: &str) -> bool {
+    matches!(
+        token,
+        "if" | "else"
+            | "loop"
+            | "while"
+            | "break"
+            | "continue"
+            | "proc"
+            | "end"
+            | "{"
+            | "}"
+    )
+}
+
+/// # Determine whether a label was generated by the compiler
+///
+/// The names of generated labels contain whitespace, which would end a token
+/// in the source text. So they can't collide with labels defined there.
+pub(crate) fn is_generated_label(name: &str) -> bool {
+    name.contains(char::is_whitespace)
+}
+
+/// # The blocks of structured control flow that haven't been closed yet
+#[derive(Default)]
+pub(crate) struct Blocks {
+    open: Vec<Block>,
+}
+
+impl Blocks {
+    pub(crate) fn compile_procedure(
+        &mut self,
+        header: Option<ProcedureHeader>,
+        range: Range<usize>,
+        operators: &mut Vec<Operator>,
+        labels: &mut Vec<Label>,
+        procedures: &mut Vec<Procedure>,
+        source_map: &mut BTreeMap<OperatorIndex, Range<usize>>,
+    ) {
+        let Some(header) = header.filter(|_| self.open.is_empty()) else {
+            push_operator(unmatched("proc"), range, operators, source_map);
+            return;
+        };
+
+        let operator = next_index(operators);
+
+        labels.push(Label {
+            name: header.name.clone(),
+            operator,
+            range: Some(header.range),
+        });
+        procedures.push(Procedure {
+            name: header.name,
+            operator,
+            arity: header.arity,
+        });
+
+        self.open.push(Block::Procedure { range });
+    }
+
+    pub(crate) fn compile_keyword(
+        &mut self,
+        keyword: &str,
+        range: Range<usize>,
+        operators: &mut Vec<Operator>,
+        labels: &mut Vec<Label>,
+        source_map: &mut BTreeMap<OperatorIndex, Range<usize>>,
+    ) {
+        let start = next_index(operators);
+        let id = labels.len();
+        let mut push = |operator| {
+            push_operator(operator, range.clone(), operators, source_map);
+        };
+
+        match keyword {
+            "if" => {
+                let conditional = Conditional {
+                    start,
+                    has_else: false,
+                };
+
+                // Skip the `if` branch, if the condition is zero.
+                push(Operator::Integer { value: 0 });
+                push(Operator::Opcode {
+                    opcode: Opcode::Equal,
+                });
+                push(reference(conditional.label("else")));
+                push(Operator::Opcode {
+                    opcode: Opcode::JumpIf,
+                });
+
+                self.open.push(Block::Conditional(conditional));
+            }
+            "else" => match self.open.last_mut() {
+                Some(Block::Conditional(conditional))
+                    if !conditional.has_else =>
+                {
+                    // The `if` branch skips the `else` branch.
+                    push(reference(conditional.label("end")));
+                    push(Operator::Opcode {
+                        opcode: Opcode::Jump,
+                    });
+
+                    define_label(conditional.label("else"), operators, labels);
+                    conditional.has_else = true;
+                }
+                _ => {
+                    push(unmatched(keyword));
+                }
+            },
+            "loop" => {
+                // A loop doesn't compile into any operators at its start, so
+                // a directly nested loop would have the same first operator.
+                // But each loop defines a label here, so the number of labels
+                // that came before is unique to it.
+                let loop_ = Loop { id, range };
+
+                define_label(loop_.label("start"), operators, labels);
+                self.open.push(Block::Loop(loop_));
+            }
+            "while" | "break" | "continue" => {
+                let Some(loop_) = self.innermost_loop() else {
+                    push(unmatched(keyword));
+                    return;
+                };
+
+                let target = if keyword == "continue" {
+                    loop_.label("start")
+                } else {
+                    loop_.label("end")
+                };
+
+                if keyword == "while" {
+                    // Leave the loop, if the condition is zero.
+                    push(Operator::Integer { value: 0 });
+                    push(Operator::Opcode {
+                        opcode: Opcode::Equal,
+                    });
+                    push(reference(target));
+                    push(Operator::Opcode {
+                        opcode: Opcode::JumpIf,
+                    });
+                } else {
+                    push(reference(target));
+                    push(Operator::Opcode {
+                        opcode: Opcode::Jump,
+                    });
+                }
+            }
+            "{" => {
+                let quotation = Quotation { start };
+
+                // Push the address of the quotation, then skip over it. It's
+                // only evaluated when called.
+                push(reference(quotation.label("start")));
+                push(reference(quotation.label("end")));
+                push(Operator::Opcode {
+                    opcode: Opcode::Jump,
+                });
+
+                define_label(quotation.label("start"), operators, labels);
+                self.open.push(Block::Quotation(quotation));
+            }
+            "}" => match self.open.last() {
+                Some(Block::Quotation(quotation)) => {
+                    let label = quotation.label("end");
+                    self.open.pop();
+
+                    push(Operator::Opcode {
+                        opcode: Opcode::Return,
+                    });
+                    define_label(label, operators, labels);
+                }
+                _ => {
+                    push(unmatched(keyword));
+                }
+            },
+            "end" if matches!(self.open.last(), Some(Block::Quotation(_))) => {
+                // Only `}` closes a quotation.
+                push(unmatched(keyword));
+            }
+            "end" => match self.open.pop() {
+                Some(Block::Conditional(conditional)) => {
+                    conditional.close(operators, labels);
+                }
+                Some(Block::Loop(loop_)) => {
+                    push(reference(loop_.label("start")));
+                    push(Operator::Opcode {
+                        opcode: Opcode::Jump,
+                    });
+
+                    define_label(loop_.label("end"), operators, labels);
+                }
+                Some(Block::Procedure { range: _ }) => {
+                    push(Operator::Opcode {
+                        opcode: Opcode::Return,
+                    });
+                }
+                None => {
+                    push(unmatched(keyword));
+                }
+                Some(Block::Quotation(_)) => {
+                    unreachable!("Handled by the previous match arm.");
+                }
+            },
+            _ => {
+                unreachable!("Only keywords are passed to this method.");
+            }
+        }
+    }
+
+    /// # Deal with the blocks that are still open, at the end of the source
+    pub(crate) fn finish(
+        self,
+        operators: &mut Vec<Operator>,
+        labels: &mut Vec<Label>,
+        source_map: &mut BTreeMap<OperatorIndex, Range<usize>>,
+    ) {
+        // Without an `end`, it's unclear where a block is supposed to end. Its
+        // first keyword becomes an identifier, so evaluating it triggers an
+        // effect, and strict mode rejects it.
+        for block in self.open.into_iter().rev() {
+            match block {
+                Block::Conditional(conditional) => {
+                    operators[conditional.start.value() as usize] =
+                        unmatched("if");
+
+                    // The generated references still need to resolve.
+                    conditional.close(operators, labels);
+                }
+                Block::Loop(loop_) => {
+                    // The `loop` didn't compile into any operators, so the
+                    // identifier goes where its `end` would have been.
+                    push_operator(
+                        unmatched("loop"),
+                        loop_.range.clone(),
+                        operators,
+                        source_map,
+                    );
+
+                    define_label(loop_.label("end"), operators, labels);
+                }
+                Block::Procedure { range } => {
+                    // Same as for loops.
+                    push_operator(
+                        unmatched("proc"),
+                        range,
+                        operators,
+                        source_map,
+                    );
+                }
+                Block::Quotation(quotation) => {
+                    // Same as for conditionals.
+                    operators[quotation.start.value() as usize] =
+                        unmatched("{");
+
+                    define_label(quotation.label("end"), operators, labels);
+                }
+            }
+        }
+    }
+
+    /// # Find the loop that `while`, `break`, and `continue` refer to
+    ///
+    /// That's the innermost loop, even if they appear within a conditional
+    /// inside of it. But a quotation is evaluated separately from the code
+    /// around it, so they can't refer to a loop outside of the quotation.
+    fn innermost_loop(&self) -> Option<&Loop> {
+        self.open
+            .iter()
+            .rev()
+            .take_while(|block| !matches!(block, Block::Quotation(_)))
+            .find_map(|block| match block {
+                Block::Loop(loop_) => Some(loop_),
+                Block::Conditional(_)
+                | Block::Procedure { range: _ }
+                | Block::Quotation(_) => None,
+            })
+    }
+}
+
+enum Block {
+    Conditional(Conditional),
+    Loop(Loop),
+    Procedure {
+        /// # The range of the `proc` keyword in the source text
+        range: Range<usize>,
+    },
+    Quotation(Quotation),
+}
+
+/// # An `if` that hasn't been closed by an `end` yet
+struct Conditional {
+    /// # The index of the first operator that the `if` compiled into
+    ///
+    /// This is unique to the conditional, so it's used to name its labels.
+    start: OperatorIndex,
+
+    has_else: bool,
+}
+
+impl Conditional {
+    fn label(&self, suffix: &str) -> String {
+        format!("if {} {suffix}", self.start.value())
+    }
+
+    fn close(&self, operators: &[Operator], labels: &mut Vec<Label>) {
+        // Without an `else` branch, the `if` branch skips to the end.
+        let suffix = if self.has_else { "end" } else { "else" };
+        define_label(self.label(suffix), operators, labels);
+    }
+}
+
+/// # A `loop` that hasn't been closed by an `end` yet
+struct Loop {
+    /// # Identifies the loop in the names of its labels
+    id: usize,
+
+    /// # The range of the `loop` keyword in the source text
+    range: Range<usize>,
+}
+
+impl Loop {
+    fn label(&self, suffix: &str) -> String {
+        format!("loop {} {suffix}", self.id)
+    }
+}
+
+/// # A `{` that hasn't been closed by a `}` yet
+struct Quotation {
+    /// # The index of the first operator that the `{` compiled into
+    ///
+    /// Like for [`Conditional`], this is used to name its labels.
+    start: OperatorIndex,
+}
+
+impl Quotation {
+    fn label(&self, suffix: &str) -> String {
+        format!("quotation {} {suffix}", self.start.value())
+    }
+}
+
+/// # The part of a procedure definition that follows `proc`
+///
+/// Like `square in 1 out 1`.
+pub(crate) struct ProcedureHeader {
+    name: String,
+
+    /// # The range of the name in the source text
+    range: Range<usize>,
+
+    arity: Arity,
+}
+
+/// # Parse the header of a procedure, from the tokens that follow `proc`
+///
+/// Consumes the tokens that are part of the header. If it's malformed, stops
+/// at the first token that doesn't fit, so that one compiles like it would
+/// without the `proc`.
+pub(crate) fn parse_procedure_header(
+    script: &str,
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+) -> Option<ProcedureHeader> {
+    let mut next = |expected: &dyn Fn(TokenKind, &str) -> bool| {
+        while tokens
+            .next_if(|token| token.kind == TokenKind::Comment)
+            .is_some()
+        {}
+
+        tokens
+            .next_if(|token| expected(token.kind, &script[token.range.clone()]))
+            .map(|token| (token.range.clone(), &script[token.range]))
+    };
+    let count = |kind, token: &str| {
+        kind == TokenKind::Integer
+            && parse_integer(token).is_some_and(|value| value >= 0)
+    };
+
+    let (range, name) = next(&|kind, token| {
+        kind == TokenKind::Identifier && !is_keyword(token)
+    })?;
+    next(&|_, token| token == "in")?;
+    let (_, inputs) = next(&count)?;
+    next(&|_, token| token == "out")?;
+    let (_, outputs) = next(&count)?;
+
+    let count = |token| {
+        parse_integer(token)
+            .map(i32::cast_unsigned)
+            .unwrap_or_default()
+    };
+
+    Some(ProcedureHeader {
+        name: name.to_string(),
+        range,
+        arity: Arity {
+            inputs: count(inputs),
+            outputs: count(outputs),
+        },
+    })
+}
+
+fn reference(name: String) -> Operator {
+    Operator::Reference { name, target: None }
+}
+
+fn define_label(name: String, operators: &[Operator], labels: &mut Vec<Label>) {
+    labels.push(Label {
+        name,
+        operator: next_index(operators),
+        range: None,
+    });
+}
+
+/// # Compile a keyword that doesn't match up with the others
+///
+/// The result is an identifier, like any other token that the compiler doesn't
+/// recognize.
+fn unmatched(keyword: &str) -> Operator {
+    Operator::Identifier {
+        value: keyword.to_string(),
+    }
+}
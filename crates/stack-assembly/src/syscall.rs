@@ -0,0 +1,166 @@
+use crate::{Eval, Memory, OperandStackUnderflow, Value};
+
+/// # A service that a script requested from the host, by yielding
+///
+/// This implements a conventional way for scripts to request services, so
+/// hosts don't have to pop each value by hand. Before triggering
+/// [`Effect::Yield`], the script pushes the arguments, then the number of
+/// arguments, then the ID of the service:
+///
+/// ```text
+/// # Request service `2`, with the arguments `10` and `20`.
+/// 10 20 2 2 yield
+/// ```
+///
+/// The host decodes the request using [`Syscall::decode`], provides the
+/// service, then uses [`Syscall::complete`] to push any outputs and continue.
+/// Which services exist, and what their arguments and outputs mean, is up to
+/// the host.
+///
+/// [`Effect::Yield`]: crate::Effect::Yield
+///
+/// ## Example
+///
+/// ```
+/// use stack_assembly::{Effect, Eval, Script, Syscall, Value};
+///
+/// let script = Script::compile("3 4 2 1 yield");
+///
+/// let mut eval = Eval::new();
+/// assert_eq!(eval.run(&script).0, Effect::Yield);
+///
+/// let Ok(syscall) = Syscall::decode(&mut eval) else {
+///     unreachable!("The script pushes a complete request.");
+/// };
+/// assert_eq!(syscall.service, 1);
+///
+/// // Service `1` adds its arguments.
+/// let sum: i32 = syscall.arguments.iter().map(|value| value.to_i32()).sum();
+/// syscall.complete(&mut eval, [Value::from(sum)]);
+///
+/// assert_eq!(eval.run(&script).0, Effect::OutOfOperators);
+/// assert_eq!(eval.operand_stack.to_i32_slice(), &[7]);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Syscall {
+    /// # The ID of the requested service
+    pub service: u32,
+
+    /// # The arguments of the request
+    ///
+    /// In the order the script pushed them.
+    pub arguments: Vec<Value>,
+}
+
+impl Syscall {
+    /// # Pop a request from the operand stack
+    ///
+    /// Call this after the script triggered [`Effect::Yield`]. Returns an
+    /// error, if the operand stack doesn't hold a complete request. The
+    /// operand stack is left as it is in that case.
+    ///
+    /// [`Effect::Yield`]: crate::Effect::Yield
+    pub fn decode(eval: &mut Eval) -> Result<Self, OperandStackUnderflow> {
+        let values = &mut eval.operand_stack.values;
+
+        let [.., count, service] = values[..] else {
+            return Err(OperandStackUnderflow);
+        };
+        let Some(start) = usize::try_from(count.to_u32())
+            .ok()
+            .and_then(|count| (values.len() - 2).checked_sub(count))
+        else {
+            return Err(OperandStackUnderflow);
+        };
+
+        let arguments = values[start..values.len() - 2].to_vec();
+        values.truncate(start);
+
+        Ok(Self {
+            service: service.to_u32(),
+            arguments,
+        })
+    }
+
+    /// # Read the string that two of the arguments refer to
+    ///
+    /// Expects the argument at `index` to be the address of the string, and
+    /// the one after it to be its length, as is the [convention for strings].
+    /// Returns `None`, if those arguments don't exist, or if they don't refer
+    /// to a valid string.
+    ///
+    /// [convention for strings]: crate::Memory#strings
+    pub fn str_argument(
+        &self,
+        memory: &Memory,
+        index: usize,
+    ) -> Option<String> {
+        let [address, length] = self.arguments.get(index..index + 2)? else {
+            return None;
+        };
+
+        let address = address.to_u32();
+        let end = address.checked_add(length.to_u32())?;
+
+        memory.read_str(address..end).ok()
+    }
+
+    /// # Provide the outputs of the service, and let the script continue
+    ///
+    /// Pushes the outputs to the operand stack, in the order provided, then
+    /// clears the active effect.
+    pub fn complete(
+        self,
+        eval: &mut Eval,
+        outputs: impl IntoIterator<Item = Value>,
+    ) {
+        for output in outputs {
+            eval.operand_stack.push(output);
+        }
+
+        eval.clear_effect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Eval, OperandStackUnderflow, Script, Value};
+
+    use super::Syscall;
+
+    #[test]
+    fn incomplete_request_leaves_stack_unchanged() {
+        let script = Script::compile("1 2 5 3 yield");
+
+        let mut eval = Eval::new();
+        eval.run(&script);
+
+        assert!(matches!(
+            Syscall::decode(&mut eval),
+            Err(OperandStackUnderflow),
+        ));
+        assert_eq!(eval.operand_stack.to_i32_slice(), &[1, 2, 5, 3]);
+    }
+
+    #[test]
+    fn str_argument_reads_string_from_memory() {
+        let script = Script::compile("7 0 5 3 1 yield");
+
+        let mut eval = Eval::new();
+        let Ok(_) = eval.memory.write_str(0, "hello") else {
+            unreachable!("String fits into memory.");
+        };
+        eval.run(&script);
+
+        let Ok(syscall) = Syscall::decode(&mut eval) else {
+            unreachable!("Script pushes a complete request.");
+        };
+        assert_eq!(syscall.service, 1);
+        assert_eq!(syscall.arguments[0], Value::from(7));
+        assert_eq!(
+            syscall.str_argument(&eval.memory, 1).as_deref(),
+            Some("hello"),
+        );
+        assert_eq!(syscall.str_argument(&eval.memory, 2), None);
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{
+    Effect, Eval, OperatorIndex, Script, Value,
+    opcode::Opcode,
+    shadow::{Label, Shadow, num_targets},
+};
+
+/// # Records which values on the operand stack and in memory are tainted
+///
+/// This is an instrumentation mode for security-analysis experiments. Call
+/// [`Eval::enable_taint_tracking`] to enable it, then mark values that come
+/// from an untrusted source using [`Eval::taint_operand`] or
+/// [`Eval::taint_memory`]. Afterwards, query which values are tainted via
+/// [`Eval::taint`], or attach a [`TaintListener`] to be notified when tainted
+/// values reach a jump or a `write`.
+///
+/// Taint propagates like this:
+///
+/// - Integers and references are never tainted.
+/// - `copy`, `drop`, and `reverse_n` move the taint along with the values.
+/// - `write` stores the taint of its value. `read` produces a tainted value,
+///   if either the value in memory or the address is tainted.
+/// - All other operators produce tainted values, if any of their inputs are
+///   tainted. Values that pass through local variables or channels lose their
+///   taint.
+/// - If a native operator or `exec` changes values on the operand stack, the
+///   new values are tainted, if any of the previous ones were.
+///
+/// Values that the host adds to the operand stack are not tainted, unless the
+/// host marks them. Changes that the host makes to memory are not tracked,
+/// and neither does [`Eval::step_back`] restore any taint.
+///
+/// [`Eval::enable_taint_tracking`]: crate::Eval::enable_taint_tracking
+/// [`Eval::taint_operand`]: crate::Eval::taint_operand
+/// [`Eval::taint_memory`]: crate::Eval::taint_memory
+/// [`Eval::taint`]: crate::Eval::taint
+/// [`Eval::step_back`]: crate::Eval::step_back
+#[derive(Clone, Default)]
+pub struct Taint {
+    shadow: Shadow<bool>,
+    listeners: Vec<Arc<Mutex<dyn TaintListener>>>,
+}
+
+impl Taint {
+    /// # Access which values on a strand's operand stack are tainted
+    ///
+    /// The last entry belongs to the value on top of the stack. This reflects
+    /// the operand stack, as of the most recently evaluated operator, or the
+    /// most recent call to [`Eval::taint_operand`].
+    ///
+    /// [`Eval::taint_operand`]: crate::Eval::taint_operand
+    pub fn operand_stack(&self, strand: u32) -> &[bool] {
+        self.shadow.operand_stack(strand)
+    }
+
+    /// # Determine whether the value at the provided memory address is tainted
+    pub fn is_memory_tainted(&self, address: u32) -> bool {
+        self.shadow.memory(address)
+    }
+
+    pub(crate) fn taint_operand(&mut self, eval: &Eval, index: usize) {
+        self.shadow.label_operand(eval, index, true);
+    }
+
+    pub(crate) fn taint_memory(&mut self, address: u32) {
+        self.shadow.label_memory(address, true);
+    }
+
+    pub(crate) fn add_listener(
+        &mut self,
+        listener: Arc<Mutex<dyn TaintListener>>,
+    ) {
+        self.listeners.push(listener);
+    }
+
+    /// # Evaluate an operator, while keeping track of the taint
+    ///
+    /// Notifies the listeners before tainted values reach a jump or `write`.
+    pub(crate) fn evaluate(
+        &mut self,
+        index: OperatorIndex,
+        script: &Script,
+        eval: &mut Eval,
+        evaluate: impl FnOnce(&mut Eval) -> Result<(), Effect>,
+    ) -> Result<(), Effect> {
+        let listeners = &self.listeners;
+        let check = |opcode, tainted: &[bool], values: &[Value]| {
+            let notify = |f: &dyn Fn(&mut dyn TaintListener)| {
+                for listener in listeners {
+                    // If a listener panicked before, it's up to the listener
+                    // to deal with any inconsistent state that might have
+                    // resulted from that.
+                    let mut listener =
+                        listener.lock().unwrap_or_else(PoisonError::into_inner);
+                    f(&mut *listener);
+                }
+            };
+
+            if let Some(targets) = values.len().checked_sub(num_targets(opcode))
+            {
+                for (&target, _) in values[targets..]
+                    .iter()
+                    .zip(&tainted[targets..])
+                    .filter(|(_, tainted)| **tainted)
+                {
+                    notify(&|listener| listener.tainted_jump(index, target));
+                }
+            }
+
+            if let (
+                Opcode::Write,
+                [.., address, value],
+                [.., address_tainted, value_tainted],
+            ) = (opcode, values, tainted)
+                && (*address_tainted || *value_tainted)
+            {
+                notify(&|listener| {
+                    listener.tainted_write(index, *address, *value);
+                });
+            }
+
+            Ok(())
+        };
+
+        self.shadow.evaluate(index, script, eval, check, evaluate)
+    }
+}
+
+impl fmt::Debug for Taint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Taint")
+            .field("shadow", &self.shadow)
+            .field("listeners", &self.listeners.len())
+            .finish()
+    }
+}
+
+impl Label for bool {
+    const LITERAL: Self = false;
+    const REFERENCE: Self = false;
+    const UNKNOWN: Self = false;
+
+    fn computed(inputs: &[Self]) -> Self {
+        inputs.contains(&true)
+    }
+
+    fn read(value: Self, address: Self) -> Self {
+        value || address
+    }
+}
+
+/// # Is notified when tainted values reach a jump or `write`
+///
+/// Attach a listener using [`Eval::add_taint_listener`]. Both methods are
+/// called right before the operator is evaluated, and do nothing by default.
+///
+/// [`Eval::add_taint_listener`]: crate::Eval::add_taint_listener
+pub trait TaintListener: Send {
+    /// # Called before a jump or call to a tainted target
+    ///
+    /// Receives the index of the operator, and the target. This covers all
+    /// jumps, `call`, and `call_either`, which is called once for each of its
+    /// targets that is tainted.
+    fn tainted_jump(&mut self, operator: OperatorIndex, target: Value) {
+        let _ = (operator, target);
+    }
+
+    /// # Called before a `write` with a tainted address or value
+    ///
+    /// Receives the index of the operator, and its inputs.
+    fn tainted_write(
+        &mut self,
+        operator: OperatorIndex,
+        address: Value,
+        value: Value,
+    ) {
+        let _ = (operator, address, value);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Effect, Eval, Script};
+use crate::{CompileOptions, Effect, Eval, OperatorIndex, Script};
 
 #[test]
 fn add() {
@@ -196,3 +196,149 @@ fn divide_triggers_effect_on_overflow() {
     assert_eq!(effect, Effect::IntegerOverflow);
     assert_eq!(eval.operand_stack.to_i32_slice(), &[]);
 }
+
+#[test]
+fn arithmetic_triggers_effect_on_overflow_if_configured() {
+    // The host can configure the evaluation to trap on overflow. Then `+`,
+    // `-`, and `*` treat their inputs as signed, and trigger an effect instead
+    // of wrapping. This also applies, if the script has been optimized, and an
+    // operator has been fused with the integer before it.
+
+    for optimize in [false, true] {
+        let options = CompileOptions {
+            optimize,
+            ..CompileOptions::default()
+        };
+
+        for source in ["2147483647 1 +", "-2147483648 1 -", "65536 65536 *"] {
+            let script = Script::compile_with_options(source, options);
+
+            let mut eval = Eval::new();
+            eval.set_trap_on_overflow(true);
+            let (effect, _) = eval.run(&script);
+
+            assert_eq!(effect, Effect::IntegerOverflow, "{source}");
+            assert_eq!(eval.operand_stack.to_i32_slice(), &[], "{source}");
+        }
+    }
+}
+
+#[test]
+fn overflow_triggers_at_the_same_operator_if_optimized() {
+    // Fusing `+` with the integer before it must not change which operator
+    // the effect is attributed to.
+
+    for optimize in [false, true] {
+        let options = CompileOptions {
+            optimize,
+            ..CompileOptions::default()
+        };
+        let script = Script::compile_with_options("2147483647 1 +", options);
+
+        let mut eval = Eval::new();
+        eval.set_trap_on_overflow(true);
+        let (effect, operator) = eval.run(&script);
+
+        assert_eq!(effect, Effect::IntegerOverflow, "optimize: {optimize}");
+        assert_eq!(operator, OperatorIndex::new(2), "optimize: {optimize}");
+    }
+}
+
+#[test]
+fn trapping_on_overflow_does_not_affect_other_results() {
+    // Arithmetic that doesn't overflow has the same result, regardless of
+    // whether the evaluation traps on overflow.
+
+    let script = Script::compile("-1 1 + 3 - -2 * 2147483646 1 +");
+
+    let mut eval = Eval::new();
+    eval.set_trap_on_overflow(true);
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[6, 2147483647]);
+}
+
+#[test]
+fn div_floor_rounds_towards_negative_infinity() {
+    // `/` truncates its quotient, rounding it towards zero. `div_floor` rounds
+    // it towards negative infinity instead, which makes a difference if the
+    // inputs have different signs.
+
+    let script = Script::compile(
+        "7 2 div_floor -7 2 div_floor 7 -2 div_floor -7 -2 div_floor",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[3, -4, -4, 3]);
+}
+
+#[test]
+fn mod_floor_has_the_sign_of_the_divisor() {
+    // `mod_floor` is the modulo that matches `div_floor`. Its result has the
+    // same sign as the divisor, unlike the remainder that `/` pushes, which has
+    // the same sign as the dividend.
+
+    let script = Script::compile(
+        "7 2 mod_floor -7 2 mod_floor 7 -2 mod_floor -7 -2 mod_floor",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[1, 1, -1, -1]);
+}
+
+#[test]
+fn flooring_division_triggers_effects_like_divide() {
+    // Like `/`, `div_floor` and `mod_floor` trigger an effect on division by
+    // zero. `div_floor` also triggers one on overflow. `mod_floor` doesn't,
+    // since its result is `0` in that case.
+
+    for (source, expected) in [
+        ("1 0 div_floor", Effect::DivisionByZero),
+        ("1 0 mod_floor", Effect::DivisionByZero),
+        ("-2147483648 -1 div_floor", Effect::IntegerOverflow),
+        ("-2147483648 -1 mod_floor", Effect::OutOfOperators),
+    ] {
+        let script = Script::compile(source);
+
+        let mut eval = Eval::new();
+        let (effect, _) = eval.run(&script);
+
+        assert_eq!(effect, expected, "{source}");
+    }
+}
+
+#[test]
+fn divmod_u_treats_its_inputs_as_unsigned() {
+    // `divmod_u` works like `/`, pushing quotient and remainder, but treats its
+    // inputs as unsigned. This is required to divide values that are larger
+    // than the largest signed 32-bit integer.
+
+    let script = Script::compile("4294967295 2 divmod_u 7 -1 divmod_u");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[2147483647, 1, 0, 7]);
+}
+
+#[test]
+fn divmod_u_by_zero_triggers_effect() {
+    // Like `/`, `divmod_u` triggers an effect on division by zero. Unlike `/`,
+    // it can't overflow.
+
+    let script = Script::compile("1 0 divmod_u");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::DivisionByZero);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[]);
+}
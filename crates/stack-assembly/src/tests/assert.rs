@@ -26,3 +26,34 @@ fn assert_triggers_effect() {
     assert_eq!(effect, Effect::AssertionFailed);
     assert_eq!(eval.operand_stack.to_i32_slice(), &[]);
 }
+
+#[test]
+fn unreachable_triggers_effect() {
+    // `unreachable` marks code that the evaluation should never reach. It
+    // triggers an effect unconditionally, without consuming any input.
+
+    let script = Script::compile("1 unreachable 2");
+
+    let mut eval = Eval::new();
+    let (effect, operator) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Unreachable);
+    assert_eq!(operator.value(), 1);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[1]);
+}
+
+#[test]
+fn todo_triggers_effect() {
+    // `todo` marks code that hasn't been written yet. Like `unreachable`, it
+    // triggers an effect unconditionally, but a different one, so the host can
+    // tell the two apart.
+
+    let script = Script::compile("1 todo 2");
+
+    let mut eval = Eval::new();
+    let (effect, operator) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Todo);
+    assert_eq!(operator.value(), 1);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[1]);
+}
@@ -157,3 +157,18 @@ fn shift_right_signed() {
     assert_eq!(effect, Effect::OutOfOperators);
     assert_eq!(eval.operand_stack.to_u32_slice(), &[0xff00000f]);
 }
+
+#[test]
+fn shift_only_considers_lowest_five_bits_of_number_of_positions() {
+    // Shifting by 32 or more positions wraps around, instead of shifting all
+    // bits out.
+
+    let script =
+        Script::compile("0x000000ff 36 shift_left 0xf00000ff 36 shift_right");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[0x00000ff0, 0xff00000f]);
+}
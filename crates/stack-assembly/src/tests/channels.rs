@@ -0,0 +1,97 @@
+use crate::{Effect, Eval, Script};
+
+#[test]
+fn receive_returns_values_in_the_order_they_were_sent() {
+    // `send` adds a value to a channel, and `receive` takes the oldest value
+    // from it.
+
+    let script = Script::compile("0 3 send 0 5 send 0 receive 0 receive");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[3, 5]);
+}
+
+#[test]
+fn channels_are_independent() {
+    // Each number refers to a different channel.
+
+    let script = Script::compile("0 3 send 1 5 send 1 receive");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[5]);
+    assert_eq!(eval.receive(0).map(|value| value.to_u32()), Some(3));
+}
+
+#[test]
+fn receive_from_empty_channel_triggers_effect_until_value_is_sent() {
+    // Receiving from an empty channel triggers an effect. Once the host has
+    // sent a value to the channel and cleared the effect, `receive` is
+    // evaluated again.
+
+    let script = Script::compile("1 0 receive");
+
+    let mut eval = Eval::new();
+
+    let (effect, operator) = eval.run(&script);
+    assert_eq!(effect, Effect::ChannelEmpty);
+    assert_eq!(eval.next_operator(), operator);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[1, 0]);
+
+    eval.send(0, 3);
+    eval.clear_effect();
+
+    let (effect, _) = eval.run(&script);
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[1, 3]);
+}
+
+#[test]
+fn strands_share_channels() {
+    // All strands of an evaluation can send to, and receive from, the same
+    // channels.
+
+    let script = Script::compile(
+        "
+        @strand spawn resume
+        0 receive
+        return
+
+        strand:
+            0 3 send
+            0 resume
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Return);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[3]);
+}
+
+#[test]
+fn host_can_connect_evaluations_through_channels() {
+    // By receiving the values that one evaluation sends, and sending them to
+    // another, a host can connect both into a pipeline.
+
+    let producer = Script::compile("0 3 send 0 5 send");
+    let consumer = Script::compile("0 receive 0 receive +");
+
+    let mut a = Eval::new();
+    let mut b = Eval::new();
+
+    a.run(&producer);
+    while let Some(value) = a.receive(0) {
+        b.send(0, value);
+    }
+    let (effect, _) = b.run(&consumer);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(b.operand_stack.to_u32_slice(), &[8]);
+}
@@ -0,0 +1,115 @@
+use crate::{Effect, Eval, Script};
+
+#[test]
+fn if_evaluates_branch_on_nonzero_condition() {
+    // `if` takes a condition. If that condition is non-zero, the evaluation
+    // continues with the operators between `if` and `end`.
+
+    let script = Script::compile("1 if 2 end 3");
+
+    let mut eval = Eval::new();
+    eval.run(&script);
+
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[2, 3]);
+}
+
+#[test]
+fn if_skips_branch_on_zero_condition() {
+    // If the condition is zero, the evaluation continues after the `end`.
+
+    let script = Script::compile("0 if 2 end 3");
+
+    let mut eval = Eval::new();
+    eval.run(&script);
+
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[3]);
+}
+
+#[test]
+fn else_branch_is_evaluated_on_zero_condition() {
+    // An `if` can have an `else` branch, which is only evaluated if the
+    // condition is zero.
+
+    let script = Script::compile(
+        "
+        0 copy if 1 else 2 end
+        ",
+    );
+
+    for (condition, expected) in [(0, 2), (-1, 1)] {
+        let mut eval = Eval::new();
+        eval.operand_stack.push(condition);
+        eval.run(&script);
+
+        assert_eq!(eval.operand_stack.to_i32_slice(), &[condition, expected]);
+    }
+}
+
+#[test]
+fn conditionals_can_be_nested() {
+    // Each `else` and `end` belongs to the innermost `if` that doesn't have
+    // one yet.
+
+    let script = Script::compile(
+        "
+        sign:
+            0 copy 0 < if
+                0 drop -1
+            else
+                0 copy 0 > if
+                    0 drop 1
+                end
+            end
+            return
+        ",
+    );
+
+    for (input, expected) in [(-5, -1), (0, 0), (5, 1)] {
+        let mut eval = Eval::new();
+        eval.operand_stack.push(input);
+        eval.run(&script);
+
+        assert_eq!(eval.operand_stack.to_i32_slice(), &[expected]);
+    }
+}
+
+#[test]
+fn generated_operators_map_to_keywords() {
+    // Conditionals compile into operators that a script could use directly.
+    // Those map to the keyword they were compiled from.
+
+    let source = "1 if 2 else 3 end";
+    let script = Script::compile(source);
+
+    let operators = script
+        .operators()
+        .map(|(operator, _)| {
+            let Ok(range) = script.map_operator_to_source(&operator) else {
+                unreachable!("All operators were compiled from the source.");
+            };
+            &source[range]
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        operators,
+        ["1", "if", "if", "if", "if", "2", "else", "else", "3"],
+    );
+}
+
+#[test]
+fn unmatched_keyword_triggers_unknown_identifier() {
+    // An `else` or `end` without an `if`, and an `if` without an `end`, can't
+    // be evaluated as intended. Like unknown identifiers, they trigger an
+    // effect.
+
+    for source in ["1 end", "1 else", "1 if 2", "1 if 2 else 3"] {
+        let script = Script::compile(source);
+
+        let mut eval = Eval::new();
+        let (effect, _) = eval.run(&script);
+
+        assert_eq!(effect, Effect::UnknownIdentifier, "Script: {source}");
+        assert_eq!(eval.operand_stack.to_i32_slice(), &[1]);
+    }
+}
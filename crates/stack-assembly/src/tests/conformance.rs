@@ -0,0 +1,22 @@
+use std::path::Path;
+
+use crate::ConformanceTest;
+
+#[test]
+fn conformance_tests_pass() {
+    // The language's behavior is also specified by the script files in the
+    // `conformance` directory. Each of them states the result that evaluating
+    // it should have.
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("conformance");
+    let Ok(tests) = ConformanceTest::discover(dir) else {
+        unreachable!("The conformance tests are available.");
+    };
+    assert!(!tests.is_empty());
+
+    for test in tests {
+        if let Err(failure) = test.run() {
+            panic!("{}: {failure}", test.path.display());
+        }
+    }
+}
@@ -51,6 +51,39 @@ fn jump_if_does_nothing_on_zero_condition() {
     assert_eq!(eval.operand_stack.to_u32_slice(), &[1, 2]);
 }
 
+#[test]
+fn jump_if_comparison_jumps_depending_on_result() {
+    // `jump_if_eq`, `jump_if_ne`, `jump_if_lt`, `jump_if_le`, `jump_if_gt`, and
+    // `jump_if_ge` combine a comparison with `jump_if`. They take two values
+    // and an operator index, and jump, if comparing the values (treating them
+    // as signed) has the respective result.
+
+    for (source, expected) in [
+        ("1 1 @target jump_if_eq", true),
+        ("1 2 @target jump_if_eq", false),
+        ("1 2 @target jump_if_ne", true),
+        ("1 1 @target jump_if_ne", false),
+        ("-1 1 @target jump_if_lt", true),
+        ("1 1 @target jump_if_lt", false),
+        ("1 1 @target jump_if_le", true),
+        ("2 1 @target jump_if_le", false),
+        ("1 -1 @target jump_if_gt", true),
+        ("1 1 @target jump_if_gt", false),
+        ("1 1 @target jump_if_ge", true),
+        ("1 2 @target jump_if_ge", false),
+    ] {
+        let script = Script::compile(&format!("{source} 1 target: 2"));
+
+        let mut eval = Eval::new();
+        let (effect, _) = eval.run(&script);
+
+        let expected: &[u32] = if expected { &[2] } else { &[1, 2] };
+
+        assert_eq!(effect, Effect::OutOfOperators, "{source}");
+        assert_eq!(eval.operand_stack.to_u32_slice(), expected, "{source}");
+    }
+}
+
 #[test]
 fn return_() {
     // If the call stack is empty, as is the case when the evaluation starts,
@@ -147,6 +180,28 @@ fn call_either_jumps_to_second_index_on_non_zero_condition() {
     assert_eq!(eval.operand_stack.to_u32_slice(), &[2]);
 }
 
+#[test]
+fn pc_pushes_index_of_current_operator() {
+    // `pc` pushes the index of the `pc` operator itself. This is the same kind
+    // of value that a reference to a label pushes, so it can be used to
+    // compute jump targets relative to the current position.
+
+    let script = Script::compile(
+        "
+        pc
+        pc 5 + jump
+        3
+        4
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[0, 4]);
+}
+
 #[test]
 fn invalid_reference_triggers_effect() {
     // A reference that is not paired with a matching label can't return a
@@ -160,3 +215,16 @@ fn invalid_reference_triggers_effect() {
     assert_eq!(effect, Effect::InvalidReference);
     assert_eq!(eval.operand_stack.to_u32_slice(), &[]);
 }
+
+#[test]
+fn reference_to_duplicate_label_refers_to_first_label() {
+    // If multiple labels have the same name, a reference to that name refers
+    // to the first of them, regardless of where the reference is.
+
+    let script = Script::compile("label: @label label: @label");
+
+    let mut eval = Eval::new();
+    eval.run(&script);
+
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[0, 0]);
+}
@@ -0,0 +1,84 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{Device, Effect, Eval, OperatorIndex, Script, Value};
+
+#[test]
+fn read_and_write_are_forwarded_to_mapped_device() {
+    // Accesses within the range of a device are serviced by the device, with
+    // addresses relative to the start of the range. The memory is untouched.
+
+    let script = Script::compile("101 3 write 102 read");
+
+    let device = Arc::new(Mutex::new(Recorder::default()));
+
+    let mut eval = Eval::new();
+    eval.map_device(100..104, device.clone());
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[2]);
+    assert_eq!(eval.memory.read(101).ok(), Some(Value::from(0)));
+
+    let device = device.lock().unwrap();
+    assert_eq!(device.writes, vec![(1, 3)]);
+}
+
+#[test]
+fn accesses_outside_of_device_range_use_memory() {
+    // Only the addresses within the range are forwarded to the device.
+
+    let script = Script::compile("99 3 write 104 5 write 99 read 104 read");
+
+    let device = Arc::new(Mutex::new(Recorder::default()));
+
+    let mut eval = Eval::new();
+    eval.map_device(100..104, device.clone());
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[3, 5]);
+    assert!(device.lock().unwrap().writes.is_empty());
+}
+
+#[test]
+fn device_can_trigger_effect() {
+    // A device can reject an access, which triggers the effect it returns.
+
+    let script = Script::compile("200 1 write");
+
+    let mut eval = Eval::new();
+    eval.map_device(100..104, Arc::new(Mutex::new(Recorder::default())));
+    eval.map_device(200..201, Arc::new(Mutex::new(ReadOnly)));
+    let (effect, operator) = eval.run(&script);
+
+    assert_eq!(effect, Effect::InvalidAddress);
+    assert_eq!(operator, OperatorIndex::new(2));
+}
+
+#[derive(Default)]
+struct Recorder {
+    writes: Vec<(u32, u32)>,
+}
+
+impl Device for Recorder {
+    fn read(&mut self, offset: u32) -> Result<Value, Effect> {
+        Ok(Value::from(offset))
+    }
+
+    fn write(&mut self, offset: u32, value: Value) -> Result<(), Effect> {
+        self.writes.push((offset, value.to_u32()));
+        Ok(())
+    }
+}
+
+struct ReadOnly;
+
+impl Device for ReadOnly {
+    fn read(&mut self, _: u32) -> Result<Value, Effect> {
+        Ok(Value::from(0))
+    }
+
+    fn write(&mut self, _: u32, _: Value) -> Result<(), Effect> {
+        Err(Effect::InvalidAddress)
+    }
+}
@@ -1,4 +1,8 @@
-use crate::{Effect, Eval, Script};
+use std::time::Duration;
+
+use crate::{
+    CompileError, CompileOptions, Effect, Eval, OperatorIndex, Script, Value,
+};
 
 #[test]
 fn empty_script_triggers_out_of_tokens() {
@@ -45,6 +49,245 @@ fn active_effect_prevents_evaluation_from_advancing() {
     assert_eq!(eval.operand_stack.to_u32_slice(), &[]);
 }
 
+#[test]
+fn evaluation_can_be_resumed_later() {
+    // After an effect, the host can put the evaluation aside and resume it
+    // later, even on another thread.
+
+    let script = Script::compile("1 yield 2");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+    assert_eq!(effect, Effect::Yield);
+
+    let Ok(eval) = std::thread::spawn(move || {
+        eval.resume(&script);
+        eval
+    })
+    .join() else {
+        unreachable!("Thread does not panic.");
+    };
+
+    assert_eq!(
+        eval.effect().map(|(effect, _)| effect),
+        Some(Effect::OutOfOperators)
+    );
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[1, 2]);
+}
+
+#[test]
+fn evaluation_can_start_at_any_operator() {
+    // The host can choose to start the evaluation at an operator other than the
+    // first one.
+
+    let script = Script::compile("1 2 return");
+
+    let mut eval = Eval::start_at(OperatorIndex::new(1));
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Return);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[2]);
+}
+
+#[test]
+fn host_can_move_evaluation_to_another_operator() {
+    // After handling an effect, the host can choose where the evaluation
+    // continues.
+
+    let script = Script::compile("1 0 / 2 handler: 3");
+    let Some((_, handler)) =
+        script.labels().find(|(name, _)| *name == "handler")
+    else {
+        unreachable!("The script defines this label.");
+    };
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+    assert_eq!(effect, Effect::DivisionByZero);
+
+    eval.set_next_operator(handler);
+    assert_eq!(eval.next_operator(), handler);
+
+    let (effect, _) = eval.resume(&script);
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice().last(), Some(&3));
+    assert!(!eval.operand_stack.to_u32_slice().contains(&2));
+}
+
+#[test]
+fn start_directive_selects_entry_point() {
+    // The `.start` directive names the label that the evaluation starts at.
+    // Code before it is reachable, even if nothing else refers to it, so
+    // stripping unreachable code keeps it.
+
+    let source = "
+        .start main
+
+        square:
+            0 copy *
+            return
+
+        main:
+            3 @square call
+        ";
+    let options = CompileOptions {
+        strip_unreachable: true,
+        ..CompileOptions::default()
+    };
+
+    for script in [
+        Script::compile(source),
+        Script::compile_with_options(source, options),
+    ] {
+        assert_eq!(script.start(), OperatorIndex::new(4));
+        assert!(script.lint().is_empty());
+
+        let mut eval = Eval::start_at(script.start());
+        eval.load_data(&script);
+        let (effect, _) = eval.run(&script);
+
+        assert_eq!(effect, Effect::OutOfOperators);
+        assert_eq!(eval.operand_stack.to_u32_slice(), &[9]);
+    }
+}
+
+#[test]
+fn start_directive_with_invalid_label_triggers_effect() {
+    // If the label of the `.start` directive doesn't exist, loading the data
+    // triggers an effect, before any operators are evaluated. A second
+    // `.start` directive is malformed.
+
+    let script = Script::compile(".start main 1");
+
+    assert_eq!(script.start(), OperatorIndex::new(0));
+    assert_eq!(
+        script.check(),
+        [CompileError::InvalidReference { range: 7..11 }],
+    );
+
+    let mut eval = Eval::start_at(script.start());
+    eval.load_data(&script);
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::InvalidReference);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[]);
+
+    let script = Script::compile(".start a .start a a:");
+    assert_eq!(
+        script.check().first(),
+        Some(&CompileError::InvalidDirective { range: 9..15 }),
+    );
+}
+
+#[test]
+fn host_can_provide_jump_target() {
+    // The host can compute operator indices and pass them to the script, which
+    // can use them like the values that references to labels push.
+
+    let script = Script::compile(
+        "
+        jump
+        start:
+            1 2 3
+        ",
+    );
+
+    let Some((_, start)) = script.labels().find(|&(name, _)| name == "start")
+    else {
+        unreachable!("Label is defined in the script.");
+    };
+    let Some(target) = start.checked_add(2) else {
+        unreachable!("Index is small enough to not overflow.");
+    };
+
+    let mut eval = Eval::new();
+    eval.operand_stack.push(target);
+    let (effect, operator) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[3]);
+    assert_eq!(operator.checked_sub(3), Some(start));
+    assert_eq!(OperatorIndex::from(Value::from(target)), target);
+}
+
+#[test]
+fn evaluation_can_advance_by_multiple_steps() {
+    // The host can advance the evaluation by a number of steps at once. This
+    // stops early, if an effect triggers.
+
+    let script = Script::compile("1 2 3 4 yield 5");
+
+    let mut eval = Eval::new();
+
+    assert_eq!(eval.step_n(&script, 3), None);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[1, 2, 3]);
+
+    let Some((effect, operator)) = eval.step_n(&script, 10) else {
+        unreachable!("`yield` triggers an effect.");
+    };
+    assert_eq!(effect, Effect::Yield);
+    assert_eq!(operator, OperatorIndex::new(4));
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[1, 2, 3, 4]);
+}
+
+#[test]
+fn evaluation_can_run_within_time_budget() {
+    // The host can limit how long an evaluation may run for. If the script
+    // triggers an effect before that, it is returned as usual.
+
+    let script = Script::compile("1 yield loop: @loop jump");
+
+    let mut eval = Eval::new();
+
+    let effect = eval.run_for(&script, Duration::from_secs(60));
+    assert_eq!(effect, Some((Effect::Yield, OperatorIndex::new(1))));
+
+    eval.clear_effect();
+    let effect = eval.run_for(&script, Duration::ZERO);
+    assert_eq!(effect, None);
+    assert_eq!(eval.effect(), None);
+}
+
+#[test]
+fn pending_effects_become_active_one_after_the_other() {
+    // The host can trigger additional effects while one is active. Those
+    // become active in order, and the evaluation only continues once all of
+    // them have been cleared.
+
+    let script = Script::compile("1 yield 2");
+
+    let mut eval = Eval::new();
+    let (_, operator) = eval.run(&script);
+    eval.trigger_effect(Effect::AssertionFailed, operator);
+    eval.trigger_effect(Effect::Cancelled, operator);
+
+    assert_eq!(
+        eval.pending_effects()
+            .map(|(effect, _)| effect)
+            .collect::<Vec<_>>(),
+        [Effect::Yield, Effect::AssertionFailed, Effect::Cancelled],
+    );
+
+    eval.clear_effect();
+    let (effect, _) = eval.run(&script);
+    assert_eq!(effect, Effect::AssertionFailed);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[1]);
+
+    let drained = eval.drain_effects();
+    assert_eq!(
+        drained,
+        [
+            (Effect::AssertionFailed, operator),
+            (Effect::Cancelled, operator)
+        ]
+    );
+    assert_eq!(eval.effect(), None);
+
+    let (effect, _) = eval.run(&script);
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[1, 2]);
+}
+
 #[test]
 fn stack_underflow_triggers_effect() {
     // Popping a value from an empty stack is a stack underflow and triggers an
@@ -58,3 +301,89 @@ fn stack_underflow_triggers_effect() {
     assert_eq!(effect, Effect::OperandStackUnderflow);
     assert_eq!(eval.operand_stack.to_u32_slice(), &[]);
 }
+
+#[test]
+fn running_out_of_fuel_triggers_effect() {
+    // If the host limits the fuel, evaluation stops with the respective effect
+    // once it has been used up.
+
+    let script = Script::compile("1 2 3");
+
+    let mut eval = Eval::new();
+    eval.set_fuel(Some(2));
+
+    let (effect, _) = eval.run(&script);
+    assert_eq!(effect, Effect::OutOfFuel);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[1, 2]);
+}
+
+#[test]
+fn evaluation_continues_after_refueling() {
+    // After the host provides more fuel, evaluation continues with the
+    // operator that could not be evaluated before.
+
+    let script = Script::compile("1 2 3");
+
+    let mut eval = Eval::new();
+    eval.set_fuel(Some(2));
+
+    let (effect, _) = eval.run(&script);
+    assert_eq!(effect, Effect::OutOfFuel);
+
+    eval.set_fuel(Some(1));
+    eval.clear_effect();
+
+    let (effect, _) = eval.run(&script);
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[1, 2, 3]);
+}
+
+#[test]
+fn cloned_evaluations_continue_independently() {
+    // Cloning an evaluation forks it. Each clone has its own operand stack and
+    // memory, even if the clones start out sharing them.
+
+    let script = Script::compile("0 yield write");
+
+    let mut a = Eval::new();
+    let (effect, _) = a.run(&script);
+    assert_eq!(effect, Effect::Yield);
+    a.clear_effect();
+
+    let mut b = a.clone();
+    a.operand_stack.push(1);
+    b.operand_stack.push(2);
+
+    a.run(&script);
+    b.run(&script);
+
+    assert_eq!(a.memory.to_u32_slice()[0], 1);
+    assert_eq!(b.memory.to_u32_slice()[0], 2);
+}
+
+#[test]
+fn cancellation_stops_evaluation() {
+    // An evaluation can be cancelled, even while it's running on another
+    // thread. Afterwards, it can continue.
+
+    let script = Script::compile("loop: @loop jump");
+
+    let mut eval = Eval::new();
+    let handle = eval.cancellation_handle();
+
+    let thread = std::thread::spawn(move || {
+        let result = eval.run(&script);
+        (eval, result)
+    });
+    handle.cancel();
+    let Ok((mut eval, (effect, _))) = thread.join() else {
+        unreachable!("Evaluation does not panic.");
+    };
+
+    assert_eq!(effect, Effect::Cancelled);
+
+    eval.clear_effect();
+    eval.set_fuel(Some(2));
+    let (effect, _) = eval.run(&Script::compile("loop: @loop jump"));
+    assert_eq!(effect, Effect::OutOfFuel);
+}
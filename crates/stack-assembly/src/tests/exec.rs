@@ -0,0 +1,181 @@
+use crate::{Effect, Eval, Limits, OperatorIndex, Script, Value};
+
+#[test]
+fn exec_evaluates_generated_code() {
+    // `exec` compiles the string in the provided range of memory, and
+    // evaluates it with the same operand stack. Afterwards, evaluation
+    // continues after `exec`.
+
+    let script = Script::compile("1 0 5 exec 4");
+
+    let mut eval = Eval::new();
+    eval.enable_exec();
+    let Ok(_) = eval.memory.write_str(0, "2 + 3") else {
+        unreachable!("String fits into memory.");
+    };
+
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[3, 3, 4]);
+}
+
+#[test]
+fn exec_is_disabled_by_default() {
+    // Generating code at runtime is a research mode, that the host has to
+    // opt into.
+
+    let script = Script::compile("0 5 exec");
+
+    let mut eval = Eval::new();
+    let (effect, operator) = eval.run(&script);
+
+    assert_eq!(effect, Effect::DisabledOperator);
+    assert_eq!(operator, OperatorIndex::new(2));
+}
+
+#[test]
+fn generated_code_can_have_its_own_control_flow() {
+    // Labels within the generated code refer to its own operators. `return`
+    // with an empty call stack ends the generated code early.
+
+    let script = Script::compile("3 0 39 exec 1");
+
+    let mut eval = Eval::new();
+    eval.enable_exec();
+    let Ok(_) = eval
+        .memory
+        .write_str(0, "loop: 1 - 0 copy @loop jump_if return 7")
+    else {
+        unreachable!("String fits into memory.");
+    };
+
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[0, 1]);
+}
+
+#[test]
+fn effects_in_generated_code_are_attributed_to_exec() {
+    // If the generated code triggers an effect, that effect triggers at
+    // `exec`. The rest of the generated code is not evaluated.
+
+    let script = Script::compile("0 10 exec 1");
+
+    let mut eval = Eval::new();
+    eval.enable_exec();
+    let Ok(_) = eval.memory.write_str(0, "2 0 assert") else {
+        unreachable!("String fits into memory.");
+    };
+
+    let (effect, operator) = eval.run(&script);
+
+    assert_eq!(effect, Effect::AssertionFailed);
+    assert_eq!(operator, OperatorIndex::new(2));
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[2]);
+}
+
+#[test]
+fn exec_fails_on_invalid_string() {
+    // The range of memory must hold a valid string.
+
+    let script = Script::compile("0 1 exec");
+
+    let mut eval = Eval::new();
+    eval.enable_exec();
+    let Ok(()) = eval.memory.write(0, Value::from(-1)) else {
+        unreachable!("Address is valid.");
+    };
+
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::InvalidAddress);
+}
+
+#[test]
+fn running_out_of_fuel_in_generated_code_does_not_repeat_exec() {
+    // Generated code has already modified the operand stack, when it runs out
+    // of fuel. Evaluating `exec` again would find the wrong operands, so the
+    // evaluation continues after it instead.
+
+    let script = Script::compile("0 3 exec");
+
+    let mut eval = Eval::new();
+    eval.enable_exec();
+    eval.set_fuel(Some(4));
+    let Ok(_) = eval.memory.write_str(0, "1 2") else {
+        unreachable!("String fits into memory.");
+    };
+
+    let (effect, operator) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfFuel);
+    assert_eq!(operator, OperatorIndex::new(2));
+    assert_eq!(eval.next_operator(), OperatorIndex::new(3));
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[1]);
+
+    eval.set_fuel(None);
+    let _ = eval.clear_effect();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[1]);
+}
+
+#[test]
+fn nesting_of_exec_is_limited() {
+    // Generated code can evaluate `exec` itself, but not without limit. Code
+    // that evaluates itself triggers an effect, instead of nesting forever.
+
+    for max_call_depth in [None, Some(3)] {
+        let script = Script::compile("0 8 exec");
+
+        let mut eval = Eval::with_limits(Limits {
+            max_call_depth,
+            ..Limits::default()
+        });
+        eval.enable_exec();
+        let Ok(_) = eval.memory.write_str(0, "0 8 exec") else {
+            unreachable!("String fits into memory.");
+        };
+
+        let (effect, operator) = eval.run(&script);
+
+        assert_eq!(effect, Effect::CallStackOverflow);
+        assert_eq!(operator, OperatorIndex::new(2));
+    }
+}
+
+#[test]
+fn generated_code_can_not_switch_strands() {
+    // Operator indices within generated code refer to the generated script. A
+    // strand that was suspended there could never be resumed correctly.
+
+    let script = Script::compile("0 22 exec");
+
+    let mut eval = Eval::new();
+    eval.enable_exec();
+    let Ok(_) = eval.memory.write_str(0, "@x spawn resume x: 7 yield") else {
+        unreachable!("String fits into memory.");
+    };
+
+    let (effect, operator) = eval.run(&script);
+
+    assert_eq!(effect, Effect::DisabledOperator);
+    assert_eq!(operator, OperatorIndex::new(2));
+    assert_eq!(eval.current_strand(), 0);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[3]);
+
+    let script = Script::compile("0 8 exec");
+
+    let mut eval = Eval::new();
+    eval.enable_exec();
+    let Ok(_) = eval.memory.write_str(0, "0 resume") else {
+        unreachable!("String fits into memory.");
+    };
+
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::DisabledOperator);
+}
@@ -0,0 +1,118 @@
+use crate::{Effect, Eval, Script};
+
+#[test]
+fn locals_start_out_as_zero() {
+    // `locals` reserves the provided number of local variables, each of which
+    // starts out as `0`. `local_get` pushes the value of the local variable
+    // with the provided index.
+
+    let script = Script::compile("2 locals 0 local_get 1 local_get");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[0, 0]);
+}
+
+#[test]
+fn local_set() {
+    // `local_set` takes an index and a value, and sets the local variable with
+    // that index to the value.
+
+    let script = Script::compile(
+        "
+        2 locals
+        0 3 local_set
+        1 5 local_set
+        1 local_get
+        0 local_get
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[5, 3]);
+}
+
+#[test]
+fn each_call_has_its_own_locals() {
+    // The local variables that a routine reserves are discarded when it
+    // returns. They don't affect those of its caller, and the next call starts
+    // from scratch.
+
+    let script = Script::compile(
+        "
+        1 locals
+        0 3 local_set
+
+        @routine call
+        @routine call
+        0 local_get
+        @end jump
+
+        routine:
+            1 locals
+            0 local_get
+            0 5 local_set
+            return
+
+        end:
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[0, 0, 3]);
+}
+
+#[test]
+fn routine_cant_access_locals_of_caller() {
+    // A called routine only has access to the local variables it reserved
+    // itself.
+
+    let script = Script::compile(
+        "
+        1 locals
+        @routine call
+
+        routine:
+            0 local_get
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::InvalidLocal);
+}
+
+#[test]
+fn invalid_index_triggers_effect() {
+    // Accessing a local variable that hasn't been reserved triggers an effect.
+
+    let script = Script::compile("1 locals 1 local_get");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::InvalidLocal);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[]);
+}
+
+#[test]
+fn reserving_too_many_locals_triggers_effect() {
+    // There's a limit on the number of local variables, which keeps a script
+    // from using up all of the host's memory.
+
+    let script = Script::compile("-1 locals");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::InvalidLocal);
+}
@@ -0,0 +1,124 @@
+use crate::{Effect, Eval, Script};
+
+#[test]
+fn loop_repeats_until_break() {
+    // `loop` starts a block that repeats, until `break` leaves it. Once the
+    // evaluation reaches the `end`, it continues at the start of the loop.
+
+    let script = Script::compile(
+        "
+        0
+        loop
+            1 +
+            0 copy 3 = if
+                break
+            end
+        end
+        10
+        ",
+    );
+
+    let mut eval = Eval::new();
+    eval.run(&script);
+
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[3, 10]);
+}
+
+#[test]
+fn while_leaves_loop_on_zero_condition() {
+    // `while` takes a condition. If that condition is zero, it leaves the loop,
+    // like `break` would. Otherwise, the evaluation continues after it.
+
+    let script = Script::compile(
+        "
+        0
+        loop
+            0 copy 5 < while
+            1 +
+        end
+        ",
+    );
+
+    let mut eval = Eval::new();
+    eval.run(&script);
+
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[5]);
+}
+
+#[test]
+fn continue_starts_next_iteration() {
+    // `continue` skips the rest of the loop, continuing at its start.
+
+    let script = Script::compile(
+        "
+        0
+        loop
+            1 +
+            0 copy 3 < if
+                continue
+            end
+            break
+        end
+        ",
+    );
+
+    let mut eval = Eval::new();
+    eval.run(&script);
+
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[3]);
+}
+
+#[test]
+fn break_and_continue_refer_to_innermost_loop() {
+    // In nested loops, `while`, `break`, and `continue` refer to the innermost
+    // loop that contains them. Here, the inner loop runs three times for each
+    // of the two iterations of the outer loop.
+
+    let script = Script::compile(
+        "
+        0
+        loop
+            0 copy 2 < while
+            1 +
+
+            0
+            loop
+                0 copy 3 = if
+                    break
+                end
+                1 +
+
+                # Count the iterations of the inner loop in memory.
+                0 0 read 1 + write
+                continue
+            end
+            0 drop
+        end
+        0 drop
+
+        0 read
+        ",
+    );
+
+    let mut eval = Eval::new();
+    eval.run(&script);
+
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[6]);
+}
+
+#[test]
+fn unmatched_loop_keyword_triggers_unknown_identifier() {
+    // `while`, `break`, and `continue` outside of a loop, and a loop without
+    // an `end`, can't be evaluated as intended. Like unknown identifiers, they
+    // trigger an effect.
+
+    for source in ["1 break", "1 while", "1 1 if continue end", "1 loop"] {
+        let script = Script::compile(source);
+
+        let mut eval = Eval::new();
+        let (effect, _) = eval.run(&script);
+
+        assert_eq!(effect, Effect::UnknownIdentifier, "Script: {source}");
+        assert_eq!(eval.operand_stack.to_i32_slice(), &[1]);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Effect, Eval, Script, Value};
+use crate::{Effect, Eval, Memory, Script, Value};
 
 #[test]
 fn read() {
@@ -8,7 +8,7 @@ fn read() {
     let script = Script::compile("1 read 1 read");
 
     let mut eval = Eval::new();
-    eval.memory.values[1] = Value::from(3);
+    eval.memory.values_mut()[1] = Value::from(3);
     let (effect, _) = eval.run(&script);
 
     assert_eq!(effect, Effect::OutOfOperators);
@@ -24,7 +24,7 @@ fn read_triggers_effect_on_out_of_bounds_access() {
 
     let mut eval = Eval::new();
     assert!(
-        eval.memory.values.len() < 1025,
+        eval.memory.values().len() < 1025,
         "Test can't work, because it makes wrong assumption about memory size.",
     );
 
@@ -44,7 +44,7 @@ fn write() {
 
     assert_eq!(effect, Effect::OutOfOperators);
     assert_eq!(eval.operand_stack.to_u32_slice(), &[]);
-    assert_eq!(eval.memory.values[1], Value::from(3));
+    assert_eq!(eval.memory.values()[1], Value::from(3));
 }
 
 #[test]
@@ -56,7 +56,7 @@ fn write_triggers_effect_on_out_of_bounds_access() {
 
     let mut eval = Eval::new();
     assert!(
-        eval.memory.values.len() < 1025,
+        eval.memory.values().len() < 1025,
         "Test can't work, because it makes wrong assumption about memory size.",
     );
 
@@ -64,3 +64,37 @@ fn write_triggers_effect_on_out_of_bounds_access() {
     assert_eq!(effect, Effect::InvalidAddress);
     assert_eq!(eval.operand_stack.to_u32_slice(), &[]);
 }
+
+#[test]
+fn memory_size_is_configurable() {
+    // The host can replace the memory with one of a different size. Addresses
+    // are valid, as long as they are within that size.
+
+    let script = Script::compile("2047 3 write 2048 3 write");
+
+    let mut eval = Eval::new();
+    eval.memory = Memory::new(2048);
+
+    let (effect, _) = eval.run(&script);
+    assert_eq!(effect, Effect::InvalidAddress);
+    assert_eq!(eval.memory.values()[2047], Value::from(3));
+}
+
+#[test]
+fn evaluations_can_share_memory() {
+    // Multiple evaluations can share a memory. A value written by one of them
+    // can then be read by the others.
+
+    let memory = Memory::default().into_shared();
+
+    let mut a = Eval::new();
+    a.memory = memory.clone();
+    let mut b = Eval::new();
+    b.memory = memory;
+
+    a.run(&Script::compile("1 3 write"));
+    let (effect, _) = b.run(&Script::compile("1 read"));
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(b.operand_stack.to_u32_slice(), &[3]);
+}
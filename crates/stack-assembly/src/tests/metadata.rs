@@ -0,0 +1,69 @@
+use crate::{Effect, Eval, Script};
+
+#[test]
+fn metadata_is_available_to_host() {
+    // A `.meta` directive defines a metadata entry, consisting of a key and a
+    // value. The value extends to the end of the line.
+
+    let script = Script::compile(
+        "\
+        .meta name Hello, world!
+        .meta memory 16
+        ",
+    );
+
+    assert_eq!(
+        script.metadata().collect::<Vec<_>>(),
+        [("name", "Hello, world!"), ("memory", "16")],
+    );
+    assert_eq!(script.metadata_value("memory"), Some("16"));
+    assert_eq!(script.metadata_value("version"), None);
+}
+
+#[test]
+fn metadata_does_not_compile_into_operators() {
+    // A metadata entry is not evaluated. The operators around it are.
+
+    let script = Script::compile(
+        "\
+        3
+        .meta name 5 8
+        13
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[3, 13]);
+}
+
+#[test]
+fn metadata_ends_at_comment() {
+    // A `#` ends the value of a metadata entry, like it would end any other
+    // code.
+
+    let script = Script::compile(".meta name counter # not part of the value");
+
+    assert_eq!(script.metadata_value("name"), Some("counter"));
+}
+
+#[test]
+fn metadata_key_may_appear_multiple_times() {
+    // All entries are retained. Looking up the value of a key finds the first
+    // one.
+
+    let script = Script::compile(
+        "\
+        .meta service read
+        .meta service write
+        ",
+    );
+
+    assert_eq!(
+        script.metadata().collect::<Vec<_>>(),
+        [("service", "read"), ("service", "write")],
+    );
+    assert_eq!(script.metadata_value("service"), Some("read"));
+}
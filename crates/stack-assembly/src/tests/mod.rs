@@ -1,10 +1,25 @@
 mod arithmetic;
 mod assert;
 mod bitwise;
+mod channels;
 mod comments;
 mod comparison;
+mod conditionals;
+mod conformance;
 mod control_flow;
+mod devices;
 mod evaluation;
+mod exec;
 mod integers;
+mod locals;
+mod loops;
 mod memory;
+mod metadata;
+mod prelude;
+mod procedures;
+mod provenance;
+mod quotations;
+mod reserved_memory;
 mod stack_shuffling;
+mod strands;
+mod taint;
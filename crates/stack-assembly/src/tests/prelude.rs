@@ -0,0 +1,111 @@
+use crate::{CompileOptions, Effect, Eval, Script, Value};
+
+fn compile_with_prelude(source: &str) -> Script {
+    let options = CompileOptions {
+        prelude: true,
+        ..CompileOptions::default()
+    };
+    Script::compile_with_options(source, options)
+}
+
+#[test]
+fn prelude_is_not_evaluated_after_end_of_script() {
+    // The prelude is placed after the script. Reaching the end of the script
+    // still triggers the respective effect, instead of evaluating the prelude.
+
+    let script = compile_with_prelude("1");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[1]);
+}
+
+#[test]
+fn labels_in_script_take_precedence_over_prelude() {
+    // If the script defines a label with the same name as a prelude routine,
+    // references to that name refer to the label in the script.
+
+    let script = compile_with_prelude("3 @abs call return abs: 5 return");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Return);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[3, 5]);
+}
+
+#[test]
+fn stack_shuffles() {
+    // The prelude provides common stack shuffles.
+
+    let cases: [(&str, &[i32]); 9] = [
+        ("1 @dup call", &[1, 1]),
+        ("1 2 @swap call", &[2, 1]),
+        ("1 2 @over call", &[1, 2, 1]),
+        ("1 2 3 @rot call", &[2, 3, 1]),
+        ("1 2 @nip call", &[2]),
+        ("1 2 @dup2 call", &[1, 2, 1, 2]),
+        ("1 2 3 4 @swap2 call", &[3, 4, 1, 2]),
+        ("1 2 3 4 @over2 call", &[1, 2, 3, 4, 1, 2]),
+        ("1 2 3 @drop2 call", &[1]),
+    ];
+
+    for (source, expected) in cases {
+        assert_eq!(run(source), expected, "{source}");
+    }
+}
+
+#[test]
+fn math_helpers() {
+    // The prelude provides common math helpers.
+
+    let cases: [(&str, &[i32]); 8] = [
+        ("-3 @abs call", &[3]),
+        ("3 @abs call", &[3]),
+        ("-3 5 @min call", &[-3]),
+        ("5 -3 @min call", &[-3]),
+        ("-3 5 @max call", &[5]),
+        ("5 -3 @max call", &[5]),
+        ("12 18 @gcd call", &[6]),
+        ("-12 18 @gcd call", &[6]),
+    ];
+
+    for (source, expected) in cases {
+        assert_eq!(run(source), expected, "{source}");
+    }
+}
+
+#[test]
+fn memory_utilities() {
+    // The prelude provides utilities for working with memory.
+
+    let script = compile_with_prelude(
+        "
+        0 3 5 @memory_fill call
+        0 4 2 @memory_copy call
+        return
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Return);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[]);
+    assert_eq!(
+        &eval.memory.values()[..7],
+        &[5, 5, 5, 0, 5, 5, 0].map(Value::from)
+    );
+}
+
+fn run(source: &str) -> Vec<i32> {
+    let script = compile_with_prelude(&format!("{source} return"));
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Return);
+    eval.operand_stack.to_i32_slice().to_vec()
+}
@@ -0,0 +1,87 @@
+use crate::{Effect, Eval, Script};
+
+#[test]
+fn procedure_can_be_called_by_name() {
+    // `proc` defines a procedure, which works like a label with a routine
+    // after it. The procedure ends with `end`, which returns to the caller.
+
+    let script = Script::compile(
+        "
+        3 @square call
+        return
+
+        proc square in 1 out 1
+            0 copy *
+        end
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Return);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[9]);
+}
+
+#[test]
+fn procedure_can_contain_structured_control_flow() {
+    // Other blocks can be nested within a procedure. Only the `end` that
+    // closes the procedure returns from it.
+
+    let script = Script::compile(
+        "
+        -3 @abs call
+        4 @abs call
+        return
+
+        proc abs in 1 out 1
+            0 copy 0 < if
+                -1 *
+            end
+        end
+        ",
+    );
+
+    let mut eval = Eval::new();
+    eval.run(&script);
+
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[3, 4]);
+}
+
+#[test]
+fn arity_is_not_enforced_during_evaluation() {
+    // The arity that a procedure declares is a contract that tools can check.
+    // The evaluation doesn't check it.
+
+    let script = Script::compile(
+        "
+        @f call
+        return
+
+        proc f in 0 out 1
+            1 2
+        end
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Return);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[1, 2]);
+}
+
+#[test]
+fn invalid_procedure_triggers_unknown_identifier() {
+    // A `proc` without a complete header, or without an `end`, can't be
+    // evaluated as intended. Like unknown identifiers, it triggers an effect.
+
+    for source in ["1 proc f in 1", "1 proc f in 1 out 1 2"] {
+        let script = Script::compile(source);
+
+        let mut eval = Eval::new();
+        let (effect, _) = eval.run(&script);
+
+        assert_eq!(effect, Effect::UnknownIdentifier, "Script: {source}");
+    }
+}
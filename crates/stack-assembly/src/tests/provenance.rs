@@ -0,0 +1,93 @@
+use crate::{Effect, Eval, OperatorIndex, Script, Tag};
+
+#[test]
+fn references_are_valid_targets() {
+    // With provenance tracking enabled, a reference can still be used as the
+    // target of jumps and calls, even after moving it around on the stack.
+
+    let script = Script::compile(
+        "
+        @f 1 @g 2 reverse_n 0 drop call 0 drop jump
+        f: 1 yield
+        g: 2 return
+        ",
+    );
+
+    let mut eval = Eval::new();
+    eval.enable_provenance();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Yield);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[1]);
+}
+
+#[test]
+fn computed_target_triggers_effect() {
+    // Only references are valid targets. An integer, or the result of
+    // arithmetic on a reference, are not. The operator is not evaluated.
+
+    for (source, target) in [("3 jump 1 yield", 1), ("@f 0 + call f:", 3)] {
+        let script = Script::compile(source);
+
+        let mut eval = Eval::new();
+        eval.enable_provenance();
+        let (effect, operator) = eval.run(&script);
+
+        assert_eq!(effect, Effect::NonReferenceTarget);
+        assert_eq!(operator, OperatorIndex::new(target));
+        assert_eq!(eval.operand_stack.to_u32_slice().len(), 1);
+    }
+
+    // Without provenance tracking, the same script is fine.
+    let script = Script::compile("@f 0 + call f: 1 yield");
+    let (effect, _) = Eval::new().run(&script);
+    assert_eq!(effect, Effect::Yield);
+}
+
+#[test]
+fn call_either_checks_both_targets() {
+    // Both targets of `call_either` are inputs, so both must be references,
+    // even though only one of them is called.
+
+    let script = Script::compile("1 @f 3 call_either f: 1 yield");
+
+    let mut eval = Eval::new();
+    eval.enable_provenance();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::NonReferenceTarget);
+}
+
+#[test]
+fn tags_are_stored_in_memory() {
+    // Writing a value to memory stores its tag, and reading it back restores
+    // the tag. References in `.data` directives are tagged as references.
+
+    let script = Script::compile(
+        "
+        .data handlers @f
+
+        1 @handlers read write
+        2 1 read 1 + write
+        1 read call
+        f: @handlers read 2 read 3
+        ",
+    );
+
+    let mut eval = Eval::new();
+    eval.enable_provenance();
+    eval.load_data(&script);
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+
+    let Some(provenance) = eval.provenance() else {
+        unreachable!("Provenance tracking has been enabled.");
+    };
+    assert_eq!(provenance.memory(1), Tag::Reference);
+    assert_eq!(provenance.memory(2), Tag::Computed);
+    assert_eq!(
+        provenance.operand_stack(0),
+        &[Tag::Reference, Tag::Computed, Tag::Literal],
+    );
+}
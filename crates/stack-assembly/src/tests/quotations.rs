@@ -0,0 +1,79 @@
+use crate::{CompileError, CompileOptions, Effect, Eval, Script};
+
+#[test]
+fn quotation_pushes_its_address() {
+    // `{` ... `}` defines a quotation. Its operators are not evaluated in
+    // place. Instead, the address of the quotation is pushed, so it can be
+    // called later.
+
+    let script = Script::compile(
+        "
+        { 2 * } 3
+        1 copy call
+        1 copy call
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[3, 12]);
+}
+
+#[test]
+fn quotation_can_be_passed_to_routine() {
+    // Since a quotation is just an address, a routine can take it as an
+    // argument, without the quotation needing a label.
+
+    let script = Script::compile(
+        "
+        3 4 { + } @apply call
+        3 4 { * } @apply call
+        return
+
+        apply:
+            call
+            return
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Return);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[7, 12]);
+}
+
+#[test]
+fn quotations_can_be_nested() {
+    // A quotation can contain other quotations, as well as structured control
+    // flow.
+
+    let script = Script::compile(
+        "
+        { { 1 if 5 end } } call call
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[5]);
+}
+
+#[test]
+fn unmatched_braces_are_rejected_in_strict_mode() {
+    // Only `}` closes a quotation, and only a quotation is closed by `}`. A
+    // quotation also can't `break` out of a loop that surrounds it.
+
+    for source in ["{ 1", "1 }", "{ 1 end", "if { } }", "loop { break } end"] {
+        let result = Script::try_compile(source, CompileOptions::default());
+
+        assert!(
+            matches!(result, Err(CompileError::UnmatchedKeyword { .. })),
+            "{source}",
+        );
+    }
+}
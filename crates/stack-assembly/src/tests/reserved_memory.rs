@@ -0,0 +1,290 @@
+use crate::{CompileError, CompileOptions, Effect, Eval, Script};
+
+#[test]
+fn regions_are_placed_one_after_the_other() {
+    // A `.zero` directive reserves a region of memory, with the provided name
+    // and size in words. Regions are placed in the order they are defined,
+    // starting at address `0`.
+
+    let script = Script::compile(
+        "
+        .zero header 2
+        .zero buffer 16
+        .zero empty 0
+        .zero footer 1
+        ",
+    );
+
+    assert_eq!(
+        script.regions().collect::<Vec<_>>(),
+        [
+            ("header", 0..2),
+            ("buffer", 2..18),
+            ("empty", 18..18),
+            ("footer", 18..19)
+        ],
+    );
+}
+
+#[test]
+fn reference_to_region_evaluates_to_its_address() {
+    // A reference to a region pushes its address. Like labels, regions can be
+    // referred to before they are defined.
+
+    let script = Script::compile(
+        "
+        @buffer 1 + 5 write
+        @header @buffer
+
+        .zero header 2
+        .zero buffer 4
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[0, 2]);
+
+    let Ok(value) = eval.memory.read(3) else {
+        unreachable!("Address is within the bounds of the memory.");
+    };
+    assert_eq!(value.to_u32(), 5);
+}
+
+#[test]
+fn region_takes_precedence_over_label() {
+    // If a label has the same name as a region, a reference to that name
+    // refers to the region. This includes the labels of the prelude.
+
+    let options = CompileOptions {
+        prelude: true,
+        ..CompileOptions::default()
+    };
+    let script = Script::compile_with_options(
+        "
+        .zero padding 3
+        .zero dup 1
+        .zero target 1
+
+        @target @dup
+        return
+
+        target:
+        ",
+        options,
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Return);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[4, 3]);
+}
+
+#[test]
+fn align_moves_next_region_to_multiple_of_alignment() {
+    // An `.align` directive makes sure that the next region starts at a
+    // multiple of the provided number of words. If the next address already is
+    // such a multiple, it has no effect.
+
+    let script = Script::compile(
+        "
+        .zero flag 1
+        .align 4
+        .zero vector 4
+        .align 2
+        .zero pair 2
+        ",
+    );
+
+    assert_eq!(
+        script.regions().collect::<Vec<_>>(),
+        [("flag", 0..1), ("vector", 4..8), ("pair", 8..10)],
+    );
+}
+
+#[test]
+fn invalid_directive_triggers_effect() {
+    // A directive that is malformed, like a `.zero` that isn't followed by a
+    // name and a size, an `.align` with an alignment of zero, a `.data`
+    // without a name, a `.var` with more than one initial value, or a
+    // `.string` without a string, has no effect on the layout. Evaluating it
+    // triggers an effect.
+
+    for source in [
+        ".zero buffer",
+        ".align 0 .zero buffer 1",
+        ".data 1 2",
+        ".var count 1 2",
+        ".string message unquoted",
+    ] {
+        let script = Script::compile(source);
+
+        let mut eval = Eval::new();
+        let (effect, _) = eval.run(&script);
+
+        assert_eq!(effect, Effect::UnknownIdentifier, "{source}");
+    }
+}
+
+#[test]
+fn data_provides_initial_values() {
+    // A `.data` directive reserves a region, like `.zero` does, with one word
+    // for each of the values that follow the name. Those extend to the end of
+    // the line, or to the start of a comment. Loading the data writes them to
+    // memory.
+
+    let script = Script::compile(
+        "
+        .data numbers 3 -1 # 5
+        5
+        .data empty
+        .data more 7
+        ",
+    );
+
+    assert_eq!(
+        script.regions().collect::<Vec<_>>(),
+        [("numbers", 0..2), ("empty", 2..2), ("more", 2..3)],
+    );
+
+    let mut eval = Eval::new();
+    eval.load_data(&script);
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[5]);
+    assert_eq!(eval.memory.to_i32_slice()[..4], [3, -1, 7, 0]);
+}
+
+#[test]
+fn data_can_store_label_addresses() {
+    // The values of a `.data` directive can be references. A reference to a
+    // label stores the index of the operator the label refers to. The script
+    // can read that from memory and call it. This makes the operator
+    // reachable, so stripping unreachable code keeps it.
+
+    let source = "
+        .data handlers @first @second @handlers
+
+        @handlers 1 + read call
+        @handlers 2 + read
+        return
+
+        first:
+            1
+            return
+        second:
+            2
+            return
+        ";
+    let options = CompileOptions {
+        strip_unreachable: true,
+        ..CompileOptions::default()
+    };
+
+    for script in [
+        Script::compile(source),
+        Script::compile_with_options(source, options),
+    ] {
+        let mut eval = Eval::new();
+        eval.load_data(&script);
+        let (effect, _) = eval.run(&script);
+
+        assert_eq!(effect, Effect::Return);
+        assert_eq!(eval.operand_stack.to_u32_slice(), &[2, 0]);
+        assert!(script.lint().is_empty());
+    }
+}
+
+#[test]
+fn data_with_invalid_reference_triggers_effect() {
+    // A reference in the values of a `.data` directive that doesn't refer to
+    // a label is an error. Loading the data triggers an effect, before any
+    // operators are evaluated.
+
+    let source = ".data table 1 @invalid 3\n1";
+    let script = Script::compile(source);
+
+    assert_eq!(
+        script.check(),
+        [CompileError::InvalidReference { range: 14..22 }],
+    );
+
+    let mut eval = Eval::new();
+    eval.load_data(&script);
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::InvalidReference);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[]);
+}
+
+#[test]
+fn var_reserves_single_word() {
+    // A `.var` directive reserves a region of one word. Optionally, the name
+    // can be followed by the value that the word starts out with. A reference
+    // to the variable evaluates to its address.
+
+    let script = Script::compile(
+        "
+        .var count
+        .var step 2
+        .var handler @increment
+
+        @handler read call
+        @handler read call
+        @count read
+        return
+
+        increment:
+            @count  @count read @step read +  write
+            return
+        ",
+    );
+
+    assert_eq!(
+        script.regions().collect::<Vec<_>>(),
+        [("count", 0..1), ("step", 1..2), ("handler", 2..3)],
+    );
+
+    let mut eval = Eval::new();
+    eval.load_data(&script);
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Return);
+    assert_eq!(eval.operand_stack.to_i32_slice(), &[4]);
+}
+
+#[test]
+fn string_provides_length_prefixed_bytes() {
+    // A `.string` directive reserves a region for a string. It starts out
+    // with the length of the string in bytes, followed by the bytes. The same
+    // string defined again shares the addresses of the first definition.
+
+    let script = Script::compile(
+        r#"
+        .string greeting "hi\n"
+        .string empty ""
+        .string again "hi\n"
+
+        @greeting 1 +  @greeting read
+        @again
+        "#,
+    );
+
+    assert_eq!(
+        script.regions().collect::<Vec<_>>(),
+        [("greeting", 0..4), ("empty", 4..5), ("again", 0..4)],
+    );
+
+    let mut eval = Eval::new();
+    eval.load_data(&script);
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[1, 3, 0]);
+    assert_eq!(eval.memory.read_str(1..4).as_deref(), Ok("hi\n"));
+    assert_eq!(eval.memory.to_i32_slice()[4], 0);
+}
@@ -40,3 +40,72 @@ fn drop() {
     assert_eq!(effect, Effect::OutOfOperators);
     assert_eq!(eval.operand_stack.to_u32_slice(), &[3, 8]);
 }
+
+#[test]
+fn copy_from_bottom() {
+    // A negative index counts from the bottom of the stack. `-1` refers to the
+    // bottom value, `-2` to the one above it, and so on.
+
+    let script = Script::compile("3 5 8 -1 copy -2 copy");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[3, 5, 8, 3, 5]);
+}
+
+#[test]
+fn drop_from_bottom() {
+    // `drop` accepts negative indices too, and interprets them the same way
+    // as `copy`.
+
+    let script = Script::compile("3 5 8 -2 drop");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[3, 8]);
+}
+
+#[test]
+fn copy_trigger_effect_on_invalid_index_from_bottom() {
+    // A negative index that reaches beyond the top of the stack is invalid.
+
+    let script = Script::compile("3 5 -3 copy");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::InvalidOperandStackIndex);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[3, 5]);
+}
+
+#[test]
+fn reverse_n() {
+    // The `reverse_n` operator reverses the order of the top values on the
+    // stack. Its input is the number of values to reverse.
+
+    let script = Script::compile("1 3 5 8 3 reverse_n");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[1, 8, 5, 3]);
+}
+
+#[test]
+fn reverse_n_trigger_effect_on_too_few_values() {
+    // If there are fewer values on the stack than `reverse_n` is supposed to
+    // reverse, this triggers an effect.
+
+    let script = Script::compile("3 5 3 reverse_n");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::InvalidOperandStackIndex);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[3, 5]);
+}
@@ -0,0 +1,124 @@
+use crate::{Effect, Eval, Script};
+
+#[test]
+fn spawn_creates_strand_without_switching_to_it() {
+    // `spawn` creates a new strand that starts at the provided operator, and
+    // pushes its ID. The current strand keeps being evaluated.
+
+    let script = Script::compile(
+        "
+        @strand spawn
+        return
+
+        strand:
+            3
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Return);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[1]);
+    assert_eq!(eval.current_strand(), 0);
+}
+
+#[test]
+fn resume_switches_between_strands() {
+    // `resume` suspends the current strand and continues with the provided
+    // one. Once that resumes the original strand, evaluation continues after
+    // the first `resume`.
+
+    let script = Script::compile(
+        "
+        @strand spawn resume
+        current
+        return
+
+        strand:
+            current 0 resume
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Return);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[0]);
+    assert_eq!(eval.current_strand(), 0);
+}
+
+#[test]
+fn strands_have_separate_stacks() {
+    // Each strand has its own operand stack and call stack.
+
+    let script = Script::compile(
+        "
+        3 @strand spawn resume
+        return
+
+        strand:
+            5 @routine call
+
+        routine:
+            yield
+        ",
+    );
+
+    let mut eval = Eval::new();
+
+    let (effect, _) = eval.run(&script);
+    assert_eq!(effect, Effect::Yield);
+    assert_eq!(eval.current_strand(), 1);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[5]);
+    assert_eq!(eval.call_stack.len(), 1);
+}
+
+#[test]
+fn strands_share_memory() {
+    // All strands share the same memory.
+
+    let script = Script::compile(
+        "
+        @strand spawn resume
+        0 read
+        return
+
+        strand:
+            0 3 write
+            0 resume
+        ",
+    );
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::Return);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[3]);
+}
+
+#[test]
+fn resuming_current_strand_does_nothing() {
+    // Resuming the strand that is already being evaluated has no effect.
+
+    let script = Script::compile("0 resume 1");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[1]);
+}
+
+#[test]
+fn resuming_invalid_strand_triggers_effect() {
+    // Resuming a strand that has not been spawned triggers the respective
+    // effect.
+
+    let script = Script::compile("1 resume");
+
+    let mut eval = Eval::new();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::InvalidStrand);
+}
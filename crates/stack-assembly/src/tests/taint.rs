@@ -0,0 +1,101 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{Effect, Eval, OperatorIndex, Script, TaintListener, Value};
+
+#[test]
+fn taint_propagates_through_operators_and_memory() {
+    // A value that the host marked as tainted, taints everything that is
+    // computed from it, including values that go through memory.
+
+    let script =
+        Script::compile("yield 0 copy 1 + 5 1 copy write 5 read 3 4 +");
+
+    let mut eval = Eval::new();
+    eval.enable_taint_tracking();
+    eval.run(&script);
+
+    eval.operand_stack.push(1);
+    assert!(eval.taint_operand(0));
+    eval.clear_effect();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[1, 2, 2, 7]);
+
+    let Some(taint) = eval.taint() else {
+        unreachable!("Taint tracking has been enabled.");
+    };
+    assert_eq!(taint.operand_stack(0), &[true, true, true, false]);
+    assert!(taint.is_memory_tainted(5));
+    assert!(!taint.is_memory_tainted(6));
+}
+
+#[test]
+fn listeners_are_notified_about_tainted_jumps_and_writes() {
+    // Tainted values reaching a jump or `write` are reported, but evaluation
+    // continues as usual.
+
+    let script = Script::compile("yield 9 1 copy write jump f: 1");
+
+    let listener = Arc::new(Mutex::new(Recorder::default()));
+
+    let mut eval = Eval::new();
+    eval.add_taint_listener(listener.clone());
+    eval.run(&script);
+
+    eval.operand_stack.push(6);
+    assert!(eval.taint_operand(0));
+    eval.clear_effect();
+    let (effect, _) = eval.run(&script);
+
+    assert_eq!(effect, Effect::OutOfOperators);
+    assert_eq!(eval.operand_stack.to_u32_slice(), &[1]);
+
+    let listener = listener.lock().unwrap();
+    assert_eq!(
+        listener.writes,
+        vec![(OperatorIndex::new(4), Value::from(9), Value::from(6))],
+    );
+    assert_eq!(
+        listener.jumps,
+        vec![(OperatorIndex::new(5), Value::from(6))]
+    );
+}
+
+#[test]
+fn marking_requires_taint_tracking_and_valid_location() {
+    // Marking a value does nothing, unless taint tracking is enabled and the
+    // value exists.
+
+    let mut eval = Eval::new();
+    eval.operand_stack.push(1);
+    assert!(!eval.taint_operand(0));
+    assert!(!eval.taint_memory(0));
+
+    eval.enable_taint_tracking();
+    assert!(!eval.taint_operand(1));
+    assert!(!eval.taint_memory(u32::MAX));
+    assert!(eval.taint_operand(-1));
+    assert!(eval.taint_memory(0));
+}
+
+#[derive(Default)]
+struct Recorder {
+    jumps: Vec<(OperatorIndex, Value)>,
+    writes: Vec<(OperatorIndex, Value, Value)>,
+}
+
+impl TaintListener for Recorder {
+    fn tainted_jump(&mut self, operator: OperatorIndex, target: Value) {
+        self.jumps.push((operator, target));
+    }
+
+    fn tainted_write(
+        &mut self,
+        operator: OperatorIndex,
+        address: Value,
+        value: Value,
+    ) {
+        self.writes.push((operator, address, value));
+    }
+}
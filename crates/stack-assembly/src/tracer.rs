@@ -0,0 +1,157 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{Eval, OperatorIndex};
+
+/// # Observes the evaluation, operator by operator
+///
+/// Attach a tracer to an evaluation using [`Eval::add_tracer`]. From then on,
+/// it is notified right before each operator is evaluated. This is the hook
+/// that instrumentation, like profiling, coverage recording, or printing a
+/// trace, is built on. [`Profile`] and [`Coverage`] implement this trait.
+///
+/// [`Profile`]: crate::Profile
+/// [`Coverage`]: crate::Coverage
+///
+/// ## Example
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+///
+/// use stack_assembly::{Eval, OperatorIndex, Script, Tracer};
+///
+/// #[derive(Default)]
+/// struct MaxDepth {
+///     depth: usize,
+/// }
+///
+/// impl Tracer for MaxDepth {
+///     fn before_step(&mut self, _: OperatorIndex, eval: &Eval) {
+///         self.depth = self.depth.max(eval.operand_stack.values.len());
+///     }
+/// }
+///
+/// let script = Script::compile("1 2 + 3 4 5");
+///
+/// let tracer = Arc::new(Mutex::new(MaxDepth::default()));
+///
+/// let mut eval = Eval::new();
+/// eval.add_tracer(tracer.clone());
+/// eval.run(&script);
+///
+/// let Ok(tracer) = tracer.lock() else {
+///     unreachable!("The tracer doesn't panic.");
+/// };
+/// assert_eq!(tracer.depth, 3);
+/// ```
+pub trait Tracer: Send {
+    /// # Called right before an operator is evaluated
+    ///
+    /// Receives the index of the operator, and the evaluation in the state
+    /// right before the operator is evaluated.
+    fn before_step(&mut self, operator: OperatorIndex, eval: &Eval);
+}
+
+/// # The tracers that are attached to an evaluation
+///
+/// Cloning this shares the tracers between the clones.
+#[derive(Clone, Default)]
+pub(crate) struct Tracers {
+    inner: Vec<Arc<Mutex<dyn Tracer>>>,
+}
+
+impl Tracers {
+    pub(crate) fn add(&mut self, tracer: Arc<Mutex<dyn Tracer>>) {
+        self.inner.push(tracer);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub(crate) fn before_step(&self, operator: OperatorIndex, eval: &Eval) {
+        for tracer in &self.inner {
+            // If a tracer panicked before, it's up to the tracer to deal with
+            // any inconsistent state that might have resulted from that.
+            let mut tracer =
+                tracer.lock().unwrap_or_else(PoisonError::into_inner);
+            tracer.before_step(operator, eval);
+        }
+    }
+}
+
+impl fmt::Debug for Tracers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Tracers({})", self.inner.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{Eval, OperatorIndex, Script};
+
+    use super::Tracer;
+
+    #[derive(Default)]
+    struct Steps {
+        operators: Vec<OperatorIndex>,
+    }
+
+    impl Tracer for Steps {
+        fn before_step(&mut self, operator: OperatorIndex, eval: &Eval) {
+            assert_eq!(operator, eval.next_operator());
+            self.operators.push(operator);
+        }
+    }
+
+    #[test]
+    fn tracer_is_notified_of_evaluated_operators() {
+        let script = Script::compile("@f call 1 return f: return");
+
+        let tracer = Arc::new(Mutex::new(Steps::default()));
+
+        let mut eval = Eval::new();
+        eval.add_tracer(tracer.clone());
+        eval.run(&script);
+
+        let Ok(tracer) = tracer.lock() else {
+            unreachable!("Tracer doesn't panic.");
+        };
+        assert_eq!(tracer.operators, [0, 1, 4, 2, 3].map(OperatorIndex::new));
+    }
+
+    #[test]
+    fn clones_share_tracers() {
+        let script = Script::compile("1 yield 2 yield 3");
+
+        let tracer = Arc::new(Mutex::new(Steps::default()));
+
+        let mut eval = Eval::new();
+        eval.add_tracer(tracer.clone());
+        eval.run(&script);
+
+        let mut fork = eval.clone();
+        eval.resume(&script);
+        fork.resume(&script);
+
+        eval.clear_tracers();
+        eval.resume(&script);
+        fork.resume(&script);
+
+        let Ok(tracer) = tracer.lock() else {
+            unreachable!("Tracer doesn't panic.");
+        };
+        assert_eq!(
+            tracer.operators,
+            [0, 1, 2, 3, 2, 3, 4].map(OperatorIndex::new),
+        );
+    }
+}
@@ -1,4 +1,8 @@
-use std::fmt;
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{BitAnd, BitOr, BitXor, Not},
+};
 
 /// # A unit of data
 ///
@@ -13,6 +17,40 @@ use std::fmt;
 ///
 /// Value::from(3i32);
 /// Value::from(5u32);
+/// Value::from(true);
+/// ```
+///
+/// Booleans are represented as `1` for `true` and `0` for `false`, which is
+/// also what comparison operators like `=` or `<` push. When converting back
+/// using [`Value::to_bool`], any non-zero value is considered `true`, matching
+/// how operators like `jump_if` or `assert` interpret their condition.
+///
+/// ```
+/// use stack_assembly::Value;
+///
+/// assert_eq!(Value::from(true).to_u32(), 1);
+/// assert_eq!(Value::from(false).to_u32(), 0);
+///
+/// assert!(Value::from(-1).to_bool());
+/// assert!(!Value::from(0).to_bool());
+/// ```
+///
+/// ## Arithmetic and bit manipulation
+///
+/// `Value` provides the operations that StackAssembly's operators perform, as
+/// methods. Where the interpretation as signed or unsigned makes a difference,
+/// the name of the method says which one it uses. All arithmetic wraps on
+/// overflow.
+///
+/// ```
+/// use stack_assembly::Value;
+///
+/// let a = Value::from(-1);
+/// let b = Value::from(1);
+///
+/// assert_eq!(a.wrapping_add(b), Value::from(0));
+/// assert!(a.cmp_signed(b).is_lt());
+/// assert!(a.cmp_unsigned(b).is_gt());
 /// ```
 ///
 /// [`OperandStack`]: crate::OperandStack
@@ -47,6 +85,149 @@ impl Value {
     pub fn to_bool(self) -> bool {
         self.inner != 0
     }
+
+    /// # Add two values, wrapping on overflow
+    ///
+    /// The result is the same, regardless of whether the values are
+    /// interpreted as signed or unsigned.
+    pub fn wrapping_add(self, other: Self) -> Self {
+        Self::from(self.inner.wrapping_add(other.inner))
+    }
+
+    /// # Subtract a value from this one, wrapping on overflow
+    ///
+    /// The result is the same, regardless of whether the values are
+    /// interpreted as signed or unsigned.
+    pub fn wrapping_sub(self, other: Self) -> Self {
+        Self::from(self.inner.wrapping_sub(other.inner))
+    }
+
+    /// # Multiply two values, wrapping on overflow
+    ///
+    /// The result is the same, regardless of whether the values are
+    /// interpreted as signed or unsigned.
+    pub fn wrapping_mul(self, other: Self) -> Self {
+        Self::from(self.inner.wrapping_mul(other.inner))
+    }
+
+    /// # Add two values, interpreted as signed
+    ///
+    /// Returns `None` on overflow.
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.to_i32().checked_add(other.to_i32()).map(Self::from)
+    }
+
+    /// # Subtract a value from this one, interpreted as signed
+    ///
+    /// Returns `None` on overflow.
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.to_i32().checked_sub(other.to_i32()).map(Self::from)
+    }
+
+    /// # Multiply two values, interpreted as signed
+    ///
+    /// Returns `None` on overflow.
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        self.to_i32().checked_mul(other.to_i32()).map(Self::from)
+    }
+
+    /// # Compare two values, interpreting them as signed
+    pub fn cmp_signed(self, other: Self) -> Ordering {
+        self.to_i32().cmp(&other.to_i32())
+    }
+
+    /// # Compare two values, interpreting them as unsigned
+    pub fn cmp_unsigned(self, other: Self) -> Ordering {
+        self.inner.cmp(&other.inner)
+    }
+
+    /// # Count the number of bits that are `1`
+    pub fn count_ones(self) -> u32 {
+        self.inner.count_ones()
+    }
+
+    /// # Count the number of leading bits that are `0`
+    pub fn leading_zeros(self) -> u32 {
+        self.inner.leading_zeros()
+    }
+
+    /// # Count the number of trailing bits that are `0`
+    pub fn trailing_zeros(self) -> u32 {
+        self.inner.trailing_zeros()
+    }
+
+    /// # Rotate the bits to the left
+    ///
+    /// Bits that are shifted out on the left are shifted back in on the right.
+    pub fn rotate_left(self, num_positions: u32) -> Self {
+        Self::from(self.inner.rotate_left(num_positions))
+    }
+
+    /// # Rotate the bits to the right
+    ///
+    /// Bits that are shifted out on the right are shifted back in on the left.
+    pub fn rotate_right(self, num_positions: u32) -> Self {
+        Self::from(self.inner.rotate_right(num_positions))
+    }
+
+    /// # Shift the bits to the left
+    ///
+    /// Only the lowest 5 bits of `num_positions` are taken into account, so
+    /// shifting by 32 or more positions wraps around.
+    pub fn shift_left(self, num_positions: u32) -> Self {
+        Self::from(self.inner.wrapping_shl(num_positions))
+    }
+
+    /// # Shift the bits to the right, interpreting the value as signed
+    ///
+    /// This is an arithmetic shift. The bits that are shifted in on the left
+    /// are copies of the sign bit. Only the lowest 5 bits of `num_positions`
+    /// are taken into account, so shifting by 32 or more positions wraps
+    /// around.
+    pub fn shift_right_signed(self, num_positions: u32) -> Self {
+        Self::from(self.to_i32().wrapping_shr(num_positions))
+    }
+
+    /// # Shift the bits to the right, interpreting the value as unsigned
+    ///
+    /// This is a logical shift. The bits that are shifted in on the left are
+    /// `0`. Only the lowest 5 bits of `num_positions` are taken into account,
+    /// so shifting by 32 or more positions wraps around.
+    pub fn shift_right_unsigned(self, num_positions: u32) -> Self {
+        Self::from(self.inner.wrapping_shr(num_positions))
+    }
+}
+
+impl BitAnd for Value {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self::Output {
+        Self::from(self.inner & other.inner)
+    }
+}
+
+impl BitOr for Value {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self::Output {
+        Self::from(self.inner | other.inner)
+    }
+}
+
+impl BitXor for Value {
+    type Output = Self;
+
+    fn bitxor(self, other: Self) -> Self::Output {
+        Self::from(self.inner ^ other.inner)
+    }
+}
+
+impl Not for Value {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self::from(!self.inner)
+    }
 }
 
 impl From<bool> for Value {
@@ -0,0 +1,982 @@
+//! # Compilation of scripts into WebAssembly modules
+//!
+//! See [`Script::to_wasm`].
+
+use wasm_encoder::{
+    BlockType, CodeSection, ConstExpr, DataSection, EntityType, ExportKind,
+    ExportSection, Function, FunctionSection, GlobalSection, GlobalType,
+    ImportSection, InstructionSink, MemArg, MemorySection, MemoryType, Module,
+    TypeSection, ValType,
+};
+
+use crate::{Effect, OperatorIndex, Script, opcode::Opcode, script::Operator};
+
+/// # The number of words in the memory that the script can access
+///
+/// This matches the default size of [`Memory`](crate::Memory).
+const MEMORY_WORDS: u32 = 1024;
+
+/// # The maximum number of values on the operand stack
+const OPERAND_STACK_WORDS: u32 = 64 * 1024;
+
+/// # The maximum number of entries on the call stack
+const CALL_STACK_WORDS: u32 = 64 * 1024;
+
+const OPERAND_STACK_BASE: u32 = MEMORY_WORDS * 4;
+const CALL_STACK_BASE: u32 = OPERAND_STACK_BASE + OPERAND_STACK_WORDS * 4;
+const MEMORY_END: u32 = CALL_STACK_BASE + CALL_STACK_WORDS * 4;
+
+const WASM_PAGE_SIZE: u32 = 64 * 1024;
+
+// Indices of the functions, globals, and locals in the generated module.
+const YIELD_FUNCTION: u32 = 0;
+const OPERAND_STACK_POINTER: u32 = 0;
+const CALL_STACK_POINTER: u32 = 1;
+const OPERATOR: u32 = 2;
+const PC: u32 = 0;
+const A: u32 = 1;
+const B: u32 = 2;
+const C: u32 = 3;
+
+impl Script {
+    /// # Compile the script into a standalone WebAssembly module
+    ///
+    /// This is only available, if the `wasm` feature is enabled.
+    ///
+    /// The module imports a function `env.yield`, which takes no arguments and
+    /// returns nothing. It is called whenever the script evaluates `yield`.
+    /// Once it returns, the evaluation continues.
+    ///
+    /// The module exports the following items:
+    ///
+    /// - `run`, a function that takes no arguments and evaluates the script
+    ///   until it triggers an effect. It returns a code that identifies the
+    ///   effect, which [`Effect::from_wasm_code`] can convert back into an
+    ///   [`Effect`].
+    /// - `operator`, a global that contains the index of the operator that
+    ///   triggered the effect, after `run` returns.
+    /// - `memory`, the linear memory of the module. The first 1024 words (4
+    ///   bytes each, little-endian) of it are the memory that the script can
+    ///   access through `read` and `write`.
+    /// - `operand_stack_base` and `operand_stack_pointer`, two globals that
+    ///   contain the byte addresses in `memory` where the operand stack starts
+    ///   and where it currently ends.
+    ///
+    /// The host can access the operand stack and memory from within `yield`,
+    /// or after `run` has returned, to communicate with the script. The stack
+    /// pointer must be updated accordingly, if the host pushes or pops values.
+    ///
+    /// Unlike the interpreter, the compiled module has a limited operand stack
+    /// and call stack, with room for 65536 entries each. Overflowing either
+    /// results in a trap. The compiled module does not support limiting the
+    /// evaluation with fuel (see [`Eval::set_fuel`]), local variables,
+    /// spawning additional strands, or channels. Evaluating `locals`,
+    /// `local_get`, `local_set`, `spawn`, `send`, or `receive` results in a
+    /// trap.
+    ///
+    /// [`Eval::set_fuel`]: crate::Eval::set_fuel
+    pub fn to_wasm(&self) -> Vec<u8> {
+        let mut module = Module::new();
+
+        let mut types = TypeSection::new();
+        types.ty().function([], []);
+        types.ty().function([], [ValType::I32]);
+        module.section(&types);
+
+        let mut imports = ImportSection::new();
+        imports.import("env", "yield", EntityType::Function(0));
+        module.section(&imports);
+
+        let mut functions = FunctionSection::new();
+        functions.function(1);
+        module.section(&functions);
+
+        let mut memories = MemorySection::new();
+        memories.memory(MemoryType {
+            minimum: u64::from(MEMORY_END.div_ceil(WASM_PAGE_SIZE)),
+            maximum: None,
+            memory64: false,
+            shared: false,
+            page_size_log2: None,
+        });
+        module.section(&memories);
+
+        let mut globals = GlobalSection::new();
+        for (mutable, value) in [
+            (true, OPERAND_STACK_BASE),
+            (true, CALL_STACK_BASE),
+            (true, 0),
+            (false, OPERAND_STACK_BASE),
+        ] {
+            globals.global(
+                GlobalType {
+                    val_type: ValType::I32,
+                    mutable,
+                    shared: false,
+                },
+                &ConstExpr::i32_const(value.cast_signed()),
+            );
+        }
+        module.section(&globals);
+
+        let mut exports = ExportSection::new();
+        exports.export("run", ExportKind::Func, 1);
+        exports.export("memory", ExportKind::Memory, 0);
+        exports.export(
+            "operand_stack_pointer",
+            ExportKind::Global,
+            OPERAND_STACK_POINTER,
+        );
+        exports.export("operator", ExportKind::Global, OPERATOR);
+        exports.export("operand_stack_base", ExportKind::Global, 3);
+        module.section(&exports);
+
+        let mut function = Function::new([(4, ValType::I32)]);
+        compile_run(self, &mut function.instructions());
+
+        let mut code = CodeSection::new();
+        code.function(&function);
+        module.section(&code);
+
+        let mut data = DataSection::new();
+        for (address, value) in self.initial_values() {
+            // If a value can't be loaded, `run` triggers an effect right away.
+            // See `compile_run`.
+            let (Some(value), true) = (value, address < MEMORY_WORDS) else {
+                continue;
+            };
+
+            data.active(
+                0,
+                &ConstExpr::i32_const((address * 4).cast_signed()),
+                value.to_u32().to_le_bytes(),
+            );
+        }
+        module.section(&data);
+
+        module.finish()
+    }
+}
+
+impl Effect {
+    /// # Convert a code returned by a compiled WebAssembly module
+    ///
+    /// See [`Script::to_wasm`]. Returns `None`, if the code doesn't identify
+    /// an effect. The codes are the same as those returned by [`Effect::code`].
+    ///
+    /// This is only available, if the `wasm` feature is enabled.
+    pub fn from_wasm_code(code: i32) -> Option<Self> {
+        u32::try_from(code).ok().and_then(Self::from_code)
+    }
+}
+
+fn wasm_code(effect: Effect) -> i32 {
+    effect.code().cast_signed()
+}
+
+/// # Compile the body of the `run` function
+///
+/// Each operator is compiled into a separate section of code. A loop
+/// dispatches to the section of the current operator, using a branch table.
+/// Sections fall through into the next one, so only jumps need to go through
+/// the dispatch loop.
+fn compile_run(script: &Script, code: &mut InstructionSink) {
+    let operators = script.operators().collect::<Vec<_>>();
+    let num_operators = operators.len() as u32;
+
+    // The data section of the module holds the values that memory starts out
+    // with. If any of them can't be loaded, fail like `Eval::load_data` does.
+    let load_error = if script.has_invalid_start() {
+        Some(Effect::InvalidReference)
+    } else {
+        script
+            .initial_values()
+            .find_map(|(address, value)| match value {
+                Some(_) if address >= MEMORY_WORDS => {
+                    Some(Effect::InvalidAddress)
+                }
+                Some(_) => None,
+                None => Some(Effect::InvalidReference),
+            })
+    };
+    if let Some(effect) = load_error {
+        code.i32_const(0);
+        code.global_set(OPERATOR);
+        code.i32_const(wasm_code(effect));
+        code.return_();
+    }
+
+    // Like the interpreter, when started via `Eval::start_at`, the evaluation
+    // starts where the `.start` directive says.
+    code.i32_const(script.start().value.cast_signed());
+    code.local_set(PC);
+
+    code.loop_(BlockType::Empty);
+
+    // One block for the end of the script, plus one for each operator.
+    for _ in 0..=num_operators {
+        code.block(BlockType::Empty);
+    }
+
+    code.local_get(PC);
+    code.br_table(0..num_operators, num_operators);
+    code.end();
+
+    for (index, operator) in operators {
+        let mut section = Section {
+            code,
+            operator: index,
+            // Each operator's section is nested in the blocks of the following
+            // operators, and in the block for the end of the script.
+            dispatch: num_operators - index.value,
+        };
+        section.compile(operator);
+
+        if index.value + 1 == num_operators {
+            // Falling through from the last operator means that we've reached
+            // the end of the script.
+            code.i32_const(num_operators.cast_signed());
+            code.local_set(PC);
+        }
+
+        code.end();
+    }
+
+    // We either fell through from the last operator, or the dispatch loop
+    // found an invalid operator index. Either way, we're at the end.
+    code.local_get(PC);
+    code.global_set(OPERATOR);
+    code.i32_const(wasm_code(Effect::OutOfOperators));
+    code.return_();
+
+    code.end();
+    code.unreachable();
+    code.end();
+}
+
+struct Section<'r, 's> {
+    code: &'r mut InstructionSink<'s>,
+    operator: OperatorIndex,
+
+    /// # The label depth of the dispatch loop
+    dispatch: u32,
+}
+
+impl Section<'_, '_> {
+    fn compile(&mut self, operator: &Operator) {
+        // Keep track of the current operator, in case it triggers an effect,
+        // or calls the host.
+        self.code.i32_const(self.operator.value.cast_signed());
+        self.code.local_set(PC);
+
+        match operator {
+            Operator::End => {
+                self.trigger(Effect::OutOfOperators);
+            }
+            Operator::Fused { superinstruction } => {
+                self.compile(&superinstruction.unfused());
+            }
+            Operator::Identifier { value: _ } => {
+                self.trigger(Effect::UnknownIdentifier);
+            }
+            Operator::Integer { value } => {
+                self.code.i32_const(*value);
+                self.push();
+            }
+            Operator::Opcode { opcode } => {
+                self.compile_opcode(*opcode);
+            }
+            Operator::Reference { name: _, target } => match target {
+                Some(target) => {
+                    self.code.i32_const(target.value.cast_signed());
+                    self.push();
+                }
+                None => {
+                    self.trigger(Effect::InvalidReference);
+                }
+            },
+        }
+    }
+
+    fn compile_opcode(&mut self, opcode: Opcode) {
+        match opcode {
+            Opcode::Multiply => self.binary(|code| {
+                code.i32_mul();
+            }),
+            Opcode::Add => self.binary(|code| {
+                code.i32_add();
+            }),
+            Opcode::Subtract => self.binary(|code| {
+                code.i32_sub();
+            }),
+            Opcode::Divide => {
+                self.pop(B);
+                self.pop(A);
+
+                self.code.local_get(B);
+                self.code.i32_eqz();
+                self.trigger_if(Effect::DivisionByZero);
+
+                self.code.local_get(A);
+                self.code.i32_const(i32::MIN);
+                self.code.i32_eq();
+                self.code.local_get(B);
+                self.code.i32_const(-1);
+                self.code.i32_eq();
+                self.code.i32_and();
+                self.trigger_if(Effect::IntegerOverflow);
+
+                self.code.local_get(A);
+                self.code.local_get(B);
+                self.code.i32_div_s();
+                self.push();
+                self.code.local_get(A);
+                self.code.local_get(B);
+                self.code.i32_rem_s();
+                self.push();
+            }
+            Opcode::DivmodU => {
+                self.pop(B);
+                self.pop(A);
+
+                self.code.local_get(B);
+                self.code.i32_eqz();
+                self.trigger_if(Effect::DivisionByZero);
+
+                self.code.local_get(A);
+                self.code.local_get(B);
+                self.code.i32_div_u();
+                self.push();
+                self.code.local_get(A);
+                self.code.local_get(B);
+                self.code.i32_rem_u();
+                self.push();
+            }
+            Opcode::DivFloor => {
+                self.pop(B);
+                self.pop(A);
+
+                self.code.local_get(B);
+                self.code.i32_eqz();
+                self.trigger_if(Effect::DivisionByZero);
+
+                self.code.local_get(A);
+                self.code.i32_const(i32::MIN);
+                self.code.i32_eq();
+                self.code.local_get(B);
+                self.code.i32_const(-1);
+                self.code.i32_eq();
+                self.code.i32_and();
+                self.trigger_if(Effect::IntegerOverflow);
+
+                // Round the truncated quotient down, if it needs adjusting.
+                self.code.local_get(A);
+                self.code.local_get(B);
+                self.code.i32_div_s();
+                self.flooring_adjustment();
+                self.code.i32_sub();
+                self.push();
+            }
+            Opcode::ModFloor => {
+                self.pop(B);
+                self.pop(A);
+
+                self.code.local_get(B);
+                self.code.i32_eqz();
+                self.trigger_if(Effect::DivisionByZero);
+
+                // If the remainder needs adjusting, add the divisor to it.
+                // Unlike division, `rem_s` doesn't trap on overflow.
+                self.code.local_get(A);
+                self.code.local_get(B);
+                self.code.i32_rem_s();
+                self.code.local_get(B);
+                self.code.i32_const(0);
+                self.flooring_adjustment();
+                self.code.select();
+                self.code.i32_add();
+                self.push();
+            }
+            Opcode::Less => self.binary(|code| {
+                code.i32_lt_s();
+            }),
+            Opcode::LessOrEqual => self.binary(|code| {
+                code.i32_le_s();
+            }),
+            Opcode::Equal => self.binary(|code| {
+                code.i32_eq();
+            }),
+            Opcode::Greater => self.binary(|code| {
+                code.i32_gt_s();
+            }),
+            Opcode::GreaterOrEqual => self.binary(|code| {
+                code.i32_ge_s();
+            }),
+            Opcode::And => self.binary(|code| {
+                code.i32_and();
+            }),
+            Opcode::Or => self.binary(|code| {
+                code.i32_or();
+            }),
+            Opcode::Xor => self.binary(|code| {
+                code.i32_xor();
+            }),
+            Opcode::CountOnes => self.unary(|code| {
+                code.i32_popcnt();
+            }),
+            Opcode::LeadingZeros => self.unary(|code| {
+                code.i32_clz();
+            }),
+            Opcode::TrailingZeros => self.unary(|code| {
+                code.i32_ctz();
+            }),
+            Opcode::RotateLeft => self.binary(|code| {
+                code.i32_rotl();
+            }),
+            Opcode::RotateRight => self.binary(|code| {
+                code.i32_rotr();
+            }),
+            Opcode::ShiftLeft => self.binary(|code| {
+                code.i32_shl();
+            }),
+            Opcode::ShiftRight => self.binary(|code| {
+                code.i32_shr_s();
+            }),
+            Opcode::Copy => {
+                self.pop(A);
+                self.operand_stack_address(A);
+                self.code.i32_load(memarg());
+                self.push();
+            }
+            Opcode::Drop => {
+                self.pop(A);
+                self.operand_stack_address(A);
+                self.code.local_set(B);
+
+                // Move all values above the dropped one down by one word.
+                self.code.local_get(B);
+                self.code.local_get(B);
+                self.code.i32_const(4);
+                self.code.i32_add();
+                self.code.global_get(OPERAND_STACK_POINTER);
+                self.code.local_get(B);
+                self.code.i32_sub();
+                self.code.i32_const(4);
+                self.code.i32_sub();
+                self.code.memory_copy(0, 0);
+
+                self.code.global_get(OPERAND_STACK_POINTER);
+                self.code.i32_const(4);
+                self.code.i32_sub();
+                self.code.global_set(OPERAND_STACK_POINTER);
+            }
+            Opcode::ReverseN => {
+                self.pop(A);
+
+                self.code.local_get(A);
+                self.operand_stack_len();
+                self.code.i32_gt_u();
+                self.trigger_if(Effect::InvalidOperandStackIndex);
+
+                // Swap the values at the addresses in `B` and `C`, moving both
+                // addresses towards each other, until they meet.
+                self.code.global_get(OPERAND_STACK_POINTER);
+                self.code.local_get(A);
+                self.code.i32_const(4);
+                self.code.i32_mul();
+                self.code.i32_sub();
+                self.code.local_set(B);
+                self.code.global_get(OPERAND_STACK_POINTER);
+                self.code.i32_const(4);
+                self.code.i32_sub();
+                self.code.local_set(C);
+
+                self.code.block(BlockType::Empty);
+                self.code.loop_(BlockType::Empty);
+
+                self.code.local_get(B);
+                self.code.local_get(C);
+                self.code.i32_ge_u();
+                self.code.br_if(1);
+
+                self.code.local_get(B);
+                self.code.i32_load(memarg());
+                self.code.local_set(A);
+                self.code.local_get(B);
+                self.code.local_get(C);
+                self.code.i32_load(memarg());
+                self.code.i32_store(memarg());
+                self.code.local_get(C);
+                self.code.local_get(A);
+                self.code.i32_store(memarg());
+
+                self.code.local_get(B);
+                self.code.i32_const(4);
+                self.code.i32_add();
+                self.code.local_set(B);
+                self.code.local_get(C);
+                self.code.i32_const(4);
+                self.code.i32_sub();
+                self.code.local_set(C);
+
+                self.code.br(0);
+                self.code.end();
+                self.code.end();
+            }
+            Opcode::Jump => {
+                self.pop(A);
+                self.jump(A, 0);
+            }
+            Opcode::JumpIf => {
+                self.pop(A);
+                self.pop(B);
+
+                self.code.local_get(B);
+                self.code.if_(BlockType::Empty);
+                self.jump(A, 1);
+                self.code.end();
+            }
+            Opcode::JumpIfEqual => self.jump_if_comparison(|code| {
+                code.i32_eq();
+            }),
+            Opcode::JumpIfNotEqual => self.jump_if_comparison(|code| {
+                code.i32_ne();
+            }),
+            Opcode::JumpIfLess => self.jump_if_comparison(|code| {
+                code.i32_lt_s();
+            }),
+            Opcode::JumpIfLessOrEqual => self.jump_if_comparison(|code| {
+                code.i32_le_s();
+            }),
+            Opcode::JumpIfGreater => self.jump_if_comparison(|code| {
+                code.i32_gt_s();
+            }),
+            Opcode::JumpIfGreaterOrEqual => self.jump_if_comparison(|code| {
+                code.i32_ge_s();
+            }),
+            Opcode::Call => {
+                self.push_return_address();
+                self.pop(A);
+                self.jump(A, 0);
+            }
+            Opcode::CallEither => {
+                self.push_return_address();
+                self.pop(C);
+                self.pop(B);
+                self.pop(A);
+
+                self.code.local_get(B);
+                self.code.local_get(C);
+                self.code.local_get(A);
+                self.code.select();
+                self.code.local_set(A);
+                self.jump(A, 0);
+            }
+            Opcode::Return => {
+                self.code.global_get(CALL_STACK_POINTER);
+                self.code.i32_const(CALL_STACK_BASE.cast_signed());
+                self.code.i32_le_u();
+                self.trigger_if(Effect::Return);
+
+                self.code.global_get(CALL_STACK_POINTER);
+                self.code.i32_const(4);
+                self.code.i32_sub();
+                self.code.global_set(CALL_STACK_POINTER);
+
+                self.code.global_get(CALL_STACK_POINTER);
+                self.code.i32_load(memarg());
+                self.code.local_set(A);
+                self.jump(A, 0);
+            }
+            Opcode::Pc => {
+                self.code.i32_const(self.operator.value.cast_signed());
+                self.push();
+            }
+            Opcode::Assert => {
+                self.pop(A);
+                self.code.local_get(A);
+                self.code.i32_eqz();
+                self.trigger_if(Effect::AssertionFailed);
+            }
+            Opcode::Unreachable => {
+                self.trigger(Effect::Unreachable);
+            }
+            Opcode::Todo => {
+                self.trigger(Effect::Todo);
+            }
+            Opcode::Yield => {
+                self.code.local_get(PC);
+                self.code.global_set(OPERATOR);
+                self.code.call(YIELD_FUNCTION);
+            }
+            Opcode::Read => {
+                self.pop(A);
+                self.memory_address(A);
+                self.code.i32_load(memarg());
+                self.push();
+            }
+            Opcode::Write => {
+                self.pop(B);
+                self.pop(A);
+                self.memory_address(A);
+                self.code.local_get(B);
+                self.code.i32_store(memarg());
+            }
+            Opcode::Locals
+            | Opcode::LocalGet
+            | Opcode::LocalSet
+            | Opcode::Spawn
+            | Opcode::Send
+            | Opcode::Receive => {
+                // Compiled modules only support a single strand, and don't
+                // support local variables or channels.
+                self.code.unreachable();
+            }
+            Opcode::Resume => {
+                // The only strand that can exist is the current one, and
+                // resuming that does nothing.
+                self.pop(A);
+                self.code.local_get(A);
+                self.trigger_if(Effect::InvalidStrand);
+            }
+            Opcode::Current => {
+                self.code.i32_const(0);
+                self.push();
+            }
+            Opcode::Exec => {
+                // Compiled modules can't compile code at runtime, which is
+                // the same as evaluating with that mode disabled.
+                self.trigger(Effect::DisabledOperator);
+            }
+        }
+    }
+
+    fn unary(&mut self, op: impl FnOnce(&mut InstructionSink)) {
+        self.pop(A);
+        self.code.local_get(A);
+        op(self.code);
+        self.push();
+    }
+
+    fn binary(&mut self, op: impl FnOnce(&mut InstructionSink)) {
+        self.pop(B);
+        self.pop(A);
+        self.code.local_get(A);
+        self.code.local_get(B);
+        op(self.code);
+        self.push();
+    }
+
+    /// # Jump, if comparing the two values below the target has a true result
+    fn jump_if_comparison(
+        &mut self,
+        compare: impl FnOnce(&mut InstructionSink),
+    ) {
+        self.pop(A);
+        self.pop(C);
+        self.pop(B);
+
+        self.code.local_get(B);
+        self.code.local_get(C);
+        compare(self.code);
+        self.code.if_(BlockType::Empty);
+        self.jump(A, 1);
+        self.code.end();
+    }
+
+    /// # Push the value on top of the WebAssembly stack to the operand stack
+    fn push(&mut self) {
+        self.code.local_set(C);
+
+        self.code.global_get(OPERAND_STACK_POINTER);
+        self.code.i32_const(CALL_STACK_BASE.cast_signed());
+        self.code.i32_ge_u();
+        self.code.if_(BlockType::Empty);
+        self.code.unreachable();
+        self.code.end();
+
+        self.code.global_get(OPERAND_STACK_POINTER);
+        self.code.local_get(C);
+        self.code.i32_store(memarg());
+
+        self.code.global_get(OPERAND_STACK_POINTER);
+        self.code.i32_const(4);
+        self.code.i32_add();
+        self.code.global_set(OPERAND_STACK_POINTER);
+    }
+
+    /// # Pop a value from the operand stack into the provided local
+    fn pop(&mut self, local: u32) {
+        self.code.global_get(OPERAND_STACK_POINTER);
+        self.code.i32_const(OPERAND_STACK_BASE.cast_signed());
+        self.code.i32_le_u();
+        self.trigger_if(Effect::OperandStackUnderflow);
+
+        self.code.global_get(OPERAND_STACK_POINTER);
+        self.code.i32_const(4);
+        self.code.i32_sub();
+        self.code.global_set(OPERAND_STACK_POINTER);
+
+        self.code.global_get(OPERAND_STACK_POINTER);
+        self.code.i32_load(memarg());
+        self.code.local_set(local);
+    }
+
+    /// # Convert the index in the provided local to an operand stack address
+    ///
+    /// The index is interpreted like the inputs of `copy` and `drop`: If it's
+    /// negative, it counts from the bottom of the stack, otherwise from the
+    /// top. Leaves the address on the WebAssembly stack.
+    fn operand_stack_address(&mut self, local: u32) {
+        // Convert a negative index into one that counts from the top, by adding
+        // the number of values on the stack. If it's still negative, the check
+        // below treats it as a very large unsigned index.
+        self.code.local_get(local);
+        self.code.i32_const(0);
+        self.code.i32_lt_s();
+        self.code.if_(BlockType::Empty);
+        self.code.local_get(local);
+        self.operand_stack_len();
+        self.code.i32_add();
+        self.code.local_set(local);
+        self.code.end();
+
+        self.code.local_get(local);
+        self.operand_stack_len();
+        self.code.i32_ge_u();
+        self.trigger_if(Effect::InvalidOperandStackIndex);
+
+        self.code.global_get(OPERAND_STACK_POINTER);
+        self.code.i32_const(4);
+        self.code.i32_sub();
+        self.code.local_get(local);
+        self.code.i32_const(4);
+        self.code.i32_mul();
+        self.code.i32_sub();
+    }
+
+    /// # Determine whether a truncating division needs adjusting to floor
+    ///
+    /// Expects the dividend and divisor in `A` and `B`. Pushes `1` to the
+    /// WebAssembly stack, if the remainder is non-zero and its sign differs
+    /// from the divisor's, `0` otherwise.
+    fn flooring_adjustment(&mut self) {
+        self.code.local_get(A);
+        self.code.local_get(B);
+        self.code.i32_rem_s();
+        self.code.local_tee(C);
+        self.code.i32_const(0);
+        self.code.i32_ne();
+        self.code.local_get(C);
+        self.code.local_get(B);
+        self.code.i32_xor();
+        self.code.i32_const(0);
+        self.code.i32_lt_s();
+        self.code.i32_and();
+    }
+
+    /// # Push the length of the operand stack to the WebAssembly stack
+    fn operand_stack_len(&mut self) {
+        self.code.global_get(OPERAND_STACK_POINTER);
+        self.code.i32_const(OPERAND_STACK_BASE.cast_signed());
+        self.code.i32_sub();
+        self.code.i32_const(4);
+        self.code.i32_div_u();
+    }
+
+    /// # Convert the memory address in the provided local to a byte address
+    ///
+    /// Leaves the byte address on the WebAssembly stack.
+    fn memory_address(&mut self, local: u32) {
+        self.code.local_get(local);
+        self.code.i32_const(MEMORY_WORDS.cast_signed());
+        self.code.i32_ge_u();
+        self.trigger_if(Effect::InvalidAddress);
+
+        self.code.local_get(local);
+        self.code.i32_const(4);
+        self.code.i32_mul();
+    }
+
+    fn push_return_address(&mut self) {
+        self.code.global_get(CALL_STACK_POINTER);
+        self.code.i32_const(MEMORY_END.cast_signed());
+        self.code.i32_ge_u();
+        self.code.if_(BlockType::Empty);
+        self.code.unreachable();
+        self.code.end();
+
+        self.code.global_get(CALL_STACK_POINTER);
+        self.code.i32_const((self.operator.value + 1).cast_signed());
+        self.code.i32_store(memarg());
+
+        self.code.global_get(CALL_STACK_POINTER);
+        self.code.i32_const(4);
+        self.code.i32_add();
+        self.code.global_set(CALL_STACK_POINTER);
+    }
+
+    /// # Continue the evaluation at the operator in the provided local
+    ///
+    /// `depth` is the number of blocks that the jump is nested in, within the
+    /// operator's section.
+    fn jump(&mut self, local: u32, depth: u32) {
+        self.code.local_get(local);
+        self.code.local_set(PC);
+        self.code.br(self.dispatch + depth);
+    }
+
+    fn trigger(&mut self, effect: Effect) {
+        self.code.local_get(PC);
+        self.code.global_set(OPERATOR);
+        self.code.i32_const(wasm_code(effect));
+        self.code.return_();
+    }
+
+    /// # Trigger the effect, if the value on top of the WebAssembly stack is 1
+    fn trigger_if(&mut self, effect: Effect) {
+        self.code.if_(BlockType::Empty);
+        self.trigger(effect);
+        self.code.end();
+    }
+}
+
+fn memarg() -> MemArg {
+    MemArg {
+        offset: 0,
+        align: 2,
+        memory_index: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use wasmi::{Caller, Engine, Linker, Module, Store};
+
+    use crate::{Effect, Eval, Script};
+
+    #[test]
+    fn compiled_module_behaves_like_interpreter() -> Result<(), Box<dyn Error>>
+    {
+        let scripts = [
+            "1 2 + 3 * 4 - 5 <",
+            "-7 2 / 5 0 /",
+            "-2147483648 -1 /",
+            "7 2 div_floor -7 2 div_floor 7 -2 div_floor -7 -2 div_floor",
+            "7 2 mod_floor -7 2 mod_floor 7 -2 mod_floor -7 -2 mod_floor",
+            "-2147483648 -1 mod_floor -2147483648 -1 div_floor",
+            "1 0 mod_floor",
+            "4294967295 2 divmod_u 7 -1 divmod_u 1 0 divmod_u",
+            "0 loop: 1 + 0 copy 10 @loop jump_if_lt -1 1 @end jump_if_gt 2 end:",
+            "1 1 @a jump_if_ne 1 1 @a jump_if_eq 3 a: 2 3 @b jump_if_ge 4 b:",
+            "1 2 3 1 copy 2 drop 3 count_ones 1 shift_left",
+            "0 loop: 1 + 0 copy 10 < @loop jump_if",
+            "@f call 3 return f: 1 2 @g @h call_either return g: 4 return h: 5",
+            "5 3 write 5 read 1024 read",
+            "1 assert 0 assert",
+            "1 unreachable 2",
+            "1 pc 2 pc",
+            "1 todo 2",
+            "1 +",
+            "@invalid",
+            "unknown",
+            "5 copy",
+            "3 5 8 -1 copy -2 drop -4 copy",
+            "1 2 3 4 5 4 reverse_n 0 reverse_n 1 reverse_n 6 reverse_n",
+            "current resume current 1 resume",
+            "0 1 exec 2",
+            ".data table 3 @f -1\n 0 read 1 read call f: 2 read",
+            ".data table @invalid 1",
+            ".zero padding 1023 .data table 1 2",
+            ".start main 1 return main: 2 @f jump f: 3",
+            ".start invalid 1",
+        ];
+
+        for source in scripts {
+            let script = Script::compile(source);
+
+            let mut eval = Eval::start_at(script.start());
+            eval.load_data(&script);
+            let (effect, operator) = eval.run(&script);
+
+            let outcome = run(&script)?;
+
+            assert_eq!(Some(effect), outcome.effect, "{source}");
+            assert_eq!(operator.value(), outcome.operator, "{source}");
+            assert_eq!(
+                eval.operand_stack.to_u32_slice(),
+                outcome.stack,
+                "{source}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn yield_calls_host() -> Result<(), Box<dyn Error>> {
+        let script = Script::compile("1 yield 2 yield");
+
+        let engine = Engine::default();
+        let module = Module::new(&engine, script.to_wasm())?;
+        let mut store = Store::new(&engine, 0);
+
+        let mut linker = Linker::new(&engine);
+        linker.func_wrap("env", "yield", |mut caller: Caller<'_, u32>| {
+            *caller.data_mut() += 1;
+        })?;
+
+        let instance = linker.instantiate_and_start(&mut store, &module)?;
+        let run = instance.get_typed_func::<(), i32>(&store, "run")?;
+        let code = run.call(&mut store, ())?;
+
+        assert_eq!(Effect::from_wasm_code(code), Some(Effect::OutOfOperators));
+        assert_eq!(*store.data(), 2);
+
+        Ok(())
+    }
+
+    fn run(script: &Script) -> Result<Outcome, Box<dyn Error>> {
+        let engine = Engine::default();
+        let module = Module::new(&engine, script.to_wasm())?;
+        let mut store = Store::new(&engine, ());
+
+        let mut linker = Linker::new(&engine);
+        linker.func_wrap("env", "yield", || {})?;
+
+        let instance = linker.instantiate_and_start(&mut store, &module)?;
+        let run = instance.get_typed_func::<(), i32>(&store, "run")?;
+        let code = run.call(&mut store, ())?;
+
+        let global = |name| {
+            instance
+                .get_global(&store, name)
+                .and_then(|global| global.get(&store).i32())
+                .map(i32::cast_unsigned)
+                .ok_or(format!("Expected global `{name}`."))
+        };
+        let operator = global("operator")?;
+        let base = global("operand_stack_base")? as usize;
+        let pointer = global("operand_stack_pointer")? as usize;
+
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or("Expected memory.")?;
+        let stack =
+            bytemuck::cast_slice(&memory.data(&store)[base..pointer]).to_vec();
+
+        Ok(Outcome {
+            effect: Effect::from_wasm_code(code),
+            operator,
+            stack,
+        })
+    }
+
+    struct Outcome {
+        effect: Option<Effect>,
+        operator: u32,
+        stack: Vec<u32>,
+    }
+}